@@ -0,0 +1,274 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    errors::CliError,
+    fingerprint::{Fingerprint, hash_source},
+};
+
+/// What a file in the target directory is, see [`BuiltArtifact`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    Binary,
+    Object,
+    /// Written with `--emit map`
+    Map,
+    /// Written with `--trace-parse`
+    Trace,
+}
+
+impl ArtifactKind {
+    pub const ALL: [ArtifactKind; 4] = [
+        ArtifactKind::Binary,
+        ArtifactKind::Object,
+        ArtifactKind::Map,
+        ArtifactKind::Trace,
+    ];
+
+    /// Appended to the artifact stem, see [`with_suffix`].
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            ArtifactKind::Binary => "",
+            ArtifactKind::Object => ".o",
+            ArtifactKind::Map => ".map",
+            ArtifactKind::Trace => ".trace",
+        }
+    }
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactKind::Binary => write!(f, "binary"),
+            ArtifactKind::Object => write!(f, "object"),
+            ArtifactKind::Map => write!(f, "map"),
+            ArtifactKind::Trace => write!(f, "trace"),
+        }
+    }
+}
+
+/// A file the last successful build wrote, as listed by `rune artifacts`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuiltArtifact {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+    /// See [`hash_source`].
+    pub hash: String,
+    /// The source file it was built from, and that file's hash at the time.
+    pub source: String,
+    pub source_hash: String,
+}
+
+/// Path of the artifacts built from `source`, relative to the target
+/// directory: its path within `source_dir` without the extension, so
+/// `src/a/main.rn` builds `target/a/main` and `target/a/main.o`.
+pub fn artifact_stem(source_dir: &Path, source: &Path) -> PathBuf {
+    source
+        .strip_prefix(source_dir)
+        .unwrap_or(source)
+        .with_extension("")
+}
+
+/// The artifacts in `target_dir` of each source file the build recorded in
+/// `fingerprint` produced a binary for, by source then kind. Artifacts a
+/// build didn't write, like a map without `--emit map`, are left out.
+pub fn built_artifacts(
+    fingerprint: &Fingerprint,
+    source_dir: &Path,
+    target_dir: &Path,
+) -> Vec<BuiltArtifact> {
+    let mut artifacts = Vec::new();
+
+    for (source, source_hash) in &fingerprint.files {
+        if !fingerprint.artifacts.contains_key(source) {
+            continue;
+        }
+
+        let stem = target_dir.join(artifact_stem(source_dir, Path::new(source)));
+        for kind in ArtifactKind::ALL {
+            let path = with_suffix(&stem, kind.suffix());
+            let Ok(contents) = fs::read(&path) else {
+                continue;
+            };
+            artifacts.push(BuiltArtifact {
+                kind,
+                path,
+                hash: hash_source(&contents),
+                source: source.clone(),
+                source_hash: source_hash.clone(),
+            });
+        }
+    }
+
+    artifacts
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `main` to `main.o`.
+/// Unlike [`Path::with_extension`] this keeps any dots already in the name.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+/// Fails on the first two sources whose artifacts would overwrite each
+/// other: stems equal up to case, which a case-insensitive file system
+/// treats as the same file, or one stem being a directory the other's
+/// artifacts are written into, like `main` and `main/util`.
+pub fn check_collisions(sources: &[PathBuf], stems: &[PathBuf]) -> Result<(), CliError> {
+    let folded: Vec<PathBuf> = stems
+        .iter()
+        .map(|stem| PathBuf::from(stem.to_string_lossy().to_lowercase()))
+        .collect();
+
+    for (i, first) in folded.iter().enumerate() {
+        for (j, second) in folded.iter().enumerate().skip(i + 1) {
+            if first.starts_with(second) || second.starts_with(first) {
+                return Err(CliError::ArtifactCollision(
+                    sources[i].display().to_string(),
+                    sources[j].display().to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Where an artifact is written before being renamed to `path`. It is a
+/// hidden file in the same directory, so the rename stays on one file
+/// system and replaces `path` in a single step.
+pub fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.tmp", file_name))
+}
+
+/// Writes `contents` to `path` through a temporary file, so an interrupted
+/// build leaves either the previous file or the complete new one, never a
+/// truncated one.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+
+    match written {
+        Ok(()) => persist(&temp, path),
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            Err(err)
+        }
+    }
+}
+
+/// Renames a finished temporary file over `path`, removing it if that
+/// fails.
+pub fn persist(temp: &Path, path: &Path) -> io::Result<()> {
+    fs::rename(temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(temp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_the_file_without_leaving_the_temporary() {
+        let dir = std::env::temp_dir().join(format!("rune-artifact-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.o");
+
+        assert_eq!(temp_path(&path), dir.join(".main.o.tmp"));
+
+        fs::write(&path, b"old").unwrap();
+        write_atomically(&path, b"new").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!temp_path(&path).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn built_artifacts_are_listed_per_source() {
+        let dir = std::env::temp_dir().join(format!("rune-built-{}", std::process::id()));
+        let (source_dir, target_dir) = (dir.join("src"), dir.join("target"));
+        fs::create_dir_all(target_dir.join("a")).unwrap();
+        fs::write(target_dir.join("a/main"), b"\x7fELF").unwrap();
+        fs::write(target_dir.join("a/main.o"), b"object").unwrap();
+        fs::write(target_dir.join("failed.o"), b"object").unwrap();
+
+        let main = source_dir.join("a/main.rn").display().to_string();
+        let failed = source_dir.join("failed.rn").display().to_string();
+        let mut fingerprint = Fingerprint::default();
+        fingerprint
+            .files
+            .insert(main.clone(), hash_source(b"print(1);"));
+        fingerprint.files.insert(failed, hash_source(b"print(x);"));
+        fingerprint
+            .record_artifact(&main, &target_dir.join("a/main"))
+            .unwrap();
+
+        let artifacts = built_artifacts(&fingerprint, &source_dir, &target_dir);
+        assert_eq!(
+            artifacts,
+            [
+                BuiltArtifact {
+                    kind: ArtifactKind::Binary,
+                    path: target_dir.join("a/main"),
+                    hash: hash_source(b"\x7fELF"),
+                    source: main.clone(),
+                    source_hash: hash_source(b"print(1);"),
+                },
+                BuiltArtifact {
+                    kind: ArtifactKind::Object,
+                    path: target_dir.join("a/main.o"),
+                    hash: hash_source(b"object"),
+                    source: main,
+                    source_hash: hash_source(b"print(1);"),
+                },
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stems_keep_directories_and_collisions_are_found() {
+        let source_dir = Path::new("/project/src");
+        let sources: Vec<PathBuf> = ["a/main.rn", "b/main.rn", "lib.v2.rn"]
+            .iter()
+            .map(|source| source_dir.join(source))
+            .collect();
+        let stems: Vec<PathBuf> = sources
+            .iter()
+            .map(|source| artifact_stem(source_dir, source))
+            .collect();
+
+        assert_eq!(stems[0], Path::new("a/main"));
+        assert_eq!(with_suffix(&stems[2], ".o"), Path::new("lib.v2.o"));
+        assert!(check_collisions(&sources, &stems).is_ok());
+
+        let clashing = [PathBuf::from("Main"), PathBuf::from("main/util")];
+        assert!(
+            check_collisions(&clashing, &[clashing[0].clone(), PathBuf::from("main")]).is_err()
+        );
+        assert_eq!(
+            check_collisions(&clashing, &clashing),
+            Err(CliError::ArtifactCollision(
+                "Main".into(),
+                "main/util".into()
+            ))
+        );
+    }
+}
@@ -1,13 +1,52 @@
 use std::{env, fs, path::Path};
 
-use clap::{Parser, Subcommand, command};
-use owo_colors::OwoColorize;
+use clap::{Parser, Subcommand, ValueEnum};
 
 use crate::errors::CliError;
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum CliCommand {
     Build,
+    Run {
+        #[arg(long, value_enum, default_value_t = Engine::Llvm)]
+        engine: Engine,
+    },
+    /// Build, then report the section and function sizes of each binary
+    Size,
+    /// List the files the last successful build wrote, with their hashes
+    /// and the source each was built from
+    Artifacts {
+        /// Print the list as JSON, for scripts and other tools
+        #[arg(long)]
+        json: bool,
+    },
+    /// Start an interactive session on the interpreter
+    Repl,
+    /// Format the source files using the `[fmt]` settings in Rune.toml
+    Fmt {
+        /// Report unformatted files instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Run generated programs on both engines and report divergences
+    FuzzDiff {
+        #[arg(long, default_value_t = 100)]
+        iterations: u64,
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Engine {
+    Llvm,
+    Interp,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    /// A `.map` file mapping each top-level statement to its IR names
+    Map,
 }
 
 #[derive(Parser, Debug)]
@@ -19,51 +58,39 @@ pub struct Cli {
     pub verbose: bool,
     #[arg(short, long)]
     pub quiet: bool,
-}
-
-#[inline]
-pub fn print_value(label: &str, value: &str, depth: usize) {
-    println!("{}{}: `{}`", " ".repeat(depth), label.bold(), value);
-}
-
-#[inline]
-pub fn print_section(label: &str, depth: usize) {
-    println!("{}{}", " ".repeat(depth), label.bold().green());
-}
-
-#[inline]
-pub fn print_error(error: &str, depth: usize) {
-    println!(
-        "{}{}{} {}",
-        " ".repeat(depth),
-        "Error".bold().red(),
-        ":".bold(),
-        error.red()
-    );
-}
-
-#[inline]
-pub fn print_warning(warning: &str, depth: usize) {
-    println!(
-        "{}{}{} {}",
-        " ".repeat(depth),
-        "warning".bold().yellow(),
-        ":".bold(),
-        warning
-    );
+    /// Decode source files that are not valid UTF-8 as Latin-1
+    #[arg(long)]
+    pub latin1_fallback: bool,
+    /// Report instruction, block and stack usage for each compiled function
+    #[arg(long)]
+    pub stats: bool,
+    /// Print each statement followed by the annotated IR generated for it
+    #[arg(long)]
+    pub explain_ir: bool,
+    /// Treat compiler warnings as errors, unless `[lints]` in Rune.toml
+    /// sets their level
+    #[arg(long)]
+    pub deny_warnings: bool,
+    /// Print LLVM's remarks on which loops were vectorized, and why not
+    #[arg(long)]
+    pub report_vectorization: bool,
+    /// Target triple to build for, defaults to the host. Settings come from
+    /// the matching `[target.'<triple>'.build]` section of Rune.toml
+    #[arg(long)]
+    pub target: Option<String>,
+    /// Log every rule the parser enters and token it consumes to a
+    /// `.trace` file next to each binary, for reporting grammar bugs
+    #[arg(long)]
+    pub trace_parse: bool,
+    /// Extra artifacts to write next to each binary (implied by --verbose)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub emit: Vec<Emit>,
 }
 
 pub fn get_current_directory() -> Result<std::path::PathBuf, CliError> {
-    let result = env::current_dir();
-
-    if result.is_err() {
-        return Err(CliError::InternalError(format!(
-            "Failed to get current directory: {}",
-            result.unwrap_err()
-        )));
-    }
-
-    Ok(result.unwrap())
+    env::current_dir().map_err(|error| {
+        CliError::InternalError(format!("Failed to get current directory: {}", error))
+    })
 }
 
 pub fn make_folder(current_dir: &Path, name: &str) -> Result<(), CliError> {
@@ -83,9 +110,77 @@ pub fn folder_exists(current_dir: &Path, name: &str) -> Result<(), CliError> {
     }
 }
 
-pub fn read_file(file_path: &Path) -> Result<String, CliError> {
-    let content = fs::read_to_string(file_path)
+pub fn check_file_size(file_path: &Path, max_file_size: u64) -> Result<(), CliError> {
+    let metadata = fs::metadata(file_path)
+        .map_err(|e| CliError::IOError(format!("Failed to read file metadata: {}", e)))?;
+
+    if metadata.len() > max_file_size {
+        return Err(CliError::LimitExceeded(format!(
+            "`{}` is {} bytes, the maximum file size is {} bytes",
+            file_path.display(),
+            metadata.len(),
+            max_file_size
+        )));
+    }
+
+    Ok(())
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+pub fn read_file(file_path: &Path, latin1_fallback: bool) -> Result<String, CliError> {
+    let bytes = fs::read(file_path)
         .map_err(|e| CliError::IOError(format!("Failed to read file: {}", e)))?;
 
-    Ok(content)
+    decode_source(bytes, latin1_fallback)
+        .map_err(|offset| CliError::InvalidUtf8(file_path.display().to_string(), offset))
+}
+
+/// Decodes source bytes, stripping a leading UTF-8 BOM.
+///
+/// On invalid UTF-8 returns the byte offset of the first invalid sequence,
+/// unless `latin1_fallback` is set, in which case every byte is mapped to
+/// the code point of the same value.
+pub fn decode_source(mut bytes: Vec<u8>, latin1_fallback: bool) -> Result<String, usize> {
+    let bom_len = if bytes.starts_with(UTF8_BOM) {
+        bytes.drain(..UTF8_BOM.len());
+        UTF8_BOM.len()
+    } else {
+        0
+    };
+
+    match String::from_utf8(bytes) {
+        Ok(source) => Ok(source),
+        Err(err) if latin1_fallback => Ok(err.into_bytes().iter().map(|&b| b as char).collect()),
+        Err(err) => Err(bom_len + err.utf8_error().valid_up_to()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_strips_bom() {
+        let bytes = [UTF8_BOM, b"let x = 1;"].concat();
+
+        assert_eq!(decode_source(bytes, false), Ok("let x = 1;".to_string()));
+    }
+
+    #[test]
+    fn decode_reports_invalid_offset() {
+        let bytes = b"let x = \"\xFF\";".to_vec();
+
+        assert_eq!(decode_source(bytes, false), Err(9));
+    }
+
+    #[test]
+    fn decode_latin1_fallback() {
+        let bytes = b"print(\"caf\xE9\")".to_vec();
+
+        assert_eq!(
+            decode_source(bytes, true),
+            Ok("print(\"caf\u{e9}\")".to_string())
+        );
+    }
 }
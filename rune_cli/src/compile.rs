@@ -0,0 +1,390 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use inkwell::{
+    OptimizationLevel,
+    context::Context,
+    llvm_sys::support::LLVMParseCommandLineOptions,
+    passes::PassBuilderOptions,
+    targets::{
+        CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+    },
+};
+use rune_core::prelude::{Expr, Span};
+use rune_core::{
+    codegen::CodeGen, ir_map::StatementIr, options::CompileOptions, stats::ModuleStats,
+    warnings::CodeGenWarning,
+};
+
+use crate::{
+    artifact::{persist, temp_path, write_atomically},
+    config::{DEFAULT_LINKER, TargetSettings},
+    errors::CliError,
+};
+
+pub fn create_target_machine(settings: &TargetSettings) -> Result<TargetMachine, CliError> {
+    Target::initialize_all(&InitializationConfig::default());
+    let triple = TargetTriple::create(&settings.triple);
+    let target =
+        Target::from_triple(&triple).map_err(|err| CliError::CompileError(err.to_string()))?;
+
+    let opt_level = match settings.opt_level {
+        0 => OptimizationLevel::None,
+        1 => OptimizationLevel::Less,
+        2 => OptimizationLevel::Default,
+        _ => OptimizationLevel::Aggressive,
+    };
+
+    target
+        .create_target_machine(
+            &triple,
+            "generic",
+            &settings.features.join(","),
+            opt_level,
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or(CliError::CompileError(format!(
+            "Failed to create target machine for `{}`",
+            settings.triple
+        )))
+}
+
+/// Has LLVM print the loop vectorizer's remarks to stderr, saying for each
+/// loop whether it was vectorized and if not, why. The options are global
+/// to the process, so this is called once before compiling anything.
+pub fn enable_vectorization_remarks() {
+    let args = [
+        c"rune",
+        c"-pass-remarks=loop-vectorize",
+        c"-pass-remarks-missed=loop-vectorize",
+        c"-pass-remarks-analysis=loop-vectorize",
+    ];
+    let args: Vec<_> = args.iter().map(|arg| arg.as_ptr()).collect();
+
+    unsafe { LLVMParseCommandLineOptions(args.len() as i32, args.as_ptr(), c"".as_ptr()) };
+}
+
+/// The host's triple, used when no `--target` is given.
+pub fn target_triple() -> String {
+    TargetMachine::get_default_triple()
+        .as_str()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Everything codegen reports back besides the object file itself.
+pub struct CompileOutput {
+    /// Each warning with the line and column of the expression or
+    /// statement it is about, if known.
+    pub warnings: Vec<(CodeGenWarning, Option<(usize, usize)>)>,
+    /// IR created by each top-level statement, if requested.
+    pub statement_ir: Option<Vec<StatementIr>>,
+    pub stats: ModuleStats,
+}
+
+/// Compiles `statements` into a native object file at `obj_path`.
+pub fn compile_to_object(
+    statements: &[Expr],
+    module_name: &str,
+    obj_path: &Path,
+    map_statements: bool,
+    settings: &TargetSettings,
+) -> Result<CompileOutput, CliError> {
+    let target_machine = create_target_machine(settings)?;
+    let context = Context::create();
+
+    let job = CompileJob {
+        statements: statements.to_vec(),
+        module_name: module_name.to_string(),
+        obj_path: obj_path.to_path_buf(),
+        map_statements,
+        source: None,
+    };
+    compile_module(&context, &target_machine, settings, &job)
+}
+
+/// One source file for [`BuildCompiler`] to compile.
+pub struct CompileJob {
+    pub statements: Vec<Expr>,
+    pub module_name: String,
+    pub obj_path: PathBuf,
+    pub map_statements: bool,
+    /// The source `statements` were parsed from, to locate codegen errors
+    /// and warnings. Both are reported without a location if `None`.
+    pub source: Option<String>,
+}
+
+/// Compiles `job` into its own module within `context`.
+fn compile_module(
+    context: &Context,
+    target_machine: &TargetMachine,
+    settings: &TargetSettings,
+    job: &CompileJob,
+) -> Result<CompileOutput, CliError> {
+    let options = CompileOptions {
+        unchecked_indexing: !settings.bounds_checks,
+        float_precision: settings.float_precision,
+        ..CompileOptions::for_target_machine(target_machine)
+    };
+    let mut codegen = CodeGen::with_options(context, &job.module_name, &options);
+
+    let statement_ir = if job.map_statements {
+        codegen.compile_statements_mapped(&job.statements).map(Some)
+    } else {
+        codegen.compile_statements(&job.statements).map(|_| None)
+    }
+    .map_err(|error| CliError::CodeGen {
+        file: Some(job.module_name.clone()),
+        error: Box::new(match (&job.source, codegen.failed_span()) {
+            (Some(source), Some(span)) => error.at(source, span),
+            _ => error,
+        }),
+    })?;
+
+    let stats = codegen.stats();
+
+    // The target machine only optimizes instruction selection, the IR
+    // passes (including the loop vectorizer) run here
+    codegen
+        .module
+        .run_passes(
+            &format!("default<O{}>", settings.opt_level),
+            target_machine,
+            PassBuilderOptions::create(),
+        )
+        .map_err(|err| CliError::CompileError(err.to_string()))?;
+
+    let mem_buffer = target_machine
+        .write_to_memory_buffer(&codegen.module, FileType::Object)
+        .map_err(|err| CliError::CompileError(err.to_string()))?;
+
+    write_atomically(&job.obj_path, mem_buffer.as_slice())
+        .map_err(|e| CliError::IOError(format!("Failed to write object file `{}`", e)))?;
+
+    let warnings = codegen
+        .warnings()
+        .iter()
+        .zip(codegen.warning_statements())
+        .zip(codegen.warning_spans())
+        .map(|((warning, statement), span)| {
+            let span = span.or_else(|| {
+                statement
+                    .and_then(|index| job.statements.get(index))
+                    .map(|statement| statement.span())
+            });
+            let location = span
+                .zip(job.source.as_deref())
+                .map(|(span, source)| span.line_column(source));
+            (warning.clone(), location)
+        })
+        .collect();
+
+    Ok(CompileOutput {
+        warnings,
+        statement_ir,
+        stats,
+    })
+}
+
+/// Compiles the files of a build one after another on a worker thread
+/// owning a single LLVM context and target machine, instead of setting up
+/// both again for every file.
+///
+/// Each file still gets its own module. A file taking longer than the
+/// timeout fails with [`CliError::LimitExceeded`] and leaves the worker
+/// busy, so callers are expected to stop the build when that happens.
+pub struct BuildCompiler {
+    jobs: mpsc::Sender<CompileJob>,
+    results: mpsc::Receiver<Result<CompileOutput, CliError>>,
+    timeout: Duration,
+}
+
+impl BuildCompiler {
+    pub fn spawn(settings: TargetSettings, timeout: Duration) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<CompileJob>();
+        let (result_sender, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            let target_machine = create_target_machine(&settings);
+            let context = Context::create();
+
+            for job in job_receiver {
+                let result = match &target_machine {
+                    Ok(target_machine) => compile_module(&context, target_machine, &settings, &job),
+                    Err(err) => Err(err.clone()),
+                };
+
+                // The receiver is gone if the build was stopped
+                if result_sender.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            jobs,
+            results,
+            timeout,
+        }
+    }
+
+    pub fn compile(&self, job: CompileJob) -> Result<CompileOutput, CliError> {
+        self.jobs
+            .send(job)
+            .map_err(|_| CliError::InternalError("compilation thread panicked".to_string()))?;
+
+        match self.results.recv_timeout(self.timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(CliError::LimitExceeded(format!(
+                "compilation took longer than {}ms",
+                self.timeout.as_millis()
+            ))),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(CliError::InternalError(
+                "compilation thread panicked".to_string(),
+            )),
+        }
+    }
+}
+
+/// Linkers tried in order when none is configured, all taking `cc` style
+/// arguments.
+const FALLBACK_LINKERS: [&str; 3] = [DEFAULT_LINKER, "gcc", "clang"];
+
+/// Links an object file into an executable using `linker`, which takes
+/// `cc` style arguments. With the default linker, `gcc` and `clang` are
+/// tried as well if `cc` is not installed.
+///
+/// The linker writes to a temporary file that only replaces `bin_path`
+/// once linking succeeded.
+pub fn link_executable(obj_path: &Path, bin_path: &Path, linker: &str) -> Result<(), CliError> {
+    let candidates: &[&str] = if linker == DEFAULT_LINKER {
+        &FALLBACK_LINKERS
+    } else {
+        std::slice::from_ref(&linker)
+    };
+    let temp_bin_path = temp_path(bin_path);
+
+    for candidate in candidates {
+        let output = Command::new(candidate)
+            .arg(obj_path)
+            .arg("-o")
+            .arg(&temp_bin_path)
+            .output();
+
+        match output {
+            Ok(output) => {
+                if !output.status.success() {
+                    let _ = fs::remove_file(&temp_bin_path);
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    return Err(CliError::LinkError(format!(
+                        "Linker `{}` failed with status {}:\n{}",
+                        candidate, output.status, stderr
+                    )));
+                }
+                return persist(&temp_bin_path, bin_path).map_err(|e| {
+                    CliError::IOError(format!(
+                        "Failed to write executable `{}`: {}",
+                        bin_path.display(),
+                        e
+                    ))
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(CliError::LinkError(format!(
+                    "Failed to execute linker `{}`: {}",
+                    candidate, e
+                )));
+            }
+        }
+    }
+
+    Err(CliError::LinkError(match candidates {
+        [linker] => format!(
+            "Linker `{}` was not found. Is it in your PATH? Remove `linker` from \
+             Rune.toml to use the system C compiler",
+            linker
+        ),
+        _ => format!(
+            "No linker found, tried {}. Install a C compiler or set `linker` in \
+             the `[build]` section of Rune.toml",
+            candidates
+                .iter()
+                .map(|candidate| format!("`{}`", candidate))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }))
+}
+
+/// Writes a `.map` sidecar with one line per top-level statement:
+/// `line:column<TAB>start..end<TAB>names`, where `start..end` is the byte
+/// span of the statement and `names` are the IR names it produced.
+pub fn write_ir_map(
+    map_path: &Path,
+    source: &str,
+    spans: &[Span],
+    statement_ir: &[StatementIr],
+) -> Result<(), CliError> {
+    let mut map = String::new();
+
+    for (span, ir) in spans.iter().zip(statement_ir) {
+        let (line, column) = span.line_column(source);
+
+        map.push_str(&format!(
+            "{}:{}\t{}..{}\t{}\n",
+            line,
+            column,
+            span.start,
+            span.end,
+            ir.names.join(" ")
+        ));
+    }
+
+    write_atomically(map_path, map.as_bytes()).map_err(|e| {
+        CliError::IOError(format!(
+            "Failed to write map file `{}`: {}",
+            map_path.display(),
+            e
+        ))
+    })
+}
+
+/// Formats each statement as a comment followed by the annotated IR it
+/// generated, for `--explain-ir`.
+pub fn explain_ir(source: &str, spans: &[Span], statement_ir: &[StatementIr]) -> String {
+    let mut explained = String::new();
+
+    for (span, ir) in spans.iter().zip(statement_ir) {
+        let (line, _) = span.line_column(source);
+
+        for (offset, source_line) in source[span.range()].lines().enumerate() {
+            explained.push_str(&format!("{:>4} | {}\n", line + offset, source_line));
+        }
+
+        if ir.lines.is_empty() {
+            explained.push_str("       (no IR, the value is a constant)\n");
+        }
+
+        for ir_line in &ir.lines {
+            match &ir_line.note {
+                Some(note) => {
+                    explained.push_str(&format!("       {:<40} ; {}\n", ir_line.text, note))
+                }
+                None => explained.push_str(&format!("       {}\n", ir_line.text)),
+            }
+        }
+
+        explained.push('\n');
+    }
+
+    explained
+}
@@ -1,8 +1,12 @@
 use std::{
-    fs,
+    collections::{HashMap, HashSet},
+    fs, io,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
+use rune_core::prelude::{FormatOptions, Indent};
+use rune_core::warnings::LINTS;
 use serde::{Deserialize, Serialize};
 use toml::from_str;
 
@@ -17,12 +21,295 @@ pub struct Config {
     pub title: String,
     pub version: String,
     pub build: BuildConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub fmt: FmtConfig,
+    #[serde(default)]
+    pub lints: LintsConfig,
+    /// `[target.'<triple>'.build]` sections, keyed by target triple.
+    #[serde(default)]
+    pub target: HashMap<String, TargetConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BuildConfig {
     pub source_dir: Option<String>,
     pub target_dir: Option<String>,
+    /// Source file extensions, without the dot, defaults to `["rn"]`.
+    pub extensions: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub codegen: CodegenConfig,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TargetConfig {
+    #[serde(default)]
+    pub build: CodegenConfig,
+}
+
+/// Build settings that can be overridden per target triple.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CodegenConfig {
+    pub opt_level: Option<u8>,
+    pub linker: Option<String>,
+    /// LLVM target features, e.g. `"+simd128"`.
+    pub features: Option<Vec<String>>,
+    /// Check array indices at runtime, on unless set to `false`.
+    pub bounds_checks: Option<bool>,
+    /// Print floats with this many decimals instead of the shortest form
+    /// that reads back as the same value.
+    pub float_precision: Option<u32>,
+}
+
+const DEFAULT_EXTENSION: &str = "rn";
+const DEFAULT_OPT_LEVEL: u8 = 2;
+pub const DEFAULT_LINKER: &str = "cc";
+
+/// Codegen settings for one target, see [`Config::target_settings`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetSettings {
+    pub triple: String,
+    pub opt_level: u8,
+    pub linker: String,
+    pub features: Vec<String>,
+    pub bounds_checks: bool,
+    pub float_precision: Option<u32>,
+}
+
+impl TargetSettings {
+    /// The default settings, as used when Rune.toml sets nothing.
+    pub fn with_defaults(triple: &str) -> Self {
+        Self {
+            triple: triple.to_string(),
+            opt_level: DEFAULT_OPT_LEVEL,
+            linker: DEFAULT_LINKER.to_string(),
+            features: Vec::new(),
+            bounds_checks: true,
+            float_precision: None,
+        }
+    }
+}
+
+impl BuildConfig {
+    /// The extensions of the files to build, rejecting empty lists and
+    /// extensions that could never match a file.
+    pub fn extensions(&self) -> Result<Vec<String>, CliError> {
+        let Some(extensions) = &self.extensions else {
+            return Ok(vec![DEFAULT_EXTENSION.to_string()]);
+        };
+
+        if extensions.is_empty() {
+            return Err(CliError::InvalidConfig(
+                "`extensions` in `[build]` is empty, no source files would be found".to_string(),
+            ));
+        }
+        if let Some(extension) = extensions
+            .iter()
+            .find(|extension| extension.is_empty() || extension.contains(['.', '/', '\\']))
+        {
+            return Err(CliError::InvalidConfig(format!(
+                "invalid extension `{}` in `[build]`, expected a name without dots, like `{}`",
+                extension, DEFAULT_EXTENSION
+            )));
+        }
+
+        Ok(extensions.clone())
+    }
+}
+
+impl Config {
+    /// Settings for building for `triple`: its `[target.'<triple>'.build]`
+    /// section, falling back to `[build]` and then the defaults.
+    pub fn target_settings(&self, triple: &str) -> Result<TargetSettings, CliError> {
+        let base = &self.build.codegen;
+        let overrides = self
+            .target
+            .get(triple)
+            .map(|target| &target.build)
+            .cloned()
+            .unwrap_or_default();
+
+        let opt_level = overrides
+            .opt_level
+            .or(base.opt_level)
+            .unwrap_or(DEFAULT_OPT_LEVEL);
+        if opt_level > 3 {
+            return Err(CliError::InvalidConfig(format!(
+                "`opt_level` must be between 0 and 3, found {}",
+                opt_level
+            )));
+        }
+
+        Ok(TargetSettings {
+            triple: triple.to_string(),
+            opt_level,
+            linker: overrides
+                .linker
+                .or(base.linker.clone())
+                .unwrap_or(DEFAULT_LINKER.to_string()),
+            features: overrides
+                .features
+                .or(base.features.clone())
+                .unwrap_or_default(),
+            bounds_checks: overrides
+                .bounds_checks
+                .or(base.bounds_checks)
+                .unwrap_or(true),
+            float_precision: overrides.float_precision.or(base.float_precision),
+        })
+    }
+}
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+const DEFAULT_MAX_TOKENS: usize = 1_000_000;
+const DEFAULT_MAX_COMPILE_TIME_MS: u64 = 60_000;
+const DEFAULT_MAX_SOURCE_DEPTH: usize = 32;
+
+/// Resource limits applied per source file by the driver, and to the
+/// source tree as a whole.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct LimitsConfig {
+    pub max_file_size: Option<u64>,
+    pub max_tokens: Option<usize>,
+    pub max_compile_time_ms: Option<u64>,
+    /// How many directories deep source files are looked for.
+    pub max_source_depth: Option<usize>,
+}
+
+impl LimitsConfig {
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE)
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)
+    }
+
+    pub fn max_compile_time(&self) -> Duration {
+        Duration::from_millis(
+            self.max_compile_time_ms
+                .unwrap_or(DEFAULT_MAX_COMPILE_TIME_MS),
+        )
+    }
+
+    pub fn max_source_depth(&self) -> usize {
+        self.max_source_depth.unwrap_or(DEFAULT_MAX_SOURCE_DEPTH)
+    }
+}
+
+/// Style used by `rune fmt`, unset values fall back to the formatter's
+/// defaults.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct FmtConfig {
+    pub max_width: Option<usize>,
+    pub indent: Option<IndentConfig>,
+    pub trailing_semicolons: Option<bool>,
+}
+
+/// Either a number of spaces or `"tab"`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum IndentConfig {
+    Spaces(usize),
+    Style(String),
+}
+
+impl FmtConfig {
+    pub fn format_options(&self) -> Result<FormatOptions, CliError> {
+        let defaults = FormatOptions::default();
+
+        let indent = match &self.indent {
+            None => defaults.indent,
+            Some(IndentConfig::Spaces(width)) => Indent::Spaces(*width),
+            Some(IndentConfig::Style(style)) if style == "tab" => Indent::Tabs,
+            Some(IndentConfig::Style(style)) => {
+                return Err(CliError::InvalidConfig(format!(
+                    "`fmt.indent` must be a number of spaces or \"tab\", found \"{}\"",
+                    style
+                )));
+            }
+        };
+
+        Ok(FormatOptions {
+            max_width: self.max_width.unwrap_or(defaults.max_width),
+            indent,
+            trailing_semicolons: self
+                .trailing_semicolons
+                .unwrap_or(defaults.trailing_semicolons),
+        })
+    }
+}
+
+/// How a lint is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The `[lints]` section: `deny_warnings = true` turns every warning into
+/// an error, and `<lint> = "allow" | "warn" | "deny"` sets a single lint,
+/// taking precedence over `deny_warnings`.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct LintsConfig {
+    #[serde(default)]
+    pub deny_warnings: bool,
+    #[serde(flatten)]
+    pub levels: HashMap<String, LintLevel>,
+}
+
+impl LintsConfig {
+    pub fn level(&self, lint: &str) -> LintLevel {
+        match self.levels.get(lint) {
+            Some(level) => *level,
+            None if self.deny_warnings => LintLevel::Deny,
+            None => LintLevel::Warn,
+        }
+    }
+
+    /// `deny-warnings` and the level of each lint set, sorted by name, for
+    /// the build fingerprint.
+    pub fn fingerprint_flags(&self) -> Vec<String> {
+        let mut levels: Vec<String> = self
+            .levels
+            .iter()
+            .map(|(lint, level)| {
+                let level = match level {
+                    LintLevel::Allow => "allow",
+                    LintLevel::Warn => "warn",
+                    LintLevel::Deny => "deny",
+                };
+                format!("lint:{}={}", lint, level)
+            })
+            .collect();
+        levels.sort();
+
+        let mut flags = Vec::new();
+        if self.deny_warnings {
+            flags.push("deny-warnings".to_string());
+        }
+        flags.extend(levels);
+        flags
+    }
+
+    /// Rejects levels set for lints that do not exist.
+    pub fn validate(&self) -> Result<(), CliError> {
+        match self
+            .levels
+            .keys()
+            .find(|lint| !LINTS.contains(&lint.as_str()))
+        {
+            Some(lint) => Err(CliError::InvalidConfig(format!(
+                "unknown lint `{}` in `[lints]`, expected one of {}",
+                lint,
+                LINTS.join(", ")
+            ))),
+            None => Ok(()),
+        }
+    }
 }
 
 pub fn get_config(current_directory: &Path) -> Result<Config, CliError> {
@@ -38,26 +325,207 @@ pub fn get_config(current_directory: &Path) -> Result<Config, CliError> {
     Ok(config)
 }
 
-pub fn find_target_files(dir: &PathBuf, extension: &str) -> Vec<PathBuf> {
+/// Finds the source files under `dir` with one of `extensions`.
+///
+/// The order is stable across platforms and runs: entries are visited
+/// sorted by path, depth first, and the build, the diagnostics and the
+/// whole-program checks all follow it. Symbolic links are followed, but
+/// every directory is walked at most once, so links forming a cycle end
+/// the walk instead of looping. Directories nested more than `max_depth`
+/// levels below `dir` are an error. A missing `dir` has no files.
+pub fn find_target_files(
+    dir: &Path,
+    extensions: &[String],
+    max_depth: usize,
+) -> Result<Vec<PathBuf>, CliError> {
+    let mut files = Vec::new();
     if dir.is_dir() {
-        let mut files = Vec::new();
-        for entry in fs::read_dir(dir)
-            .map_err(|_| CliError::IOError("Failed to read directory".to_string()))
-            .unwrap()
+        let mut walk = SourceWalk {
+            extensions,
+            max_depth,
+            visited: HashSet::new(),
+            files: &mut files,
+        };
+        walk.visit(dir, 0)?;
+    }
+    Ok(files)
+}
+
+struct SourceWalk<'a> {
+    extensions: &'a [String],
+    max_depth: usize,
+    /// Canonical paths of the directories walked so far
+    visited: HashSet<PathBuf>,
+    files: &'a mut Vec<PathBuf>,
+}
+
+impl SourceWalk<'_> {
+    fn visit(&mut self, dir: &Path, depth: usize) -> Result<(), CliError> {
+        let read_error =
+            |e: io::Error| CliError::IOError(format!("Failed to read `{}`: {}", dir.display(), e));
+
+        if !self
+            .visited
+            .insert(fs::canonicalize(dir).map_err(read_error)?)
         {
-            let entry = entry
-                .map_err(|_| CliError::IOError("Failed to read directory entry".to_string()))
-                .unwrap();
-
-            let path = entry.path();
-            if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
-                files.push(path);
-            } else if path.is_dir() {
-                files.extend(find_target_files(&path, extension));
+            return Ok(());
+        }
+        if depth > self.max_depth {
+            return Err(CliError::LimitExceeded(format!(
+                "`{}` is nested more than {} directories deep, the maximum is `max_source_depth`",
+                dir.display(),
+                self.max_depth
+            )));
+        }
+
+        let mut entries = fs::read_dir(dir)
+            .map_err(read_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error)?;
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                self.visit(&path, depth + 1)?;
+            } else if path.is_file()
+                && path.extension().is_some_and(|ext| {
+                    self.extensions
+                        .iter()
+                        .any(|extension| ext == extension.as_str())
+                })
+            {
+                self.files.push(path);
             }
         }
-        files
-    } else {
-        Vec::new()
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_sections_override_build() {
+        let config: Config = from_str(
+            r#"
+            title = "demo"
+            version = "0.1.0"
+
+            [build]
+            opt_level = 3
+            features = ["+sse4.2"]
+
+            [target.'wasm32-unknown-unknown'.build]
+            opt_level = 1
+            linker = "wasm-ld"
+            bounds_checks = false
+            float_precision = 2
+            "#,
+        )
+        .unwrap();
+
+        let native = config.target_settings("x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(native.opt_level, 3);
+        assert_eq!(native.linker, "cc");
+        assert_eq!(native.features, ["+sse4.2"]);
+        assert!(native.bounds_checks);
+        assert_eq!(native.float_precision, None);
+
+        let wasm = config.target_settings("wasm32-unknown-unknown").unwrap();
+        assert_eq!(wasm.opt_level, 1);
+        assert_eq!(wasm.linker, "wasm-ld");
+        assert_eq!(wasm.features, ["+sse4.2"]);
+        assert!(!wasm.bounds_checks);
+        assert_eq!(wasm.float_precision, Some(2));
+    }
+
+    #[test]
+    fn lint_levels_override_deny_warnings() {
+        let lints = |section: &str| {
+            from_str::<Config>(&format!(
+                "title = \"demo\"\nversion = \"0.1.0\"\n[build]\n[lints]\n{}",
+                section
+            ))
+            .unwrap()
+            .lints
+        };
+
+        let lints_config = lints("deny_warnings = true\nshadowed_variable = \"allow\"");
+        assert!(lints_config.validate().is_ok());
+        assert_eq!(lints_config.level("shadowed_variable"), LintLevel::Allow);
+        assert_eq!(lints_config.level("unreachable_code"), LintLevel::Deny);
+        assert_eq!(lints("").level("unreachable_code"), LintLevel::Warn);
+        assert_eq!(
+            lints_config.fingerprint_flags(),
+            ["deny-warnings", "lint:shadowed_variable=allow"]
+        );
+        assert!(lints("").fingerprint_flags().is_empty());
+
+        assert!(lints("shadowing = \"deny\"").validate().is_err());
+    }
+
+    #[test]
+    fn extensions_default_to_rn() {
+        let build = |section: &str| {
+            from_str::<Config>(&format!(
+                "title = \"demo\"\nversion = \"0.1.0\"\n[build]\n{}",
+                section
+            ))
+            .unwrap()
+            .build
+        };
+
+        assert_eq!(build("").extensions().unwrap(), ["rn"]);
+        assert_eq!(
+            build("extensions = [\"rn\", \"rune\"]")
+                .extensions()
+                .unwrap(),
+            ["rn", "rune"]
+        );
+        assert!(build("extensions = []").extensions().is_err());
+        assert!(build("extensions = [\".rn\"]").extensions().is_err());
+    }
+
+    #[test]
+    fn target_files_match_any_extension() {
+        let dir = std::env::temp_dir().join(format!("rune-extensions-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        for file in ["main.rn", "nested/util.rune", "notes.txt", "rn"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+
+        let extensions = ["rn".to_string(), "rune".to_string()];
+        let files = find_target_files(&dir, &extensions, 8).unwrap();
+        assert_eq!(files, [dir.join("main.rn"), dir.join("nested/util.rune")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn target_files_are_sorted_and_survive_link_cycles() {
+        let dir = std::env::temp_dir().join(format!("rune-walk-{}", std::process::id()));
+        fs::create_dir_all(dir.join("b/c")).unwrap();
+        for file in ["z.rn", "a.rn", "b/m.rn", "b/c/d.rn"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        std::os::unix::fs::symlink(&dir, dir.join("b/c/loop")).unwrap();
+
+        let extensions = ["rn".to_string()];
+        let files = find_target_files(&dir, &extensions, 8).unwrap();
+        assert_eq!(
+            files,
+            [
+                dir.join("a.rn"),
+                dir.join("b/c/d.rn"),
+                dir.join("b/m.rn"),
+                dir.join("z.rn")
+            ]
+        );
+        assert!(find_target_files(&dir, &extensions, 1).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
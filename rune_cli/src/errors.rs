@@ -1,10 +1,61 @@
 use std::fmt::{self, Display};
 
-#[derive(PartialEq)]
+use rune_core::errors::CodeGenError;
+use rune_core::prelude::ParserError;
+
+#[derive(Clone, PartialEq)]
 pub enum CliError {
     InternalError(String),
     InvalidConfig(String),
     IOError(String),
+    CompileError(String),
+    LinkError(String),
+    InvalidUtf8(String, usize),
+    LimitExceeded(String),
+    ArtifactCollision(String, String),
+    /// A parse error, in the source file at `file` once known.
+    Parse {
+        file: Option<String>,
+        error: ParserError,
+    },
+    /// A codegen error, in the module compiled from `file` once known.
+    CodeGen {
+        file: Option<String>,
+        error: Box<CodeGenError>,
+    },
+}
+
+impl CliError {
+    /// Attributes a parse or codegen error to the source file at `file`.
+    /// Other errors already say what they are about and are unchanged.
+    pub fn in_file(self, file: &str) -> Self {
+        match self {
+            CliError::Parse { error, .. } => CliError::Parse {
+                file: Some(file.to_string()),
+                error,
+            },
+            CliError::CodeGen { error, .. } => CliError::CodeGen {
+                file: Some(file.to_string()),
+                error,
+            },
+            error => error,
+        }
+    }
+}
+
+impl From<ParserError> for CliError {
+    fn from(error: ParserError) -> Self {
+        CliError::Parse { file: None, error }
+    }
+}
+
+impl From<CodeGenError> for CliError {
+    fn from(error: CodeGenError) -> Self {
+        CliError::CodeGen {
+            file: None,
+            error: Box::new(error),
+        }
+    }
 }
 
 impl fmt::Debug for CliError {
@@ -24,5 +75,70 @@ pub fn get_print_error(error: &CliError) -> String {
         CliError::InternalError(msg) => format!("(C000): Internal error: {}", msg),
         CliError::InvalidConfig(msg) => format!("(C001): Invalid configuration: {}", msg),
         CliError::IOError(msg) => format!("(C002): IO error: {}", msg),
+        CliError::CompileError(msg) => format!("(C003): Compile error: {}", msg),
+        CliError::LinkError(msg) => format!("(C004): Link error: {}", msg),
+        CliError::InvalidUtf8(path, offset) => format!(
+            "(C005): Invalid UTF-8 in `{}` at byte offset {} (use --latin1-fallback to decode as Latin-1)",
+            path, offset
+        ),
+        CliError::LimitExceeded(msg) => format!("(C006): Limit exceeded: {}", msg),
+        CliError::ArtifactCollision(first, second) => format!(
+            "(C007): `{}` and `{}` would overwrite each other's build artifacts, rename one of them",
+            first, second
+        ),
+        // Parser and codegen errors carry their own code and location,
+        // `file:line:column: (P000) ...`
+        CliError::Parse {
+            file: Some(file),
+            error: error @ ParserError::Located { .. },
+        } => format!("{}:{}", file, error),
+        CliError::Parse {
+            file: Some(file),
+            error,
+        } => format!("{}: {}", file, error),
+        CliError::Parse { file: None, error } => error.to_string(),
+        CliError::CodeGen {
+            file: Some(file),
+            error,
+        } if matches!(**error, CodeGenError::Located { .. }) => format!("{}:{}", file, error),
+        CliError::CodeGen {
+            file: Some(file),
+            error,
+        } => format!("{}: {}", file, error),
+        CliError::CodeGen { file: None, error } => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_core::prelude::Span;
+
+    #[test]
+    fn wrapped_errors_keep_their_code_and_gain_the_file() {
+        let parse = CliError::from(ParserError::Located {
+            line: 2,
+            column: 5,
+            error: Box::new(ParserError::UnexpectedEndOfInput),
+        });
+        assert!(parse.to_string().starts_with("2:5: (P"));
+        assert!(
+            parse
+                .in_file("main.rn")
+                .to_string()
+                .starts_with("main.rn:2:5: (P")
+        );
+
+        let codegen = CliError::from(CodeGenError::NoFunction).in_file("main.rn");
+        assert_eq!(codegen.to_string(), "main.rn: (C004): No function found");
+
+        let located = CliError::from(CodeGenError::NoFunction.at("\n  x", Span::new(3, 4)));
+        assert_eq!(
+            located.in_file("main.rn").to_string(),
+            "main.rn:2:3: (C004): No function found"
+        );
+
+        let io = CliError::IOError("disk full".into());
+        assert_eq!(io.clone().in_file("main.rn"), io);
     }
 }
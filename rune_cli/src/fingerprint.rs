@@ -0,0 +1,142 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{artifact::write_atomically, errors::CliError};
+
+pub const FINGERPRINT_FILE: &str = ".fingerprint.json";
+
+/// Snapshot of the inputs of the last successful build, stored in
+/// `target/.fingerprint.json`.
+///
+/// A file whose hash is unchanged can be reused as long as the compiler
+/// version, flags and target triple are the same as in the previous build,
+/// and its executable is still the one that build produced.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Fingerprint {
+    pub compiler_version: String,
+    pub flags: Vec<String>,
+    pub target_triple: String,
+    /// Source file name to content hash.
+    pub files: BTreeMap<String, String>,
+    /// Source file name to the hash of the executable built from it.
+    #[serde(default)]
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl Fingerprint {
+    pub fn new(flags: Vec<String>, target_triple: String) -> Self {
+        Self {
+            compiler_version: env!("CARGO_PKG_VERSION").to_string(),
+            flags,
+            target_triple,
+            files: BTreeMap::new(),
+            artifacts: BTreeMap::new(),
+        }
+    }
+
+    /// Loads the fingerprint from `target_dir`. A missing or unreadable
+    /// fingerprint just means everything gets rebuilt.
+    pub fn load(target_dir: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(target_dir.join(FINGERPRINT_FILE)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self, target_dir: &Path) -> Result<(), CliError> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|err| CliError::InternalError(err.to_string()))?;
+
+        write_atomically(&target_dir.join(FINGERPRINT_FILE), contents.as_bytes())
+            .map_err(|e| CliError::IOError(format!("Failed to write fingerprint: {}", e)))
+    }
+
+    /// Whether artifacts built under `other` can be reused by this build.
+    pub fn is_compatible(&self, other: &Fingerprint) -> bool {
+        self.compiler_version == other.compiler_version
+            && self.flags == other.flags
+            && self.target_triple == other.target_triple
+    }
+
+    pub fn is_fresh(&self, previous: &Fingerprint, file: &str, hash: &str) -> bool {
+        self.is_compatible(previous) && previous.files.get(file).is_some_and(|h| h == hash)
+    }
+
+    /// Records the executable built from `file`, see [`Fingerprint::artifact_matches`].
+    pub fn record_artifact(&mut self, file: &str, artifact: &Path) -> Result<(), CliError> {
+        let contents = fs::read(artifact).map_err(|e| {
+            CliError::IOError(format!("Failed to read `{}`: {}", artifact.display(), e))
+        })?;
+        self.artifacts
+            .insert(file.to_string(), hash_source(&contents));
+        Ok(())
+    }
+
+    /// Whether `artifact` is the complete executable recorded for `file`,
+    /// and not missing, truncated or replaced since.
+    pub fn artifact_matches(&self, file: &str, artifact: &Path) -> bool {
+        self.artifacts.get(file).is_some_and(|recorded| {
+            fs::read(artifact).is_ok_and(|contents| hash_source(&contents) == *recorded)
+        })
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of `bytes`, as hex.
+///
+/// `DefaultHasher` is not guaranteed to be stable across Rust releases, so
+/// it can't be used for anything written to disk.
+pub fn hash_source(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_is_stable() {
+        assert_eq!(hash_source(b""), "cbf29ce484222325");
+        assert_eq!(hash_source(b"a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn fresh_only_with_same_flags_and_hash() {
+        let mut previous = Fingerprint::new(vec![], "x86_64-unknown-linux-gnu".into());
+        previous
+            .files
+            .insert("main.rn".into(), hash_source(b"let x = 1;"));
+
+        let current = Fingerprint::new(vec![], "x86_64-unknown-linux-gnu".into());
+        assert!(current.is_fresh(&previous, "main.rn", &hash_source(b"let x = 1;")));
+        assert!(!current.is_fresh(&previous, "main.rn", &hash_source(b"let x = 2;")));
+        assert!(!current.is_fresh(&previous, "other.rn", &hash_source(b"let x = 1;")));
+
+        let flagged = Fingerprint::new(vec!["emit=map".into()], "x86_64-unknown-linux-gnu".into());
+        assert!(!flagged.is_compatible(&previous));
+        assert!(!flagged.is_fresh(&previous, "main.rn", &hash_source(b"let x = 1;")));
+    }
+
+    #[test]
+    fn artifacts_must_match_their_recorded_hash() {
+        let dir = std::env::temp_dir().join(format!("rune-fingerprint-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("main");
+        fs::write(&binary, b"\x7fELF complete").unwrap();
+
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.record_artifact("main.rn", &binary).unwrap();
+        assert!(fingerprint.artifact_matches("main.rn", &binary));
+        assert!(!fingerprint.artifact_matches("other.rn", &binary));
+
+        fs::write(&binary, b"\x7fELF").unwrap();
+        assert!(!fingerprint.artifact_matches("main.rn", &binary));
+
+        fs::remove_file(&binary).unwrap();
+        assert!(!fingerprint.artifact_matches("main.rn", &binary));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
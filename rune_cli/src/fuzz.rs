@@ -0,0 +1,273 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use rune_core::prelude::{Expr, Parser};
+use rune_interp::interpreter::Interpreter;
+
+use crate::{
+    compile::{compile_to_object, link_executable, target_triple},
+    config::TargetSettings,
+    errors::CliError,
+};
+
+/// Generates small random Rune programs whose behaviour is fully defined,
+/// so any difference between engines points at a backend bug.
+///
+/// Programs only use integer arithmetic, comparisons, `if`/`else` and
+/// printing of string literals. Division and remainder always use a
+/// non-zero literal divisor.
+pub struct ProgramGenerator {
+    state: u64,
+    variables: Vec<String>,
+    prints: usize,
+}
+
+impl ProgramGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            // xorshift must never be seeded with zero
+            state: (seed ^ 0x9E37_79B9_7F4A_7C15).max(1),
+            variables: Vec::new(),
+            prints: 0,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    pub fn generate(&mut self) -> String {
+        self.variables.clear();
+        self.prints = 0;
+
+        let mut lines = Vec::new();
+        let count = 3 + self.below(8);
+
+        for _ in 0..count {
+            lines.push(self.top_level_statement());
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    fn top_level_statement(&mut self) -> String {
+        match self.below(4) {
+            0 if !self.variables.is_empty() => self.statement(2),
+            1 => self.statement(2),
+            _ => {
+                let value = self.int_expr(3);
+                let name = format!("v{}", self.variables.len());
+                self.variables.push(name.clone());
+                format!("let mut {} = {};", name, value)
+            }
+        }
+    }
+
+    /// Statements that are valid inside branches: no declarations, so the
+    /// set of live variables is the same whichever branch runs.
+    fn statement(&mut self, depth: usize) -> String {
+        match self.below(3) {
+            0 if depth > 0 => {
+                let condition = self.condition();
+                let then_branch = self.branch(depth - 1);
+                let else_branch = self.branch(depth - 1);
+                format!(
+                    "if {} {{ {} }} else {{ {} }}",
+                    condition, then_branch, else_branch
+                )
+            }
+            1 if !self.variables.is_empty() => {
+                let index = self.below(self.variables.len() as u64) as usize;
+                let name = self.variables[index].clone();
+                let value = self.int_expr(3);
+                format!("{} = {};", name, value)
+            }
+            _ => {
+                self.prints += 1;
+                format!("print(\"p{}\");", self.prints)
+            }
+        }
+    }
+
+    fn branch(&mut self, depth: usize) -> String {
+        let count = 1 + self.below(3);
+        (0..count)
+            .map(|_| self.statement(depth))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn condition(&mut self) -> String {
+        let operators = ["<", ">", "<=", ">=", "==", "!="];
+        let operator = operators[self.below(operators.len() as u64) as usize];
+        format!("{} {} {}", self.int_expr(2), operator, self.int_expr(2))
+    }
+
+    fn int_expr(&mut self, depth: usize) -> String {
+        if depth == 0 || self.below(3) == 0 {
+            return self.int_atom();
+        }
+
+        match self.below(5) {
+            0 => format!(
+                "({} + {})",
+                self.int_expr(depth - 1),
+                self.int_expr(depth - 1)
+            ),
+            1 => format!(
+                "({} - {})",
+                self.int_expr(depth - 1),
+                self.int_expr(depth - 1)
+            ),
+            2 => format!(
+                "({} * {})",
+                self.int_expr(depth - 1),
+                self.int_expr(depth - 1)
+            ),
+            3 => format!("({} / {})", self.int_expr(depth - 1), 1 + self.below(9)),
+            _ => format!("({} % {})", self.int_expr(depth - 1), 1 + self.below(9)),
+        }
+    }
+
+    fn int_atom(&mut self) -> String {
+        if !self.variables.is_empty() && self.below(2) == 0 {
+            let index = self.below(self.variables.len() as u64) as usize;
+            self.variables[index].clone()
+        } else {
+            self.below(100).to_string()
+        }
+    }
+}
+
+/// Observable behaviour of a program run on one engine.
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub stdout: String,
+    pub exit_code: Option<i32>,
+}
+
+pub struct Divergence {
+    pub seed: u64,
+    pub program: PathBuf,
+    pub interp: Outcome,
+    pub llvm: Result<Outcome, CliError>,
+}
+
+fn parse_program(source: &str) -> Result<Vec<Expr>, CliError> {
+    let mut parser = Parser::new(source.to_string())?;
+
+    Ok(parser.parse()?)
+}
+
+pub fn run_interp(statements: &[Expr]) -> Outcome {
+    let mut interpreter = Interpreter::with_output(Vec::new());
+    let result = interpreter.run(statements);
+    let stdout = String::from_utf8_lossy(interpreter.output()).to_string();
+
+    Outcome {
+        stdout,
+        // Exit statuses are truncated to a byte, as for the compiled binary
+        exit_code: Some(match result {
+            Ok(()) => interpreter.exit_code() & 0xff,
+            Err(_) => 1,
+        }),
+    }
+}
+
+pub fn run_llvm(statements: &[Expr], work_dir: &Path, name: &str) -> Result<Outcome, CliError> {
+    let obj_path = work_dir.join(format!("{}.o", name));
+    let bin_path = work_dir.join(name);
+
+    // The binary runs here, so it is always built for the host
+    let settings = TargetSettings::with_defaults(&target_triple());
+    compile_to_object(statements, name, &obj_path, false, &settings)?;
+    link_executable(&obj_path, &bin_path, &settings.linker)?;
+
+    let output = Command::new(&bin_path)
+        .output()
+        .map_err(|e| CliError::IOError(format!("Failed to execute `{}`: {}", name, e)))?;
+
+    Ok(Outcome {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        exit_code: output.status.code(),
+    })
+}
+
+/// Runs the program generated from `seed` on both engines and returns a
+/// [`Divergence`] if their observable behaviour differs.
+pub fn diff_seed(seed: u64, work_dir: &Path) -> Result<Option<Divergence>, CliError> {
+    let source = ProgramGenerator::new(seed).generate();
+    let statements = parse_program(&source)?;
+
+    let name = format!("fuzz_{}", seed);
+    let interp = run_interp(&statements);
+    let llvm = run_llvm(&statements, work_dir, &name);
+
+    if llvm.as_ref().is_ok_and(|llvm| *llvm == interp) {
+        return Ok(None);
+    }
+
+    let program = work_dir.join(format!("{}.rn", name));
+    fs::write(&program, source)
+        .map_err(|e| CliError::IOError(format!("Failed to write program: {}", e)))?;
+
+    Ok(Some(Divergence {
+        seed,
+        program,
+        interp,
+        llvm,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generator_is_deterministic() {
+        let first = ProgramGenerator::new(42).generate();
+        let second = ProgramGenerator::new(42).generate();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn generated_programs_run_on_interpreter() {
+        for seed in 0..200 {
+            let source = ProgramGenerator::new(seed).generate();
+            let statements = parse_program(&source).expect("Expected statements");
+
+            let outcome = run_interp(&statements);
+            assert_eq!(outcome.exit_code, Some(0), "seed {}:\n{}", seed, source);
+        }
+    }
+
+    #[test]
+    fn engines_agree_on_generated_programs() {
+        let work_dir = std::env::temp_dir().join("rune_fuzz_diff_test");
+        fs::create_dir_all(&work_dir).unwrap();
+
+        for seed in 0..20 {
+            let divergence = diff_seed(seed, &work_dir).expect("Expected program to compile");
+
+            if let Some(divergence) = divergence {
+                panic!(
+                    "seed {} diverged: interp {:?}, llvm {:?}",
+                    divergence.seed, divergence.interp, divergence.llvm
+                );
+            }
+        }
+    }
+}
@@ -1,265 +1,703 @@
 use std::{
-    fs::File,
-    io::Write,
-    path::Path,
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
     process::{self, Command},
-    time::Instant,
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
-use clap::Parser;
-use inkwell::{
-    OptimizationLevel,
-    context::Context,
-    targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine},
-};
+use clap::Parser as _;
 use owo_colors::OwoColorize;
-use rune_parser::parser;
+use rune_core::prelude::{Parser, ParserError, format_source};
+use rune_core::stats::ModuleStats;
+use rune_interp::interpreter::Interpreter;
 
 use crate::{
-    cli::{
-        Cli, CliCommand, make_folder, print_error, print_section, print_value, print_warning,
-        read_file,
+    artifact::{artifact_stem, built_artifacts, check_collisions, with_suffix},
+    cli::{Cli, CliCommand, Emit, Engine, check_file_size, make_folder, read_file},
+    compile::{
+        BuildCompiler, CompileJob, enable_vectorization_remarks, explain_ir, link_executable,
+        target_triple, write_ir_map,
     },
-    config::find_target_files,
+    config::{FmtConfig, LimitsConfig, LintsConfig, TargetSettings, find_target_files},
     errors::CliError,
+    fingerprint::{Fingerprint, hash_source},
+    memory::{AllocationSnapshot, CountingAllocator, format_bytes, peak_rss},
+    program::{ParsedSource, Program, ProgramFile},
+    repl::Repl,
+    reporter::{LogLevel, Reporter, Severity},
+    size::SizeReport,
 };
 
+mod artifact;
 mod cli;
+mod compile;
 mod config;
 mod errors;
+mod fingerprint;
+mod fuzz;
+mod memory;
+mod program;
+mod repl;
+mod reporter;
+mod size;
 
-const DEFAULT_EXTENSION: &str = "rn";
-
-#[derive(Debug, PartialEq)]
-enum LogLevel {
-    Verbose,
-    Quiet,
-    Default,
-}
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn main() {
     let cli = Cli::parse();
 
     let log_level = match (cli.quiet, cli.verbose) {
-        (true, true) => {
-            print_warning("quiet and verbose flags passed, using verbose", 0);
-            LogLevel::Verbose
-        }
+        (true, true) => LogLevel::Verbose,
         (true, false) => LogLevel::Quiet,
         (false, true) => LogLevel::Verbose,
         (false, false) => LogLevel::Default,
     };
 
-    let current_dir = cli::get_current_directory();
+    let mut reporter = Reporter::new(log_level);
 
-    if current_dir.is_err() {
-        let err = current_dir.unwrap_err();
-        print_error(err.to_string().as_str(), 0);
-        process::exit(1);
+    if cli.quiet && cli.verbose {
+        reporter.warning("quiet and verbose flags passed, using verbose", 0);
     }
 
-    let current_dir = current_dir.unwrap();
+    let current_dir = reporter.unwrap_or_exit(cli::get_current_directory());
+
+    let options = BuildOptions {
+        latin1_fallback: cli.latin1_fallback,
+        emit_map: cli.verbose || cli.emit.contains(&Emit::Map),
+        explain_ir: cli.explain_ir,
+        report_vectorization: cli.report_vectorization,
+        deny_warnings: cli.deny_warnings,
+        stats: cli.stats,
+        trace_parse: cli.trace_parse,
+        target: cli.target,
+    };
 
     match cli.command {
-        CliCommand::Build => build(&current_dir, log_level),
+        CliCommand::Build => {
+            build(&current_dir, &mut reporter, &options);
+        }
+        CliCommand::Run { engine } => run(&current_dir, &mut reporter, engine, &options),
+        CliCommand::Size => size(&current_dir, &mut reporter, &options),
+        CliCommand::Artifacts { json } => artifacts(&current_dir, &mut reporter, json),
+        CliCommand::Repl => reporter.unwrap_or_exit(Repl::new().run()),
+        CliCommand::Fmt { check } => fmt(&current_dir, &mut reporter, check, &options),
+        CliCommand::FuzzDiff { iterations, seed } => {
+            fuzz_diff(&current_dir, &mut reporter, iterations, seed)
+        }
     }
+
+    reporter.report_and_exit_if_errors();
 }
 
-fn build(current_dir: &Path, log_level: LogLevel) {
-    println!("{} `build`", "Running".green().bold());
+/// Driver settings taken from the command line rather than `Rune.toml`.
+struct BuildOptions {
+    latin1_fallback: bool,
+    emit_map: bool,
+    explain_ir: bool,
+    report_vectorization: bool,
+    deny_warnings: bool,
+    stats: bool,
+    trace_parse: bool,
+    target: Option<String>,
+}
 
-    let config = config::get_config(current_dir);
+impl BuildOptions {
+    /// Flags that change the build output, recorded in the fingerprint.
+    /// Lint levels are among them, as warnings are only reported when a
+    /// file is compiled.
+    fn fingerprint_flags(&self, settings: &TargetSettings, lints: &LintsConfig) -> Vec<String> {
+        let mut flags = vec![
+            format!("opt-level={}", settings.opt_level),
+            format!("linker={}", settings.linker),
+        ];
+        if !settings.features.is_empty() {
+            flags.push(format!("features={}", settings.features.join(",")));
+        }
+        if !settings.bounds_checks {
+            flags.push("no-bounds-checks".to_string());
+        }
+        if let Some(precision) = settings.float_precision {
+            flags.push(format!("float-precision={}", precision));
+        }
+        if self.latin1_fallback {
+            flags.push("latin1-fallback".to_string());
+        }
+        if self.emit_map {
+            flags.push("emit=map".to_string());
+        }
+        flags.extend(lints.fingerprint_flags());
+        flags
+    }
 
-    if config.is_err() {
-        let err = config.unwrap_err();
-        print_error(err.to_string().as_str(), 0);
-        process::exit(1);
+    /// Where `--trace-parse` writes the trace of the source building the
+    /// artifact at `stem`.
+    fn trace_path(&self, target_dir: &Path, stem: &Path) -> Option<PathBuf> {
+        self.trace_parse
+            .then(|| with_suffix(&target_dir.join(stem), ".trace"))
     }
+}
 
-    let config = config.unwrap();
+struct Project {
+    source_dir: PathBuf,
+    target_dir: PathBuf,
+    targets: Vec<PathBuf>,
+    limits: LimitsConfig,
+    fmt: FmtConfig,
+    settings: TargetSettings,
+    lints: LintsConfig,
+}
+
+fn load_project(current_dir: &Path, reporter: &mut Reporter, options: &BuildOptions) -> Project {
+    let config = reporter.unwrap_or_exit(config::get_config(current_dir));
+
+    let triple = options.target.clone().unwrap_or_else(target_triple);
+    let settings = reporter.unwrap_or_exit(config.target_settings(&triple));
 
-    if log_level == LogLevel::Verbose {
-        print_section("Config", 4);
-        print_value("Title", config.title.as_str(), 5);
-        print_value("Version", config.version.as_str(), 5);
+    let mut lints = config.lints;
+    lints.deny_warnings |= options.deny_warnings;
+    reporter.unwrap_or_exit(lints.validate());
+    reporter.set_lints(lints.clone());
+
+    if reporter.is_verbose() {
+        reporter.section("Config", 4);
+        reporter.value("Title", config.title.as_str(), 5);
+        reporter.value("Version", config.version.as_str(), 5);
     }
 
+    let extensions = reporter.unwrap_or_exit(config.build.extensions());
     let source_dir = config.build.source_dir.unwrap_or("src".into());
     let target_dir = config.build.target_dir.unwrap_or("target".into());
 
-    if let Err(err) = cli::folder_exists(current_dir, source_dir.as_str()) {
-        print_error(err.to_string().as_str(), 0);
-        process::exit(1);
-    }
+    reporter.unwrap_or_exit(cli::folder_exists(current_dir, source_dir.as_str()));
 
     if cli::folder_exists(current_dir, target_dir.as_str()).is_err() {
-        let result = make_folder(current_dir, "target");
-        if result.is_err() {
-            print_error(result.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
-        }
+        reporter.unwrap_or_exit(make_folder(current_dir, "target"));
     }
 
-    let source_dir = &current_dir.join(source_dir);
-    let target_dir = &current_dir.join(target_dir);
+    let source_dir = current_dir.join(source_dir);
+    let target_dir = current_dir.join(target_dir);
 
-    let targets = find_target_files(source_dir, DEFAULT_EXTENSION);
+    let targets = reporter.unwrap_or_exit(find_target_files(
+        &source_dir,
+        &extensions,
+        config.limits.max_source_depth(),
+    ));
 
     if targets.is_empty() {
-        print_warning("No target files found.", 0);
+        reporter.warning("No target files found.", 0);
         process::exit(1);
     }
 
-    println!("{} {} target(s).", "Found".bold().green(), targets.len());
+    reporter.status(
+        "Found".bold().green(),
+        &format!("{} target(s).", targets.len()),
+    );
+
+    Project {
+        source_dir,
+        target_dir,
+        targets,
+        limits: config.limits,
+        fmt: config.fmt,
+        settings,
+        lints,
+    }
+}
 
-    let start = Instant::now();
-    for target_file in targets {
-        let source = read_file(&source_dir.join(&target_file));
+fn parse_source(
+    source_file: &Path,
+    reporter: &mut Reporter,
+    latin1_fallback: bool,
+    limits: &LimitsConfig,
+    trace_path: Option<PathBuf>,
+) -> ParsedSource {
+    reporter.unwrap_or_exit(check_file_size(source_file, limits.max_file_size()));
+
+    let source = reporter.unwrap_or_exit(read_file(source_file, latin1_fallback));
+
+    let file = source_file.display().to_string();
+    let located = |err: ParserError| CliError::from(err).in_file(&file);
+
+    let mut parser = reporter.unwrap_or_exit(Parser::new(source.clone()).map_err(located));
+
+    if parser.token_count() > limits.max_tokens() {
+        reporter.exit_with_error(CliError::LimitExceeded(format!(
+            "`{}` has {} tokens, the maximum is {}",
+            source_file.display(),
+            parser.token_count(),
+            limits.max_tokens()
+        )));
+    }
 
-        if source.is_err() {
-            print_error(source.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
-        }
+    if trace_path.is_some() {
+        parser.enable_trace();
+    }
+    let statements = parser.parse().map_err(located);
+
+    // Written before reporting parse errors, which is when it's wanted most
+    if let (Some(trace_path), Some(trace)) = (trace_path, parser.trace()) {
+        let written = trace_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&trace_path, trace.to_string()))
+            .map_err(|e| {
+                CliError::IOError(format!(
+                    "Failed to write parse trace `{}`: {}",
+                    trace_path.display(),
+                    e
+                ))
+            });
+        reporter.unwrap_or_exit(written);
+    }
 
-        let source = source.unwrap();
+    let statements = reporter.unwrap_or_exit(statements);
+
+    ParsedSource {
+        source,
+        statements,
+        spans: parser.statement_spans().to_vec(),
+        ids: parser.statement_ids().to_vec(),
+    }
+}
 
-        let context = Context::create();
-        let mut codegen = rune_core::codegen::CodeGen::new(&context, source.as_str());
+fn run(current_dir: &Path, reporter: &mut Reporter, engine: Engine, options: &BuildOptions) {
+    match engine {
+        Engine::Llvm => {
+            let binaries = build(current_dir, reporter, options);
 
-        let parser = parser::Parser::new(source);
+            for binary in binaries {
+                let status = Command::new(&binary).status().map_err(|e| {
+                    CliError::IOError(format!("Failed to execute `{}`: {}", binary.display(), e))
+                });
+                let status = reporter.unwrap_or_exit(status);
 
-        if parser.is_err() {
-            print_error(parser.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
+                if !status.success() {
+                    process::exit(status.code().unwrap_or(1));
+                }
+            }
         }
+        Engine::Interp => {
+            reporter.status("Running".green().bold(), "`run` (interp)");
+
+            let project = load_project(current_dir, reporter, options);
+
+            for target_file in project.targets {
+                let stem = artifact_stem(&project.source_dir, &target_file);
+                let parsed = parse_source(
+                    &project.source_dir.join(&target_file),
+                    reporter,
+                    options.latin1_fallback,
+                    &project.limits,
+                    options.trace_path(&project.target_dir, &stem),
+                );
 
-        let mut parser = parser.unwrap();
+                let mut interpreter = Interpreter::new();
+                if let Some(precision) = project.settings.float_precision {
+                    interpreter = interpreter.with_float_precision(precision as usize);
+                }
+                reporter.unwrap_or_exit(interpreter.run(&parsed.statements));
 
-        let statements = parser.parse();
+                if interpreter.exit_code() != 0 {
+                    process::exit(interpreter.exit_code());
+                }
+            }
+        }
+    }
+}
+
+fn size(current_dir: &Path, reporter: &mut Reporter, options: &BuildOptions) {
+    let binaries = build(current_dir, reporter, options);
+
+    for binary in binaries {
+        let report = reporter.unwrap_or_exit(SizeReport::read(&binary));
+        // The object file holds only what was compiled from the source, the
+        // rest of the binary is the C runtime and startup code
+        let object = reporter.unwrap_or_exit(SizeReport::read(&with_suffix(&binary, ".o")));
+        let compiled: HashSet<String> = object
+            .functions
+            .iter()
+            .map(|function| function.name.clone())
+            .collect();
+        let code = report.code_size(&compiled);
+
+        let name = binary.strip_prefix(current_dir).unwrap_or(&binary);
+        reporter.section(&format!("Size `{}`", name.display()), 4);
+        reporter.value("text", &format_bytes(report.text), 5);
+        reporter.value("data", &format_bytes(report.data), 5);
+        reporter.value("bss", &format_bytes(report.bss), 5);
+        reporter.value("Code", &format_bytes(code), 5);
+        reporter.value("Strings and constants", &format_bytes(object.rodata), 5);
+        reporter.value(
+            "Runtime",
+            &format_bytes(report.text.saturating_sub(code + object.rodata)),
+            5,
+        );
 
-        if statements.is_err() {
-            print_error(statements.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
+        reporter.section("Functions", 5);
+        for function in &report.functions {
+            let origin = if compiled.contains(&function.name) {
+                ""
+            } else {
+                " (runtime)"
+            };
+            reporter.value(
+                &function.name,
+                &format!("{}{}", format_bytes(function.size), origin),
+                6,
+            );
         }
+    }
+}
 
-        let statements = statements.unwrap();
+/// Lists what the last successful build wrote, read from its fingerprint
+/// rather than by building, so the output only ever describes the target
+/// directory as it is.
+fn artifacts(current_dir: &Path, reporter: &mut Reporter, json: bool) {
+    let config = reporter.unwrap_or_exit(config::get_config(current_dir));
+    let source_dir = current_dir.join(config.build.source_dir.unwrap_or("src".into()));
+    let target_dir = current_dir.join(config.build.target_dir.unwrap_or("target".into()));
+
+    let Some(fingerprint) = Fingerprint::load(&target_dir) else {
+        reporter.exit_with_error(CliError::IOError(format!(
+            "No build found in `{}`, run `build` first",
+            target_dir.display()
+        )));
+    };
+    let artifacts = built_artifacts(&fingerprint, &source_dir, &target_dir);
 
-        let result = codegen.compile_statements(&statements);
+    if json {
+        let json = serde_json::to_string_pretty(&artifacts)
+            .map_err(|err| CliError::InternalError(err.to_string()));
+        println!("{}", reporter.unwrap_or_exit(json));
+        return;
+    }
 
-        if result.is_err() {
-            print_error(result.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
-        }
+    for artifact in &artifacts {
+        let path = artifact
+            .path
+            .strip_prefix(current_dir)
+            .unwrap_or(&artifact.path);
+        reporter.section(&format!("`{}`", path.display()), 4);
+        reporter.value("Kind", &artifact.kind.to_string(), 5);
+        reporter.value("Hash", &artifact.hash, 5);
+        reporter.value("Source", &artifact.source, 5);
+    }
+}
+
+fn fmt(current_dir: &Path, reporter: &mut Reporter, check: bool, options: &BuildOptions) {
+    reporter.status("Running".green().bold(), "`fmt`");
+
+    let project = load_project(current_dir, reporter, options);
+    let format_options = reporter.unwrap_or_exit(project.fmt.format_options());
 
-        Target::initialize_x86(&InitializationConfig::default());
-        let triple = TargetMachine::get_default_triple();
-        let target = Target::from_triple(&triple);
+    for target_file in &project.targets {
+        let source_file = project.source_dir.join(target_file);
+
+        reporter.unwrap_or_exit(check_file_size(
+            &source_file,
+            project.limits.max_file_size(),
+        ));
+        let source = reporter.unwrap_or_exit(read_file(&source_file, options.latin1_fallback));
+        let formatted = reporter.unwrap_or_exit(format_source(&source, &format_options));
+
+        if formatted == source {
+            continue;
+        }
 
-        if target.is_err() {
-            print_error(target.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
+        if check {
+            reporter.error(&format!("`{}` is not formatted", target_file.display()), 0);
+            continue;
         }
 
-        let target = target.unwrap();
-        let target_machine = target.create_target_machine(
-            &triple,
-            "generic",
-            "",
-            OptimizationLevel::Default,
-            RelocMode::PIC,
-            CodeModel::Default,
+        reporter.unwrap_or_exit(fs::write(&source_file, formatted).map_err(|e| {
+            CliError::IOError(format!(
+                "Failed to write `{}`: {}",
+                source_file.display(),
+                e
+            ))
+        }));
+        reporter.status(
+            "Formatted".bold().yellow(),
+            &format!("`{}`.", target_file.display()),
         );
+    }
+}
 
-        if target_machine.is_none() {
-            print_error("Failed to create target machine", 0);
-            process::exit(1);
+fn fuzz_diff(current_dir: &Path, reporter: &mut Reporter, iterations: u64, seed: Option<u64>) {
+    reporter.status("Running".green().bold(), "`fuzz-diff`");
+
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0)
+    });
+
+    let work_dir = current_dir.join("target").join("fuzz");
+
+    reporter.unwrap_or_exit(make_folder(current_dir, "target/fuzz"));
+
+    reporter.value("Seed", seed.to_string().as_str(), 4);
+
+    let mut divergences = 0;
+    for iteration in 0..iterations {
+        let result = fuzz::diff_seed(seed.wrapping_add(iteration), &work_dir);
+
+        if let Some(divergence) = reporter.unwrap_or_exit(result) {
+            divergences += 1;
+            reporter.error(
+                &format!(
+                    "Engines diverged on `{}` (seed {})",
+                    divergence.program.display(),
+                    divergence.seed
+                ),
+                0,
+            );
+            reporter.value("interp", &format!("{:?}", divergence.interp), 4);
+            reporter.value("llvm", &format!("{:?}", divergence.llvm), 4);
         }
+    }
 
-        let target_machine = target_machine.unwrap();
+    reporter.report_and_exit_if_errors();
 
-        let mem_buffer = target_machine.write_to_memory_buffer(&codegen.module, FileType::Object);
+    reporter.status(
+        "Checked".bold().green(),
+        &format!("{} programs, {} divergences.", iterations, divergences),
+    );
+}
 
-        if mem_buffer.is_err() {
-            print_error(mem_buffer.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
-        }
+fn build(current_dir: &Path, reporter: &mut Reporter, options: &BuildOptions) -> Vec<PathBuf> {
+    reporter.status("Running".green().bold(), "`build`");
+
+    let Project {
+        source_dir,
+        target_dir,
+        targets,
+        limits,
+        settings,
+        lints,
+        ..
+    } = load_project(current_dir, reporter, options);
+
+    let previous = Fingerprint::load(&target_dir);
+    let mut fingerprint = Fingerprint::new(
+        options.fingerprint_flags(&settings, &lints),
+        settings.triple.clone(),
+    );
+
+    if let Some(previous) = &previous
+        && !fingerprint.is_compatible(previous)
+    {
+        reporter.warning(
+            "compiler, flags or target changed since the last build, rebuilding everything",
+            0,
+        );
+    }
 
-        let mem_buffer = mem_buffer.unwrap();
+    let mut binaries = Vec::new();
 
-        let file_name = target_file.file_stem();
+    let start = Instant::now();
 
-        if file_name.is_none() {
-            print_error("Failed to get file name", 0);
-            process::exit(1);
-        }
+    // Artifacts mirror the source tree, so `a/main.rn` and `b/main.rn`
+    // don't overwrite each other's `main`
+    let stems: Vec<PathBuf> = targets
+        .iter()
+        .map(|target| artifact_stem(&source_dir, target))
+        .collect();
+    reporter.unwrap_or_exit(check_collisions(&targets, &stems));
+
+    let mut program = Program::default();
+    for (target, stem) in targets.into_iter().zip(&stems) {
+        let allocations = AllocationSnapshot::now();
+
+        let parsed = parse_source(
+            &source_dir.join(&target),
+            reporter,
+            options.latin1_fallback,
+            &limits,
+            options.trace_path(&target_dir, stem),
+        );
 
-        let file_name = file_name.unwrap().to_str();
+        program.files.push(ProgramFile {
+            target,
+            parsed,
+            allocations: allocations.elapsed(),
+        });
+    }
 
-        if file_name.is_none() {
-            print_error("Could not convert file name to string", 0);
-            process::exit(1);
-        }
+    // Report the type errors of every file before compiling any of them
+    for diagnostic in program.check() {
+        reporter.diagnostic(
+            &diagnostic.target.display().to_string(),
+            Some((diagnostic.line, diagnostic.column)),
+            Severity::Error,
+            diagnostic.error.to_string(),
+        );
+    }
+    reporter.report_and_exit_if_errors();
 
-        let file_name = file_name.unwrap();
+    if options.report_vectorization {
+        enable_vectorization_remarks();
+    }
 
-        let obj_path = target_dir.join(format!("{}.o", file_name));
-        let obj_file = File::create(&obj_path)
-            .map_err(|e| CliError::IOError(format!("Failed to create object file `{}`", e)));
+    let compiler = BuildCompiler::spawn(settings.clone(), limits.max_compile_time());
+
+    for ProgramFile {
+        target: target_file,
+        parsed,
+        allocations: load_allocations,
+    } in program.files
+    {
+        let allocations = AllocationSnapshot::now();
+
+        let stem = artifact_stem(&source_dir, &target_file);
+        let file_name = stem.display().to_string();
+        let file_name = file_name.as_str();
+
+        let bin_path = target_dir.join(&stem);
+        let obj_path = with_suffix(&bin_path, ".o");
+        if let Some(parent) = bin_path.parent() {
+            reporter.unwrap_or_exit(fs::create_dir_all(parent).map_err(|e| {
+                CliError::IOError(format!("Failed to create `{}`: {}", parent.display(), e))
+            }));
+        }
 
-        if obj_file.is_err() {
-            print_error(obj_file.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
+        let source_key = target_file.to_string_lossy().to_string();
+        let hash = hash_source(parsed.source.as_bytes());
+        fingerprint.files.insert(source_key.clone(), hash.clone());
+
+        // Reports about the compilation itself need the target recompiled
+        let wants_report = options.explain_ir || options.stats || options.report_vectorization;
+
+        if !wants_report
+            && let Some(previous) = &previous
+            && fingerprint.is_fresh(previous, &source_key, &hash)
+            && previous.artifact_matches(&source_key, &bin_path)
+        {
+            fingerprint
+                .artifacts
+                .insert(source_key.clone(), previous.artifacts[&source_key].clone());
+            reporter.status("Fresh".bold().green(), &format!("`{}`.", file_name.bold()));
+            binaries.push(bin_path);
+            continue;
         }
 
-        let mut obj_file = obj_file.unwrap();
-        let result = obj_file.write_all(mem_buffer.as_slice());
-        if result.is_err() {
-            print_error(result.err().unwrap().to_string().as_str(), 0);
-            process::exit(1);
+        let output = reporter.unwrap_or_exit(compiler.compile(CompileJob {
+            statements: parsed.statements,
+            module_name: source_key.clone(),
+            obj_path: obj_path.clone(),
+            map_statements: options.emit_map || options.explain_ir,
+            source: Some(parsed.source.clone()),
+        }));
+
+        // Other files are still compiled, so every file's warnings are
+        // reported together, but one with a denied warning isn't linked
+        let errors = reporter.error_count();
+        for (warning, location) in &output.warnings {
+            reporter.lint(&source_key, warning, *location);
+        }
+        if reporter.error_count() > errors {
+            continue;
         }
 
-        let bin_path = target_dir.join(file_name);
-
-        // Use a C compiler (like gcc or clang) to link the object file into an executable
-        let output = Command::new("cc") // common alias for the system's C compiler
-            .arg(&obj_path)
-            .arg("-o")
-            .arg(&bin_path)
-            .output();
-
-        match output {
-            Ok(output) => {
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    print_error(
-                        &format!("Linker failed with status {}:\n{}", output.status, stderr),
-                        0,
-                    );
-                    process::exit(1);
-                }
+        if let Some(statement_ir) = output.statement_ir {
+            if options.emit_map {
+                let map_path = with_suffix(&bin_path, ".map");
+                reporter.unwrap_or_exit(write_ir_map(
+                    &map_path,
+                    &parsed.source,
+                    &parsed.spans,
+                    &statement_ir,
+                ));
             }
-            Err(e) => {
-                print_error(
-                    &format!(
-                        "Failed to execute linker: {}. Is 'cc' (or 'gcc'/'clang') in your PATH?",
-                        e
-                    ),
-                    0,
+
+            if options.explain_ir {
+                print!(
+                    "{}",
+                    explain_ir(&parsed.source, &parsed.spans, &statement_ir)
                 );
-                process::exit(1);
             }
         }
 
-        println!("{} `{}`.", "Compiled".bold().yellow(), file_name.bold(),);
+        if options.stats {
+            report_stats(
+                reporter,
+                file_name,
+                &output.stats,
+                load_allocations + allocations.elapsed(),
+            );
+        }
+
+        reporter.unwrap_or_exit(link_executable(&obj_path, &bin_path, &settings.linker));
+        reporter.unwrap_or_exit(fingerprint.record_artifact(&source_key, &bin_path));
+
+        reporter.status(
+            "Compiled".bold().yellow(),
+            &format!("`{}`.", file_name.bold()),
+        );
+
+        binaries.push(bin_path);
     }
     let end = Instant::now();
+
+    reporter.report_and_exit_if_errors();
+    reporter.flush_diagnostics();
+
+    reporter.unwrap_or_exit(fingerprint.save(&target_dir));
     let duration = end - start;
 
-    if log_level == LogLevel::Verbose {
-        print_value(
+    if reporter.is_verbose() {
+        reporter.value(
             "Compile Duration",
             format!("{}ms", duration.as_millis()).as_str(),
             0,
         );
     }
+
+    binaries
+}
+
+fn report_stats(
+    reporter: &Reporter,
+    file_name: &str,
+    stats: &ModuleStats,
+    allocations: AllocationSnapshot,
+) {
+    reporter.section(&format!("Stats `{}`", file_name), 4);
+
+    for function in &stats.functions {
+        reporter.value(
+            &function.name,
+            &format!(
+                "{} blocks, {} instructions, {} bytes stack",
+                function.basic_blocks, function.instructions, function.stack_bytes
+            ),
+            5,
+        );
+    }
+
+    reporter.value(
+        "Total",
+        &format!(
+            "{} blocks, {} instructions, {} bytes stack",
+            stats.basic_blocks(),
+            stats.instructions(),
+            stats.stack_bytes()
+        ),
+        5,
+    );
+
+    // Parsing and compiling this file, the peak covers the whole build so far
+    reporter.value(
+        "Memory",
+        &format!(
+            "{} allocations, {} allocated, {} peak RSS",
+            allocations.allocations,
+            format_bytes(allocations.bytes as u64),
+            peak_rss().map_or("unknown".to_string(), format_bytes)
+        ),
+        5,
+    );
 }
@@ -0,0 +1,123 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs,
+    ops::Add,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting every allocation made through it.
+///
+/// LLVM allocates through `malloc` directly, so the counts cover the
+/// driver, parser and AST but not the IR itself, which only shows up in the
+/// peak RSS.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+/// Allocation counters at some point in time, see [`AllocationSnapshot::elapsed`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AllocationSnapshot {
+    pub allocations: usize,
+    pub bytes: usize,
+}
+
+impl AllocationSnapshot {
+    pub fn now() -> Self {
+        Self {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Allocations made since this snapshot was taken, by any thread.
+    pub fn elapsed(&self) -> Self {
+        let now = Self::now();
+        Self {
+            allocations: now.allocations.saturating_sub(self.allocations),
+            bytes: now.bytes.saturating_sub(self.bytes),
+        }
+    }
+}
+
+impl Add for AllocationSnapshot {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            allocations: self.allocations + other.allocations,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+/// Peak resident set size of the process in bytes, on platforms that
+/// report it through `/proc`.
+pub fn peak_rss() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kilobytes| kilobytes.trim().parse::<u64>().ok())
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_counts_allocations() {
+        let before = AllocationSnapshot::now();
+        let buffer = vec![0u8; 4096];
+        let elapsed = before.elapsed();
+
+        assert_eq!(buffer.len(), 4096);
+        assert!(elapsed.allocations >= 1);
+        assert!(elapsed.bytes >= 4096);
+    }
+
+    #[test]
+    fn bytes_are_formatted_with_binary_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+}
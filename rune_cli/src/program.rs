@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    path::PathBuf,
+};
+
+use rune_core::prelude::{Expr, NodeId, Span};
+use rune_core::{codegen::RUNTIME_SYMBOLS, errors::TypeError, query::CheckQueries};
+
+use crate::memory::AllocationSnapshot;
+
+pub struct ParsedSource {
+    pub source: String,
+    pub statements: Vec<Expr>,
+    pub spans: Vec<Span>,
+    pub ids: Vec<NodeId>,
+}
+
+/// A parsed target file, along with the allocations spent loading it.
+pub struct ProgramFile {
+    pub target: PathBuf,
+    pub parsed: ParsedSource,
+    pub allocations: AllocationSnapshot,
+}
+
+/// Every target of a build. All files are parsed and checked before any of
+/// them is compiled, so codegen only ever sees well-typed programs.
+#[derive(Default)]
+pub struct Program {
+    pub files: Vec<ProgramFile>,
+}
+
+/// A type error, located at the top-level statement it was found in.
+pub struct Diagnostic {
+    pub target: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub error: TypeError,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.target.display(),
+            self.line,
+            self.column,
+            self.error
+        )
+    }
+}
+
+impl Program {
+    /// Type checks every file and returns all errors found. Checking a file
+    /// stops at its first error, as the statements after it usually depend
+    /// on the one that failed.
+    pub fn check(&self) -> Vec<Diagnostic> {
+        let mut queries = CheckQueries::new();
+        let mut diagnostics = self.check_definitions();
+
+        for file in &self.files {
+            let parsed = &file.parsed;
+            if let Err((index, error)) = queries.check(&parsed.statements, &parsed.ids) {
+                let (line, column) = parsed.spans[index].line_column(&parsed.source);
+                diagnostics.push(Diagnostic {
+                    target: file.target.clone(),
+                    line,
+                    column,
+                    error,
+                });
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Reports functions and enums defined twice in a file, `pub` functions
+    /// defined in more than one file, and functions named after one the
+    /// runtime defines, each at the second definition and naming the first.
+    /// LLVM or the linker would otherwise reject these with far less
+    /// context.
+    fn check_definitions(&self) -> Vec<Diagnostic> {
+        let mut public: HashMap<&str, String> = HashMap::new();
+        let mut diagnostics = Vec::new();
+
+        for file in &self.files {
+            let parsed = &file.parsed;
+            let mut local: HashMap<&str, String> = HashMap::new();
+            // Enums have their own namespace, so `Color` may also be a function
+            let mut local_enums: HashMap<&str, String> = HashMap::new();
+
+            for (statement, span) in parsed.statements.iter().zip(&parsed.spans) {
+                let (name, is_public) = match statement {
+                    Expr::FunctionDef { public, name, .. } => (name, *public),
+                    Expr::EnumDef { name, .. } => (name, false),
+                    _ => continue,
+                };
+
+                let (line, column) = span.line_column(&parsed.source);
+                let site = format!("at {}:{}:{}", file.target.display(), line, column);
+
+                if let Expr::EnumDef { .. } = statement {
+                    if let Some(first) = local_enums.get(name.as_str()) {
+                        diagnostics.push(Diagnostic {
+                            target: file.target.clone(),
+                            line,
+                            column,
+                            error: TypeError::DuplicateDefinition(name.clone(), first.clone()),
+                        });
+                    } else {
+                        local_enums.insert(name, site);
+                    }
+                    continue;
+                }
+
+                let first = if RUNTIME_SYMBOLS.contains(&name.as_str()) {
+                    Some("by the runtime".to_string())
+                } else if let Some(first) = local.get(name.as_str()) {
+                    Some(first.clone())
+                } else if is_public {
+                    public.get(name.as_str()).cloned()
+                } else {
+                    None
+                };
+
+                if let Some(first) = first {
+                    diagnostics.push(Diagnostic {
+                        target: file.target.clone(),
+                        line,
+                        column,
+                        error: TypeError::DuplicateDefinition(name.clone(), first),
+                    });
+                    continue;
+                }
+
+                if is_public {
+                    public.insert(name, site.clone());
+                }
+                local.insert(name, site);
+            }
+        }
+
+        diagnostics
+    }
+}
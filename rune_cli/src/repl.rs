@@ -0,0 +1,224 @@
+use std::{env, io::Stdout, path::PathBuf};
+
+use inkwell::context::Context;
+use owo_colors::OwoColorize;
+use rune_core::prelude::{Expr, Parser, intrinsics};
+use rune_core::{TypeContext, codegen::CodeGen, type_of};
+use rune_interp::interpreter::Interpreter;
+use rustyline::{DefaultEditor, error::ReadlineError};
+
+use crate::errors::CliError;
+
+const HISTORY_FILE: &str = ".rune_history";
+const PROMPT: &str = "rune> ";
+const CONTINUATION_PROMPT: &str = "  ... ";
+const HELP: &str = "\
+:ir          show the LLVM IR for everything entered so far
+:type <expr> show the type of an expression
+:help        show this message
+:quit        exit the REPL";
+
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+/// Whether `input` has unclosed parentheses, braces or strings and should be
+/// continued on the next line.
+fn is_incomplete(input: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in input.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '(' | '{' => depth += 1,
+            ')' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    in_string || depth > 0
+}
+
+/// Interactive session backed by the interpreter.
+///
+/// Input is type checked before it is evaluated, and statements that ran
+/// successfully are kept so `:ir` can compile them with the LLVM backend.
+pub struct Repl {
+    interpreter: Interpreter<Stdout>,
+    types: TypeContext,
+    statements: Vec<Expr>,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+            types: TypeContext::new(),
+            statements: Vec::new(),
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), CliError> {
+        let mut editor =
+            DefaultEditor::new().map_err(|err| CliError::InternalError(err.to_string()))?;
+
+        let history = history_path();
+        if let Some(path) = &history {
+            // There is no history file before the first session
+            let _ = editor.load_history(path);
+        }
+
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    buffer.push_str(&line);
+                    buffer.push('\n');
+
+                    if is_incomplete(&buffer) {
+                        continue;
+                    }
+
+                    let input = std::mem::take(&mut buffer);
+                    let input = input.trim();
+                    if input.is_empty() {
+                        continue;
+                    }
+
+                    let _ = editor.add_history_entry(input);
+
+                    if !self.handle(input) {
+                        break;
+                    }
+                }
+                // Ctrl-C discards the current input, Ctrl-D exits
+                Err(ReadlineError::Interrupted) => buffer.clear(),
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(CliError::IOError(err.to_string())),
+            }
+        }
+
+        if let Some(path) = &history {
+            editor
+                .save_history(path)
+                .map_err(|e| CliError::IOError(format!("Failed to save history: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles one complete input, returning `false` when the session should
+    /// end.
+    fn handle(&mut self, input: &str) -> bool {
+        let (command, argument) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+        let result = match command {
+            ":quit" | ":q" => return false,
+            ":ir" => self.ir().map(|ir| print!("{}", ir)),
+            ":type" => type_of(argument.trim(), &self.types)
+                .map(|var_type| println!("{}", var_type))
+                .map_err(|err| err.to_string()),
+            ":help" => {
+                println!("{}\n\nBuilt-in functions:", HELP);
+                for entry in &intrinsics::TABLE {
+                    println!("  {}\n      {}", entry, entry.doc);
+                }
+                Ok(())
+            }
+            _ if command.starts_with(':') => Err(format!(
+                "Unknown command `{}`, see :help for a list of commands",
+                command
+            )),
+            _ => self.eval(input),
+        };
+
+        if let Err(err) = result {
+            eprintln!("{}{} {}", "Error".bold().red(), ":".bold(), err.red());
+        }
+
+        true
+    }
+
+    fn eval(&mut self, input: &str) -> Result<(), String> {
+        let statements = parse(input)?;
+
+        for statement in statements {
+            self.types
+                .check(&statement)
+                .map_err(|err| err.to_string())?;
+
+            let value = self
+                .interpreter
+                .eval(&statement)
+                .map_err(|err| err.to_string())?;
+
+            if matches!(
+                statement,
+                Expr::Literal(..)
+                    | Expr::Variable { .. }
+                    | Expr::Binary { .. }
+                    | Expr::Unary { .. }
+                    | Expr::Cast { .. }
+                    | Expr::ArrayLiteral(..)
+                    | Expr::Index { .. }
+                    | Expr::EnumVariant { .. }
+                    | Expr::Match { .. }
+            ) {
+                println!("{}", value);
+            }
+
+            self.statements.push(statement);
+        }
+
+        Ok(())
+    }
+
+    fn ir(&self) -> Result<String, String> {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "repl");
+
+        codegen
+            .compile_statements(&self.statements)
+            .map_err(|err| err.to_string())?;
+
+        Ok(codegen.get_ir_string())
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Expr>, String> {
+    Parser::new(source.to_string())
+        .and_then(|mut parser| parser.parse())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbalanced_input_is_incomplete() {
+        assert!(is_incomplete("if x {"));
+        assert!(is_incomplete("print(\"a\""));
+        assert!(is_incomplete("let s = \"unterminated"));
+        assert!(!is_incomplete("if x { print(\"}\"); }"));
+        assert!(!is_incomplete("let s = \"\\\"{\";"));
+    }
+}
@@ -0,0 +1,304 @@
+use std::{collections::BTreeMap, fmt::Display, process};
+
+use owo_colors::OwoColorize;
+use rune_core::warnings::CodeGenWarning;
+
+use crate::config::{LintLevel, LintsConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogLevel {
+    Verbose,
+    Quiet,
+    Default,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// An error or warning about a source file, held back until
+/// [`Reporter::flush_diagnostics`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDiagnostic {
+    pub file: String,
+    /// Line and column, `None` for the file as a whole.
+    pub location: Option<(usize, usize)>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Sink for everything the driver prints.
+///
+/// Progress output goes to stdout and is silenced by `--quiet`. Errors and
+/// warnings go to stderr and are counted, so the exit code always reflects
+/// whether an error was reported. Those about source files are collected
+/// instead and printed grouped by file, in source order, so the output
+/// doesn't depend on the order files are compiled in.
+pub struct Reporter {
+    log_level: LogLevel,
+    errors: usize,
+    warnings: usize,
+    lints: LintsConfig,
+    diagnostics: Vec<FileDiagnostic>,
+}
+
+impl Reporter {
+    pub fn new(log_level: LogLevel) -> Self {
+        Self {
+            log_level,
+            errors: 0,
+            warnings: 0,
+            lints: LintsConfig::default(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Sets the levels compiler warnings are reported at, see [`Self::lint`].
+    pub fn set_lints(&mut self, lints: LintsConfig) {
+        self.lints = lints;
+    }
+
+    pub fn is_verbose(&self) -> bool {
+        self.log_level == LogLevel::Verbose
+    }
+
+    /// Errors reported so far, including those not printed yet.
+    pub fn error_count(&self) -> usize {
+        self.errors
+    }
+}
+
+// Output
+impl Reporter {
+    pub fn status(&self, verb: impl Display, message: &str) {
+        if self.log_level == LogLevel::Quiet {
+            return;
+        }
+        println!("{} {}", verb, message);
+    }
+
+    pub fn value(&self, label: &str, value: &str, depth: usize) {
+        if self.log_level == LogLevel::Quiet {
+            return;
+        }
+        println!("{}{}: `{}`", " ".repeat(depth), label.bold(), value);
+    }
+
+    pub fn section(&self, label: &str, depth: usize) {
+        if self.log_level == LogLevel::Quiet {
+            return;
+        }
+        println!("{}{}", " ".repeat(depth), label.bold().green());
+    }
+
+    pub fn error(&mut self, error: &str, depth: usize) {
+        self.errors += 1;
+        eprintln!(
+            "{}{}{} {}",
+            " ".repeat(depth),
+            "Error".bold().red(),
+            ":".bold(),
+            error.red()
+        );
+    }
+
+    pub fn warning(&mut self, warning: &str, depth: usize) {
+        self.warnings += 1;
+        eprintln!(
+            "{}{}{} {}",
+            " ".repeat(depth),
+            "warning".bold().yellow(),
+            ":".bold(),
+            warning
+        );
+    }
+
+    /// Collects an error or warning about `file`, see [`FileDiagnostic`].
+    pub fn diagnostic(
+        &mut self,
+        file: &str,
+        location: Option<(usize, usize)>,
+        severity: Severity,
+        message: String,
+    ) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.diagnostics.push(FileDiagnostic {
+            file: file.to_string(),
+            location,
+            severity,
+            message,
+        });
+    }
+
+    /// Collects a compiler warning about `file` as a warning, an error or
+    /// not at all, depending on the level of its lint.
+    pub fn lint(&mut self, file: &str, warning: &CodeGenWarning, location: Option<(usize, usize)>) {
+        match self.lints.level(warning.lint()) {
+            LintLevel::Allow => {}
+            LintLevel::Warn => {
+                self.diagnostic(file, location, Severity::Warning, warning.to_string())
+            }
+            LintLevel::Deny => self.diagnostic(
+                file,
+                location,
+                Severity::Error,
+                format!("{} [denied by `{}`]", warning, warning.lint()),
+            ),
+        }
+    }
+
+    /// Prints the collected diagnostics grouped by file and a summary of
+    /// them, if there are any.
+    pub fn flush_diagnostics(&mut self) {
+        let files = self.print_diagnostics();
+        if files > 0 {
+            eprintln!("{}", summary(self.errors, self.warnings, files).bold());
+        }
+    }
+
+    /// Prints and forgets the collected diagnostics, returning how many
+    /// files they were about. Each keeps the `file:line:column` prefix of
+    /// other errors, so it can still be followed from the terminal.
+    fn print_diagnostics(&mut self) -> usize {
+        let files = group_by_file(std::mem::take(&mut self.diagnostics));
+        for (file, diagnostics) in &files {
+            eprintln!("{}", file.bold());
+            for diagnostic in diagnostics {
+                let message = match diagnostic.location {
+                    Some((line, column)) => {
+                        format!("{}:{}:{}: {}", file, line, column, diagnostic.message)
+                    }
+                    None => format!("{}: {}", file, diagnostic.message),
+                };
+                match diagnostic.severity {
+                    Severity::Error => {
+                        eprintln!("  {}{} {}", "Error".bold().red(), ":".bold(), message.red())
+                    }
+                    Severity::Warning => {
+                        eprintln!("  {}{} {}", "warning".bold().yellow(), ":".bold(), message)
+                    }
+                }
+            }
+        }
+        files.len()
+    }
+}
+
+/// Diagnostics sorted by file, then by line and column.
+fn group_by_file(diagnostics: Vec<FileDiagnostic>) -> BTreeMap<String, Vec<FileDiagnostic>> {
+    let mut files: BTreeMap<String, Vec<FileDiagnostic>> = BTreeMap::new();
+    for diagnostic in diagnostics {
+        files
+            .entry(diagnostic.file.clone())
+            .or_default()
+            .push(diagnostic);
+    }
+    for diagnostics in files.values_mut() {
+        // Stable, so diagnostics at the same place keep the order they were
+        // reported in
+        diagnostics.sort_by_key(|diagnostic| diagnostic.location);
+    }
+    files
+}
+
+/// Like `2 errors, 1 warning in 3 files`, leaving out the files when none
+/// of the diagnostics was about one.
+fn summary(errors: usize, warnings: usize, files: usize) -> String {
+    let count = |n: usize, noun: &str| match n {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    };
+    let counts = format!("{}, {}", count(errors, "error"), count(warnings, "warning"));
+    match files {
+        0 => counts,
+        files => format!("{} in {}", counts, count(files, "file")),
+    }
+}
+
+// Exit handling
+impl Reporter {
+    /// Exits with status 1 if any error has been reported so far, printing
+    /// the collected diagnostics first.
+    pub fn report_and_exit_if_errors(&mut self) {
+        if self.errors == 0 {
+            return;
+        }
+
+        let files = self.print_diagnostics();
+        eprintln!(
+            "{}{} aborting due to {}",
+            "Error".bold().red(),
+            ":".bold(),
+            summary(self.errors, self.warnings, files)
+        );
+        process::exit(1);
+    }
+
+    pub fn exit_with_error(&mut self, error: impl Display) -> ! {
+        self.error(error.to_string().as_str(), 0);
+        self.report_and_exit_if_errors();
+        unreachable!("an error was just reported")
+    }
+
+    pub fn unwrap_or_exit<T, E: Display>(&mut self, result: Result<T, E>) -> T {
+        match result {
+            Ok(value) => value,
+            Err(err) => self.exit_with_error(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(file: &str, location: Option<(usize, usize)>, message: &str) -> FileDiagnostic {
+        FileDiagnostic {
+            file: file.to_string(),
+            location,
+            severity: Severity::Warning,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn diagnostics_are_grouped_in_source_order() {
+        let files = group_by_file(vec![
+            diagnostic("src/main.rn", Some((7, 1)), "late"),
+            diagnostic("src/a.rn", Some((2, 5)), "other file"),
+            diagnostic("src/main.rn", Some((3, 9)), "early"),
+            diagnostic("src/main.rn", None, "whole file"),
+            diagnostic("src/main.rn", Some((3, 9)), "same place"),
+        ]);
+
+        let messages: Vec<(&str, Vec<&str>)> = files
+            .iter()
+            .map(|(file, diagnostics)| {
+                let messages = diagnostics.iter().map(|d| d.message.as_str()).collect();
+                (file.as_str(), messages)
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("src/a.rn", vec!["other file"]),
+                (
+                    "src/main.rn",
+                    vec!["whole file", "early", "same place", "late"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn summaries() {
+        assert_eq!(summary(2, 1, 3), "2 errors, 1 warning in 3 files");
+        assert_eq!(summary(1, 0, 1), "1 error, 0 warnings in 1 file");
+        assert_eq!(summary(1, 2, 0), "1 error, 2 warnings");
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashSet, path::Path};
+
+use inkwell::memory_buffer::MemoryBuffer;
+
+use crate::errors::CliError;
+
+/// The `size` column a section is counted in, as reported by `size(1)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SectionKind {
+    /// Code and read-only data
+    Text,
+    /// Initialized writable data
+    Data,
+    /// Zero-initialized data, taking no space in the file
+    Bss,
+}
+
+/// Classifies a section by name, `None` for sections that are not loaded
+/// at runtime, like symbol tables and debug info.
+pub fn section_kind(name: &str) -> Option<SectionKind> {
+    const TEXT: [&str; 15] = [
+        ".text",
+        ".rodata",
+        ".init",
+        ".fini",
+        ".plt",
+        ".eh_frame",
+        ".eh_frame_hdr",
+        ".gcc_except_table",
+        ".interp",
+        ".note",
+        ".hash",
+        ".gnu",
+        ".rela",
+        ".dynsym",
+        ".dynstr",
+    ];
+    const DATA: [&str; 6] = [
+        ".data",
+        ".got",
+        ".init_array",
+        ".fini_array",
+        ".dynamic",
+        ".tdata",
+    ];
+
+    let matches = |prefix: &&str| name == *prefix || name.starts_with(&format!("{}.", prefix));
+
+    if name == ".bss" || name == ".tbss" || name.starts_with(".bss.") {
+        Some(SectionKind::Bss)
+    } else if DATA.iter().any(matches) {
+        Some(SectionKind::Data)
+    } else if TEXT.iter().any(matches) {
+        Some(SectionKind::Text)
+    } else {
+        None
+    }
+}
+
+/// A function symbol and the bytes of code it takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSize {
+    pub name: String,
+    pub size: u64,
+}
+
+/// What a compiled artifact is made of, from its section headers and
+/// symbol table.
+#[derive(Debug, Clone, Default)]
+pub struct SizeReport {
+    pub text: u64,
+    pub data: u64,
+    pub bss: u64,
+    /// Read-only data: string literals, format strings and constants
+    pub rodata: u64,
+    /// Function symbols by descending size
+    pub functions: Vec<FunctionSize>,
+}
+
+impl SizeReport {
+    /// Reads the object file or executable at `path`.
+    pub fn read(path: &Path) -> Result<Self, CliError> {
+        let buffer = MemoryBuffer::create_from_file(path).map_err(|e| {
+            CliError::IOError(format!("Failed to read `{}`: {}", path.display(), e))
+        })?;
+        let object = buffer.create_object_file().map_err(|_| {
+            CliError::InternalError(format!("`{}` is not an object file", path.display()))
+        })?;
+
+        let mut report = SizeReport::default();
+        let mut code_ranges = Vec::new();
+
+        for section in object.get_sections() {
+            let Some(name) = section.get_name().and_then(|name| name.to_str().ok()) else {
+                continue;
+            };
+            let size = section.size();
+
+            match section_kind(name) {
+                Some(SectionKind::Text) => report.text += size,
+                Some(SectionKind::Data) => report.data += size,
+                Some(SectionKind::Bss) => report.bss += size,
+                None => {}
+            }
+            if name == ".rodata" || name.starts_with(".rodata.") {
+                report.rodata += size;
+            }
+            if name == ".text" || name.starts_with(".text.") {
+                let start = section.get_address();
+                code_ranges.push(start..start + size);
+            }
+        }
+
+        // In an object file every section starts at address 0, which would
+        // let data symbols pass for code, so relocatable files are checked
+        // by name only
+        let relocatable = code_ranges.iter().all(|range| range.start == 0);
+
+        let mut seen = HashSet::new();
+        for symbol in object.get_symbols() {
+            let size = symbol.size();
+            let Some(name) = symbol.get_name().and_then(|name| name.to_str().ok()) else {
+                continue;
+            };
+            let address = symbol.get_address();
+            let is_code = relocatable || code_ranges.iter().any(|range| range.contains(&address));
+
+            if size == 0 || name.is_empty() || !is_code || !seen.insert(name.to_string()) {
+                continue;
+            }
+            report.functions.push(FunctionSize {
+                name: name.to_string(),
+                size,
+            });
+        }
+        report
+            .functions
+            .sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+
+        Ok(report)
+    }
+
+    /// The bytes of code taken by the functions named in `names`.
+    pub fn code_size(&self, names: &HashSet<String>) -> u64 {
+        self.functions
+            .iter()
+            .filter(|function| names.contains(&function.name))
+            .map(|function| function.size)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_are_classified_like_size() {
+        assert_eq!(section_kind(".text"), Some(SectionKind::Text));
+        assert_eq!(section_kind(".text.startup"), Some(SectionKind::Text));
+        assert_eq!(section_kind(".rodata.str1.1"), Some(SectionKind::Text));
+        assert_eq!(section_kind(".eh_frame_hdr"), Some(SectionKind::Text));
+        assert_eq!(section_kind(".gnu.version"), Some(SectionKind::Text));
+        assert_eq!(section_kind(".eh_frame"), Some(SectionKind::Text));
+        assert_eq!(section_kind(".data.rel.ro"), Some(SectionKind::Data));
+        assert_eq!(section_kind(".got.plt"), Some(SectionKind::Data));
+        assert_eq!(section_kind(".init_array"), Some(SectionKind::Data));
+        assert_eq!(section_kind(".bss"), Some(SectionKind::Bss));
+        assert_eq!(section_kind(".tbss"), Some(SectionKind::Bss));
+        assert_eq!(section_kind(".symtab"), None);
+        assert_eq!(section_kind(".debug_info"), None);
+        assert_eq!(section_kind(".datafoo"), None);
+    }
+}
@@ -0,0 +1,659 @@
+use std::{fs, path::Path};
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"title = "test"
+version = "0.1.0"
+
+[build]
+"#;
+
+/// Creates a temporary project with a `Rune.toml` and the given source files
+/// under `src/`.
+fn project(files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+
+    fs::write(dir.path().join("Rune.toml"), CONFIG).unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+
+    for (name, source) in files {
+        fs::write(dir.path().join("src").join(name), source).unwrap();
+    }
+
+    dir
+}
+
+fn rune(dir: &Path) -> Command {
+    let mut command = Command::cargo_bin("rune_cli").unwrap();
+    command.current_dir(dir);
+    command
+}
+
+#[test]
+fn build_creates_executable() {
+    let dir = project(&[("main.rn", "let x = 1 + 2;")]);
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compiled"));
+
+    assert!(dir.path().join("target").join("main").exists());
+    assert!(dir.path().join("target").join("main.o").exists());
+}
+
+#[test]
+fn run_prints_program_output() {
+    let dir = project(&[("main.rn", r#"print("hello");"#)]);
+
+    rune(dir.path())
+        .arg("run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello\n"));
+}
+
+#[test]
+fn print_numbers_and_booleans_on_both_engines() {
+    let dir = project(&[(
+        "main.rn",
+        "let x: i32 = -7; print(x); print(40 + 2); print(2.5); print(x < 0);",
+    )]);
+
+    for engine in ["llvm", "interp"] {
+        rune(dir.path())
+            .args(["run", "--engine", engine])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("-7\n42\n2.5\ntrue\n"));
+    }
+}
+
+#[test]
+fn floats_print_shortest_or_fixed_on_both_engines() {
+    let dir = project(&[(
+        "main.rn",
+        "print(0.1); print(0.1 + 0.2); print(100.0); print(1e20); print(0.00001);",
+    )]);
+
+    for engine in ["llvm", "interp"] {
+        rune(dir.path())
+            .args(["run", "--engine", engine])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "0.1\n0.30000000000000004\n100\n1e+20\n1e-05\n",
+            ));
+    }
+
+    let config = format!("{}float_precision = 2\n", CONFIG);
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    for engine in ["llvm", "interp"] {
+        rune(dir.path())
+            .args(["run", "--engine", engine])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("0.10\n0.30\n100.00\n"));
+    }
+}
+
+#[test]
+fn float_to_integer_casts_saturate_on_both_engines() {
+    let dir = project(&[(
+        "main.rn",
+        "let zero = 0.0; let big = 1e20;
+        print(big as i64); print((0.0 - big) as i32);
+        print((zero / zero) as i64); print((1.0 / zero) as i32);",
+    )]);
+
+    for engine in ["llvm", "interp"] {
+        rune(dir.path())
+            .args(["run", "--engine", engine])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "9223372036854775807\n-2147483648\n0\n2147483647\n",
+            ));
+    }
+}
+
+#[test]
+fn run_with_interpreter() {
+    let dir = project(&[(
+        "main.rn",
+        r#"if true { print("yes"); } else { print("no"); }"#,
+    )]);
+
+    rune(dir.path())
+        .args(["run", "--engine", "interp"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("yes\n").and(predicate::str::contains("no\n").not()));
+}
+
+#[test]
+fn artifacts_mirror_the_source_tree() {
+    let dir = project(&[]);
+    for (sub_dir, source) in [("a", "print(1);"), ("b", "print(2);")] {
+        fs::create_dir(dir.path().join("src").join(sub_dir)).unwrap();
+        fs::write(dir.path().join("src").join(sub_dir).join("main.rn"), source).unwrap();
+    }
+
+    rune(dir.path()).arg("build").assert().success();
+
+    let target = dir.path().join("target");
+    for sub_dir in ["a", "b"] {
+        assert!(target.join(sub_dir).join("main").exists());
+        assert!(target.join(sub_dir).join("main.o").exists());
+    }
+
+    fs::write(dir.path().join("src").join("a.rn"), "print(3);").unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("(C007)"));
+}
+
+#[test]
+fn artifacts_lists_what_the_last_build_wrote() {
+    let dir = project(&[("main.rn", "print(1);")]);
+
+    rune(dir.path())
+        .args(["artifacts", "--json"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("run `build` first"));
+
+    rune(dir.path())
+        .args(["--emit", "map", "build"])
+        .assert()
+        .success();
+
+    let output = rune(dir.path())
+        .args(["artifacts", "--json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let artifacts: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let kinds: Vec<&str> = artifacts
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|artifact| artifact["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["binary", "object", "map"]);
+
+    let path = artifacts[0]["path"].as_str().unwrap();
+    assert_eq!(Path::new(path), dir.path().join("target").join("main"));
+    assert_eq!(artifacts[0]["hash"].as_str().unwrap().len(), 16);
+    assert!(
+        artifacts[0]["source"]
+            .as_str()
+            .unwrap()
+            .ends_with("main.rn")
+    );
+}
+
+#[test]
+fn emit_map_writes_statement_names() {
+    let dir = project(&[("main.rn", "let x = 1;\nprint(\"hi\");")]);
+
+    rune(dir.path())
+        .args(["--emit", "map", "build"])
+        .assert()
+        .success();
+
+    let map = fs::read_to_string(dir.path().join("target").join("main.map")).unwrap();
+    let lines: Vec<&str> = map.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("1:1\t0..9\t"));
+    assert!(lines[0].contains("%x"));
+    assert!(lines[1].starts_with("2:1\t11..22\t"));
+    assert!(lines[1].contains("@str"));
+}
+
+#[test]
+fn unchanged_sources_are_fresh() {
+    let dir = project(&[("main.rn", "let x = 1;")]);
+
+    rune(dir.path()).arg("build").assert().success();
+    assert!(dir.path().join("target").join(".fingerprint.json").exists());
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fresh"));
+
+    fs::write(dir.path().join("src").join("main.rn"), "let x = 2;").unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compiled"));
+}
+
+#[test]
+fn truncated_binary_is_rebuilt() {
+    let dir = project(&[("main.rn", "let x = 1;")]);
+    let target = dir.path().join("target");
+
+    rune(dir.path()).arg("build").assert().success();
+    assert!(!target.join(".main.tmp").exists());
+    assert!(!target.join(".main.o.tmp").exists());
+
+    // As left by a build interrupted before artifacts were written atomically
+    fs::write(target.join("main"), b"").unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compiled"));
+    assert!(fs::metadata(target.join("main")).unwrap().len() > 0);
+}
+
+#[test]
+fn changed_flags_invalidate_fingerprint() {
+    let dir = project(&[("main.rn", "let x = 1;")]);
+
+    rune(dir.path()).arg("build").assert().success();
+
+    rune(dir.path())
+        .args(["--emit", "map", "build"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compiled"))
+        .stderr(predicate::str::contains("rebuilding everything"));
+}
+
+#[test]
+fn changed_float_precision_forces_a_rebuild() {
+    let dir = project(&[("main.rn", "print(0.1);")]);
+
+    rune(dir.path()).arg("build").assert().success();
+
+    let config = format!("{}float_precision = 2\n", CONFIG);
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Compiled"))
+        .stderr(predicate::str::contains("rebuilding everything"));
+    Command::new(dir.path().join("target").join("main"))
+        .assert()
+        .success()
+        .stdout("0.10\n");
+}
+
+#[test]
+fn repl_continues_unbalanced_input() {
+    let home = TempDir::new().unwrap();
+
+    rune(home.path())
+        .arg("repl")
+        .env("HOME", home.path())
+        .write_stdin("let x = (1 +\n 2);\nx * 3\n:type x\n:quit\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("9\n").and(predicate::str::contains("i64")));
+
+    assert!(home.path().join(".rune_history").exists());
+}
+
+#[test]
+fn stats_reports_per_function_counts() {
+    let dir = project(&[("main.rn", "let x = 1; let y: i32 = 2;")]);
+
+    rune(dir.path())
+        .args(["--stats", "build"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("main")
+                .and(predicate::str::contains("12 bytes stack"))
+                .and(predicate::str::contains("allocations"))
+                .and(predicate::str::contains("peak RSS")),
+        );
+}
+
+#[test]
+fn explain_ir_annotates_statements() {
+    let dir = project(&[("main.rn", "let mut x = 1;\nif x > 0 { x = 2; }")]);
+
+    rune(dir.path())
+        .args(["--explain-ir", "build"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("   1 | let mut x = 1")
+                .and(predicate::str::contains("; stack slot for `x`"))
+                .and(predicate::str::contains("   2 | if x > 0"))
+                .and(predicate::str::contains("; branch on a condition")),
+        );
+}
+
+#[test]
+fn report_vectorization_prints_loop_remarks() {
+    let dir = project(&[("main.rn", "let n = 8; for i in 0..n { print(i); }")]);
+
+    rune(dir.path())
+        .args(["--report-vectorization", "build"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("loop not vectorized"));
+}
+
+#[test]
+fn return_sets_exit_code_on_both_engines() {
+    let dir = project(&[("main.rn", "let x = 3; if x > 2 { return x; } return 0;")]);
+
+    rune(dir.path()).arg("run").assert().code(3);
+    rune(dir.path())
+        .args(["run", "--engine", "interp"])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn fmt_applies_config_and_check_reports() {
+    let dir = project(&[("main.rn", "let x=1;if x>0{print(\"a\")}")]);
+    let config = format!(
+        "{}\n[fmt]\nindent = 2\ntrailing_semicolons = false\n",
+        CONFIG
+    );
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    rune(dir.path())
+        .args(["fmt", "--check"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not formatted"));
+
+    rune(dir.path()).arg("fmt").assert().success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("src/main.rn")).unwrap(),
+        "let x = 1;\nif x > 0 {\n  print(\"a\")\n}\n"
+    );
+
+    rune(dir.path()).args(["fmt", "--check"]).assert().success();
+}
+
+#[test]
+fn target_sections_apply_to_matching_triple() {
+    let dir = project(&[("main.rn", "let x = 1;")]);
+    let config = format!(
+        "{}\n[target.'x86_64-unknown-linux-gnu'.build]\nlinker = \"no-such-linker\"\n",
+        CONFIG
+    );
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    rune(dir.path())
+        .args(["--target", "x86_64-unknown-linux-gnu", "build"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no-such-linker"));
+
+    rune(dir.path())
+        .args(["--target", "x86_64-pc-linux-gnu", "build"])
+        .assert()
+        .success();
+}
+
+#[cfg(unix)]
+#[test]
+fn missing_cc_falls_back_to_other_linkers() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = project(&[("main.rn", "let x = 1;")]);
+    let bin = TempDir::new().unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .env("PATH", bin.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "No linker found, tried `cc`, `gcc`, `clang`",
+        ));
+
+    // A stand-in `gcc` that only creates the output file
+    let gcc = bin.path().join("gcc");
+    fs::write(&gcc, "#!/bin/sh\n: > \"$3\"\n").unwrap();
+    fs::set_permissions(&gcc, fs::Permissions::from_mode(0o755)).unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .env("PATH", bin.path())
+        .assert()
+        .success();
+    assert!(dir.path().join("target").join("main").exists());
+}
+
+#[test]
+fn type_errors_of_all_files_are_reported_before_codegen() {
+    let dir = project(&[
+        ("a.rn", "let x = 1;\nlet y: i32 = true;"),
+        ("b.rn", "print(missing);"),
+        ("c.rn", "let ok = 1;"),
+    ]);
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("a.rn:2:1: (T002)")
+                .and(predicate::str::contains("b.rn:1:1: (T001)")),
+        )
+        .stdout(predicate::str::contains("Compiled").not());
+}
+
+#[test]
+fn duplicate_definitions_name_both_sites() {
+    let dir = project(&[
+        ("a.rn", "pub fn helper() {}\nfn twice() {}\nfn twice() {}"),
+        ("b.rn", "let x = 1;\npub fn helper() {}"),
+        ("c.rn", "fn main() {}\nenum E { A }\nenum E { B }"),
+    ]);
+
+    rune(dir.path()).arg("build").assert().code(1).stderr(
+        predicate::str::is_match(
+            r"a\.rn:3:1: \(T010\): `twice` is defined more than once, first at \S*a\.rn:2:1",
+        )
+        .unwrap()
+        .and(predicate::str::contains(
+            "`helper` is defined more than once, first at",
+        ))
+        .and(predicate::str::contains(
+            "`main` is defined more than once, first by the runtime",
+        ))
+        .and(
+            predicate::str::is_match(
+                r"c\.rn:3:1: \(T010\): `E` is defined more than once, first at \S*c\.rn:2:1",
+            )
+            .unwrap(),
+        ),
+    );
+}
+
+#[test]
+fn quiet_suppresses_status_output() {
+    let dir = project(&[("main.rn", "let x = 1;")]);
+
+    rune(dir.path())
+        .args(["--quiet", "build"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn missing_config_fails() {
+    let dir = TempDir::new().unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Rune.toml"));
+}
+
+#[test]
+fn compile_error_fails_without_artifacts() {
+    let dir = project(&[("main.rn", "let x: bool = 42;")]);
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("main.rn:1:1: (T002)"));
+
+    assert!(!dir.path().join("target").join("main").exists());
+}
+
+#[test]
+fn parse_errors_report_line_and_column() {
+    let dir = project(&[("main.rn", "let x = 1;\nlet = 2;")]);
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("main.rn:2:5: (P005)"));
+}
+
+#[test]
+fn trace_parse_is_written_even_on_errors() {
+    let dir = project(&[("main.rn", "let x = 1;\nlet = 2;")]);
+
+    rune(dir.path())
+        .args(["--trace-parse", "build"])
+        .assert()
+        .code(1);
+
+    let trace = fs::read_to_string(dir.path().join("target").join("main.trace")).unwrap();
+    assert!(trace.starts_with("expression at `let` 1:1\n"));
+    assert!(trace.contains("consume `x` 1:5"));
+    assert!(
+        trace
+            .trim_end()
+            .ends_with("Expected `identifier` after `let`")
+    );
+}
+
+#[test]
+fn warnings_go_to_stderr() {
+    let dir = project(&[("main.rn", "let x = 1; let x = 2;")]);
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("(W000)"));
+}
+
+#[test]
+fn diagnostics_are_grouped_by_file_with_a_summary() {
+    let dir = project(&[
+        ("a.rn", "let x = 1;\nlet x = 2;\nlet y = 1; let y = 2;"),
+        ("b.rn", "let z = 1; let z = 2;"),
+    ]);
+
+    rune(dir.path()).arg("build").assert().success().stderr(
+        predicate::str::is_match(r"(?s)a\.rn:2:1: \(W000\).*a\.rn:3:12: \(W000\).*b\.rn:1:12")
+            .unwrap()
+            .and(predicate::str::contains("0 errors, 3 warnings in 2 files")),
+    );
+}
+
+#[test]
+fn deny_warnings_fails_unless_the_lint_is_allowed() {
+    let dir = project(&[("main.rn", "let x = 1; let x = 2;")]);
+
+    rune(dir.path())
+        .args(["--deny-warnings", "build"])
+        .assert()
+        .code(1)
+        .stderr(
+            predicate::str::contains("(W000)")
+                .and(predicate::str::contains("1 error, 0 warnings in 1 file")),
+        );
+
+    let config = format!("{}\n[lints]\nshadowed_variable = \"allow\"\n", CONFIG);
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    rune(dir.path())
+        .args(["--deny-warnings", "build"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("(W000)").not());
+}
+
+#[test]
+fn denying_warnings_after_a_build_recompiles() {
+    let dir = project(&[("main.rn", "let x = 1; let x = 2;")]);
+
+    rune(dir.path()).arg("build").assert().success();
+
+    rune(dir.path())
+        .args(["--deny-warnings", "build"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("(W000)"));
+
+    let config = format!("{}\n[lints]\nshadowed_variable = \"deny\"\n", CONFIG);
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("denied by `shadowed_variable`"));
+}
+
+#[test]
+fn lossy_promotions_warn_at_the_operand() {
+    let dir = project(&[("main.rn", "let n = 1;\nlet f = n * 0.5;\nprint(f);")]);
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "main.rn:2:9: (W004): `n` is implicitly converted from `i64` to `f64`",
+        ));
+
+    let config = format!("{}\n[lints]\nimplicit_promotion = \"allow\"\n", CONFIG);
+    fs::write(dir.path().join("Rune.toml"), config).unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("(W004)").not());
+}
+
+#[test]
+fn invalid_utf8_reports_offset() {
+    let dir = project(&[]);
+    fs::write(dir.path().join("src").join("main.rn"), b"let x = \xff;").unwrap();
+
+    rune(dir.path())
+        .arg("build")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("byte offset 8"));
+}
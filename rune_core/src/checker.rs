@@ -0,0 +1,1556 @@
+use std::collections::{HashMap, HashSet};
+
+use rune_parser::intrinsics::{Intrinsic, ParamKind, Returns};
+use rune_parser::parser::Parser;
+use rune_parser::parser::expr::{Expr, Hook, MatchArm, Parameter, Pattern};
+use rune_parser::parser::nodes::Nodes;
+use rune_parser::parser::ops::{BinaryOp, UnaryOp};
+use rune_parser::parser::types::Types;
+use rune_parser::scope::SymbolTable;
+
+use crate::errors::TypeError;
+
+/// Variables visible to the checker, by type and mutability.
+///
+/// The typing rules follow what `CodeGen` accepts, so an expression that
+/// checks here compiles to a value of the reported type.
+#[derive(Debug, Clone, Default)]
+pub struct TypeContext {
+    variables: SymbolTable<(Types, bool)>,
+    /// Top-level `let`s, which function bodies can use too.
+    globals: HashMap<String, (Types, bool)>,
+    functions: HashMap<String, (Vec<Parameter>, Option<Types>)>,
+    /// Variants of each enum, in declaration order.
+    enums: HashMap<String, Vec<String>>,
+    /// Types of the top-level constants, visible everywhere unless shadowed.
+    constants: HashMap<String, Types>,
+    /// The constants whose definitions have been checked, which are the
+    /// ones a constant initializer may refer to.
+    defined_constants: HashSet<String>,
+    /// Names and return types of the functions being checked, innermost last.
+    return_types: Vec<(String, Option<Types>)>,
+    /// How many loops enclose the statement being checked, within the
+    /// current function.
+    loops: usize,
+}
+
+/// Returns the type of the last statement in `source`, with `context`
+/// providing the variables in scope. The context itself is left untouched.
+pub fn type_of(source: &str, context: &TypeContext) -> Result<Types, TypeError> {
+    let statements = Parser::new(source.to_string())
+        .and_then(|mut parser| parser.parse())
+        .map_err(|err| TypeError::ParseError(err.to_string()))?;
+
+    let mut context = context.clone();
+    context.declare_functions(&statements);
+
+    statements
+        .iter()
+        .try_fold(None, |_, statement| context.check(statement).map(Some))?
+        .ok_or(TypeError::ParseError("expected an expression".to_string()))
+}
+
+impl TypeContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a mutable variable, as if by `let mut`.
+    pub fn declare(&mut self, identifier: &str, var_type: Types) {
+        self.variables.declare(identifier, (var_type, true));
+    }
+
+    /// Records the signatures of the top-level functions, the variants of
+    /// the top-level enums and the types of the constants in `statements`,
+    /// so they can be used before their definition like in `CodeGen`.
+    pub fn declare_functions(&mut self, statements: &[Expr]) {
+        for statement in statements {
+            match statement {
+                Expr::FunctionDef {
+                    name,
+                    parameters,
+                    return_type,
+                    ..
+                } => self.declare_function(name, parameters, return_type),
+                Expr::EnumDef { name, variants, .. } => {
+                    self.enums.insert(name.clone(), variants.clone());
+                }
+                Expr::ConstDef {
+                    name, const_type, ..
+                } => {
+                    self.constants.insert(name.clone(), const_type.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Type and mutability of the variable `name`, where locals and
+    /// parameters shadow globals.
+    fn variable(&self, name: &str) -> Option<(Types, bool)> {
+        self.variables
+            .get(name)
+            .or_else(|| self.globals.get(name))
+            .cloned()
+    }
+
+    fn declare_function(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<Types>,
+    ) {
+        self.functions
+            .insert(name.to_string(), (parameters.to_vec(), return_type.clone()));
+    }
+
+    /// Infers the type of `expr`. Declarations are added to the context.
+    pub fn check(&mut self, expr: &Expr) -> Result<Types, TypeError> {
+        match expr {
+            Expr::Literal(node, _) => self.check_literal(node),
+            Expr::Variable { name, .. } => self
+                .variable(name)
+                .map(|(var_type, _)| var_type)
+                .or_else(|| self.constants.get(name).cloned())
+                .ok_or_else(|| TypeError::UndefinedVariable(name.clone())),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => self.check_binary_op(left, operator, right),
+            Expr::Unary {
+                operator, operand, ..
+            } => self.check_unary_op(operator, operand),
+            Expr::Cast { value, target, .. } => self.check_cast(value, target),
+            Expr::Assignment {
+                identifier, value, ..
+            } => self.check_assignment(identifier, value),
+            Expr::Discard(value, _) | Expr::Statement(value, _) => {
+                self.check(value).map(|_| Types::Unit)
+            }
+            Expr::LetDeclaration {
+                identifier,
+                mutable,
+                var_type,
+                value,
+                ..
+            } => self.check_let_declaration(identifier, *mutable, value, var_type),
+            Expr::IfElse {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => self.check_if_else(condition, then_branch, else_branch),
+            Expr::For {
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => self.check_for(variable, start, end, body),
+            Expr::Block(statements, _) => self.check_block(statements),
+            Expr::Print(value, _) => {
+                // Every type but arrays, enums and `()` is printable
+                if let Types::Array(..) | Types::Enum(_) | Types::Unit = self.check(value)? {
+                    return Err(TypeError::InvalidOperation(expr.to_string()));
+                }
+                Ok(Types::Unit)
+            }
+            Expr::MethodCall {
+                target,
+                method_name,
+                arguments,
+                ..
+            } => self.check_method_call(target, method_name, arguments),
+            Expr::SizeOf(query_type, _) | Expr::AlignOf(query_type, _) => {
+                self.check_type(query_type)?;
+                Ok(Types::I64)
+            }
+            Expr::Return(value, _) => self.check_return(value),
+            Expr::Break(_) | Expr::Continue(_) if self.loops == 0 => {
+                Err(TypeError::OutsideLoop(expr.to_string()))
+            }
+            Expr::Break(_) | Expr::Continue(_) => Ok(Types::Unit),
+            Expr::FunctionDef {
+                name,
+                parameters,
+                return_type,
+                body,
+                hook,
+                ..
+            } => self.check_function_def(name, parameters, return_type, body, *hook),
+            Expr::Call {
+                callee, arguments, ..
+            } => self.check_call(callee, arguments),
+            Expr::ArrayLiteral(elements, _) => self.check_array_literal(elements),
+            Expr::Index { target, index, .. } => self.check_index(target, index),
+            Expr::EnumDef { name, variants, .. } => self.check_enum_def(name, variants),
+            Expr::EnumVariant {
+                enum_name, variant, ..
+            } => self.check_variant(enum_name, variant),
+            Expr::Match {
+                scrutinee, arms, ..
+            } => self.check_match(scrutinee, arms),
+            Expr::ConstDef {
+                name,
+                const_type,
+                value,
+                ..
+            } => self.check_const_def(name, const_type, value),
+        }
+    }
+
+    /// Fails on enums used as a type without being defined.
+    fn check_type(&self, var_type: &Types) -> Result<(), TypeError> {
+        match var_type {
+            Types::Array(element, _) => self.check_type(element),
+            Types::Enum(name) if !self.enums.contains_key(name) => {
+                Err(TypeError::UndefinedType(name.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_literal(&self, node: &Nodes) -> Result<Types, TypeError> {
+        match node {
+            Nodes::Integer(_) => Ok(Types::I64),
+            Nodes::Float(_) => Ok(Types::F64),
+            Nodes::TypedInteger(_, literal_type) | Nodes::TypedFloat(_, literal_type) => {
+                Ok(literal_type.clone())
+            }
+            Nodes::Boolean(_) => Ok(Types::Bool),
+            Nodes::Char(_) => Ok(Types::Char),
+            Nodes::String(value) => match nul_position(value) {
+                Some(offset) => Err(TypeError::InvalidString(format!(
+                    "NUL byte at offset {}",
+                    offset
+                ))),
+                None => Ok(Types::String),
+            },
+        }
+    }
+}
+
+/// Strings are passed to C as NUL-terminated, so an embedded NUL would
+/// silently cut them short.
+pub(crate) fn nul_position(value: &str) -> Option<usize> {
+    value.find('\0')
+}
+
+fn is_int(var_type: &Types) -> bool {
+    matches!(var_type, Types::I32 | Types::I64 | Types::Bool)
+}
+
+fn is_float(var_type: &Types) -> bool {
+    matches!(var_type, Types::F32 | Types::F64)
+}
+
+/// Whether `as` converts `from` to `to`: between numeric types, from
+/// `bool`, `char` and enums to integers, and from any type to itself.
+fn is_castable(from: &Types, to: &Types) -> bool {
+    let numeric =
+        |var_type: &Types| matches!(var_type, Types::I32 | Types::I64 | Types::F32 | Types::F64);
+
+    from == to
+        || (numeric(from) && numeric(to))
+        || (matches!(from, Types::Bool | Types::Char | Types::Enum(_))
+            && matches!(to, Types::I32 | Types::I64))
+}
+
+/// Whether a value of type `from` can be stored in a `to` slot, i.e. the
+/// same implicit conversions `CodeGen::coerce_value` performs.
+fn is_coercible(from: &Types, to: &Types) -> bool {
+    match (from, to) {
+        (Types::Array(from, from_length), Types::Array(to, to_length)) => {
+            from_length == to_length && is_coercible(from, to)
+        }
+        _ => {
+            from == to
+                || (is_int(from) && is_int(to) && *from != Types::Bool && *to != Types::Bool)
+                || (is_float(from) && is_float(to))
+        }
+    }
+}
+
+// Operations
+impl TypeContext {
+    fn check_binary_op(
+        &mut self,
+        left: &Expr,
+        operator: &BinaryOp,
+        right: &Expr,
+    ) -> Result<Types, TypeError> {
+        let left_type = self.check(left)?;
+        let right_type = self.check(right)?;
+
+        let is_comparison = matches!(
+            operator,
+            BinaryOp::Equal
+                | BinaryOp::NotEqual
+                | BinaryOp::Greater
+                | BinaryOp::Less
+                | BinaryOp::GreaterEqual
+                | BinaryOp::LessEqual
+        );
+
+        let not_supported = || {
+            TypeError::OperatorNotSupported(
+                format!("{:?}", operator),
+                format!("{} | {}", left_type, right_type),
+            )
+        };
+
+        // Logical operators only take `bool`s, use `!= 0` to test an integer
+        if matches!(operator, BinaryOp::And | BinaryOp::Or) {
+            return match (&left_type, &right_type) {
+                (Types::Bool, Types::Bool) => Ok(Types::Bool),
+                _ => Err(not_supported()),
+            };
+        }
+
+        // Enums only compare for equality, by variant
+        if let Types::Enum(_) = left_type {
+            return match (left_type == right_type, operator) {
+                (true, BinaryOp::Equal | BinaryOp::NotEqual) => Ok(Types::Bool),
+                (false, _) => Err(TypeError::TypeMismatch(
+                    left_type.to_string(),
+                    right_type.to_string(),
+                )),
+                _ => Err(not_supported()),
+            };
+        }
+
+        // Characters only compare, by code point
+        if left_type == Types::Char || right_type == Types::Char {
+            return match (left_type == right_type, is_comparison) {
+                (true, true) => Ok(Types::Bool),
+                _ => Err(not_supported()),
+            };
+        }
+
+        if is_int(&left_type) && is_int(&right_type) {
+            if left_type != right_type {
+                return Err(TypeError::TypeMismatch(
+                    left_type.to_string(),
+                    right_type.to_string(),
+                ));
+            }
+
+            return Ok(if is_comparison {
+                Types::Bool
+            } else {
+                left_type
+            });
+        }
+
+        if is_float(&left_type) || is_float(&right_type) {
+            // Integers are promoted to f64
+            let promote = |var_type: &Types| {
+                if is_int(var_type) {
+                    Some(Types::F64)
+                } else if is_float(var_type) {
+                    Some(var_type.clone())
+                } else {
+                    None
+                }
+            };
+
+            let (Some(left_float), Some(right_float)) = (promote(&left_type), promote(&right_type))
+            else {
+                return Err(not_supported());
+            };
+
+            if left_float != right_float {
+                return Err(TypeError::TypeMismatch(
+                    left_float.to_string(),
+                    right_float.to_string(),
+                ));
+            }
+
+            return Ok(if is_comparison {
+                Types::Bool
+            } else {
+                left_float
+            });
+        }
+
+        // Strings only join, with each other
+        if left_type == Types::String && right_type == Types::String && *operator == BinaryOp::Add {
+            return Ok(Types::String);
+        }
+
+        Err(not_supported())
+    }
+
+    /// All elements must have the same type, which an empty literal doesn't
+    /// give.
+    fn check_array_literal(&mut self, elements: &[Expr]) -> Result<Types, TypeError> {
+        let Some((first, rest)) = elements.split_first() else {
+            return Err(TypeError::InvalidOperation("[]".into()));
+        };
+
+        let element_type = self.check(first)?;
+        for element in rest {
+            let other = self.check(element)?;
+            if other != element_type {
+                return Err(TypeError::TypeMismatch(
+                    element_type.to_string(),
+                    other.to_string(),
+                ));
+            }
+        }
+
+        Ok(Types::Array(Box::new(element_type), elements.len()))
+    }
+
+    fn check_index(&mut self, target: &Expr, index: &Expr) -> Result<Types, TypeError> {
+        let Types::Array(element_type, _) = self.check(target)? else {
+            return Err(TypeError::InvalidOperation(format!(
+                "indexing into `{}`",
+                target
+            )));
+        };
+
+        let index_type = self.check(index)?;
+        if !is_int(&index_type) || index_type == Types::Bool {
+            return Err(TypeError::TypeMismatch(
+                "i64".into(),
+                index_type.to_string(),
+            ));
+        }
+
+        Ok(*element_type)
+    }
+
+    fn check_unary_op(&mut self, operator: &UnaryOp, operand: &Expr) -> Result<Types, TypeError> {
+        let operand_type = self.check(operand)?;
+
+        let supported = match operator {
+            UnaryOp::Minus => is_int(&operand_type) || is_float(&operand_type),
+            UnaryOp::Not => operand_type == Types::Bool,
+            UnaryOp::BitNot => is_int(&operand_type) && operand_type != Types::Bool,
+        };
+
+        if supported {
+            Ok(operand_type)
+        } else {
+            Err(TypeError::OperatorNotSupported(
+                format!("{:?}", operator),
+                operand_type.to_string(),
+            ))
+        }
+    }
+
+    fn check_cast(&mut self, value: &Expr, target: &Types) -> Result<Types, TypeError> {
+        self.check_type(target)?;
+        let value_type = self.check(value)?;
+
+        if !is_castable(&value_type, target) {
+            return Err(TypeError::InvalidCast(
+                value.to_string(),
+                value_type.to_string(),
+                target.to_string(),
+            ));
+        }
+        Ok(target.clone())
+    }
+}
+
+// Assignments
+impl TypeContext {
+    fn check_assignment(&mut self, identifier: &str, value: &Expr) -> Result<Types, TypeError> {
+        if self.variable(identifier).is_none() && self.constants.contains_key(identifier) {
+            return Err(TypeError::InvalidConst(
+                identifier.to_string(),
+                "cannot be assigned to".into(),
+            ));
+        }
+        let (var_type, mutable) = self
+            .variable(identifier)
+            .ok_or_else(|| TypeError::UndefinedVariable(identifier.to_string()))?;
+
+        if !mutable {
+            return Err(TypeError::AssignToImmutable(identifier.to_string()));
+        }
+
+        let value_type = self.check(value)?;
+
+        if !is_coercible(&value_type, &var_type) {
+            return Err(TypeError::AssignTypeMismatch(
+                identifier.to_string(),
+                var_type.to_string(),
+                value_type.to_string(),
+                value.to_string(),
+            ));
+        }
+
+        Ok(Types::Unit)
+    }
+
+    fn check_let_declaration(
+        &mut self,
+        identifier: &str,
+        mutable: bool,
+        value: &Expr,
+        var_type: &Option<Types>,
+    ) -> Result<Types, TypeError> {
+        if let Some(var_type) = var_type {
+            self.check_type(var_type)?;
+        }
+
+        let value_type = self.check(value)?;
+        let var_type = var_type.clone().unwrap_or_else(|| value_type.clone());
+
+        if !is_coercible(&value_type, &var_type) {
+            return Err(TypeError::LetTypeMismatch(
+                identifier.to_string(),
+                var_type.to_string(),
+                value_type.to_string(),
+                value.to_string(),
+            ));
+        }
+
+        // Outside any function or block, the binding is a global
+        if self.return_types.is_empty() && self.variables.depth() == 1 {
+            self.globals
+                .insert(identifier.to_string(), (var_type, mutable));
+        } else {
+            self.variables.declare(identifier, (var_type, mutable));
+        }
+
+        Ok(Types::Unit)
+    }
+
+    /// Constants hold a single scalar computed at compile time, from
+    /// literals and the constants defined before them.
+    fn check_const_def(
+        &mut self,
+        name: &str,
+        const_type: &Types,
+        value: &Expr,
+    ) -> Result<Types, TypeError> {
+        let invalid = |reason: String| Err(TypeError::InvalidConst(name.to_string(), reason));
+
+        if !self.defined_constants.insert(name.to_string()) {
+            return invalid("is defined more than once".into());
+        }
+        if !matches!(
+            const_type,
+            Types::I32 | Types::I64 | Types::F32 | Types::F64 | Types::Bool | Types::Char
+        ) {
+            return invalid(format!("type `{}` is not a scalar", const_type));
+        }
+        let is_const =
+            |name: &str| self.variable(name).is_none() && self.defined_constants.contains(name);
+        if !value.is_constant(&is_const) {
+            return invalid(format!(
+                "initializer `{}` is not a constant expression",
+                value
+            ));
+        }
+
+        let value_type = self.check(value)?;
+        if !is_coercible(&value_type, const_type) {
+            return invalid(format!(
+                "expected `{}` but initializer `{}` has type `{}`",
+                const_type, value, value_type
+            ));
+        }
+
+        self.constants.insert(name.to_string(), const_type.clone());
+
+        Ok(const_type.clone())
+    }
+}
+
+// If-Else
+impl TypeContext {
+    fn check_if_else(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Option<Box<Expr>>,
+    ) -> Result<Types, TypeError> {
+        let condition_type = self.check(condition)?;
+        if !is_int(&condition_type) {
+            return Err(TypeError::TypeMismatch(
+                Types::Bool.to_string(),
+                condition_type.to_string(),
+            ));
+        }
+
+        let then_type = self.check(then_branch)?;
+        let Some(else_branch) = else_branch else {
+            return Ok(Types::Unit);
+        };
+        let else_type = self.check(else_branch)?;
+
+        // A branch that leaves through `return`, `break` or `continue` has
+        // no value to merge
+        if diverges(then_branch) {
+            Ok(else_type)
+        } else if diverges(else_branch) || then_type == else_type {
+            Ok(then_type)
+        } else {
+            Err(TypeError::TypeMismatch(
+                then_type.to_string(),
+                else_type.to_string(),
+            ))
+        }
+    }
+}
+
+// Enums
+impl TypeContext {
+    fn check_enum_def(&mut self, name: &str, variants: &[String]) -> Result<Types, TypeError> {
+        for (index, variant) in variants.iter().enumerate() {
+            if variants[..index].contains(variant) {
+                return Err(TypeError::DuplicateDefinition(
+                    format!("{}::{}", name, variant),
+                    format!("in `enum {}`", name),
+                ));
+            }
+        }
+
+        self.enums.insert(name.to_string(), variants.to_vec());
+
+        Ok(Types::Unit)
+    }
+
+    fn check_variant(&self, enum_name: &str, variant: &str) -> Result<Types, TypeError> {
+        let variants = self
+            .enums
+            .get(enum_name)
+            .ok_or_else(|| TypeError::UndefinedType(enum_name.to_string()))?;
+
+        if !variants.iter().any(|name| name == variant) {
+            return Err(TypeError::UndefinedVariant(
+                enum_name.to_string(),
+                variant.to_string(),
+            ));
+        }
+
+        Ok(Types::Enum(enum_name.to_string()))
+    }
+
+    /// Every variant must be covered exactly once, by its own arm or a
+    /// trailing `_`, and the arms that don't diverge must agree on a type.
+    fn check_match(&mut self, scrutinee: &Expr, arms: &[MatchArm]) -> Result<Types, TypeError> {
+        let scrutinee_type = self.check(scrutinee)?;
+        let Types::Enum(enum_name) = &scrutinee_type else {
+            return Err(TypeError::InvalidOperation(format!(
+                "match on `{}` of type `{}`",
+                scrutinee, scrutinee_type
+            )));
+        };
+
+        let mut missing = self
+            .enums
+            .get(enum_name)
+            .cloned()
+            .ok_or_else(|| TypeError::UndefinedType(enum_name.clone()))?;
+        let mut arm_type: Option<Types> = None;
+
+        for arm in arms {
+            let covered = match &arm.pattern {
+                Pattern::Wildcard => !missing.is_empty(),
+                Pattern::Variant {
+                    enum_name: pattern_enum,
+                    variant,
+                } => {
+                    if self.check_variant(pattern_enum, variant)? != scrutinee_type {
+                        return Err(TypeError::TypeMismatch(
+                            enum_name.clone(),
+                            pattern_enum.clone(),
+                        ));
+                    }
+                    missing.iter().any(|name| name == variant)
+                }
+            };
+            if !covered {
+                return Err(TypeError::UnreachableArm(arm.pattern.to_string()));
+            }
+
+            match &arm.pattern {
+                Pattern::Wildcard => missing.clear(),
+                Pattern::Variant { variant, .. } => missing.retain(|name| name != variant),
+            }
+
+            let body_type = self.check(&arm.body)?;
+            if diverges(&arm.body) {
+                continue;
+            }
+            match &arm_type {
+                Some(arm_type) if *arm_type != body_type => {
+                    return Err(TypeError::TypeMismatch(
+                        arm_type.to_string(),
+                        body_type.to_string(),
+                    ));
+                }
+                _ => arm_type = Some(body_type),
+            }
+        }
+
+        if !missing.is_empty() {
+            let missing = missing
+                .iter()
+                .map(|variant| format!("{}::{}", enum_name, variant))
+                .collect();
+            return Err(TypeError::NonExhaustiveMatch(
+                scrutinee.to_string(),
+                missing,
+            ));
+        }
+
+        // Like a function body, a match whose arms all return yields `()`
+        Ok(arm_type.unwrap_or(Types::Unit))
+    }
+}
+
+// For
+impl TypeContext {
+    fn check_for(
+        &mut self,
+        variable: &str,
+        start: &Expr,
+        end: &Expr,
+        body: &Expr,
+    ) -> Result<Types, TypeError> {
+        let start_type = self.check(start)?;
+        let end_type = self.check(end)?;
+
+        for bound_type in [&start_type, &end_type] {
+            if !is_int(bound_type) {
+                return Err(TypeError::TypeMismatch(
+                    Types::I64.to_string(),
+                    bound_type.to_string(),
+                ));
+            }
+        }
+
+        self.variables.push_scope();
+        self.variables.declare(variable, (start_type, false));
+        self.loops += 1;
+
+        let result = self.check(body);
+
+        self.loops -= 1;
+        self.variables.pop_scope();
+
+        result.map(|_| Types::Unit)
+    }
+}
+
+// Functions
+impl TypeContext {
+    fn check_function_def(
+        &mut self,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<Types>,
+        body: &Expr,
+        hook: Option<Hook>,
+    ) -> Result<Types, TypeError> {
+        // Hooks are called by the runtime, which passes and expects nothing
+        if let Some(hook) = hook
+            && (!parameters.is_empty() || return_type.is_some())
+        {
+            return Err(TypeError::InvalidHook(hook.to_string(), name.to_string()));
+        }
+        for parameter in parameters {
+            self.check_type(&parameter.param_type)?;
+        }
+        if let Some(return_type) = return_type {
+            self.check_type(return_type)?;
+        }
+
+        self.declare_function(name, parameters, return_type);
+
+        // The body only sees its parameters
+        let mut scope = SymbolTable::new();
+        for parameter in parameters {
+            scope.declare(&parameter.name, (parameter.param_type.clone(), false));
+        }
+
+        let outer_variables = std::mem::replace(&mut self.variables, scope);
+        let outer_loops = std::mem::take(&mut self.loops);
+        self.return_types
+            .push((name.to_string(), return_type.clone()));
+
+        let result = self.check(body);
+
+        self.return_types.pop();
+        self.loops = outer_loops;
+        self.variables = outer_variables;
+
+        let body_type = result?;
+        if let Some(return_type) = return_type
+            && !ends_with_return(body)
+            && !is_coercible(&body_type, return_type)
+        {
+            return Err(TypeError::ReturnTypeMismatch(
+                name.to_string(),
+                return_type.to_string(),
+                body_type.to_string(),
+            ));
+        }
+
+        Ok(Types::Unit)
+    }
+
+    fn check_call(&mut self, callee: &str, arguments: &[Expr]) -> Result<Types, TypeError> {
+        let Some((parameters, return_type)) = self.functions.get(callee).cloned() else {
+            return match Intrinsic::resolve(callee) {
+                Some(intrinsic) => self.check_intrinsic(intrinsic, arguments),
+                None => Err(TypeError::UndefinedFunction(callee.to_string())),
+            };
+        };
+
+        if arguments.len() != parameters.len() {
+            return Err(TypeError::WrongArgumentCount(
+                callee.to_string(),
+                parameters.len(),
+                arguments.len(),
+            ));
+        }
+
+        for (argument, parameter) in arguments.iter().zip(&parameters) {
+            let argument_type = self.check(argument)?;
+            if !is_coercible(&argument_type, &parameter.param_type) {
+                return Err(TypeError::ArgumentTypeMismatch(
+                    callee.to_string(),
+                    parameter.name.clone(),
+                    parameter.param_type.to_string(),
+                    argument_type.to_string(),
+                ));
+            }
+        }
+
+        // Calls to functions without a return type evaluate to `()`
+        Ok(return_type.unwrap_or(Types::Unit))
+    }
+
+    fn check_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        arguments: &[Expr],
+    ) -> Result<Types, TypeError> {
+        if arguments.len() != intrinsic.arity() {
+            return Err(TypeError::WrongArgumentCount(
+                intrinsic.name().to_string(),
+                intrinsic.arity(),
+                arguments.len(),
+            ));
+        }
+
+        let mut argument_types = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            argument_types.push(self.check(argument)?);
+        }
+
+        let entry = intrinsic.entry();
+        for (parameter, argument_type) in entry.parameters.iter().zip(&argument_types) {
+            let first = &argument_types[0];
+            if parameter.accepts(argument_type, first) {
+                continue;
+            }
+            return Err(match parameter {
+                ParamKind::SameAsFirst => {
+                    TypeError::TypeMismatch(first.to_string(), argument_type.to_string())
+                }
+                _ => TypeError::InvalidOperation(intrinsic.call_text(arguments)),
+            });
+        }
+
+        match &entry.returns {
+            Returns::Type(return_type) => Ok(return_type.clone()),
+            Returns::First => Ok(argument_types[0].clone()),
+        }
+    }
+
+    /// `target.method(args)` calls `method(target, args)`, as in `CodeGen`.
+    fn check_method_call(
+        &mut self,
+        target: &Expr,
+        method_name: &str,
+        arguments: &[Expr],
+    ) -> Result<Types, TypeError> {
+        let takes_receiver = self
+            .functions
+            .get(method_name)
+            .is_some_and(|(parameters, _)| !parameters.is_empty());
+        if !takes_receiver {
+            return Err(TypeError::UndefinedMethod(method_name.to_string()));
+        }
+
+        let arguments: Vec<Expr> = std::iter::once(target.clone())
+            .chain(arguments.iter().cloned())
+            .collect();
+
+        self.check_call(method_name, &arguments)
+    }
+
+    fn check_return(&mut self, value: &Option<Box<Expr>>) -> Result<Types, TypeError> {
+        let value_type = match value {
+            Some(value) => Some(self.check(value)?),
+            None => None,
+        };
+
+        match (self.return_types.last(), value_type) {
+            // `main` returns an `i32` exit code
+            (None, None) => Ok(Types::I64),
+            (None, Some(value_type)) if is_int(&value_type) => Ok(value_type),
+            (None, Some(value_type)) => Err(TypeError::ReturnTypeMismatch(
+                "main".to_string(),
+                Types::I32.to_string(),
+                value_type.to_string(),
+            )),
+            (Some((_, None)), None) => Ok(Types::I64),
+            (Some((_, Some(return_type))), Some(value_type))
+                if is_coercible(&value_type, return_type) =>
+            {
+                Ok(return_type.clone())
+            }
+            (Some((function, return_type)), value_type) => Err(TypeError::ReturnTypeMismatch(
+                function.clone(),
+                return_type
+                    .as_ref()
+                    .map_or("nothing".to_string(), |t| t.to_string()),
+                value_type.map_or("nothing".to_string(), |t| t.to_string()),
+            )),
+        }
+    }
+}
+
+/// Whether the last statement of a function body is a `return`, whose
+/// value has already been checked against the return type.
+fn ends_with_return(body: &Expr) -> bool {
+    match body {
+        Expr::Block(statements, _) => statements.last().is_some_and(ends_with_return),
+        Expr::Statement(statement, _) => ends_with_return(statement),
+        Expr::Return(..) => true,
+        _ => false,
+    }
+}
+
+/// Whether `body` ends by leaving it through `return`, `break` or
+/// `continue`, so it has no value of its own.
+fn diverges(body: &Expr) -> bool {
+    match body {
+        Expr::Block(statements, _) => statements.last().is_some_and(diverges),
+        Expr::Statement(statement, _) => diverges(statement),
+        Expr::Break(_) | Expr::Continue(_) => true,
+        _ => ends_with_return(body),
+    }
+}
+
+// Block
+impl TypeContext {
+    fn check_block(&mut self, statements: &[Expr]) -> Result<Types, TypeError> {
+        self.variables.push_scope();
+
+        let result = statements
+            .iter()
+            .try_fold(Types::Unit, |_, statement| self.check(statement));
+
+        self.variables.pop_scope();
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_parser::span::Span;
+
+    fn context(source: &str) -> TypeContext {
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let mut context = TypeContext::new();
+
+        for statement in parser.parse().unwrap() {
+            context.check(&statement).unwrap();
+        }
+
+        context
+    }
+
+    #[test]
+    fn literal_types() {
+        let context = TypeContext::new();
+
+        assert_eq!(type_of("1", &context), Ok(Types::I64));
+        assert_eq!(type_of("1.5", &context), Ok(Types::F64));
+        assert_eq!(type_of("true", &context), Ok(Types::Bool));
+        assert_eq!(type_of("\"hi\"", &context), Ok(Types::String));
+        assert_eq!(type_of("size_of(f32)", &context), Ok(Types::I64));
+        assert_eq!(type_of("5i32", &context), Ok(Types::I32));
+        assert_eq!(type_of("2.5f32 * 2f32", &context), Ok(Types::F32));
+        assert_eq!(type_of("'a'", &context), Ok(Types::Char));
+        assert_eq!(type_of("'a' <= 'b'", &context), Ok(Types::Bool));
+        assert_eq!(
+            type_of("1 < 2 && true || !false", &context),
+            Ok(Types::Bool)
+        );
+        assert_eq!(
+            type_of("'a' + 'b'", &context),
+            Err(TypeError::OperatorNotSupported(
+                "Add".into(),
+                "char | char".into()
+            ))
+        );
+        assert_eq!(type_of("\"a\" + \"b\"", &context), Ok(Types::String));
+        assert_eq!(
+            type_of("\"a\" + 1", &context),
+            Err(TypeError::OperatorNotSupported(
+                "Add".into(),
+                "string | i64".into()
+            ))
+        );
+        assert_eq!(
+            type_of("\"b\" > \"a\"", &context),
+            Err(TypeError::OperatorNotSupported(
+                "Greater".into(),
+                "string | string".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn logical_operators_take_bools() {
+        let context = context("let x: i32 = 5; let b = true;");
+
+        assert_eq!(type_of("!b || x > 1i32", &context), Ok(Types::Bool));
+        assert_eq!(type_of("~x", &context), Ok(Types::I32));
+        assert_eq!(
+            type_of("x && b", &context),
+            Err(TypeError::OperatorNotSupported(
+                "And".into(),
+                "i32 | bool".into()
+            ))
+        );
+        assert_eq!(
+            type_of("!x", &context),
+            Err(TypeError::OperatorNotSupported("Not".into(), "i32".into()))
+        );
+        assert_eq!(
+            type_of("~b", &context),
+            Err(TypeError::OperatorNotSupported(
+                "BitNot".into(),
+                "bool".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn variables_and_operators() {
+        let context = context("let x: i32 = 1; let y = 2.5;");
+
+        assert_eq!(type_of("x", &context), Ok(Types::I32));
+        assert_eq!(type_of("x * x", &context), Ok(Types::I32));
+        assert_eq!(type_of("x < x", &context), Ok(Types::Bool));
+        assert_eq!(type_of("y + 1", &context), Ok(Types::F64));
+        assert_eq!(type_of("-y", &context), Ok(Types::F64));
+        assert_eq!(
+            type_of("x + 1", &context),
+            Err(TypeError::TypeMismatch("i32".into(), "i64".into()))
+        );
+    }
+
+    #[test]
+    fn array_types() {
+        let context = context("let xs: [i32; 3] = [1, 2, 3]; let i = 1;");
+        let array = |element: Types, length| Types::Array(Box::new(element), length);
+
+        assert_eq!(type_of("xs", &context), Ok(array(Types::I32, 3)));
+        assert_eq!(type_of("xs[i] * xs[0]", &context), Ok(Types::I32));
+        assert_eq!(
+            type_of("[[1.5], [2.5]]", &context),
+            Ok(array(array(Types::F64, 1), 2))
+        );
+        assert_eq!(
+            type_of("[1, true]", &context),
+            Err(TypeError::TypeMismatch("i64".into(), "bool".into()))
+        );
+        assert_eq!(
+            type_of("xs[true]", &context),
+            Err(TypeError::TypeMismatch("i64".into(), "bool".into()))
+        );
+        assert_eq!(
+            type_of("i[0]", &context),
+            Err(TypeError::InvalidOperation("indexing into `i`".into()))
+        );
+        assert_eq!(
+            type_of("[]", &context),
+            Err(TypeError::InvalidOperation("[]".into()))
+        );
+        assert_eq!(
+            type_of("print(xs)", &context),
+            Err(TypeError::InvalidOperation("print(xs)".into()))
+        );
+        assert!(matches!(
+            type_of("let ys: [i64; 2] = xs", &context),
+            Err(TypeError::LetTypeMismatch(..))
+        ));
+    }
+
+    #[test]
+    fn query_does_not_change_context() {
+        let context = TypeContext::new();
+
+        assert_eq!(type_of("let z = true; z", &context), Ok(Types::Bool));
+        assert_eq!(
+            type_of("z", &context),
+            Err(TypeError::UndefinedVariable("z".into()))
+        );
+    }
+
+    #[test]
+    fn assignment_requires_let_mut() {
+        let context = context("let x = 1; let mut y = 2;");
+
+        assert_eq!(type_of("y = 3", &context), Ok(Types::Unit));
+        assert_eq!(type_of("let z = y = 3; z", &context), Ok(Types::Unit));
+        assert_eq!(
+            type_of("x = 3", &context),
+            Err(TypeError::AssignToImmutable("x".into()))
+        );
+        assert_eq!(
+            type_of("for i in 0..3 { i = 0; }", &context),
+            Err(TypeError::AssignToImmutable("i".into()))
+        );
+        assert_eq!(
+            type_of("fn f(n: i64) { n = 0; }", &context),
+            Err(TypeError::AssignToImmutable("n".into()))
+        );
+    }
+
+    #[test]
+    fn block_and_if_types() {
+        let context = TypeContext::new();
+
+        assert_eq!(type_of("{ let a = 1.0; a }", &context), Ok(Types::F64));
+        assert_eq!(type_of("{}", &context), Ok(Types::Unit));
+        assert_eq!(
+            type_of("if true { 1i32 } else { 0i32 }", &context),
+            Ok(Types::I32)
+        );
+        assert_eq!(
+            type_of("let x = if true { 1 } else { 2.0 };", &context),
+            Err(TypeError::TypeMismatch("i64".into(), "f64".into()))
+        );
+        assert_eq!(
+            type_of(
+                "for i in 0..3 { let x = if i > 1 { break; } else { 2.0 }; }",
+                &context
+            ),
+            Ok(Types::Unit)
+        );
+        assert_eq!(
+            type_of("if true { print(1) } else { print(2.0) }", &context),
+            Ok(Types::Unit)
+        );
+        assert_eq!(
+            type_of("let x = if true { 1 } else { print(\"a\"); };", &context),
+            Err(TypeError::TypeMismatch("i64".into(), "()".into()))
+        );
+        assert_eq!(type_of("print(1 < 2.5)", &context), Ok(Types::Unit));
+        assert_eq!(
+            type_of("\"a\0b\"", &context),
+            Err(TypeError::InvalidString("NUL byte at offset 1".into()))
+        );
+        assert_eq!(
+            type_of("if 1.0 { 1 }", &context),
+            Err(TypeError::TypeMismatch("bool".into(), "f64".into()))
+        );
+    }
+
+    #[test]
+    fn statements_are_unit() {
+        let context = context("fn greet() { print(\"hi\"); } fn one() -> i64 { 1 }");
+
+        assert_eq!(type_of("if true { 1 }", &context), Ok(Types::Unit));
+        assert_eq!(type_of("greet()", &context), Ok(Types::Unit));
+        assert_eq!(type_of("_ = one()", &context), Ok(Types::Unit));
+        assert_eq!(type_of("{ 1 }", &context), Ok(Types::I64));
+        assert_eq!(type_of("{ 1; }", &context), Ok(Types::Unit));
+        assert_eq!(type_of("let v = { one(); }; v", &context), Ok(Types::Unit));
+        assert_eq!(
+            type_of("fn f() -> i64 { 1; }", &context),
+            Err(TypeError::ReturnTypeMismatch(
+                "f".into(),
+                "i64".into(),
+                "()".into()
+            ))
+        );
+        assert_eq!(
+            type_of("print(greet())", &context),
+            Err(TypeError::InvalidOperation("print(greet())".into()))
+        );
+        assert_eq!(
+            type_of("fn f() -> i64 { if true { 1 } }", &context),
+            Err(TypeError::ReturnTypeMismatch(
+                "f".into(),
+                "i64".into(),
+                "()".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn for_loop_types() {
+        let context = TypeContext::new();
+
+        assert_eq!(
+            type_of("let n: i32 = 3; for i in 0..n { i + 1 }", &context),
+            Ok(Types::Unit)
+        );
+        assert_eq!(
+            type_of("for i in 0..2.5 {}", &context),
+            Err(TypeError::TypeMismatch("i64".into(), "f64".into()))
+        );
+        assert_eq!(
+            type_of("for i in 0..3 {} i", &context),
+            Err(TypeError::UndefinedVariable("i".into()))
+        );
+    }
+
+    #[test]
+    fn function_types() {
+        let context = TypeContext::new();
+
+        assert_eq!(
+            type_of(
+                "let a = half(3.0); fn half(x: f64) -> f64 { x / 2.0 } a",
+                &context
+            ),
+            Ok(Types::F64)
+        );
+        assert_eq!(
+            type_of("fn f(x: i32) -> i32 { return x; } f(1)", &context),
+            Ok(Types::I32)
+        );
+        assert_eq!(
+            type_of("fn f() -> i32 { 1.5 }", &context),
+            Err(TypeError::ReturnTypeMismatch(
+                "f".into(),
+                "i32".into(),
+                "f64".into()
+            ))
+        );
+        assert_eq!(
+            type_of("fn f() { return 1; }", &context),
+            Err(TypeError::ReturnTypeMismatch(
+                "f".into(),
+                "nothing".into(),
+                "i64".into()
+            ))
+        );
+        assert_eq!(
+            type_of("fn f(x: bool) {} f(1, 2)", &context),
+            Err(TypeError::WrongArgumentCount("f".into(), 1, 2))
+        );
+        assert_eq!(
+            type_of("g()", &context),
+            Err(TypeError::UndefinedFunction("g".into()))
+        );
+        assert_eq!(
+            type_of("fn half(x: f64) -> f64 { x / 2.0 } half(true)", &context),
+            Err(TypeError::ArgumentTypeMismatch(
+                "half".into(),
+                "x".into(),
+                "f64".into(),
+                "bool".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn declaration_errors_name_the_expression() {
+        let context = context("let mut x: i32 = 1;");
+
+        assert_eq!(
+            type_of("let y: bool = x * 2i32", &context),
+            Err(TypeError::LetTypeMismatch(
+                "y".into(),
+                "bool".into(),
+                "i32".into(),
+                "x * 2i32".into()
+            ))
+        );
+        assert_eq!(
+            type_of("x = \"one\"", &context),
+            Err(TypeError::AssignTypeMismatch(
+                "x".into(),
+                "i32".into(),
+                "string".into(),
+                "\"one\"".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn enums_and_match_exhaustiveness() {
+        let context = context("enum Color { Red, Green, Blue } let c = Color::Red;");
+
+        assert_eq!(type_of("c", &context), Ok(Types::Enum("Color".into())));
+        assert_eq!(type_of("c != Color::Blue", &context), Ok(Types::Bool));
+        assert_eq!(
+            type_of(
+                "match c { Color::Red => 1i32, Color::Green => return 0, _ => 3i32 }",
+                &context
+            ),
+            Ok(Types::I32)
+        );
+        assert_eq!(
+            type_of("match c { Color::Red => 1, Color::Blue => 2 }", &context),
+            Err(TypeError::NonExhaustiveMatch(
+                "c".into(),
+                vec!["Color::Green".into()]
+            ))
+        );
+        assert_eq!(
+            type_of(
+                "match c { Color::Red => 1, _ => 2, Color::Blue => 3 }",
+                &context
+            ),
+            Err(TypeError::UnreachableArm("Color::Blue".into()))
+        );
+        assert_eq!(
+            type_of("match c { Color::Red => 1, _ => true }", &context),
+            Err(TypeError::TypeMismatch("i64".into(), "bool".into()))
+        );
+        assert_eq!(
+            type_of(r#"match c { Color::Red => 1, _ => "one" }"#, &context),
+            Err(TypeError::TypeMismatch("i64".into(), "string".into()))
+        );
+        assert_eq!(
+            type_of("Color::Purple", &context),
+            Err(TypeError::UndefinedVariant("Color".into(), "Purple".into()))
+        );
+        assert_eq!(
+            type_of("let s: Shape = c", &context),
+            Err(TypeError::UndefinedType("Shape".into()))
+        );
+        assert_eq!(
+            type_of("match 1 { _ => 0 }", &context),
+            Err(TypeError::InvalidOperation(
+                "match on `1` of type `i64`".into()
+            ))
+        );
+        assert_eq!(
+            type_of("enum Dup { A, A }", &context),
+            Err(TypeError::DuplicateDefinition(
+                "Dup::A".into(),
+                "in `enum Dup`".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn casts() {
+        let context = context("enum Color { Red }");
+
+        assert_eq!(type_of("1.5 as i32", &context), Ok(Types::I32));
+        assert_eq!(type_of("'a' as i64 + 1", &context), Ok(Types::I64));
+        assert_eq!(type_of("Color::Red as i32", &context), Ok(Types::I32));
+        assert_eq!(type_of("-1 as f64 * 2.0", &context), Ok(Types::F64));
+        assert_eq!(
+            type_of("1 as bool", &context),
+            Err(TypeError::InvalidCast(
+                "1".into(),
+                "i64".into(),
+                "bool".into()
+            ))
+        );
+        assert_eq!(
+            type_of("\"s\" as i64", &context),
+            Err(TypeError::InvalidCast(
+                "\"s\"".into(),
+                "string".into(),
+                "i64".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn hooks_take_and_return_nothing() {
+        assert_eq!(
+            type_of("#[on_start] fn init() { print(1); } 1", &TypeContext::new()),
+            Ok(Types::I64)
+        );
+        assert_eq!(
+            type_of("#[on_exit] fn done() -> i64 { 0 }", &TypeContext::new()),
+            Err(TypeError::InvalidHook("on_exit".into(), "done".into()))
+        );
+    }
+
+    #[test]
+    fn functions_see_earlier_globals() {
+        let context = context("let mut count = 0; fn bump() { count += 1; }");
+
+        assert_eq!(type_of("count", &context), Ok(Types::I64));
+        assert_eq!(
+            type_of("fn f(count: bool) -> bool { count }", &context),
+            Ok(Types::Unit)
+        );
+        assert_eq!(
+            type_of("let total = 1; fn f() { total = 2; }", &TypeContext::new()),
+            Err(TypeError::AssignToImmutable("total".into()))
+        );
+        assert_eq!(
+            type_of("fn f() -> i64 { late } let late = 1;", &TypeContext::new()),
+            Err(TypeError::UndefinedVariable("late".into()))
+        );
+    }
+
+    #[test]
+    fn constants() {
+        let context = context("const MAX: i64 = 100; const RATE: f32 = 0.5;");
+
+        // Like functions, constants may be used before their definition
+        assert_eq!(
+            type_of(
+                "fn twice() -> i64 { return MAX * 2; } const MAX: i64 = 100; twice()",
+                &TypeContext::new()
+            ),
+            Ok(Types::I64)
+        );
+        assert_eq!(type_of("MAX + 1", &context), Ok(Types::I64));
+        assert_eq!(type_of("RATE", &context), Ok(Types::F32));
+        assert_eq!(type_of("let MAX = true; MAX", &context), Ok(Types::Bool));
+        assert_eq!(
+            type_of("MAX = 2", &context),
+            Err(TypeError::InvalidConst(
+                "MAX".into(),
+                "cannot be assigned to".into()
+            ))
+        );
+
+        for (source, reason) in [
+            (
+                "let x = 1; const N: i64 = x;",
+                "initializer `x` is not a constant expression",
+            ),
+            (
+                "const N: i64 = M; const M: i64 = 1;",
+                "initializer `M` is not a constant expression",
+            ),
+            ("const N: String = \"s\";", "type `string` is not a scalar"),
+            (
+                "const N: i32 = 1.5;",
+                "expected `i32` but initializer `1.5` has type `f64`",
+            ),
+            (
+                "const N: i64 = 1; const N: i64 = 2;",
+                "is defined more than once",
+            ),
+        ] {
+            assert_eq!(
+                type_of(source, &TypeContext::new()),
+                Err(TypeError::InvalidConst("N".into(), reason.into())),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn break_and_continue_need_a_loop() {
+        let context = TypeContext::new();
+
+        assert_eq!(
+            type_of("for i in 0..3 { if i > 1 { break; } continue; }", &context),
+            Ok(Types::Unit)
+        );
+        assert_eq!(
+            type_of("break", &context),
+            Err(TypeError::OutsideLoop("break".into()))
+        );
+        assert_eq!(
+            type_of("for i in 0..3 { fn f() { continue; } }", &context),
+            Err(TypeError::OutsideLoop("continue".into()))
+        );
+    }
+
+    #[test]
+    fn method_call_types() {
+        let mut context =
+            context("fn scale(x: f64, by: f64) -> f64 { x * by } fn now() -> i64 { 0 }");
+        let method_call = |method_name: &str, arguments: Vec<Expr>| Expr::MethodCall {
+            target: Box::new(Expr::Literal(Nodes::Float(2.0), Span::default())),
+            method_name: method_name.to_string(),
+            arguments,
+            span: Span::default(),
+        };
+
+        assert_eq!(
+            context.check(&method_call(
+                "scale",
+                vec![Expr::Literal(Nodes::Float(3.0), Span::default())]
+            )),
+            Ok(Types::F64)
+        );
+        assert_eq!(
+            context.check(&method_call("now", vec![])),
+            Err(TypeError::UndefinedMethod("now".into()))
+        );
+    }
+
+    #[test]
+    fn len_intrinsic() {
+        let context = context("let xs = [1.5, 2.5]; let name = \"rune\";");
+
+        assert_eq!(type_of("len(xs) + len(name)", &context), Ok(Types::I64));
+        assert_eq!(type_of("len(input())", &context), Ok(Types::I64));
+        assert_eq!(
+            type_of("len(1)", &context),
+            Err(TypeError::InvalidOperation("len(1)".into()))
+        );
+        assert_eq!(
+            type_of("len(xs, name)", &context),
+            Err(TypeError::WrongArgumentCount("len".into(), 1, 2))
+        );
+        assert_eq!(
+            type_of("fn len(x: bool) -> bool { x } len(true)", &context),
+            Ok(Types::Bool)
+        );
+    }
+
+    #[test]
+    fn euclidean_division_intrinsics() {
+        let context = context("let a: i32 = -7; let b: i32 = 2;");
+
+        assert_eq!(type_of("div_euclid(a, b)", &context), Ok(Types::I32));
+        assert_eq!(type_of("rem_euclid(-7, 2)", &context), Ok(Types::I64));
+        assert_eq!(
+            type_of("rem_euclid(a, 2)", &context),
+            Err(TypeError::TypeMismatch("i32".into(), "i64".into()))
+        );
+        assert_eq!(
+            type_of("div_euclid(1.5, 2.0)", &context),
+            Err(TypeError::InvalidOperation("div_euclid(1.5, 2.0)".into()))
+        );
+        assert_eq!(
+            type_of("rem_euclid(true, false)", &context),
+            Err(TypeError::InvalidOperation(
+                "rem_euclid(true, false)".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn float_classification_intrinsics() {
+        let context = context("let x: f32 = 1.5;");
+
+        assert_eq!(
+            type_of("is_nan(x) || is_inf(0.0)", &context),
+            Ok(Types::Bool)
+        );
+        assert_eq!(
+            type_of("is_inf(1)", &context),
+            Err(TypeError::InvalidOperation("is_inf(1)".into()))
+        );
+    }
+}
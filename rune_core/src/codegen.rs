@@ -1,43 +1,194 @@
 use inkwell::AddressSpace;
 use inkwell::FloatPredicate;
 use inkwell::IntPredicate;
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
-use inkwell::context::Context;
-use inkwell::module::Module;
-use inkwell::types::BasicTypeEnum;
-use inkwell::values::{BasicValueEnum, FloatValue, FunctionValue, IntValue, PointerValue};
-use rune_parser::parser::expr::Expr;
+use inkwell::context::{AsContextRef, Context};
+use inkwell::llvm_sys::{core, debuginfo};
+use inkwell::module::{Linkage, Module};
+use inkwell::targets::TargetData;
+use inkwell::types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, IntType, StructType};
+use inkwell::values::{
+    AsValueRef, BasicMetadataValueEnum, BasicValueEnum, FloatValue, FunctionValue,
+    InstructionValue, IntValue, PointerValue, StructValue,
+};
+use rune_parser::intrinsics::{Intrinsic, ParamKind};
+use rune_parser::parser::expr::{Expr, Hook, MatchArm, Parameter, Pattern};
 use rune_parser::parser::nodes::Nodes;
 use rune_parser::parser::ops::{BinaryOp, UnaryOp};
 use rune_parser::parser::types::Types;
-use std::collections::HashMap;
+use rune_parser::scope::SymbolTable;
+use rune_parser::span::Span;
+use std::collections::{HashMap, HashSet};
 
+use crate::checker::nul_position;
 use crate::errors::CodeGenError;
+use crate::ir_map::{StatementIr, module_entities};
+use crate::options::CompileOptions;
+use crate::stats::ModuleStats;
+use crate::warnings::CodeGenWarning;
+
+/// Functions every module defines or declares itself, which user functions
+/// can't be named after.
+pub const RUNTIME_SYMBOLS: [&str; 15] = [
+    "main",
+    "puts",
+    "printf",
+    "exit",
+    "atexit",
+    "getchar",
+    "malloc",
+    "realloc",
+    "free",
+    "rune_input",
+    "snprintf",
+    "strtod",
+    "strchr",
+    "atoi",
+    "rune_print_float",
+];
 
 pub struct CodeGen<'ctx> {
     pub context: &'ctx Context,
     pub module: Module<'ctx>,
     pub builder: Builder<'ctx>,
-    variables: HashMap<String, (PointerValue<'ctx>, BasicTypeEnum<'ctx>)>,
+    variables: SymbolTable<(PointerValue<'ctx>, BasicTypeEnum<'ctx>, bool)>,
+    /// Top-level `let`s, kept in module globals so functions can use them.
+    globals: HashMap<String, (PointerValue<'ctx>, BasicTypeEnum<'ctx>, bool)>,
+    /// Names used inside function bodies. Only top-level `let`s of these
+    /// names become globals, the rest stay in `main`'s stack slots, which
+    /// LLVM can keep in registers.
+    function_names: HashSet<String>,
+    /// Slots of `char` variables, which are `i32` in IR but print as text.
+    char_slots: HashSet<PointerValue<'ctx>>,
     function: Option<FunctionValue<'ctx>>,
+    functions: HashMap<String, FunctionSignature<'ctx>>,
+    /// Variants of each enum, whose index is the variant's `i32` tag.
+    enums: HashMap<String, Vec<String>>,
+    constants: HashMap<String, Constant<'ctx>>,
     puts_fn: Option<FunctionValue<'ctx>>,
+    printf_fn: Option<FunctionValue<'ctx>>,
+    /// `printf` format strings, created once per module.
+    format_strings: HashMap<&'static str, PointerValue<'ctx>>,
+    warnings: Vec<CodeGenWarning>,
+    /// Index of the top-level statement each warning is about, if any.
+    warning_statements: Vec<Option<usize>>,
+    /// Span of the expression each warning is about, where narrower than
+    /// its statement.
+    warning_spans: Vec<Option<Span>>,
+    /// Index of the top-level statement being compiled.
+    statement: Option<usize>,
+    /// Span of the innermost expression that failed to compile, if any.
+    failed_span: Option<Span>,
+    /// See [`CompileOptions::unchecked_indexing`].
+    unchecked_indexing: bool,
+    /// See [`CompileOptions::float_precision`].
+    float_precision: Option<u32>,
+    /// The blocks `break` and `continue` jump to, innermost loop last.
+    loops: Vec<LoopTargets<'ctx>>,
+    /// Heap buffers to free at the end of each statement being compiled,
+    /// innermost statement last. See [`CodeGen::compile_statement`].
+    temporaries: Vec<Vec<PointerValue<'ctx>>>,
+}
+
+#[derive(Clone, Copy)]
+struct LoopTargets<'ctx> {
+    exit: BasicBlock<'ctx>,
+    next: BasicBlock<'ctx>,
+    /// How many statements' temporaries were pending outside the loop, the
+    /// rest are freed before jumping out of the body.
+    temporaries: usize,
+}
+
+/// A top-level `const`, kept in an internal global constant. Reads use the
+/// folded value directly.
+#[derive(Clone, Copy)]
+struct Constant<'ctx> {
+    global: PointerValue<'ctx>,
+    value: BasicValueEnum<'ctx>,
+    is_char: bool,
+}
+
+/// A user-defined function and its parameters' names and types.
+#[derive(Clone)]
+struct FunctionSignature<'ctx> {
+    value: FunctionValue<'ctx>,
+    parameters: Vec<(String, BasicTypeEnum<'ctx>)>,
+    returns_char: bool,
 }
 
 impl<'ctx> CodeGen<'ctx> {
     pub fn new(context: &'ctx Context, module_name: &str) -> Self {
+        Self::with_options(context, module_name, &CompileOptions::default())
+    }
+
+    pub fn with_options(
+        context: &'ctx Context,
+        module_name: &str,
+        options: &CompileOptions,
+    ) -> Self {
         let module = context.create_module(module_name);
         let builder = context.create_builder();
 
+        if let Some(triple) = options.target_triple() {
+            module.set_triple(&triple);
+        }
+        if let Some(target_data) = options.target_data() {
+            module.set_data_layout(&target_data.get_data_layout());
+        }
+
         Self {
             context,
             module,
             builder,
-            variables: HashMap::new(),
+            variables: SymbolTable::new(),
+            globals: HashMap::new(),
+            function_names: HashSet::new(),
+            char_slots: HashSet::new(),
             function: None,
+            functions: HashMap::new(),
+            enums: HashMap::new(),
+            constants: HashMap::new(),
             puts_fn: None,
+            printf_fn: None,
+            format_strings: HashMap::new(),
+            warnings: Vec::new(),
+            warning_statements: Vec::new(),
+            warning_spans: Vec::new(),
+            statement: None,
+            failed_span: None,
+            unchecked_indexing: options.unchecked_indexing,
+            float_precision: options.float_precision,
+            loops: Vec::new(),
+            temporaries: Vec::new(),
         }
     }
 
+    /// Warnings collected while compiling so far.
+    pub fn warnings(&self) -> &[CodeGenWarning] {
+        &self.warnings
+    }
+
+    /// Index of the statement passed to [`CodeGen::compile_statements`]
+    /// each of [`CodeGen::warnings`] is about, in the same order. `None`
+    /// for warnings raised outside the statements, like in constants.
+    pub fn warning_statements(&self) -> &[Option<usize>] {
+        &self.warning_statements
+    }
+
+    /// Span of the expression each of [`CodeGen::warnings`] is about, in
+    /// the same order. `None` for warnings about a whole statement.
+    pub fn warning_spans(&self) -> &[Option<Span>] {
+        &self.warning_spans
+    }
+
+    /// Span of the innermost expression whose error was returned, so
+    /// callers can locate it in the source with [`CodeGenError::at`].
+    /// `None` for errors not caused by an expression.
+    pub fn failed_span(&self) -> Option<Span> {
+        self.failed_span
+    }
+
     pub fn create_main_function(&mut self) {
         let i32_type = self.context.i32_type();
         let fn_type = i32_type.fn_type(&[], false);
@@ -47,6 +198,7 @@ impl<'ctx> CodeGen<'ctx> {
         self.builder.position_at_end(basic_block);
         self.function = Some(function);
         self.declare_puts_function();
+        self.declare_printf_function();
     }
 
     fn declare_puts_function(&mut self) {
@@ -56,6 +208,14 @@ impl<'ctx> CodeGen<'ctx> {
         let puts_fn = self.module.add_function("puts", puts_fn_type, None);
         self.puts_fn = Some(puts_fn);
     }
+
+    fn declare_printf_function(&mut self) {
+        let i32_type = self.context.i32_type();
+        let i8_ptr_type = self.context.ptr_type(AddressSpace::default());
+        let printf_fn_type = i32_type.fn_type(&[i8_ptr_type.into()], true);
+        let printf_fn = self.module.add_function("printf", printf_fn_type, None);
+        self.printf_fn = Some(printf_fn);
+    }
 }
 
 // Core
@@ -65,8 +225,188 @@ impl<'ctx> CodeGen<'ctx> {
             self.create_main_function();
         }
 
-        for statement in statements {
-            self.compile_expression(statement)?;
+        self.declare_functions(statements)?;
+        self.build_hooks(statements)?;
+
+        for (index, statement) in statements.iter().enumerate() {
+            self.statement = Some(index);
+            self.warn_if_unused(statement);
+            self.compile_statement(statement, false)?;
+
+            // Code after a diverging statement is reported on the next one
+            self.statement = Some(index + 1);
+            if self.diverged_before(&statements[index + 1..]) {
+                break;
+            }
+        }
+        self.statement = None;
+
+        self.build_main_return()
+    }
+
+    /// Like [`CodeGen::compile_statements`], but also returns the IR first
+    /// created by each statement.
+    pub fn compile_statements_mapped(
+        &mut self,
+        statements: &[Expr],
+    ) -> Result<Vec<StatementIr>, CodeGenError> {
+        if self.function.is_none() {
+            self.create_main_function();
+        }
+
+        self.declare_functions(statements)?;
+        self.build_hooks(statements)?;
+
+        let mut seen: HashSet<String> = module_entities(&self.module)
+            .into_iter()
+            .map(|entity| entity.key)
+            .collect();
+        let mut mapped = Vec::with_capacity(statements.len());
+
+        for (index, statement) in statements.iter().enumerate() {
+            self.statement = Some(index);
+            self.warn_if_unused(statement);
+            self.compile_statement(statement, false)?;
+
+            let mut statement_ir = StatementIr::default();
+            for entity in module_entities(&self.module) {
+                if !seen.insert(entity.key) {
+                    continue;
+                }
+                statement_ir.names.extend(entity.name);
+                statement_ir.lines.extend(entity.line);
+            }
+            mapped.push(statement_ir);
+
+            // Code after a diverging statement is reported on the next one
+            self.statement = Some(index + 1);
+            if self.diverged_before(&statements[index + 1..]) {
+                break;
+            }
+        }
+        self.statement = None;
+
+        self.build_main_return()?;
+
+        Ok(mapped)
+    }
+
+    /// Whether the builder's current block already ends in a terminator,
+    /// e.g. after a `return`, so nothing more may be appended to it.
+    fn is_block_terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_some()
+    }
+
+    /// Checks whether the last statement diverged, warning about the first
+    /// of the `remaining` statements since none of them will be compiled.
+    fn diverged_before(&mut self, remaining: &[Expr]) -> bool {
+        if !self.is_block_terminated() {
+            return false;
+        }
+
+        if let Some(statement) = remaining.first() {
+            self.warn(CodeGenWarning::UnreachableCode(statement.to_string()));
+        }
+
+        true
+    }
+
+    /// Warns about a statement computed only for the value it discards, or
+    /// calling a function that returns one. `_ = value` discards on purpose.
+    fn warn_if_unused(&mut self, statement: &Expr) {
+        let unused = match statement {
+            Expr::Literal(..)
+            | Expr::Variable { .. }
+            | Expr::Binary { .. }
+            | Expr::Unary { .. }
+            | Expr::Cast { .. }
+            | Expr::ArrayLiteral(..)
+            | Expr::Index { .. }
+            | Expr::EnumVariant { .. }
+            | Expr::SizeOf(..)
+            | Expr::AlignOf(..) => true,
+            Expr::Call { callee, .. }
+            | Expr::MethodCall {
+                method_name: callee,
+                ..
+            } => match self.functions.get(callee) {
+                Some(signature) => signature.value.get_type().get_return_type().is_some(),
+                None => Intrinsic::resolve(callee).is_some(),
+            },
+            _ => false,
+        };
+
+        if unused {
+            self.warn(CodeGenWarning::UnusedValue(statement.to_string()));
+        }
+    }
+
+    /// Records `warning` against the statement being compiled.
+    fn warn(&mut self, warning: CodeGenWarning) {
+        self.warnings.push(warning);
+        self.warning_statements.push(self.statement);
+        self.warning_spans.push(None);
+    }
+
+    /// Records `warning` against the expression at `span`.
+    fn warn_at(&mut self, warning: CodeGenWarning, span: Span) {
+        self.warn(warning);
+        *self.warning_spans.last_mut().unwrap() = Some(span);
+    }
+
+    /// Whether a declaration here is at the top level of the program, outside
+    /// any function or block, where it may be shared with functions.
+    fn is_top_level(&self) -> bool {
+        self.variables.depth() == 1
+            && self
+                .function
+                .is_some_and(|function| function.get_name().to_bytes() == b"main")
+    }
+
+    /// Slot, type and mutability of the variable `name`, where locals and
+    /// parameters shadow globals.
+    fn variable(&self, name: &str) -> Option<(PointerValue<'ctx>, BasicTypeEnum<'ctx>, bool)> {
+        self.variables
+            .get(name)
+            .or_else(|| self.globals.get(name))
+            .copied()
+    }
+
+    /// Builds a zero-initialized global for a top-level `let`. Its value is
+    /// stored by the declaration, which runs in `main` in statement order.
+    fn build_global(&self, llvm_type: BasicTypeEnum<'ctx>, name: &str) -> PointerValue<'ctx> {
+        let global = self.module.add_global(llvm_type, None, name);
+        global.set_initializer(&llvm_type.const_zero());
+        global.set_linkage(Linkage::Internal);
+        global.as_pointer_value()
+    }
+
+    /// Builds an alloca at the start of the entry block, so a declaration
+    /// inside a loop reuses one stack slot rather than growing the stack on
+    /// every iteration.
+    fn build_entry_alloca(&self, llvm_type: BasicTypeEnum<'ctx>, name: &str) -> PointerValue<'ctx> {
+        let Some(entry) = self
+            .function
+            .and_then(|function| function.get_first_basic_block())
+        else {
+            return self.builder.build_alloca(llvm_type, name).unwrap();
+        };
+
+        let builder = self.context.create_builder();
+        match entry.get_first_instruction() {
+            Some(first) => builder.position_before(&first),
+            None => builder.position_at_end(entry),
+        }
+
+        builder.build_alloca(llvm_type, name).unwrap()
+    }
+
+    fn build_main_return(&mut self) -> Result<(), CodeGenError> {
+        if self.is_block_terminated() {
+            return Ok(());
         }
 
         // Return 0 from main
@@ -82,47 +422,135 @@ impl<'ctx> CodeGen<'ctx> {
         Ok(())
     }
 
+    /// Compiles `expr`, recording it as the [`CodeGen::failed_span`] if it
+    /// fails and none of its operands did.
     pub fn compile_expression(
         &mut self,
         expr: &Expr,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let result = self.compile_expression_inner(expr);
+        self.located(expr, result)
+    }
+
+    /// Records `expr` as where a failed `result` was raised, unless an
+    /// expression inside it already was.
+    fn located<T>(
+        &mut self,
+        expr: &Expr,
+        result: Result<T, CodeGenError>,
+    ) -> Result<T, CodeGenError> {
+        if result.is_err() {
+            self.failed_span.get_or_insert(expr.span());
+        }
+        result
+    }
+
+    fn compile_expression_inner(
+        &mut self,
+        expr: &Expr,
     ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
         match expr {
-            Expr::Literal(Nodes::Identifier(name)) => {
-                if let Some((var_ptr, pointee_type)) = self.variables.get(name) {
+            Expr::Variable { name, .. } => {
+                if let Some((var_ptr, pointee_type, _)) = self.variable(name) {
                     let loaded_val = self
                         .builder
-                        .build_load(*pointee_type, *var_ptr, name)
+                        .build_load(pointee_type, var_ptr, name)
                         .unwrap();
                     Ok(loaded_val)
+                } else if let Some(constant) = self.constants.get(name) {
+                    Ok(constant.value)
                 } else {
                     Err(CodeGenError::UndefinedVariable(name.clone()))
                 }
             }
-            Expr::Literal(node) => self.compile_literal(node),
+            Expr::Literal(node, _) => self.compile_literal(node),
             Expr::Binary {
                 left,
                 operator,
                 right,
+                ..
             } => self.compile_binary_op(left, operator, right),
-            Expr::Unary { operator, operand } => self.compile_unary_op(operator, operand),
-            Expr::Assignment { identifier, value } => self.compile_assignment(identifier, value),
+            Expr::Unary {
+                operator, operand, ..
+            } => self.compile_unary_op(operator, operand),
+            Expr::Cast { value, target, .. } => self.compile_cast(value, target),
+            Expr::Assignment {
+                identifier, value, ..
+            } => self.compile_assignment(identifier, value),
+            Expr::Discard(value, _) => {
+                self.compile_temporary(value)?;
+                Ok(self.unit_value())
+            }
             Expr::LetDeclaration {
                 identifier,
-                value,
+                mutable,
                 var_type,
-            } => self.compile_let_declaration(identifier, value, var_type),
+                value,
+                ..
+            } => self.compile_let_declaration(identifier, *mutable, value, var_type),
             Expr::IfElse {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => self.compile_if_else(condition, then_branch, else_branch),
-            Expr::Block(statements) => self.compile_block(statements),
-            Expr::Print(expr) => self.compile_print(expr),
+            Expr::For {
+                variable,
+                start,
+                end,
+                body,
+                unroll,
+                ..
+            } => self.compile_for(variable, start, end, body, *unroll),
+            Expr::Block(statements, _) => self.compile_block(statements),
+            Expr::Statement(value, _) => {
+                self.warn_if_unused(value);
+                self.compile_temporary(value)?;
+                Ok(self.unit_value())
+            }
+            Expr::Print(expr, _) => self.compile_print(expr).map(|_| self.unit_value()),
             Expr::MethodCall {
                 target,
                 method_name,
                 arguments,
-            } => todo!(),
+                ..
+            } => self.compile_method_call(target, method_name, arguments),
+            Expr::SizeOf(query_type, _) => Ok(self.compile_size_of(query_type)),
+            Expr::AlignOf(query_type, _) => Ok(self.compile_align_of(query_type)),
+            Expr::Return(value, _) => self.compile_return(value),
+            Expr::Break(_) => self.compile_jump(expr, |targets| targets.exit),
+            Expr::Continue(_) => self.compile_jump(expr, |targets| targets.next),
+            Expr::FunctionDef {
+                public,
+                name,
+                parameters,
+                return_type,
+                body,
+                ..
+            } => self.compile_function_def(*public, name, parameters, return_type, body),
+            Expr::Call {
+                callee, arguments, ..
+            } => self.compile_call(callee, arguments),
+            Expr::ArrayLiteral(elements, _) => self.compile_array_literal(elements),
+            Expr::Index { target, index, .. } => {
+                let (element_ptr, element_type) = self.compile_element_ptr(target, index)?;
+                Ok(self
+                    .builder
+                    .build_load(element_type, element_ptr, "element")
+                    .unwrap())
+            }
+            Expr::EnumDef { name, variants, .. } => {
+                self.enums.insert(name.clone(), variants.clone());
+                Ok(self.unit_value())
+            }
+            Expr::EnumVariant {
+                enum_name, variant, ..
+            } => Ok(self.variant_tag(enum_name, variant)?.into()),
+            // Compiled up front by `declare_functions`
+            Expr::ConstDef { .. } => Ok(self.unit_value()),
+            Expr::Match {
+                scrutinee, arms, ..
+            } => self.compile_match(scrutinee, arms),
         }
     }
 
@@ -136,26 +564,186 @@ impl<'ctx> CodeGen<'ctx> {
                 let float_val = self.context.f64_type().const_float(*value);
                 Ok(float_val.into())
             }
+            Nodes::TypedInteger(value, literal_type) => {
+                let int_type = self.llvm_type(literal_type).into_int_type();
+                Ok(int_type.const_int(*value as u64, true).into())
+            }
+            Nodes::TypedFloat(value, literal_type) => {
+                let float_type = self.llvm_type(literal_type).into_float_type();
+                Ok(float_type.const_float(*value).into())
+            }
             Nodes::Boolean(value) => {
                 let bool_val = self.context.bool_type().const_int(*value as u64, false);
                 Ok(bool_val.into())
             }
+            Nodes::Char(value) => Ok(self
+                .context
+                .i32_type()
+                .const_int(*value as u64, false)
+                .into()),
             Nodes::String(value) => {
-                let string_val = self.builder.build_global_string_ptr(value, "str");
+                if let Some(offset) = nul_position(value) {
+                    return Err(CodeGenError::StringError(format!(
+                        "NUL byte at offset {} would end the string early",
+                        offset
+                    )));
+                }
+
+                self.compile_string_literal(value)
+            }
+        }
+    }
+}
+
+/// The value of an expression made only of string literals joined by `+`.
+fn constant_string(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Literal(Nodes::String(value), _) => Some(value.clone()),
+        Expr::Binary {
+            left,
+            operator: BinaryOp::Add,
+            right,
+            ..
+        } => {
+            let mut joined = constant_string(left)?;
+            joined.push_str(&constant_string(right)?);
+            Some(joined)
+        }
+        _ => None,
+    }
+}
+
+// Types
+impl<'ctx> CodeGen<'ctx> {
+    fn llvm_type(&self, var_type: &Types) -> BasicTypeEnum<'ctx> {
+        match var_type {
+            Types::I32 => self.context.i32_type().into(),
+            Types::I64 => self.context.i64_type().into(),
+            Types::F32 => self.context.f32_type().into(),
+            Types::F64 => self.context.f64_type().into(),
+            Types::Bool => self.context.bool_type().into(),
+            Types::String => self.string_type().into(),
+            Types::Char | Types::Enum(_) => self.context.i32_type().into(),
+            Types::Array(element, length) => {
+                self.llvm_type(element).array_type(*length as u32).into()
+            }
+            Types::Unit => self.unit_type().into(),
+        }
+    }
+
+    /// `()` is an empty struct, which takes no space and no instructions.
+    fn unit_type(&self) -> StructType<'ctx> {
+        self.context.struct_type(&[], false)
+    }
+
+    fn unit_value(&self) -> BasicValueEnum<'ctx> {
+        self.unit_type().const_zero().into()
+    }
+
+    fn is_unit(&self, value: BasicValueEnum<'ctx>) -> bool {
+        value.get_type() == self.unit_type().into()
+    }
+
+    fn type_name(&self, llvm_type: BasicTypeEnum<'ctx>) -> String {
+        match llvm_type {
+            BasicTypeEnum::IntType(int_type) if int_type.get_bit_width() == 1 => "bool".into(),
+            BasicTypeEnum::IntType(int_type) => format!("i{}", int_type.get_bit_width()),
+            BasicTypeEnum::FloatType(float_type) if float_type == self.context.f32_type() => {
+                "f32".into()
+            }
+            BasicTypeEnum::FloatType(_) => "f64".into(),
+            BasicTypeEnum::StructType(struct_type) if struct_type == self.string_type() => {
+                "string".into()
+            }
+            BasicTypeEnum::StructType(struct_type) if struct_type == self.unit_type() => {
+                "()".into()
+            }
+            BasicTypeEnum::PointerType(_) => "ptr".into(),
+            BasicTypeEnum::ArrayType(array_type) => format!(
+                "[{}; {}]",
+                self.type_name(array_type.get_element_type()),
+                array_type.len()
+            ),
+            _ => format!("{:?}", llvm_type),
+        }
+    }
+
+    /// Converts `value` to `target` where the language allows it implicitly:
+    /// between integer widths and between float widths, and element-wise
+    /// between arrays of the same length. Returns `None` if the types are
+    /// incompatible.
+    fn coerce_value(
+        &self,
+        value: BasicValueEnum<'ctx>,
+        target: BasicTypeEnum<'ctx>,
+    ) -> Option<BasicValueEnum<'ctx>> {
+        if value.get_type() == target {
+            return Some(value);
+        }
 
-                match string_val {
-                    Ok(global_val) => Ok(global_val.as_pointer_value().into()),
-                    Err(err) => Err(CodeGenError::StringError(err.to_string())),
+        match (value, target) {
+            (BasicValueEnum::IntValue(int_val), BasicTypeEnum::IntType(int_type))
+                if int_val.get_type().get_bit_width() != 1 && int_type.get_bit_width() != 1 =>
+            {
+                let result = self
+                    .builder
+                    .build_int_cast_sign_flag(int_val, int_type, true, "intcast")
+                    .unwrap();
+                Some(result.into())
+            }
+            (BasicValueEnum::FloatValue(float_val), BasicTypeEnum::FloatType(float_type)) => {
+                let result = self
+                    .builder
+                    .build_float_cast(float_val, float_type, "floatcast")
+                    .unwrap();
+                Some(result.into())
+            }
+            (BasicValueEnum::ArrayValue(array_val), BasicTypeEnum::ArrayType(array_type))
+                if array_val.get_type().len() == array_type.len() =>
+            {
+                let mut result = array_type.get_undef();
+                for index in 0..array_type.len() {
+                    let element = self
+                        .builder
+                        .build_extract_value(array_val, index, "element")
+                        .unwrap();
+                    let element = self.coerce_value(element, array_type.get_element_type())?;
+                    result = self
+                        .builder
+                        .build_insert_value(result, element, index, "arraycast")
+                        .unwrap()
+                        .into_array_value();
                 }
+                Some(result.into())
             }
-            Nodes::Identifier(name) => Err(CodeGenError::InternalError(format!(
-                "Unexpected identifier node {} in literal position",
-                name
-            ))),
+            _ => None,
         }
     }
 }
 
+// Built-ins
+impl<'ctx> CodeGen<'ctx> {
+    /// Target data for the module's data layout, see [`CompileOptions`].
+    fn target_data(&self) -> TargetData {
+        TargetData::create(&self.module.get_data_layout().as_str().to_string_lossy())
+    }
+
+    fn compile_size_of(&self, query_type: &Types) -> BasicValueEnum<'ctx> {
+        let size = self.target_data().get_abi_size(&self.llvm_type(query_type));
+        self.context.i64_type().const_int(size, false).into()
+    }
+
+    fn compile_align_of(&self, query_type: &Types) -> BasicValueEnum<'ctx> {
+        let align = self
+            .target_data()
+            .get_abi_alignment(&self.llvm_type(query_type));
+        self.context
+            .i64_type()
+            .const_int(align as u64, false)
+            .into()
+    }
+}
+
 // Operations
 impl<'ctx> CodeGen<'ctx> {
     fn compile_binary_op(
@@ -164,6 +752,18 @@ impl<'ctx> CodeGen<'ctx> {
         operator: &BinaryOp,
         right: &Expr,
     ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        // Joining constant strings is done here, leaving a single global
+        if *operator == BinaryOp::Add
+            && let (Some(mut joined), Some(right)) = (constant_string(left), constant_string(right))
+        {
+            joined.push_str(&right);
+            return self.compile_literal(&Nodes::String(joined));
+        }
+
+        if let BinaryOp::And | BinaryOp::Or = operator {
+            return self.compile_logical_op(left, operator, right);
+        }
+
         let left_val = self.compile_expression(left)?;
         let right_val = self.compile_expression(right)?;
 
@@ -175,95 +775,115 @@ impl<'ctx> CodeGen<'ctx> {
                 self.compile_float_binary_op(l, operator, r)
             }
             (BasicValueEnum::IntValue(l), BasicValueEnum::FloatValue(r)) => {
-                let l_float = self
-                    .builder
-                    .build_signed_int_to_float(l, self.context.f64_type(), "int_to_float")
-                    .unwrap();
+                let l_float = self.promote_to_float(l, left);
                 self.compile_float_binary_op(l_float, operator, r)
             }
             (BasicValueEnum::FloatValue(l), BasicValueEnum::IntValue(r)) => {
-                let r_float = self
-                    .builder
-                    .build_signed_int_to_float(r, self.context.f64_type(), "int_to_float")
-                    .unwrap();
+                let r_float = self.promote_to_float(r, right);
                 self.compile_float_binary_op(l, operator, r_float)
             }
-            (BasicValueEnum::PointerValue(l), BasicValueEnum::PointerValue(r)) => {
-                self.compile_ptr_binary_op(l, operator, r)
-            }
-            (BasicValueEnum::PointerValue(l), BasicValueEnum::IntValue(r)) => {
-                let r_ptr = self
-                    .builder
-                    .build_int_to_ptr(
-                        r,
-                        self.context.ptr_type(AddressSpace::default()),
-                        "int_to_ptr",
-                    )
-                    .unwrap();
-                self.compile_ptr_binary_op(l, operator, r_ptr)
-            }
-            (BasicValueEnum::IntValue(l), BasicValueEnum::PointerValue(r)) => {
-                let l_ptr = self
-                    .builder
-                    .build_int_to_ptr(
-                        l,
-                        self.context.ptr_type(AddressSpace::default()),
-                        "int_to_ptr",
-                    )
-                    .unwrap();
-                self.compile_ptr_binary_op(l_ptr, operator, r)
-            }
-            _ => Err(CodeGenError::InternalError(format!(
-                "No binary operator for {:?} | {:?}",
-                left_val.get_type(),
-                right_val.get_type()
-            ))),
+            // Strings, arrays and `()` have no arithmetic or ordering, the
+            // only string operator is `+` on constants, joined above
+            _ => Err(CodeGenError::OperatorNotSupported(
+                operator.to_string(),
+                format!(
+                    "{} | {}",
+                    self.type_name(left_val.get_type()),
+                    self.type_name(right_val.get_type())
+                ),
+            )),
         }
     }
 
-    fn compile_ptr_binary_op(
-        &self,
-        left: PointerValue<'ctx>,
+    /// Converts the integer operand of a mixed operation to `f64`. Integers
+    /// wider than the 53 bits of an `f64` mantissa may be rounded, so that
+    /// is warned about unless the value is a constant that converts exactly.
+    fn promote_to_float(&mut self, value: IntValue<'ctx>, operand: &Expr) -> FloatValue<'ctx> {
+        const EXACT_LIMIT: u64 = 1 << f64::MANTISSA_DIGITS;
+
+        let exact = value.get_type().get_bit_width() <= f64::MANTISSA_DIGITS
+            || value
+                .get_sign_extended_constant()
+                .is_some_and(|constant| constant.unsigned_abs() <= EXACT_LIMIT);
+        if !exact {
+            self.warn_at(
+                CodeGenWarning::ImplicitPromotion(
+                    operand.to_string(),
+                    self.type_name(value.get_type().into()),
+                    "f64".to_string(),
+                ),
+                operand.span(),
+            );
+        }
+
+        self.builder
+            .build_signed_int_to_float(value, self.context.f64_type(), "int_to_float")
+            .unwrap()
+    }
+
+    /// `&&` and `||` only evaluate `right` when `left` doesn't already
+    /// decide the result. Both sides must be `bool`s.
+    fn compile_logical_op(
+        &mut self,
+        left: &Expr,
         operator: &BinaryOp,
-        right: PointerValue<'ctx>,
+        right: &Expr,
     ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
-        match operator {
-            BinaryOp::Add => {
-                let result = self.builder.build_int_add(left, right, "add").unwrap();
-                Ok(BasicValueEnum::PointerValue(result))
-            }
-            BinaryOp::Subtract => {
-                let result = self.builder.build_int_sub(left, right, "sub").unwrap();
-                Ok(BasicValueEnum::PointerValue(result))
-            }
-            BinaryOp::Multiply => {
-                let result = self.builder.build_int_mul(left, right, "mul").unwrap();
-                Ok(BasicValueEnum::PointerValue(result))
-            }
-            BinaryOp::Divide => {
-                let result = self
-                    .builder
-                    .build_int_unsigned_div(left, right, "div")
-                    .unwrap();
-                Ok(BasicValueEnum::PointerValue(result))
-            }
-            BinaryOp::Modulo => {
-                let result = self
-                    .builder
-                    .build_int_unsigned_rem(left, right, "rem")
-                    .unwrap();
-                Ok(BasicValueEnum::PointerValue(result))
-            }
-            BinaryOp::Greater => {
-                let result = self
-                    .builder
-                    .build_int_compare(IntPredicate::UGT, left, right, "gt")
-                    .unwrap();
-                Ok(BasicValueEnum::IntValue(result))
+        let function = self.function.ok_or(CodeGenError::NoFunction)?;
+
+        let left_bool = self.compile_logical_operand(left, operator)?;
+        let left_bb = self.builder.get_insert_block().unwrap();
+
+        let right_bb = self.context.append_basic_block(function, "rhs");
+        let merge_bb = self.context.append_basic_block(function, "logicalcont");
+
+        let (true_bb, false_bb) = match operator {
+            BinaryOp::And => (right_bb, merge_bb),
+            _ => (merge_bb, right_bb),
+        };
+        self.builder
+            .build_conditional_branch(left_bool, true_bb, false_bb)
+            .unwrap();
+
+        self.builder.position_at_end(right_bb);
+        self.temporaries.push(Vec::new());
+        let right_bool = self.compile_logical_operand(right, operator);
+        self.end_temporaries();
+        let right_bool = right_bool?;
+        let right_bb_end = self.builder.get_insert_block().unwrap();
+
+        let right_falls_through = !self.is_block_terminated();
+        if right_falls_through {
+            self.build_branch_to(merge_bb)?;
+        }
+
+        self.builder.position_at_end(merge_bb);
+
+        // Skipping `right` means `left` was false for `&&` or true for `||`
+        let bool_type = self.context.bool_type();
+        let decided = bool_type.const_int((*operator == BinaryOp::Or) as u64, false);
+
+        let phi = self.builder.build_phi(bool_type, "logical").unwrap();
+        phi.add_incoming(&[(&decided, left_bb)]);
+        if right_falls_through {
+            phi.add_incoming(&[(&right_bool, right_bb_end)]);
+        }
+
+        Ok(phi.as_basic_value())
+    }
+
+    fn compile_logical_operand(
+        &mut self,
+        operand: &Expr,
+        operator: &BinaryOp,
+    ) -> Result<IntValue<'ctx>, CodeGenError> {
+        match self.compile_expression(operand)? {
+            BasicValueEnum::IntValue(bool_val) if bool_val.get_type().get_bit_width() == 1 => {
+                Ok(bool_val)
             }
             _ => Err(CodeGenError::OperatorNotSupported(
-                format!("{:?}", operator),
-                format!("{:?} | {:?}", left.get_type(), right.get_type()),
+                operator.to_string(),
+                operand.to_string(),
             )),
         }
     }
@@ -310,8 +930,12 @@ impl<'ctx> CodeGen<'ctx> {
                 .builder
                 .build_int_compare(IntPredicate::SLE, left, right, "le")
                 .unwrap(),
-            BinaryOp::And => self.builder.build_and(left, right, "and").unwrap(),
-            BinaryOp::Or => self.builder.build_or(left, right, "or").unwrap(),
+            BinaryOp::And | BinaryOp::Or => {
+                return Err(CodeGenError::InternalError(format!(
+                    "`{}` is lowered with branches by `compile_logical_op`",
+                    operator
+                )));
+            }
         };
         Ok(result.into())
     }
@@ -353,7 +977,7 @@ impl<'ctx> CodeGen<'ctx> {
             BinaryOp::NotEqual => {
                 let result = self
                     .builder
-                    .build_float_compare(FloatPredicate::ONE, left, right, "fne")
+                    .build_float_compare(FloatPredicate::UNE, left, right, "fne")
                     .unwrap();
                 Ok(result.into())
             }
@@ -414,8 +1038,9 @@ impl<'ctx> CodeGen<'ctx> {
                 )),
             },
             UnaryOp::Not => match operand_val {
-                BasicValueEnum::IntValue(int_val) => {
-                    let result = self.builder.build_not(int_val, "not").unwrap();
+                BasicValueEnum::IntValue(bool_val) if bool_val.get_type().get_bit_width() == 1 => {
+                    let true_val = bool_val.get_type().const_all_ones();
+                    let result = self.builder.build_xor(bool_val, true_val, "not").unwrap();
                     Ok(result.into())
                 }
                 _ => Err(CodeGenError::OperatorNotSupported(
@@ -423,309 +1048,3866 @@ impl<'ctx> CodeGen<'ctx> {
                     operand.to_string(),
                 )),
             },
+            UnaryOp::BitNot => match operand_val {
+                BasicValueEnum::IntValue(int_val) if int_val.get_type().get_bit_width() > 1 => {
+                    let result = self.builder.build_not(int_val, "bitnot").unwrap();
+                    Ok(result.into())
+                }
+                _ => Err(CodeGenError::OperatorNotSupported(
+                    "~".into(),
+                    operand.to_string(),
+                )),
+            },
         }
     }
-}
 
-// Assignments
-impl<'ctx> CodeGen<'ctx> {
-    fn compile_assignment(
+    /// `value as target`. Integers are truncated or extended to the target
+    /// width, `bool`s and `char`s zero-extended, and floats converted to
+    /// and from signed integers, saturating like the interpreter.
+    fn compile_cast(
         &mut self,
-        identifier: &str,
         value: &Expr,
+        target: &Types,
     ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let is_char = self.is_char(value);
         let val = self.compile_expression(value)?;
+        let target_type = self.llvm_type(target);
 
-        if let Some((var_ptr, _)) = self.variables.get(identifier) {
-            self.builder.build_store(*var_ptr, val).unwrap();
-            Ok(val)
-        } else {
-            Err(CodeGenError::UndefinedVariable(identifier.to_string()))
+        if val.get_type() == target_type {
+            return Ok(val);
         }
+
+        let result: BasicValueEnum<'ctx> = match (val, target_type) {
+            (BasicValueEnum::IntValue(int_val), BasicTypeEnum::IntType(int_type))
+                if int_type.get_bit_width() != 1 =>
+            {
+                let width = int_val.get_type().get_bit_width();
+                if width > int_type.get_bit_width() {
+                    self.builder.build_int_truncate(int_val, int_type, "trunc")
+                } else if width == 1 || is_char {
+                    self.builder.build_int_z_extend(int_val, int_type, "zext")
+                } else {
+                    self.builder.build_int_s_extend(int_val, int_type, "sext")
+                }
+                .unwrap()
+                .into()
+            }
+            (BasicValueEnum::IntValue(int_val), BasicTypeEnum::FloatType(float_type))
+                if int_val.get_type().get_bit_width() != 1 && !is_char =>
+            {
+                self.builder
+                    .build_signed_int_to_float(int_val, float_type, "sitofp")
+                    .unwrap()
+                    .into()
+            }
+            (BasicValueEnum::FloatValue(float_val), BasicTypeEnum::IntType(int_type))
+                if int_type.get_bit_width() != 1 =>
+            {
+                self.build_saturating_float_to_int(float_val, int_type)
+                    .into()
+            }
+            (BasicValueEnum::FloatValue(float_val), BasicTypeEnum::FloatType(float_type)) => self
+                .builder
+                .build_float_cast(float_val, float_type, "fpcast")
+                .unwrap()
+                .into(),
+            _ => {
+                return Err(CodeGenError::InvalidCast(
+                    value.to_string(),
+                    self.type_name(val.get_type()),
+                    target.to_string(),
+                ));
+            }
+        };
+        Ok(result)
     }
 
-    fn compile_let_declaration(
+    /// Plain `fptosi` is poison for NaN and out-of-range values, so this
+    /// calls `llvm.fptosi.sat`, which clamps to the integer's range and
+    /// maps NaN to 0.
+    fn build_saturating_float_to_int(
+        &self,
+        value: FloatValue<'ctx>,
+        int_type: IntType<'ctx>,
+    ) -> IntValue<'ctx> {
+        let float_type = value.get_type();
+        let name = format!(
+            "llvm.fptosi.sat.{}.{}",
+            self.type_name(int_type.into()),
+            self.type_name(float_type.into())
+        );
+        let fptosi_sat_fn = self.module.get_function(&name).unwrap_or_else(|| {
+            self.module
+                .add_function(&name, int_type.fn_type(&[float_type.into()], false), None)
+        });
+
+        self.builder
+            .build_call(fptosi_sat_fn, &[value.into()], "fptosi")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value()
+    }
+}
+
+// Assignments
+impl<'ctx> CodeGen<'ctx> {
+    fn compile_assignment(
         &mut self,
         identifier: &str,
         value: &Expr,
-        var_type: &Option<Types>,
     ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
-        let val = self.compile_expression(value)?;
+        let Some((var_ptr, var_type, mutable)) = self.variable(identifier) else {
+            if self.constants.contains_key(identifier) {
+                return Err(CodeGenError::InvalidConst(
+                    identifier.to_string(),
+                    "cannot be assigned to".into(),
+                ));
+            }
+            return Err(CodeGenError::UndefinedVariable(identifier.to_string()));
+        };
+
+        if !mutable {
+            return Err(CodeGenError::AssignToImmutable(identifier.to_string()));
+        }
+
+        // `x += 1` and `x = x - 2` on an integer add a constant of the
+        // variable's own width, rather than widening it to `i64` and back
+        if let Some(step) = increment_step(identifier, value)
+            && let BasicTypeEnum::IntType(int_type) = var_type
+            && int_type.get_bit_width() != 1
+        {
+            let current = self
+                .builder
+                .build_load(int_type, var_ptr, identifier)
+                .unwrap()
+                .into_int_value();
+            let updated = self
+                .builder
+                .build_int_add(current, int_type.const_int(step as u64, true), "inc")
+                .unwrap();
+            self.builder.build_store(var_ptr, updated).unwrap();
+            return Ok(self.unit_value());
+        }
+
+        self.check_literal_width(value, var_type)?;
+        let val = self.compile_expression(value)?;
+
+        let val = self.coerce_value(val, var_type).ok_or_else(|| {
+            CodeGenError::AssignTypeMismatch(
+                identifier.to_string(),
+                self.type_name(var_type),
+                self.type_name(val.get_type()),
+                value.to_string(),
+            )
+        })?;
+
+        self.builder.build_store(var_ptr, val).unwrap();
+        Ok(self.unit_value())
+    }
+
+    fn compile_let_declaration(
+        &mut self,
+        identifier: &str,
+        mutable: bool,
+        value: &Expr,
+        var_type: &Option<Types>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let is_global = self.is_top_level() && self.function_names.contains(identifier);
+        let is_char = match var_type {
+            Some(var_type) => holds_chars(var_type),
+            None => self.is_char(value),
+        };
+        let val = self.compile_expression(value)?;
+
+        // Use the specified type, or infer it from the value
+        let llvm_type = match var_type {
+            Some(var_type) => self.llvm_type(var_type),
+            None => val.get_type(),
+        };
+
+        self.check_literal_width(value, llvm_type)?;
+
+        let val = self.coerce_value(val, llvm_type).ok_or_else(|| {
+            CodeGenError::LetTypeMismatch(
+                identifier.to_string(),
+                self.type_name(llvm_type),
+                self.type_name(val.get_type()),
+                value.to_string(),
+            )
+        })?;
+
+        let slot = if is_global {
+            self.build_global(llvm_type, identifier)
+        } else {
+            self.build_entry_alloca(llvm_type, identifier)
+        };
+        if is_char {
+            self.char_slots.insert(slot);
+        }
+
+        let result = self.builder.build_store(slot, val);
+
+        if result.is_err() {
+            return Err(CodeGenError::StoreError(identifier.to_string()));
+        }
+
+        let shadowed = if is_global {
+            self.globals
+                .insert(identifier.to_string(), (slot, llvm_type, mutable))
+        } else {
+            self.variables
+                .declare(identifier, (slot, llvm_type, mutable))
+        };
+        if shadowed.is_some() {
+            self.warn(CodeGenWarning::ShadowedVariable(identifier.to_string()));
+        }
+
+        Ok(self.unit_value())
+    }
+
+    /// Rejects an integer literal stored into a variable too narrow for it,
+    /// which would silently keep only its low bits. A float literal too
+    /// large for `f32` becomes infinite, which is only warned about.
+    fn check_literal_width(
+        &mut self,
+        value: &Expr,
+        target: BasicTypeEnum<'ctx>,
+    ) -> Result<(), CodeGenError> {
+        let Some(narrowed) = narrowed_literal(value, target) else {
+            return Ok(());
+        };
+
+        if target.is_int_type() {
+            return Err(CodeGenError::LiteralOutOfRange(
+                value.to_string(),
+                self.type_name(target),
+            ));
+        }
+        self.warn(CodeGenWarning::LiteralOverflow(
+            value.to_string(),
+            self.type_name(target),
+            narrowed,
+        ));
+        Ok(())
+    }
+}
+
+// Arrays
+impl<'ctx> CodeGen<'ctx> {
+    fn compile_array_literal(
+        &mut self,
+        elements: &[Expr],
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let values = elements
+            .iter()
+            .map(|element| self.compile_expression(element))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let Some(element_type) = values.first().map(|value| value.get_type()) else {
+            return Err(CodeGenError::InvalidOperation(
+                "Cannot infer the element type of `[]`".to_string(),
+            ));
+        };
+
+        let mut array = element_type.array_type(values.len() as u32).get_undef();
+        for (index, value) in values.into_iter().enumerate() {
+            if value.get_type() != element_type {
+                return Err(CodeGenError::TypeMismatch(
+                    self.type_name(element_type),
+                    self.type_name(value.get_type()),
+                ));
+            }
+
+            array = self
+                .builder
+                .build_insert_value(array, value, index as u32, "array")
+                .unwrap()
+                .into_array_value();
+        }
+
+        Ok(array.into())
+    }
+
+    /// Pointer to and type of the storage `expr` refers to: a variable's
+    /// slot, an array element, or a temporary slot holding any other value.
+    fn compile_place(
+        &mut self,
+        expr: &Expr,
+    ) -> Result<(PointerValue<'ctx>, BasicTypeEnum<'ctx>), CodeGenError> {
+        match expr {
+            Expr::Variable { name, .. } => self
+                .variable(name)
+                .map(|(slot, slot_type, _)| (slot, slot_type))
+                .or_else(|| {
+                    let constant = self.constants.get(name)?;
+                    Some((constant.global, constant.value.get_type()))
+                })
+                .ok_or_else(|| CodeGenError::UndefinedVariable(name.clone())),
+            Expr::Index { target, index, .. } => self.compile_element_ptr(target, index),
+            _ => {
+                let value = self.compile_expression(expr)?;
+                let slot = self.build_entry_alloca(value.get_type(), "temp");
+                self.builder.build_store(slot, value).unwrap();
+                Ok((slot, value.get_type()))
+            }
+        }
+    }
+
+    /// Pointer to and type of `target[index]`, checking the index against
+    /// the array's length unless [`CompileOptions::unchecked_indexing`].
+    fn compile_element_ptr(
+        &mut self,
+        target: &Expr,
+        index: &Expr,
+    ) -> Result<(PointerValue<'ctx>, BasicTypeEnum<'ctx>), CodeGenError> {
+        let (array_ptr, array_type) = self.compile_place(target)?;
+        let BasicTypeEnum::ArrayType(array_type) = array_type else {
+            return Err(CodeGenError::InvalidOperation(format!(
+                "indexing into `{}`",
+                target
+            )));
+        };
+
+        let BasicValueEnum::IntValue(index_val) = self.compile_expression(index)? else {
+            return Err(CodeGenError::TypeMismatchCustom(
+                "Array index must be an integer".to_string(),
+            ));
+        };
+        let i64_type = self.context.i64_type();
+        let index_val = self
+            .builder
+            .build_int_cast_sign_flag(index_val, i64_type, true, "index")
+            .unwrap();
+
+        if !self.unchecked_indexing {
+            self.build_bounds_check(index_val, array_type.len())?;
+        }
+
+        let element_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    array_type,
+                    array_ptr,
+                    &[i64_type.const_zero(), index_val],
+                    "element_ptr",
+                )
+                .unwrap()
+        };
+
+        Ok((element_ptr, array_type.get_element_type()))
+    }
+
+    /// Exits with status 101 after printing the index if it isn't below
+    /// `length`. Negative indices wrap to large unsigned values, so a
+    /// single unsigned compare covers both ends.
+    fn build_bounds_check(
+        &mut self,
+        index: IntValue<'ctx>,
+        length: u32,
+    ) -> Result<(), CodeGenError> {
+        let function = self.function.ok_or(CodeGenError::NoFunction)?;
+        let i64_type = self.context.i64_type();
+        let length = i64_type.const_int(length as u64, false);
+
+        let in_bounds = self
+            .builder
+            .build_int_compare(IntPredicate::ULT, index, length, "in_bounds")
+            .unwrap();
+        let fail_bb = self.context.append_basic_block(function, "out_of_bounds");
+        let ok_bb = self.context.append_basic_block(function, "in_bounds");
+        self.builder
+            .build_conditional_branch(in_bounds, ok_bb, fail_bb)
+            .unwrap();
+
+        self.builder.position_at_end(fail_bb);
+        let printf_fn = self.printf_fn.ok_or(CodeGenError::InternalError(
+            "printf function not declared".to_string(),
+        ))?;
+        let format = self.format_string("Index %lld out of bounds for array of length %lld\n");
+        self.builder
+            .build_call(
+                printf_fn,
+                &[format.into(), index.into(), length.into()],
+                "printf_call",
+            )
+            .unwrap();
+
+        let exit_fn = self.module.get_function("exit").unwrap_or_else(|| {
+            let exit_type = self
+                .context
+                .void_type()
+                .fn_type(&[self.context.i32_type().into()], false);
+            self.module.add_function("exit", exit_type, None)
+        });
+        let status = self.context.i32_type().const_int(101, false);
+        self.builder
+            .build_call(exit_fn, &[status.into()], "exit_call")
+            .unwrap();
+        self.builder.build_unreachable().unwrap();
+
+        self.builder.position_at_end(ok_bb);
+        Ok(())
+    }
+}
+
+/// Whether values of `var_type` are `char`s, directly or as array elements.
+fn holds_chars(var_type: &Types) -> bool {
+    match var_type {
+        Types::Char => true,
+        Types::Array(element, _) => holds_chars(element),
+        _ => false,
+    }
+}
+
+/// The value a literal `value` takes when stored as `target`, if that
+/// differs from what was written: integers keep their low bits and floats
+/// too large for `f32` become infinite.
+fn narrowed_literal(value: &Expr, target: BasicTypeEnum<'_>) -> Option<String> {
+    let (node, negate) = match value {
+        Expr::Literal(node, _) => (node, false),
+        Expr::Unary {
+            operator: UnaryOp::Minus,
+            operand,
+            ..
+        } => match operand.as_ref() {
+            Expr::Literal(node, _) => (node, true),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    match (node, target) {
+        (Nodes::Integer(value), BasicTypeEnum::IntType(int_type)) => {
+            let bits = int_type.get_bit_width();
+            if !(2..64).contains(&bits) {
+                return None;
+            }
+
+            let value = if negate { value.wrapping_neg() } else { *value };
+            let shift = 64 - bits;
+            let narrowed = (value << shift) >> shift;
+            (narrowed != value).then(|| narrowed.to_string())
+        }
+        (Nodes::Float(value), BasicTypeEnum::FloatType(float_type))
+            if float_type == float_type.get_context().f32_type() =>
+        {
+            let value = if negate { -value } else { *value };
+            let narrowed = value as f32;
+            (value.is_finite() && narrowed.is_infinite()).then(|| narrowed.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The constant added to `identifier` when `value` is `identifier + n`,
+/// `n + identifier` or `identifier - n` for an integer literal `n`.
+fn increment_step(identifier: &str, value: &Expr) -> Option<i64> {
+    let Expr::Binary {
+        left,
+        operator,
+        right,
+        ..
+    } = value
+    else {
+        return None;
+    };
+    let is_target = |expr: &Expr| matches!(expr, Expr::Variable { name, .. } if name == identifier);
+    let literal = |expr: &Expr| match expr {
+        Expr::Literal(Nodes::Integer(n), _) => Some(*n),
+        _ => None,
+    };
+
+    match operator {
+        BinaryOp::Add if is_target(left) => literal(right),
+        BinaryOp::Add if is_target(right) => literal(left),
+        BinaryOp::Subtract if is_target(left) => literal(right)?.checked_neg(),
+        _ => None,
+    }
+}
+
+/// Adds the name of every variable read or assigned in `expr` to `names`.
+fn collect_names(expr: &Expr, names: &mut HashSet<String>) {
+    match expr {
+        Expr::Variable { name, .. }
+        | Expr::Assignment {
+            identifier: name, ..
+        } => {
+            names.insert(name.clone());
+        }
+        _ => {}
+    }
+    for child in expr.children() {
+        collect_names(child, names);
+    }
+}
+
+/// Adds the names used in the body of every function in `expr`, nested
+/// ones included, to `names`.
+fn collect_function_names(expr: &Expr, names: &mut HashSet<String>) {
+    match expr {
+        Expr::FunctionDef { body, .. } => collect_names(body, names),
+        _ => {
+            for child in expr.children() {
+                collect_function_names(child, names);
+            }
+        }
+    }
+}
+
+// For
+impl<'ctx> CodeGen<'ctx> {
+    fn compile_for(
+        &mut self,
+        variable: &str,
+        start: &Expr,
+        end: &Expr,
+        body: &Expr,
+        unroll: Option<u32>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let function = self.function.ok_or(CodeGenError::NoFunction)?;
+
+        let (BasicValueEnum::IntValue(start_val), BasicValueEnum::IntValue(end_val)) = (
+            self.compile_expression(start)?,
+            self.compile_expression(end)?,
+        ) else {
+            return Err(CodeGenError::TypeMismatchCustom(
+                "Range bounds must be integers".to_string(),
+            ));
+        };
+
+        // The loop variable takes the type of the range start
+        let index_type = start_val.get_type();
+        let end_val = self
+            .builder
+            .build_int_cast_sign_flag(end_val, index_type, true, "forend")
+            .unwrap();
+
+        let cond_bb = self.context.append_basic_block(function, "forcond");
+        let body_bb = self.context.append_basic_block(function, "forbody");
+        let next_bb = self.context.append_basic_block(function, "forinc");
+        let after_bb = self.context.append_basic_block(function, "forafter");
+
+        self.variables.push_scope();
+
+        let index_ptr = self.build_entry_alloca(index_type.into(), variable);
+        self.builder.build_store(index_ptr, start_val).unwrap();
+        self.variables
+            .declare(variable, (index_ptr, index_type.into(), false));
+        self.build_branch_to(cond_bb)?;
+
+        self.builder.position_at_end(cond_bb);
+        let index = self
+            .builder
+            .build_load(index_type, index_ptr, variable)
+            .unwrap()
+            .into_int_value();
+        let in_range = self
+            .builder
+            .build_int_compare(IntPredicate::SLT, index, end_val, "forcmp")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_range, body_bb, after_bb)
+            .unwrap();
+
+        self.builder.position_at_end(body_bb);
+        self.loops.push(LoopTargets {
+            exit: after_bb,
+            next: next_bb,
+            temporaries: self.temporaries.len(),
+        });
+        let result = self.compile_expression(body);
+        self.loops.pop();
+
+        if result.is_ok() && !self.is_block_terminated() {
+            self.build_branch_to(next_bb)?;
+        }
+
+        // `continue` and the end of the body both step to the next index
+        if result.is_ok() && next_bb.get_first_use().is_some() {
+            self.builder.position_at_end(next_bb);
+            let index = self
+                .builder
+                .build_load(index_type, index_ptr, variable)
+                .unwrap()
+                .into_int_value();
+            let next = self
+                .builder
+                .build_int_add(index, index_type.const_int(1, false), "fornext")
+                .unwrap();
+            self.builder.build_store(index_ptr, next).unwrap();
+            let latch = self
+                .builder
+                .build_unconditional_branch(cond_bb)
+                .map_err(|_| {
+                    CodeGenError::TypeMismatchCustom("Branch must be an integer".to_string())
+                })?;
+
+            self.set_loop_hints(latch, unroll);
+        } else if result.is_ok() {
+            // Every iteration leaves the loop, so nothing steps the index
+            unsafe { next_bb.delete() }.unwrap();
+        }
+
+        self.variables.pop_scope();
+        result?;
+
+        self.builder.position_at_end(after_bb);
+
+        Ok(self.unit_value())
+    }
+
+    /// `break` and `continue` branch to a block of the innermost loop.
+    fn compile_jump(
+        &mut self,
+        jump: &Expr,
+        target: fn(&LoopTargets<'ctx>) -> BasicBlock<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let Some(targets) = self.loops.last().copied() else {
+            return Err(CodeGenError::OutsideLoop(jump.to_string()));
+        };
+
+        self.free_temporaries(targets.temporaries);
+        self.build_branch_to(target(&targets))?;
+
+        Ok(self.unit_value())
+    }
+
+    /// Attaches `llvm.loop` hints to the back edge of a loop: every `for`
+    /// loop counts over a range, so the vectorizer is always asked to try,
+    /// and `#[unroll(n)]` adds an unroll count.
+    ///
+    /// A loop id must name itself as its first operand, which inkwell cannot
+    /// express, so the node is built through the C API: a temporary stands in
+    /// for the self reference and is then replaced by the node itself.
+    fn set_loop_hints(&self, latch: InstructionValue<'ctx>, unroll: Option<u32>) {
+        let context = self.context.as_ctx_ref();
+        let enable = self.context.bool_type().const_int(1, false);
+        let mut hints = vec![("llvm.loop.vectorize.enable", enable)];
+        if let Some(count) = unroll {
+            let count = self.context.i32_type().const_int(count as u64, false);
+            hints.push(("llvm.loop.unroll.count", count));
+        }
+        let kind_name = "llvm.loop";
+
+        unsafe {
+            let placeholder = debuginfo::LLVMTemporaryMDNode(context, std::ptr::null_mut(), 0);
+            let mut operands = vec![placeholder];
+
+            for (name, value) in hints {
+                let mut hint = [
+                    core::LLVMMDStringInContext2(context, name.as_ptr().cast(), name.len()),
+                    core::LLVMValueAsMetadata(value.as_value_ref()),
+                ];
+                operands.push(core::LLVMMDNodeInContext2(
+                    context,
+                    hint.as_mut_ptr(),
+                    hint.len(),
+                ));
+            }
+
+            let loop_id =
+                core::LLVMMDNodeInContext2(context, operands.as_mut_ptr(), operands.len());
+            debuginfo::LLVMMetadataReplaceAllUsesWith(placeholder, loop_id);
+
+            let kind = core::LLVMGetMDKindIDInContext(
+                context,
+                kind_name.as_ptr().cast(),
+                kind_name.len() as u32,
+            );
+            core::LLVMSetMetadata(
+                latch.as_value_ref(),
+                kind,
+                core::LLVMMetadataAsValue(context, loop_id),
+            );
+        }
+    }
+}
+
+// If-Else
+impl<'ctx> CodeGen<'ctx> {
+    fn compile_if_else(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Option<Box<Expr>>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let function = self.function.ok_or(CodeGenError::NoFunction)?;
+
+        let condition_bool = self.compile_condition(condition)?;
+
+        let then_bb = self.context.append_basic_block(function, "then");
+        let else_bb = self.context.append_basic_block(function, "else");
+        let merge_bb = self.context.append_basic_block(function, "ifcont");
+
+        let built_cond_branch =
+            self.builder
+                .build_conditional_branch(condition_bool, then_bb, else_bb);
+
+        if built_cond_branch.is_err() {
+            return Err(CodeGenError::TypeMismatchCustom(
+                "Condition must be an integer".to_string(),
+            ));
+        }
+
+        self.builder.position_at_end(then_bb);
+        let then_val = self.compile_expression(then_branch)?;
+        let then_bb_end = self.builder.get_insert_block().unwrap();
+
+        // A branch ending in `return` must not also jump to the merge block
+        let then_falls_through = !self.is_block_terminated();
+        if then_falls_through {
+            self.build_branch_to(merge_bb)?;
+        }
+
+        self.builder.position_at_end(else_bb);
+        let else_val = match else_branch {
+            Some(else_expr) => self.compile_expression(else_expr)?,
+            None => self.unit_value(),
+        };
+        let else_bb_end = self.builder.get_insert_block().unwrap();
+
+        let else_falls_through = !self.is_block_terminated();
+        if else_falls_through {
+            self.build_branch_to(merge_bb)?;
+        }
+
+        // merge block with phi node
+        self.builder.position_at_end(merge_bb);
+
+        match (then_falls_through, else_falls_through) {
+            // Without an `else`, or with two unit branches, the `if` is a
+            // statement and there is nothing to merge
+            (true, true)
+                if else_branch.is_none() || (self.is_unit(then_val) && self.is_unit(else_val)) =>
+            {
+                Ok(self.unit_value())
+            }
+            (true, true) if then_val.get_type() != else_val.get_type() => {
+                Err(CodeGenError::TypeMismatch(
+                    self.type_name(then_val.get_type()),
+                    self.type_name(else_val.get_type()),
+                ))
+            }
+            (true, true) => {
+                let phi = self
+                    .builder
+                    .build_phi(then_val.get_type(), "iftmp")
+                    .unwrap();
+                phi.add_incoming(&[(&then_val, then_bb_end), (&else_val, else_bb_end)]);
+                Ok(phi.as_basic_value())
+            }
+            (true, false) => Ok(then_val),
+            (false, true) => Ok(else_val),
+            (false, false) => {
+                // Neither branch falls through, so the `if` itself diverges
+                self.builder.build_unreachable().unwrap();
+                Ok(then_val)
+            }
+        }
+    }
+
+    /// Compiles `condition` to an `i1`, where any non-zero integer is true.
+    fn compile_condition(&mut self, condition: &Expr) -> Result<IntValue<'ctx>, CodeGenError> {
+        match self.compile_expression(condition)? {
+            BasicValueEnum::IntValue(int_val) if int_val.get_type().get_bit_width() == 1 => {
+                Ok(int_val)
+            }
+            BasicValueEnum::IntValue(int_val) => {
+                let zero = int_val.get_type().const_zero();
+                Ok(self
+                    .builder
+                    .build_int_compare(IntPredicate::NE, int_val, zero, "tobool")
+                    .unwrap())
+            }
+            _ => Err(CodeGenError::TypeMismatchCustom(
+                "Condition must be an integer".to_string(),
+            )),
+        }
+    }
+
+    fn build_branch_to(&self, block: BasicBlock<'ctx>) -> Result<(), CodeGenError> {
+        self.builder
+            .build_unconditional_branch(block)
+            .map(|_| ())
+            .map_err(|_| CodeGenError::TypeMismatchCustom("Branch must be an integer".to_string()))
+    }
+}
+
+// Enums
+impl<'ctx> CodeGen<'ctx> {
+    fn variant_tag(&self, enum_name: &str, variant: &str) -> Result<IntValue<'ctx>, CodeGenError> {
+        let tag = self
+            .enums
+            .get(enum_name)
+            .and_then(|variants| variants.iter().position(|name| name == variant))
+            .ok_or_else(|| CodeGenError::InvalidOperation(format!("{}::{}", enum_name, variant)))?;
+
+        Ok(self.context.i32_type().const_int(tag as u64, false))
+    }
+
+    /// Switches on the variant's tag, with `_` as the default case. Without
+    /// one the checker has made sure every tag has an arm, so the default
+    /// is unreachable.
+    fn compile_match(
+        &mut self,
+        scrutinee: &Expr,
+        arms: &[MatchArm],
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let function = self.function.ok_or(CodeGenError::NoFunction)?;
+
+        let BasicValueEnum::IntValue(tag) = self.compile_expression(scrutinee)? else {
+            return Err(CodeGenError::TypeMismatchCustom(
+                "Match value must be an enum".to_string(),
+            ));
+        };
+
+        let switch_bb = self.builder.get_insert_block().unwrap();
+
+        let mut cases: Vec<(IntValue<'ctx>, BasicBlock<'ctx>)> = Vec::new();
+        let mut default_bb = None;
+        let mut arm_blocks = Vec::with_capacity(arms.len());
+
+        for arm in arms {
+            let arm_bb = self.context.append_basic_block(function, "arm");
+            arm_blocks.push(arm_bb);
+
+            // Arms after a `_` are never taken, like in the interpreter
+            if default_bb.is_some() {
+                continue;
+            }
+            match &arm.pattern {
+                Pattern::Wildcard => default_bb = Some(arm_bb),
+                Pattern::Variant { enum_name, variant } => {
+                    let case = self.variant_tag(enum_name, variant)?;
+                    if !cases.iter().any(|(tag, _)| *tag == case) {
+                        cases.push((case, arm_bb));
+                    }
+                }
+            }
+        }
+
+        let default_bb = match default_bb {
+            Some(default_bb) => default_bb,
+            None => {
+                let unmatched_bb = self.context.append_basic_block(function, "nomatch");
+                self.builder.position_at_end(unmatched_bb);
+                self.builder.build_unreachable().unwrap();
+                unmatched_bb
+            }
+        };
+
+        let merge_bb = self.context.append_basic_block(function, "matchcont");
+
+        self.builder.position_at_end(switch_bb);
+        self.builder.build_switch(tag, default_bb, &cases).unwrap();
+
+        // The values of the arms that don't `return`, with their last block
+        let mut incoming: Vec<(BasicValueEnum<'ctx>, BasicBlock<'ctx>)> = Vec::new();
+        for (arm, arm_bb) in arms.iter().zip(arm_blocks) {
+            self.builder.position_at_end(arm_bb);
+            let value = self.compile_statement(&arm.body, true)?;
+
+            if !self.is_block_terminated() {
+                incoming.push((value, self.builder.get_insert_block().unwrap()));
+                self.build_branch_to(merge_bb)?;
+            }
+        }
+
+        self.builder.position_at_end(merge_bb);
+
+        let Some((first, _)) = incoming.first() else {
+            // Every arm returns, so the `match` itself diverges
+            self.builder.build_unreachable().unwrap();
+            return Ok(self.unit_value());
+        };
+
+        // Arms of unit type make the `match` a statement, with nothing to merge
+        if incoming.iter().all(|(value, _)| self.is_unit(*value)) {
+            return Ok(self.unit_value());
+        }
+        if let Some((other, _)) = incoming
+            .iter()
+            .find(|(value, _)| value.get_type() != first.get_type())
+        {
+            return Err(CodeGenError::TypeMismatch(
+                self.type_name(first.get_type()),
+                self.type_name(other.get_type()),
+            ));
+        }
+
+        let phi = self
+            .builder
+            .build_phi(first.get_type(), "matchtmp")
+            .unwrap();
+        for (value, block) in &incoming {
+            phi.add_incoming(&[(value, *block)]);
+        }
+
+        Ok(phi.as_basic_value())
+    }
+}
+
+// Block
+impl<'ctx> CodeGen<'ctx> {
+    fn compile_block(&mut self, statements: &[Expr]) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        self.variables.push_scope();
+
+        // An empty block is `()`
+        let mut result = Ok(self.unit_value());
+        for (index, statement) in statements.iter().enumerate() {
+            // The last statement is the block's value
+            if index + 1 < statements.len() {
+                self.warn_if_unused(statement);
+            }
+            result = self.compile_statement(statement, index + 1 == statements.len());
+
+            if result.is_err() || self.diverged_before(&statements[index + 1..]) {
+                break;
+            }
+        }
+
+        self.variables.pop_scope();
+
+        result
+    }
+
+    /// Compiles a statement, or anything else compiled only on some paths
+    /// like a `match` arm, freeing the heap temporaries it created once it
+    /// is done. A value that isn't `used` is a temporary itself.
+    fn compile_statement(
+        &mut self,
+        statement: &Expr,
+        used: bool,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        self.temporaries.push(Vec::new());
+        let result = if used {
+            self.compile_expression(statement)
+        } else {
+            self.compile_temporary(statement)
+        };
+        self.end_temporaries();
+
+        result
+    }
+
+    /// Compiles a value that is dropped once it has been read, so that if
+    /// it was allocated for this expression alone it is freed at the end
+    /// of the statement.
+    ///
+    /// The buffers returned by `input()` are the only such values: string
+    /// concatenation is folded at compile time and arrays live on the
+    /// stack.
+    fn compile_temporary(&mut self, value: &Expr) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let compiled = self.compile_expression(value)?;
+
+        let allocated = match value {
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                arguments.is_empty()
+                    && !self.functions.contains_key(callee)
+                    && Intrinsic::resolve(callee) == Some(Intrinsic::Input)
+            }
+            _ => false,
+        };
+        if let (true, BasicValueEnum::StructValue(string)) = (allocated, compiled) {
+            let buffer = self.string_to_c(string);
+            if let Some(frame) = self.temporaries.last_mut() {
+                frame.push(buffer);
+            }
+        }
+
+        Ok(compiled)
+    }
+
+    /// Frees the temporaries of the innermost statement, unless it left the
+    /// block through `return`, `break` or `continue`, which free them first.
+    fn end_temporaries(&mut self) {
+        let depth = self.temporaries.len() - 1;
+        if !self.is_block_terminated() {
+            self.free_temporaries(depth);
+        }
+        self.temporaries.truncate(depth);
+    }
+
+    /// Frees the temporaries of every statement from `depth` inwards, before
+    /// jumping out of them.
+    fn free_temporaries(&mut self, depth: usize) {
+        let buffers: Vec<PointerValue<'ctx>> = self.temporaries[depth..]
+            .iter()
+            .flatten()
+            .copied()
+            .collect();
+        if buffers.is_empty() {
+            return;
+        }
+
+        let free_fn = self.module.get_function("free").unwrap_or_else(|| {
+            let ptr_type = self.context.ptr_type(AddressSpace::default());
+            self.module.add_function(
+                "free",
+                self.context.void_type().fn_type(&[ptr_type.into()], false),
+                None,
+            )
+        });
+        for buffer in buffers {
+            self.builder
+                .build_call(free_fn, &[buffer.into()], "")
+                .unwrap();
+        }
+    }
+}
+
+// Return
+impl<'ctx> CodeGen<'ctx> {
+    fn compile_return(
+        &mut self,
+        value: &Option<Box<Expr>>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let function = self.function.ok_or(CodeGenError::NoFunction)?;
+        if self.module.get_function("main") != Some(function) {
+            return self.compile_function_return(function, value);
+        }
+
+        let val = match value {
+            Some(value) => self.compile_expression(value)?,
+            None => self.context.i64_type().const_int(0, false).into(),
+        };
+
+        let BasicValueEnum::IntValue(int_val) = val else {
+            return Err(CodeGenError::TypeMismatchCustom(
+                "Return must be an integer".to_string(),
+            ));
+        };
+
+        // `main` returns an `i32`, wider integers are truncated
+        let i32_type = self.context.i32_type();
+        let exit_code = if int_val.get_type().get_bit_width() == 1 {
+            self.builder
+                .build_int_z_extend(int_val, i32_type, "retext")
+                .unwrap()
+        } else {
+            self.builder
+                .build_int_cast_sign_flag(int_val, i32_type, true, "retcast")
+                .unwrap()
+        };
+
+        self.free_temporaries(0);
+        self.builder.build_return(Some(&exit_code)).unwrap();
+
+        Ok(val)
+    }
+
+    /// `return` inside a user-defined function, checked against its
+    /// declared return type.
+    fn compile_function_return(
+        &mut self,
+        function: FunctionValue<'ctx>,
+        value: &Option<Box<Expr>>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let val = match value {
+            Some(value) => Some(self.compile_expression(value)?),
+            None => None,
+        };
+
+        match (function.get_type().get_return_type(), val) {
+            (Some(return_type), Some(val)) => {
+                let return_val = self.coerce_return(function, return_type, val)?;
+                self.free_temporaries(0);
+                self.builder.build_return(Some(&return_val)).unwrap();
+                Ok(val)
+            }
+            (None, None) => {
+                self.free_temporaries(0);
+                self.builder.build_return(None).unwrap();
+                Ok(self.unit_value())
+            }
+            (return_type, val) => Err(CodeGenError::ReturnTypeMismatch(
+                function.get_name().to_string_lossy().to_string(),
+                return_type.map_or("nothing".to_string(), |t| self.type_name(t)),
+                val.map_or("nothing".to_string(), |v| self.type_name(v.get_type())),
+            )),
+        }
+    }
+
+    fn coerce_return(
+        &self,
+        function: FunctionValue<'ctx>,
+        return_type: BasicTypeEnum<'ctx>,
+        val: BasicValueEnum<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        self.coerce_value(val, return_type).ok_or_else(|| {
+            CodeGenError::ReturnTypeMismatch(
+                function.get_name().to_string_lossy().to_string(),
+                self.type_name(return_type),
+                self.type_name(val.get_type()),
+            )
+        })
+    }
+}
+
+// Functions
+impl<'ctx> CodeGen<'ctx> {
+    /// Declares every top-level function up front, so a call may come before
+    /// the function's definition. Constants are compiled here too, so
+    /// function bodies can use them wherever they are defined.
+    fn declare_functions(&mut self, statements: &[Expr]) -> Result<(), CodeGenError> {
+        for statement in statements {
+            match statement {
+                Expr::FunctionDef {
+                    public,
+                    name,
+                    parameters,
+                    return_type,
+                    ..
+                } => {
+                    let declared = self.declare_function(*public, name, parameters, return_type);
+                    self.located(statement, declared)?;
+                }
+                // Signatures may name enums defined further down
+                Expr::EnumDef { name, variants, .. } => {
+                    self.enums.insert(name.clone(), variants.clone());
+                }
+                _ => {}
+            }
+        }
+
+        for statement in statements {
+            collect_function_names(statement, &mut self.function_names);
+        }
+
+        // Constants may use the enums and each other, in order
+        for statement in statements {
+            if let Expr::ConstDef {
+                name,
+                const_type,
+                value,
+                ..
+            } = statement
+            {
+                let defined = self.compile_const_def(name, const_type, value);
+                self.located(statement, defined)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Folds the initializer to a constant and stores it in a global, so
+    /// the constant takes no stack slot and no code runs to initialize it.
+    fn compile_const_def(
+        &mut self,
+        name: &str,
+        const_type: &Types,
+        value: &Expr,
+    ) -> Result<(), CodeGenError> {
+        let invalid = |reason: String| CodeGenError::InvalidConst(name.to_string(), reason);
+
+        if self.constants.contains_key(name) {
+            return Err(invalid("is defined more than once".into()));
+        }
+        if !matches!(
+            const_type,
+            Types::I32 | Types::I64 | Types::F32 | Types::F64 | Types::Bool | Types::Char
+        ) {
+            return Err(invalid(format!("type `{}` is not a scalar", const_type)));
+        }
+        let is_const =
+            |name: &str| self.variable(name).is_none() && self.constants.contains_key(name);
+        if !value.is_constant(&is_const) {
+            return Err(invalid(format!(
+                "initializer `{}` is not a constant expression",
+                value
+            )));
+        }
+
+        // The builder folds instructions on constant operands, so this
+        // emits no code
+        let llvm_type = self.llvm_type(const_type);
+        let compiled = self.compile_expression(value)?;
+        let folded = self.coerce_value(compiled, llvm_type).ok_or_else(|| {
+            invalid(format!(
+                "expected `{}` but initializer `{}` has type `{}`",
+                const_type,
+                value,
+                self.type_name(compiled.get_type())
+            ))
+        })?;
+        let is_folded = match folded {
+            BasicValueEnum::IntValue(int_val) => int_val.is_const(),
+            BasicValueEnum::FloatValue(float_val) => float_val.is_const(),
+            _ => false,
+        };
+        if !is_folded {
+            return Err(invalid(format!(
+                "initializer `{}` does not fold to a constant",
+                value
+            )));
+        }
+
+        let global = self.module.add_global(llvm_type, None, name);
+        global.set_initializer(&folded);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Internal);
+        global.set_unnamed_addr(true);
+
+        self.constants.insert(
+            name.to_string(),
+            Constant {
+                global: global.as_pointer_value(),
+                value: folded,
+                is_char: *const_type == Types::Char,
+            },
+        );
+        Ok(())
+    }
+
+    /// Calls the `#[on_start]` functions and registers the `#[on_exit]` ones
+    /// with `atexit`, at the start of `main`. `atexit` runs them in reverse
+    /// order, whether `main` returns or the program calls `exit`.
+    fn build_hooks(&mut self, statements: &[Expr]) -> Result<(), CodeGenError> {
+        for statement in statements {
+            let Expr::FunctionDef {
+                name,
+                parameters,
+                return_type,
+                hook: Some(hook),
+                ..
+            } = statement
+            else {
+                continue;
+            };
+            if !parameters.is_empty() || return_type.is_some() {
+                let error = CodeGenError::InvalidHook(hook.to_string(), name.clone());
+                return self.located(statement, Err(error));
+            }
+            let Some(function) = self.functions.get(name).map(|signature| signature.value) else {
+                return Err(CodeGenError::UndefinedFunction(name.clone()));
+            };
+
+            match hook {
+                Hook::OnStart => {
+                    self.builder.build_call(function, &[], "hook_call").unwrap();
+                }
+                Hook::OnExit => {
+                    let atexit_fn = self.module.get_function("atexit").unwrap_or_else(|| {
+                        let handler_type = self.context.ptr_type(AddressSpace::default());
+                        let atexit_type = self
+                            .context
+                            .i32_type()
+                            .fn_type(&[handler_type.into()], false);
+                        self.module.add_function("atexit", atexit_type, None)
+                    });
+                    let handler = function.as_global_value().as_pointer_value();
+                    self.builder
+                        .build_call(atexit_fn, &[handler.into()], "atexit_call")
+                        .unwrap();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Functions without `pub` get internal linkage, so they don't clash
+    /// with functions of the same name in other modules.
+    fn declare_function(
+        &mut self,
+        public: bool,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<Types>,
+    ) -> Result<FunctionValue<'ctx>, CodeGenError> {
+        // Also rejects clashes with `main` and the runtime functions
+        if self.module.get_function(name).is_some() || RUNTIME_SYMBOLS.contains(&name) {
+            return Err(CodeGenError::FunctionRedefinition(name.to_string()));
+        }
+
+        let parameters: Vec<(String, BasicTypeEnum<'ctx>)> = parameters
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter.name.clone(),
+                    self.llvm_type(&parameter.param_type),
+                )
+            })
+            .collect();
+        let parameter_types: Vec<BasicMetadataTypeEnum<'ctx>> = parameters
+            .iter()
+            .map(|(_, llvm_type)| (*llvm_type).into())
+            .collect();
+
+        let fn_type = match return_type {
+            Some(return_type) => self.llvm_type(return_type).fn_type(&parameter_types, false),
+            None => self.context.void_type().fn_type(&parameter_types, false),
+        };
+
+        let linkage = (!public).then_some(Linkage::Internal);
+        let function = self.module.add_function(name, fn_type, linkage);
+        self.functions.insert(
+            name.to_string(),
+            FunctionSignature {
+                value: function,
+                parameters,
+                returns_char: return_type.as_ref().is_some_and(holds_chars),
+            },
+        );
+
+        Ok(function)
+    }
+
+    fn compile_function_def(
+        &mut self,
+        public: bool,
+        name: &str,
+        parameters: &[Parameter],
+        return_type: &Option<Types>,
+        body: &Expr,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let function = match self.functions.get(name) {
+            // Declared up front by `declare_functions`
+            Some(signature) if signature.value.count_basic_blocks() == 0 => signature.value,
+            Some(_) => return Err(CodeGenError::FunctionRedefinition(name.to_string())),
+            None => self.declare_function(public, name, parameters, return_type)?,
+        };
+
+        let caller_block = self.builder.get_insert_block();
+        let caller_function = self.function.replace(function);
+        // The body only sees its parameters, and not the loops around it
+        let caller_variables = std::mem::take(&mut self.variables);
+        let caller_loops = std::mem::take(&mut self.loops);
+        let caller_temporaries = std::mem::take(&mut self.temporaries);
+
+        let entry = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(entry);
+
+        let result = self.compile_function_body(function, parameters, body);
+
+        self.temporaries = caller_temporaries;
+        self.loops = caller_loops;
+        self.variables = caller_variables;
+        self.function = caller_function;
+        if let Some(block) = caller_block {
+            self.builder.position_at_end(block);
+        }
+
+        result.map(|_| self.unit_value())
+    }
+
+    fn compile_function_body(
+        &mut self,
+        function: FunctionValue<'ctx>,
+        parameters: &[Parameter],
+        body: &Expr,
+    ) -> Result<(), CodeGenError> {
+        for (parameter, value) in parameters.iter().zip(function.get_param_iter()) {
+            value.set_name(&parameter.name);
+
+            let alloca = self.build_entry_alloca(value.get_type(), &parameter.name);
+            self.builder.build_store(alloca, value).unwrap();
+            if holds_chars(&parameter.param_type) {
+                self.char_slots.insert(alloca);
+            }
+            self.variables
+                .declare(&parameter.name, (alloca, value.get_type(), false));
+        }
+
+        let val = self.compile_expression(body)?;
+
+        if self.is_block_terminated() {
+            return Ok(());
+        }
+
+        // A body that falls through returns its value
+        match function.get_type().get_return_type() {
+            Some(return_type) => {
+                let return_val = self.coerce_return(function, return_type, val)?;
+                self.builder.build_return(Some(&return_val)).unwrap();
+            }
+            None => {
+                self.builder.build_return(None).unwrap();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_call(
+        &mut self,
+        callee: &str,
+        arguments: &[Expr],
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let Some(signature) = self.functions.get(callee).cloned() else {
+            return match Intrinsic::resolve(callee) {
+                Some(intrinsic) => self.compile_intrinsic(intrinsic, arguments),
+                None => Err(CodeGenError::UndefinedFunction(callee.to_string())),
+            };
+        };
+
+        if arguments.len() != signature.parameters.len() {
+            return Err(CodeGenError::WrongArgumentCount(
+                callee.to_string(),
+                signature.parameters.len(),
+                arguments.len(),
+            ));
+        }
+
+        let mut values: Vec<BasicMetadataValueEnum<'ctx>> = Vec::with_capacity(arguments.len());
+        for (argument, (parameter, parameter_type)) in arguments.iter().zip(&signature.parameters) {
+            let val = self.compile_expression(argument)?;
+            let val = self.coerce_value(val, *parameter_type).ok_or_else(|| {
+                CodeGenError::ArgumentTypeMismatch(
+                    callee.to_string(),
+                    parameter.clone(),
+                    self.type_name(*parameter_type),
+                    self.type_name(val.get_type()),
+                )
+            })?;
+            values.push(val.into());
+        }
+
+        // LLVM does not allow naming the result of a `void` call
+        let returns_value = signature.value.get_type().get_return_type().is_some();
+        let call = self
+            .builder
+            .build_call(
+                signature.value,
+                &values,
+                if returns_value { "call" } else { "" },
+            )
+            .unwrap();
+
+        // Calls to functions without a return type evaluate to `()`
+        Ok(call
+            .try_as_basic_value()
+            .left()
+            .unwrap_or_else(|| self.unit_value()))
+    }
+
+    fn compile_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        arguments: &[Expr],
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        if arguments.len() != intrinsic.arity() {
+            return Err(CodeGenError::WrongArgumentCount(
+                intrinsic.name().to_string(),
+                intrinsic.arity(),
+                arguments.len(),
+            ));
+        }
+
+        // No intrinsic keeps its arguments
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.compile_temporary(argument)?);
+        }
+
+        for (parameter, value) in intrinsic.entry().parameters.iter().zip(&values) {
+            let first = values[0];
+            let accepted = match parameter {
+                ParamKind::Int => {
+                    matches!(value, BasicValueEnum::IntValue(int) if int.get_type().get_bit_width() > 1)
+                }
+                ParamKind::Float => value.is_float_value(),
+                ParamKind::Sized => {
+                    value.is_array_value() || value.get_type() == self.string_type().into()
+                }
+                ParamKind::SameAsFirst if value.get_type() != first.get_type() => {
+                    return Err(CodeGenError::TypeMismatch(
+                        self.type_name(first.get_type()),
+                        self.type_name(value.get_type()),
+                    ));
+                }
+                ParamKind::SameAsFirst => true,
+            };
+            if !accepted {
+                return Err(CodeGenError::InvalidOperation(
+                    intrinsic.call_text(arguments),
+                ));
+            }
+        }
+
+        match (intrinsic, values.as_slice()) {
+            // The length of an array is part of its type and strings carry
+            // theirs, so neither has to be scanned
+            (Intrinsic::Len, [BasicValueEnum::ArrayValue(array)]) => Ok(self
+                .context
+                .i64_type()
+                .const_int(array.get_type().len() as u64, false)
+                .into()),
+            (Intrinsic::Len, [BasicValueEnum::StructValue(string)]) => {
+                Ok(self.string_len(*string).into())
+            }
+            (
+                Intrinsic::DivEuclid | Intrinsic::RemEuclid,
+                [
+                    BasicValueEnum::IntValue(left),
+                    BasicValueEnum::IntValue(right),
+                ],
+            ) => Ok(self.build_euclid(intrinsic, *left, *right).into()),
+            (Intrinsic::Input, []) => {
+                let input_fn = self.input_function();
+                Ok(self
+                    .builder
+                    .build_call(input_fn, &[], "input")
+                    .unwrap()
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap())
+            }
+            (Intrinsic::IsNan | Intrinsic::IsInf, [BasicValueEnum::FloatValue(value)]) => {
+                Ok(self.build_float_class(intrinsic, *value).into())
+            }
+            _ => Err(CodeGenError::InternalError(format!(
+                "`{}` accepted arguments it has no lowering for",
+                intrinsic.name()
+            ))),
+        }
+    }
+
+    /// NaN is the only value unordered with itself, and both infinities
+    /// have the magnitude of positive infinity.
+    fn build_float_class(&self, intrinsic: Intrinsic, value: FloatValue<'ctx>) -> IntValue<'ctx> {
+        if intrinsic == Intrinsic::IsNan {
+            return self
+                .builder
+                .build_float_compare(FloatPredicate::UNO, value, value, "is_nan")
+                .unwrap();
+        }
+
+        let float_type = value.get_type();
+        let name = format!("llvm.fabs.{}", self.type_name(float_type.into()));
+        let fabs_fn = self.module.get_function(&name).unwrap_or_else(|| {
+            self.module
+                .add_function(&name, float_type.fn_type(&[float_type.into()], false), None)
+        });
+        let magnitude = self
+            .builder
+            .build_call(fabs_fn, &[value.into()], "fabs")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_float_value();
+        let infinity = float_type.const_float(f64::INFINITY);
+
+        self.builder
+            .build_float_compare(FloatPredicate::OEQ, magnitude, infinity, "is_inf")
+            .unwrap()
+    }
+
+    /// The helper behind `input()`, defined on first use. It reads stdin
+    /// with `getchar` into a `malloc`ed buffer, doubling it as needed, up
+    /// to a newline or the end of input, and returns the line without the
+    /// newline as a string.
+    fn input_function(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("rune_input") {
+            return function;
+        }
+
+        let i8_type = self.context.i8_type();
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+
+        let getchar_fn = self
+            .module
+            .add_function("getchar", i32_type.fn_type(&[], false), None);
+        let malloc_fn =
+            self.module
+                .add_function("malloc", ptr_type.fn_type(&[i64_type.into()], false), None);
+        let realloc_fn = self.module.add_function(
+            "realloc",
+            ptr_type.fn_type(&[ptr_type.into(), i64_type.into()], false),
+            None,
+        );
+        let function = self.module.add_function(
+            "rune_input",
+            self.string_type().fn_type(&[], false),
+            Some(Linkage::Internal),
+        );
+
+        let caller_block = self.builder.get_insert_block();
+        let entry = self.context.append_basic_block(function, "entry");
+        let read = self.context.append_basic_block(function, "read");
+        let check = self.context.append_basic_block(function, "check");
+        let grow = self.context.append_basic_block(function, "grow");
+        let append = self.context.append_basic_block(function, "append");
+        let done = self.context.append_basic_block(function, "done");
+        let builder = &self.builder;
+
+        builder.position_at_end(entry);
+        let initial_capacity = i64_type.const_int(64, false);
+        let initial_buffer = builder
+            .build_call(malloc_fn, &[initial_capacity.into()], "initial_buffer")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+        builder.build_unconditional_branch(read).unwrap();
+
+        builder.position_at_end(read);
+        let buffer = builder.build_phi(ptr_type, "buffer").unwrap();
+        let length = builder.build_phi(i64_type, "length").unwrap();
+        let capacity = builder.build_phi(i64_type, "capacity").unwrap();
+        let buffer_value = buffer.as_basic_value().into_pointer_value();
+        let length_value = length.as_basic_value().into_int_value();
+        let capacity_value = capacity.as_basic_value().into_int_value();
+        let c = builder
+            .build_call(getchar_fn, &[], "c")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        // `getchar` returns a negative `EOF` at the end of input
+        let at_end = builder
+            .build_int_compare(IntPredicate::SLT, c, i32_type.const_zero(), "at_end")
+            .unwrap();
+        let at_newline = builder
+            .build_int_compare(
+                IntPredicate::EQ,
+                c,
+                i32_type.const_int(10, false),
+                "at_newline",
+            )
+            .unwrap();
+        let stop = builder.build_or(at_end, at_newline, "stop").unwrap();
+        builder.build_conditional_branch(stop, done, check).unwrap();
+
+        // One byte is always kept free for the NUL
+        builder.position_at_end(check);
+        let one = i64_type.const_int(1, false);
+        let needed = builder.build_int_add(length_value, one, "needed").unwrap();
+        let full = builder
+            .build_int_compare(IntPredicate::UGE, needed, capacity_value, "full")
+            .unwrap();
+        builder
+            .build_conditional_branch(full, grow, append)
+            .unwrap();
+
+        builder.position_at_end(grow);
+        let grown_capacity = builder
+            .build_int_mul(
+                capacity_value,
+                i64_type.const_int(2, false),
+                "grown_capacity",
+            )
+            .unwrap();
+        let grown_buffer = builder
+            .build_call(
+                realloc_fn,
+                &[buffer_value.into(), grown_capacity.into()],
+                "grown_buffer",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+        builder.build_unconditional_branch(append).unwrap();
+
+        builder.position_at_end(append);
+        let next_buffer = builder.build_phi(ptr_type, "next_buffer").unwrap();
+        next_buffer.add_incoming(&[(&buffer_value, check), (&grown_buffer, grow)]);
+        let next_capacity = builder.build_phi(i64_type, "next_capacity").unwrap();
+        next_capacity.add_incoming(&[(&capacity_value, check), (&grown_capacity, grow)]);
+        let next_buffer_value = next_buffer.as_basic_value().into_pointer_value();
+        let slot = unsafe {
+            builder
+                .build_in_bounds_gep(i8_type, next_buffer_value, &[length_value], "slot")
+                .unwrap()
+        };
+        let byte = builder.build_int_truncate(c, i8_type, "byte").unwrap();
+        builder.build_store(slot, byte).unwrap();
+        let next_length = builder
+            .build_int_add(length_value, one, "next_length")
+            .unwrap();
+        builder.build_unconditional_branch(read).unwrap();
+
+        buffer.add_incoming(&[(&initial_buffer, entry), (&next_buffer_value, append)]);
+        length.add_incoming(&[(&i64_type.const_zero(), entry), (&next_length, append)]);
+        capacity.add_incoming(&[
+            (&initial_capacity, entry),
+            (&next_capacity.as_basic_value(), append),
+        ]);
+
+        builder.position_at_end(done);
+        let end = unsafe {
+            builder
+                .build_in_bounds_gep(i8_type, buffer_value, &[length_value], "end")
+                .unwrap()
+        };
+        builder.build_store(end, i8_type.const_zero()).unwrap();
+        let line = builder
+            .build_insert_value(self.string_type().get_undef(), buffer_value, 0, "line")
+            .unwrap()
+            .into_struct_value();
+        let line = builder
+            .build_insert_value(line, length_value, 1, "line")
+            .unwrap()
+            .into_struct_value();
+        builder.build_return(Some(&line)).unwrap();
+
+        if let Some(block) = caller_block {
+            self.builder.position_at_end(block);
+        }
+
+        function
+    }
+
+    /// Adjusts the truncating `sdiv` and `srem` so the remainder is never
+    /// negative: a negative remainder gets `|right|` added, and the
+    /// quotient moves one step away from zero to match.
+    fn build_euclid(
+        &self,
+        intrinsic: Intrinsic,
+        left: IntValue<'ctx>,
+        right: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let builder = &self.builder;
+        let zero = left.get_type().const_zero();
+        let one = left.get_type().const_int(1, false);
+
+        let quotient = builder.build_int_signed_div(left, right, "div").unwrap();
+        let remainder = builder.build_int_signed_rem(left, right, "rem").unwrap();
+        let negative = builder
+            .build_int_compare(IntPredicate::SLT, remainder, zero, "rem_negative")
+            .unwrap();
+        let right_negative = builder
+            .build_int_compare(IntPredicate::SLT, right, zero, "right_negative")
+            .unwrap();
+
+        if intrinsic == Intrinsic::RemEuclid {
+            let negated = builder.build_int_neg(right, "neg").unwrap();
+            let magnitude = builder
+                .build_select(right_negative, negated, right, "abs")
+                .unwrap()
+                .into_int_value();
+            let adjusted = builder
+                .build_int_add(remainder, magnitude, "rem_adjusted")
+                .unwrap();
+            return builder
+                .build_select(negative, adjusted, remainder, "rem_euclid")
+                .unwrap()
+                .into_int_value();
+        }
+
+        let up = builder.build_int_add(quotient, one, "div_up").unwrap();
+        let down = builder.build_int_sub(quotient, one, "div_down").unwrap();
+        let adjusted = builder
+            .build_select(right_negative, up, down, "div_adjusted")
+            .unwrap()
+            .into_int_value();
+        builder
+            .build_select(negative, adjusted, quotient, "div_euclid")
+            .unwrap()
+            .into_int_value()
+    }
+
+    /// `target.method(arguments)` calls the function `method` with `target`
+    /// as its first argument.
+    fn compile_method_call(
+        &mut self,
+        target: &Expr,
+        method_name: &str,
+        arguments: &[Expr],
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let takes_receiver = self
+            .functions
+            .get(method_name)
+            .is_some_and(|signature| !signature.parameters.is_empty());
+        if !takes_receiver {
+            return Err(CodeGenError::UndefinedMethod(method_name.to_string()));
+        }
+
+        let arguments: Vec<Expr> = std::iter::once(target.clone())
+            .chain(arguments.iter().cloned())
+            .collect();
+
+        self.compile_call(method_name, &arguments)
+    }
+}
+
+// Linking
+impl<'ctx> CodeGen<'ctx> {
+    /// Links `modules` into the first of them, which is returned.
+    ///
+    /// Every module must come from the same context. A function or global
+    /// defined with external linkage in more than one module is reported
+    /// with [`CodeGenError::DuplicateSymbol`] before LLVM sees it, private
+    /// symbols such as string literals are renamed by LLVM as needed.
+    pub fn link_modules(modules: Vec<Module<'ctx>>) -> Result<Module<'ctx>, CodeGenError> {
+        let mut modules = modules.into_iter();
+        let linked = modules
+            .next()
+            .ok_or(CodeGenError::LinkError("no modules to link".to_string()))?;
+
+        let mut definitions: HashMap<String, String> = HashMap::new();
+        record_definitions(&linked, &mut definitions)?;
+
+        for module in modules {
+            record_definitions(&module, &mut definitions)?;
+            linked
+                .link_in_module(module)
+                .map_err(|err| CodeGenError::LinkError(err.to_string()))?;
+        }
+
+        Ok(linked)
+    }
+}
+
+/// Adds the externally visible definitions of `module` to `definitions`,
+/// which maps symbol names to the name of the module defining them.
+fn record_definitions(
+    module: &Module<'_>,
+    definitions: &mut HashMap<String, String>,
+) -> Result<(), CodeGenError> {
+    let module_name = module.get_name().to_string_lossy().into_owned();
+
+    let functions = module
+        .get_functions()
+        .filter(|function| function.count_basic_blocks() > 0)
+        .map(|function| (function.get_name().to_owned(), function.get_linkage()));
+    let globals = module
+        .get_globals()
+        .filter(|global| !global.is_declaration())
+        .map(|global| (global.get_name().to_owned(), global.get_linkage()));
+
+    for (name, linkage) in functions.chain(globals) {
+        if matches!(linkage, Linkage::Private | Linkage::Internal) {
+            continue;
+        }
+
+        let name = name.to_string_lossy().into_owned();
+        if let Some(first) = definitions.insert(name.clone(), module_name.clone()) {
+            return Err(CodeGenError::DuplicateSymbol(name, first, module_name));
+        }
+    }
+
+    Ok(())
+}
+
+// Display
+impl<'ctx> CodeGen<'ctx> {
+    pub fn print_ir(&self) {
+        self.module.print_to_stderr();
+    }
+
+    pub fn get_ir_string(&self) -> String {
+        self.module.print_to_string().to_string()
+    }
+
+    /// Code size statistics for the functions compiled so far.
+    pub fn stats(&self) -> ModuleStats {
+        ModuleStats::collect(&self.module, &self.target_data())
+    }
+}
+
+// Strings
+impl<'ctx> CodeGen<'ctx> {
+    /// Strings are a `{ ptr, i64 }` pair of their bytes and length, so
+    /// slicing and concatenation never have to scan for the end.
+    ///
+    /// The bytes are always followed by a NUL that is not counted in the
+    /// length, which lets the pointer be handed to C as is, see
+    /// `string_to_c`.
+    pub fn string_type(&self) -> StructType<'ctx> {
+        self.context.struct_type(
+            &[
+                self.context.ptr_type(AddressSpace::default()).into(),
+                self.context.i64_type().into(),
+            ],
+            false,
+        )
+    }
+
+    fn compile_string_literal(&self, value: &str) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let bytes = self
+            .builder
+            .build_global_string_ptr(value, "str")
+            .map_err(|err| CodeGenError::StringError(err.to_string()))?;
+        let len = self.context.i64_type().const_int(value.len() as u64, false);
+
+        Ok(self
+            .string_type()
+            .const_named_struct(&[bytes.as_pointer_value().into(), len.into()])
+            .into())
+    }
+
+    /// The NUL-terminated bytes of `string`, for passing it to C.
+    fn string_to_c(&self, string: StructValue<'ctx>) -> PointerValue<'ctx> {
+        self.builder
+            .build_extract_value(string, 0, "cstr")
+            .unwrap()
+            .into_pointer_value()
+    }
+
+    /// The number of bytes in `string`, not counting the NUL.
+    fn string_len(&self, string: StructValue<'ctx>) -> IntValue<'ctx> {
+        self.builder
+            .build_extract_value(string, 1, "len")
+            .unwrap()
+            .into_int_value()
+    }
+}
+
+// Print
+impl<'ctx> CodeGen<'ctx> {
+    /// Strings go through `puts`, numbers and booleans through `printf`.
+    /// Each value is printed on its own line.
+    fn compile_print(&mut self, value: &Expr) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let is_char = self.is_char(value);
+        let printed_val = self.compile_temporary(value)?;
+
+        if let (true, BasicValueEnum::IntValue(code_point)) = (is_char, printed_val) {
+            return self.build_print_char(code_point);
+        }
+
+        let (format, argument): (&'static str, BasicValueEnum<'ctx>) = match printed_val {
+            BasicValueEnum::StructValue(string) if string.get_type() == self.string_type() => {
+                let text = self.string_to_c(string);
+                return self.build_puts(text);
+            }
+            BasicValueEnum::IntValue(int_val) => match int_val.get_type().get_bit_width() {
+                1 => {
+                    let true_str = self.format_string("true");
+                    let false_str = self.format_string("false");
+                    let text = self
+                        .builder
+                        .build_select(int_val, true_str, false_str, "bool_str")
+                        .unwrap();
+                    ("%s\n", text)
+                }
+                32 => ("%d\n", int_val.into()),
+                64 => ("%lld\n", int_val.into()),
+                _ => {
+                    let widened = self
+                        .builder
+                        .build_int_s_extend(int_val, self.context.i64_type(), "widen")
+                        .unwrap();
+                    ("%lld\n", widened.into())
+                }
+            },
+            BasicValueEnum::FloatValue(float_val) => {
+                // Variadic arguments are promoted to `double`
+                let f64_type = self.context.f64_type();
+                let promoted = if float_val.get_type() == f64_type {
+                    float_val
+                } else {
+                    self.builder
+                        .build_float_ext(float_val, f64_type, "promote")
+                        .unwrap()
+                };
+                return self.build_print_float(promoted);
+            }
+            other => {
+                return Err(CodeGenError::TypeMismatchCustom(format!(
+                    "values of type `{}` cannot be printed",
+                    self.type_name(other.get_type())
+                )));
+            }
+        };
+
+        let printf_fn = self.printf_fn.ok_or(CodeGenError::InternalError(
+            "printf function not declared".to_string(),
+        ))?;
+        let format = self.format_string(format);
+
+        let call_result = self
+            .builder
+            .build_call(printf_fn, &[format.into(), argument.into()], "printf_call")
+            .unwrap();
+
+        Ok(call_result.try_as_basic_value().left().unwrap())
+    }
+
+    /// Whether `expr` is a `char`, which codegen can't tell from its `i32`,
+    /// or an array of them.
+    fn is_char(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal(Nodes::Char(_), _) => true,
+            Expr::Cast { target, .. } => *target == Types::Char,
+            Expr::ArrayLiteral(elements, _) => {
+                elements.first().is_some_and(|first| self.is_char(first))
+            }
+            Expr::Index { target, .. } => self.is_char(target),
+            Expr::Match { arms, .. } => arms.first().is_some_and(|arm| self.is_char(&arm.body)),
+            Expr::Variable { name, .. } => match self.variable(name) {
+                Some((slot, _, _)) => self.char_slots.contains(&slot),
+                None => self
+                    .constants
+                    .get(name)
+                    .is_some_and(|constant| constant.is_char),
+            },
+            Expr::Call { callee, .. } => self
+                .functions
+                .get(callee)
+                .is_some_and(|signature| signature.returns_char),
+            Expr::Block(statements, _) => statements.last().is_some_and(|last| self.is_char(last)),
+            _ => false,
+        }
+    }
+
+    /// Prints a code point as UTF-8 through `puts`. The lead byte holds the
+    /// top bits, each continuation byte the next 6 bits.
+    fn build_print_char(
+        &mut self,
+        code_point: IntValue<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let i8_type = self.context.i8_type();
+        let i32_type = self.context.i32_type();
+        let buffer_type = i8_type.array_type(5);
+        let buffer = self.build_entry_alloca(buffer_type.into(), "char_buf");
+        let constant = |value: u64| i32_type.const_int(value, false);
+
+        // Number of continuation bytes
+        let mut continuations = constant(0);
+        for bound in [0x80, 0x800, 0x10000] {
+            let above = self
+                .builder
+                .build_int_compare(IntPredicate::UGE, code_point, constant(bound), "above")
+                .unwrap();
+            let above = self
+                .builder
+                .build_int_z_extend(above, i32_type, "above_ext")
+                .unwrap();
+            continuations = self
+                .builder
+                .build_int_add(continuations, above, "continuations")
+                .unwrap();
+        }
+
+        let store_byte = |codegen: &Self, index: IntValue<'ctx>, byte: IntValue<'ctx>| {
+            let byte = codegen
+                .builder
+                .build_int_truncate(byte, i8_type, "byte")
+                .unwrap();
+            let slot = unsafe {
+                codegen
+                    .builder
+                    .build_in_bounds_gep(buffer_type, buffer, &[constant(0), index], "byte_slot")
+                    .unwrap()
+            };
+            codegen.builder.build_store(slot, byte).unwrap();
+        };
+
+        // 0xxxxxxx, 110xxxxx, 1110xxxx or 11110xxx
+        let mut prefix = constant(0);
+        for (count, lead) in [(1, 0xC0), (2, 0xE0), (3, 0xF0)] {
+            let is_count = self
+                .builder
+                .build_int_compare(IntPredicate::EQ, continuations, constant(count), "is_count")
+                .unwrap();
+            prefix = self
+                .builder
+                .build_select(is_count, constant(lead), prefix, "prefix")
+                .unwrap()
+                .into_int_value();
+        }
+        let lead_shift = self
+            .builder
+            .build_int_mul(continuations, constant(6), "lead_shift")
+            .unwrap();
+        let lead = self
+            .builder
+            .build_right_shift(code_point, lead_shift, false, "lead")
+            .unwrap();
+        let lead = self.builder.build_or(lead, prefix, "lead").unwrap();
+        store_byte(self, constant(0), lead);
+
+        // 10xxxxxx, unused ones land after the terminator
+        for index in 1..=3 {
+            let used = self
+                .builder
+                .build_int_compare(IntPredicate::UGE, continuations, constant(index), "used")
+                .unwrap();
+            let remaining = self
+                .builder
+                .build_int_sub(continuations, constant(index), "remaining")
+                .unwrap();
+            let shift = self
+                .builder
+                .build_int_mul(remaining, constant(6), "shift")
+                .unwrap();
+            let shift = self
+                .builder
+                .build_select(used, shift, constant(0), "shift")
+                .unwrap()
+                .into_int_value();
+            let bits = self
+                .builder
+                .build_right_shift(code_point, shift, false, "bits")
+                .unwrap();
+            let bits = self
+                .builder
+                .build_and(bits, constant(0x3F), "bits")
+                .unwrap();
+            let byte = self.builder.build_or(bits, constant(0x80), "byte").unwrap();
+            store_byte(self, constant(index), byte);
+        }
+
+        let end = self
+            .builder
+            .build_int_add(continuations, constant(1), "end")
+            .unwrap();
+        store_byte(self, end, constant(0));
+
+        self.build_puts(buffer)
+    }
+
+    fn build_puts(
+        &mut self,
+        text: PointerValue<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let puts_fn = self.puts_fn.ok_or(CodeGenError::InternalError(
+            "puts function not declared".to_string(),
+        ))?;
+
+        let call_result = self
+            .builder
+            .build_call(puts_fn, &[text.into()], "puts_call")
+            .unwrap();
+
+        Ok(call_result.try_as_basic_value().left().unwrap())
+    }
+
+    /// Prints a `double` with [`CompileOptions::float_precision`] decimals,
+    /// or through the helper from [`CodeGen::print_float_function`].
+    fn build_print_float(
+        &mut self,
+        value: FloatValue<'ctx>,
+    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+        let printf_fn = self.printf_fn.ok_or(CodeGenError::InternalError(
+            "printf function not declared".to_string(),
+        ))?;
+
+        let call = match self.float_precision {
+            Some(precision) => {
+                let format = self.format_string("%.*f\n");
+                let precision = self.context.i32_type().const_int(precision as u64, false);
+                self.builder.build_call(
+                    printf_fn,
+                    &[format.into(), precision.into(), value.into()],
+                    "printf_call",
+                )
+            }
+            None => {
+                let print_fn = self.print_float_function(printf_fn);
+                self.builder
+                    .build_call(print_fn, &[value.into()], "printf_call")
+            }
+        };
+
+        Ok(call.unwrap().try_as_basic_value().left().unwrap())
+    }
+
+    /// The helper floats are printed with, defined on first use. It looks
+    /// for the fewest significant digits that `strtod` reads back as the
+    /// same value, so `0.1` prints as `0.1` rather than `0.100000`, and
+    /// prints them without an exponent unless it is below -4 or above 16.
+    /// `nan` and `inf` are printed by `%g`.
+    fn print_float_function(&mut self, printf_fn: FunctionValue<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("rune_print_float") {
+            return function;
+        }
+
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let f64_type = self.context.f64_type();
+        let ptr_type = self.context.ptr_type(AddressSpace::default());
+
+        let snprintf_fn = self.module.add_function(
+            "snprintf",
+            i32_type.fn_type(&[ptr_type.into(), i64_type.into(), ptr_type.into()], true),
+            None,
+        );
+        let strtod_fn = self.module.add_function(
+            "strtod",
+            f64_type.fn_type(&[ptr_type.into(), ptr_type.into()], false),
+            None,
+        );
+        let strchr_fn = self.module.add_function(
+            "strchr",
+            ptr_type.fn_type(&[ptr_type.into(), i32_type.into()], false),
+            None,
+        );
+        let atoi_fn =
+            self.module
+                .add_function("atoi", i32_type.fn_type(&[ptr_type.into()], false), None);
+        let function = self.module.add_function(
+            "rune_print_float",
+            i32_type.fn_type(&[f64_type.into()], false),
+            Some(Linkage::Internal),
+        );
+        let value = function.get_nth_param(0).unwrap().into_float_value();
+
+        let caller_block = self.builder.get_insert_block();
+        let entry = self.context.append_basic_block(function, "entry");
+        let special = self.context.append_basic_block(function, "special");
+        let search = self.context.append_basic_block(function, "search");
+        let retry = self.context.append_basic_block(function, "retry");
+        let found = self.context.append_basic_block(function, "found");
+        let scientific = self.context.append_basic_block(function, "scientific");
+        let fixed = self.context.append_basic_block(function, "fixed");
+
+        // The format strings are globals, created from inside the helper
+        self.builder.position_at_end(entry);
+        let general_format = self.format_string("%g\n");
+        let exponent_format = self.format_string("%.*e");
+        let text_format = self.format_string("%s\n");
+        let fixed_format = self.format_string("%.*f\n");
+        let builder = &self.builder;
+
+        let buffer_type = self.context.i8_type().array_type(32);
+        let buffer = builder.build_alloca(buffer_type, "buffer").unwrap();
+        // `x - x` is only zero for finite values
+        let difference = builder.build_float_sub(value, value, "difference").unwrap();
+        let finite = builder
+            .build_float_compare(
+                FloatPredicate::OEQ,
+                difference,
+                f64_type.const_zero(),
+                "finite",
+            )
+            .unwrap();
+        builder
+            .build_conditional_branch(finite, search, special)
+            .unwrap();
+
+        builder.position_at_end(special);
+        let printed = builder
+            .build_call(printf_fn, &[general_format.into(), value.into()], "printed")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        builder.build_return(Some(&printed)).unwrap();
+
+        // 17 significant digits always read back as the same value
+        builder.position_at_end(search);
+        let digits = builder.build_phi(i32_type, "digits").unwrap();
+        let digits_value = digits.as_basic_value().into_int_value();
+        let one = i32_type.const_int(1, false);
+        let decimals = builder
+            .build_int_sub(digits_value, one, "decimals")
+            .unwrap();
+        builder
+            .build_call(
+                snprintf_fn,
+                &[
+                    buffer.into(),
+                    i64_type.const_int(32, false).into(),
+                    exponent_format.into(),
+                    decimals.into(),
+                    value.into(),
+                ],
+                "",
+            )
+            .unwrap();
+        let read_back = builder
+            .build_call(
+                strtod_fn,
+                &[buffer.into(), ptr_type.const_null().into()],
+                "read_back",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_float_value();
+        let exact = builder
+            .build_float_compare(FloatPredicate::OEQ, read_back, value, "exact")
+            .unwrap();
+        let most = builder
+            .build_int_compare(
+                IntPredicate::SGE,
+                digits_value,
+                i32_type.const_int(17, false),
+                "most",
+            )
+            .unwrap();
+        let stop = builder.build_or(exact, most, "stop").unwrap();
+        builder
+            .build_conditional_branch(stop, found, retry)
+            .unwrap();
+
+        builder.position_at_end(retry);
+        let more_digits = builder
+            .build_int_add(digits_value, one, "more_digits")
+            .unwrap();
+        builder.build_unconditional_branch(search).unwrap();
+        digits.add_incoming(&[(&one, entry), (&more_digits, retry)]);
+
+        builder.position_at_end(found);
+        let e = builder
+            .build_call(
+                strchr_fn,
+                &[buffer.into(), i32_type.const_int('e' as u64, false).into()],
+                "e",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_pointer_value();
+        let exponent_text = unsafe {
+            builder
+                .build_in_bounds_gep(
+                    self.context.i8_type(),
+                    e,
+                    &[i64_type.const_int(1, false)],
+                    "exponent_text",
+                )
+                .unwrap()
+        };
+        let exponent = builder
+            .build_call(atoi_fn, &[exponent_text.into()], "exponent")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value();
+        let tiny = builder
+            .build_int_compare(
+                IntPredicate::SLT,
+                exponent,
+                i32_type.const_int(-4i64 as u64, true),
+                "tiny",
+            )
+            .unwrap();
+        let huge = builder
+            .build_int_compare(
+                IntPredicate::SGE,
+                exponent,
+                i32_type.const_int(17, false),
+                "huge",
+            )
+            .unwrap();
+        let use_exponent = builder.build_or(tiny, huge, "use_exponent").unwrap();
+        builder
+            .build_conditional_branch(use_exponent, scientific, fixed)
+            .unwrap();
+
+        builder.position_at_end(scientific);
+        let printed = builder
+            .build_call(printf_fn, &[text_format.into(), buffer.into()], "printed")
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        builder.build_return(Some(&printed)).unwrap();
+
+        // The digits after the point are the significant ones left of the
+        // exponent's position
+        builder.position_at_end(fixed);
+        let places = builder.build_int_sub(decimals, exponent, "places").unwrap();
+        let negative = builder
+            .build_int_compare(IntPredicate::SLT, places, i32_type.const_zero(), "negative")
+            .unwrap();
+        let places = builder
+            .build_select(negative, i32_type.const_zero(), places, "places")
+            .unwrap();
+        let printed = builder
+            .build_call(
+                printf_fn,
+                &[fixed_format.into(), places.into(), value.into()],
+                "printed",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        builder.build_return(Some(&printed)).unwrap();
+
+        if let Some(block) = caller_block {
+            self.builder.position_at_end(block);
+        }
+
+        function
+    }
+
+    fn format_string(&mut self, text: &'static str) -> PointerValue<'ctx> {
+        if let Some(ptr) = self.format_strings.get(text) {
+            return *ptr;
+        }
+
+        let ptr = self
+            .builder
+            .build_global_string_ptr(text, "fmt")
+            .unwrap()
+            .as_pointer_value();
+        self.format_strings.insert(text, ptr);
+        ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inkwell::values::InstructionOpcode;
+    use rune_parser::parser::Parser;
+
+    #[test]
+    fn test_simple_arithmetic() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let x = 5 + 3".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        // Verify module is valid
+        assert_ne!(codegen.module.to_string(), "");
+        assert!(codegen.module.verify().is_ok());
+    }
+
+    #[test]
+    fn test_variables() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let x = 10; let y = x + 5".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        let result = codegen.module.verify();
+
+        if result.is_err() {
+            panic!("Module verification failed");
+        }
+    }
+
+    #[test]
+    fn test_if_else() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("let x = 5; if x > 3 { let y = 10 } else { let y = 20 }".to_string())
+                .unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        let result = codegen.module.verify();
+
+        dbg!(&result);
+        if let Err(error) = result {
+            dbg!(error);
+            panic!("Module verification failed");
+        }
+    }
+
+    #[test]
+    fn unit_branches_are_not_merged() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "fn greet() { print(\"hi\"); } let x = 5; if x > 3 { 1 } if x > 4 { greet() } else { print(x) }"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(!ir.contains("phi"));
+        assert!(!ir.contains("iftmp"));
+
+        let statements = Parser::new("fn greet() {} print(greet())".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            CodeGen::new(&context, "test").compile_statements(&statements),
+            Err(CodeGenError::TypeMismatchCustom(
+                "values of type `()` cannot be printed".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn if_branches_must_agree() {
+        let context = Context::create();
+
+        let compile = |source: &str| {
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            CodeGen::new(&context, "test").compile_statements(&statements)
+        };
+
+        assert_eq!(
+            compile("let c = true; let x = if c { 1 } else { 2.0 };"),
+            Err(CodeGenError::TypeMismatch("i64".into(), "f64".into()))
+        );
+        assert_eq!(
+            compile("let c = true; let x = if c { 1 } else { print(\"a\"); };"),
+            Err(CodeGenError::TypeMismatch("i64".into(), "()".into()))
+        );
+        assert_eq!(
+            compile("fn f(c: bool) -> f64 { let x = if c { return 0.0; } else { 2.0 }; x }"),
+            Ok(())
+        );
+
+        let mut codegen = CodeGen::new(&context, "test");
+        let statements = Parser::new("let c = true; let x = if c { 1 } else { 2 };".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
+        codegen.compile_statements(&statements).unwrap();
+        assert!(codegen.module.verify().is_ok());
+        assert!(codegen.get_ir_string().contains("%iftmp = phi i64"));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "fn check() -> bool { print(\"checked\"); true } let x = 2; let a = x > 3 && check(); let b = !a || check();"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("br i1 %gt, label %rhs, label %logicalcont"));
+        assert!(ir.contains("phi i1 [ false, %entry ], [ %call, %rhs ]"));
+        assert!(ir.contains("%not = xor i1 %a, true"));
+        assert!(ir.contains("phi i1 [ true, %logicalcont ]"));
+        assert!(!ir.contains(" and i"));
+        assert!(!ir.contains(" or i"));
+    }
+
+    #[test]
+    fn logical_operators_take_bools() {
+        let context = Context::create();
+
+        let compile = |source: &str| {
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            let mut codegen = CodeGen::new(&context, "test");
+            codegen
+                .compile_statements(&statements)
+                .map(|_| codegen.get_ir_string())
+        };
+
+        assert_eq!(
+            compile("let x = 2; let b = x || true;"),
+            Err(CodeGenError::OperatorNotSupported("||".into(), "x".into()))
+        );
+        assert_eq!(
+            compile("let x = 5; let y = !x;"),
+            Err(CodeGenError::OperatorNotSupported("!".into(), "x".into()))
+        );
+        assert_eq!(
+            compile("let x = true; let y = ~x;"),
+            Err(CodeGenError::OperatorNotSupported("~".into(), "x".into()))
+        );
+        assert!(
+            compile("let x = 5; let y = ~x;")
+                .unwrap()
+                .contains("%bitnot = xor i64 %x, -1")
+        );
+    }
+
+    #[test]
+    fn strings_have_no_arithmetic() {
+        let context = Context::create();
+
+        let compile = |source: &str| {
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            CodeGen::new(&context, "test").compile_statements(&statements)
+        };
+
+        assert!(compile(r#"let s = "a" + "b"; print(s);"#).is_ok());
+        assert_eq!(
+            compile(r#"let a = "x"; let b = a > "y";"#),
+            Err(CodeGenError::OperatorNotSupported(
+                ">".into(),
+                "string | string".into()
+            ))
+        );
+        assert_eq!(
+            compile(r#"let a = "x"; let b = a + 1;"#),
+            Err(CodeGenError::OperatorNotSupported(
+                "+".into(),
+                "string | i64".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn explicit_type_annotation() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let x: i64 = 5;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        let result = codegen.module.verify();
+
+        dbg!(&result);
+        if let Err(error) = result {
+            dbg!(error);
+            panic!("Module verification failed");
+        }
+    }
+
+    #[test]
+    fn let_type_mismatch() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let x: bool = 42;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let result = codegen.compile_statements(&statements);
+
+        assert_eq!(
+            result.unwrap_err(),
+            CodeGenError::LetTypeMismatch("x".into(), "bool".into(), "i64".into(), "42".into())
+        );
+    }
+
+    #[test]
+    fn let_integer_narrowing() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let x: i32 = 42; let y: f32 = 1.5;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert!(codegen.get_ir_string().contains("alloca i32"));
+    }
+
+    #[test]
+    fn typed_literals_and_overflow() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let source = format!(
+            "let a = 7i32 * 6i32; let b = 2.5f32; let c: i32 = -2147483648; let d: i32 = 4294967297i64; let e: f32 = 1{}.0;",
+            "0".repeat(39)
+        );
+        let mut parser = Parser::new(source).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        let ir = codegen.get_ir_string();
+        assert!(codegen.module.verify().is_ok());
+        assert!(ir.contains("store i32 42, ptr %a"));
+        assert!(ir.contains("store float 2.500000e+00, ptr %b"));
+        assert!(ir.contains("store i32 -2147483648, ptr %c"));
+        assert!(ir.contains("store i32 1, ptr %d"));
+        assert_eq!(
+            codegen.warnings(),
+            &[CodeGenWarning::LiteralOverflow(
+                "1e39".into(),
+                "f32".into(),
+                "inf".into()
+            )]
+        );
+
+        for (source, literal) in [
+            ("let d: i32 = 4294967297;", "4294967297"),
+            ("let mut d = 0i32; d = -2147483649;", "-2147483649"),
+        ] {
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            assert_eq!(
+                CodeGen::new(&context, "test").compile_statements(&statements),
+                Err(CodeGenError::LiteralOutOfRange(
+                    literal.into(),
+                    "i32".into()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn assignment_type_mismatch() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let mut x = 1; x = true;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let result = codegen.compile_statements(&statements);
+
+        assert_eq!(
+            result.unwrap_err(),
+            CodeGenError::AssignTypeMismatch(
+                "x".into(),
+                "i64".into(),
+                "bool".into(),
+                "true".into()
+            )
+        );
+    }
+
+    #[test]
+    fn assignment_integer_coercion() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("let mut x: i32 = 1; x = x + 2;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert!(codegen.get_ir_string().contains("store i32"));
+    }
+
+    #[test]
+    fn assignment_to_immutable_binding() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let source = "let x = 1; let x = x + 1;\nfor i in 0..x { x = i; }";
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let error = codegen.compile_statements(&statements).unwrap_err();
+        assert_eq!(error, CodeGenError::AssignToImmutable("x".into()));
+
+        let span = codegen.failed_span().unwrap();
+        assert_eq!(&source[span.range()], "x = i");
+        assert_eq!(
+            error.at(source, span).to_string(),
+            "2:17: (C014): Cannot assign twice to immutable variable `x`, declare it with `let mut`"
+        );
+    }
+
+    #[test]
+    fn redeclaration_shadows_with_warning() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new(r#"let x = 1; let x = "two"; print(x);"#.to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert_eq!(
+            codegen.warnings(),
+            &[CodeGenWarning::ShadowedVariable("x".into())]
+        );
+    }
+
+    #[test]
+    fn shadowing_creates_a_fresh_binding() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        // The initializer still sees the old `x`, later uses see the new one
+        let mut parser = Parser::new(
+            "let x = 2; let mut x = x * 1.5; { let mut x = true; x = false; } x = x / 2.0;"
+                .to_string(),
+        )
+        .unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("%x = alloca i64"));
+        assert!(ir.contains("%x1 = alloca double"));
+        assert!(ir.contains("%x2 = alloca i1"));
+        assert!(ir.contains("store double %fdiv, ptr %x1"));
+        assert_eq!(
+            codegen.warnings(),
+            &[
+                CodeGenWarning::ImplicitPromotion("x".into(), "i64".into(), "f64".into()),
+                CodeGenWarning::ShadowedVariable("x".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn block_scoped_declaration() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("let mut x = 1; { let x = true; } x = x + 1;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert!(codegen.warnings().is_empty());
+    }
+
+    #[test]
+    fn branch_declarations_stay_in_their_branch() {
+        let compile = |source: &str| {
+            let context = Context::create();
+            let mut codegen = CodeGen::new(&context, "test");
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            codegen
+                .compile_statements(&statements)
+                .map(|_| codegen.warnings().to_vec())
+        };
+
+        assert_eq!(
+            compile("let mut y = 1; if y > 0 { let y = 2.5; } else { let y = true; } y = y + 1;"),
+            Ok(vec![])
+        );
+        assert_eq!(
+            compile("if true { let z = 1; } z"),
+            Err(CodeGenError::UndefinedVariable("z".into()))
+        );
+        assert_eq!(
+            compile("for i in 0..2 { let w = i; } w"),
+            Err(CodeGenError::UndefinedVariable("w".into()))
+        );
+    }
+
+    #[test]
+    fn statement_ir_names() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new(r#"let mut x = 1 + 2; print("hi"); x = x * 3;"#.to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mapped = codegen.compile_statements_mapped(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert_eq!(mapped.len(), 3);
+        assert!(mapped[0].names.contains(&"%x".to_string()));
+        assert!(mapped[1].names.contains(&"@str".to_string()));
+        assert!(!mapped[2].names.contains(&"%x".to_string()));
+
+        let notes: Vec<_> = mapped[0]
+            .lines
+            .iter()
+            .filter_map(|line| line.note.as_deref())
+            .collect();
+        assert!(notes.contains(&"stack slot for `x`"));
+        assert!(notes.contains(&"write a value to a stack slot"));
+    }
+
+    #[test]
+    fn type_queries() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("let s = size_of(i32); let a = align_of(bool);".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("store i64 4, ptr %s"));
+        assert!(ir.contains("store i64 1, ptr %a"));
+    }
+
+    #[test]
+    fn target_options_are_applied() {
+        let context = Context::create();
+        let options = CompileOptions {
+            triple: Some("x86_64-unknown-linux-gnu".into()),
+            data_layout: Some("e-m:e-p:32:32-i64:32-n8:16:32:64-S128".into()),
+            ..CompileOptions::default()
+        };
+        let mut codegen = CodeGen::with_options(&context, "test", &options);
+
+        let mut parser = Parser::new("let p = size_of(string);".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains(r#"target triple = "x86_64-unknown-linux-gnu""#));
+        assert!(ir.contains(r#"target datalayout = "e-m:e-p:32:32-i64:32-n8:16:32:64-S128""#));
+        // A 32-bit pointer and a 32-bit aligned length in the layout above
+        assert!(ir.contains("store i64 12, ptr %p"));
+    }
+
+    #[test]
+    fn code_stats() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("let mut x = 1; let y: i32 = 2; if x > 0 { x = 2; }".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        let stats = codegen.stats();
+        assert_eq!(stats.functions.len(), 1);
+
+        let main = &stats.functions[0];
+        assert_eq!(main.name, "main");
+        assert_eq!(main.basic_blocks, 4);
+        assert_eq!(main.stack_bytes, 12);
+        assert!(main.instructions > 0);
+        assert_eq!(stats.instructions(), main.instructions);
+    }
+
+    #[test]
+    fn adjacent_strings_share_one_global() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new(r#"print("foo" "bar");"#.to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert_eq!(codegen.module.get_globals().count(), 1);
+        assert!(codegen.get_ir_string().contains(r#"c"foobar\00""#));
+    }
+
+    #[test]
+    fn early_return_in_branch() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new(r#"let x = 3; if x > 2 { return x; } print("small");"#.to_string())
+                .unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert_eq!(codegen.get_ir_string().matches("ret i32").count(), 2);
+    }
+
+    #[test]
+    fn both_branches_return() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("if true { return 1; } else { return false; }".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+    }
+
+    #[test]
+    fn statements_after_return_are_skipped() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new("return 1; let y = 2;".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert!(!codegen.get_ir_string().contains("%y"));
+    }
+
+    #[test]
+    fn unreachable_statements_warn() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new(
+            "let mut x = 1; if x > 0 { return x; let y = 2; } else { return 0; } x = 3;"
+                .to_string(),
+        )
+        .unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert_eq!(codegen.warnings().len(), 2);
+        assert_eq!(
+            codegen.warnings()[1],
+            CodeGenWarning::UnreachableCode(statements[2].to_string())
+        );
+    }
+
+    #[test]
+    fn lossy_int_to_float_promotions_warn() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let source = "let n = 9007199254740993; let small: i32 = 3; let a = 2 * 1.5; let b = small * 1.5; let c = n * 1.5; let d = 0.5 + 9007199254740993;";
+        let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        // Small constants and `i32`s convert exactly
+        assert_eq!(
+            codegen.warnings(),
+            &[
+                CodeGenWarning::ImplicitPromotion("n".into(), "i64".into(), "f64".into()),
+                CodeGenWarning::ImplicitPromotion(
+                    "9007199254740993".into(),
+                    "i64".into(),
+                    "f64".into()
+                ),
+            ]
+        );
+        assert_eq!(codegen.warning_statements(), &[Some(4), Some(5)]);
+        // Each promotion points at its operand, not the whole statement
+        let operands: Vec<_> = codegen
+            .warning_spans()
+            .iter()
+            .map(|span| span.map(|span| &source[span.start..span.end]))
+            .collect();
+        assert_eq!(operands, [Some("n"), Some("9007199254740993")]);
+    }
+
+    #[test]
+    fn discarded_values_warn() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "fn f() -> i64 { 1 } fn g() {} let x = 1; x + 1; f(); g(); _ = f(); { x; x * 2 }"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert_eq!(
+            codegen.warnings(),
+            &[
+                CodeGenWarning::UnusedValue("x + 1".into()),
+                CodeGenWarning::UnusedValue("f()".into()),
+                CodeGenWarning::UnusedValue("x".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn break_and_continue_branch_to_the_innermost_loop() {
+        let compile = |source: &str| -> Result<String, CodeGenError> {
+            let context = Context::create();
+            let mut codegen = CodeGen::new(&context, "test");
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+
+            codegen.compile_statements(&statements)?;
+            assert!(codegen.module.verify().is_ok());
+            Ok(codegen.get_ir_string())
+        };
+
+        let ir = compile(
+            "for i in 0..3 { for j in 0..3 { if j > i { break; } if j == 0 { continue; } print(j); } }",
+        )
+        .unwrap();
+        // Besides the loop conditions, only `break` leaves for `forafter`
+        assert_eq!(ir.matches("br label %forafter").count(), 1);
+        assert!(ir.contains("forinc:"));
+
+        let ir = compile("for i in 0..3 { break; }").unwrap();
+        assert!(!ir.contains("forinc"));
+
+        assert_eq!(
+            compile("for i in 0..3 { fn f() { break; } }"),
+            Err(CodeGenError::OutsideLoop("break".into()))
+        );
+    }
+
+    #[test]
+    fn for_loop_reuses_stack_slots() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("let n = 10; for i in 0..n { let square = i * i; }".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+        assert!(codegen.module.verify().is_ok());
+
+        // Every alloca sits in the entry block, ahead of the loop
+        let entry = codegen.function.unwrap().get_first_basic_block().unwrap();
+        let ir = codegen.get_ir_string();
+        assert_eq!(ir.matches("alloca").count(), 3);
+        assert!(ir.contains("forcond:"));
+        assert!(ir.contains("!{!\"llvm.loop.vectorize.enable\", i1 true}"));
+        assert_eq!(
+            entry
+                .get_instructions()
+                .filter(|instruction| instruction.get_opcode() == InstructionOpcode::Alloca)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn unroll_attribute_sets_loop_metadata() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("let mut x = 0; #[unroll(4)] for i in 0..16 { x = x + i; }".to_string())
+                .unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+        assert!(codegen.module.verify().is_ok());
+
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("br label %forcond, !llvm.loop !0"));
+        assert!(ir.contains("!0 = distinct !{!0, !1, !2}"));
+        assert!(ir.contains("!1 = !{!\"llvm.loop.vectorize.enable\", i1 true}"));
+        assert!(ir.contains("!2 = !{!\"llvm.loop.unroll.count\", i32 4}"));
+    }
+
+    #[test]
+    fn user_functions() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser = Parser::new(
+            r#"
+            let mut total = 0;
+            for i in 0..5 { total = total + square(i); }
+            fn square(x: i64) -> i64 { x * x }
+            fn fact(n: i64) -> i64 { if n <= 1 { return 1; } n * fact(n - 1) }
+            pub fn greet() { print("hi"); }
+            greet();
+            return fact(5);
+            "#
+            .to_string(),
+        )
+        .unwrap();
+        let statements = parser.parse().unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("define internal i64 @square(i64 %x)"));
+        assert!(ir.contains("define internal i64 @fact(i64 %n)"));
+        assert!(ir.contains("define void @greet()"));
+        assert!(ir.contains("call void @greet()"));
+    }
+
+    #[test]
+    fn hooks_run_at_start_and_exit() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "print(1); #[on_exit] fn done() { print(3); } #[on_start] fn init() { print(0); }"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("declare i32 @atexit(ptr)"));
+        let register = ir.find("call i32 @atexit(ptr @done)").unwrap();
+        let start = ir.find("call void @init()").unwrap();
+        assert!(register < start);
+
+        let statements = Parser::new("#[on_start] fn f(x: i64) {}".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            CodeGen::new(&context, "test")
+                .compile_statements(&statements)
+                .unwrap_err(),
+            CodeGenError::InvalidHook("on_start".into(), "f".into())
+        );
+    }
+
+    #[test]
+    fn method_calls_pass_the_receiver_first() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let mut parser =
+            Parser::new("fn scale(x: i64, by: i64) -> i64 { x * by } fn zero() {}".to_string())
+                .unwrap();
+        let mut statements = parser.parse().unwrap();
+        let method_call = |method_name: &str, arguments: Vec<Expr>| Expr::MethodCall {
+            target: Box::new(Expr::Literal(Nodes::Integer(21), Span::default())),
+            method_name: method_name.to_string(),
+            arguments,
+            span: Span::default(),
+        };
+        statements.push(method_call(
+            "scale",
+            vec![Expr::Literal(Nodes::Integer(2), Span::default())],
+        ));
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        assert!(
+            codegen
+                .get_ir_string()
+                .contains("call i64 @scale(i64 21, i64 2)")
+        );
+
+        for method_name in ["missing", "zero"] {
+            assert_eq!(
+                codegen.compile_expression(&method_call(method_name, vec![])),
+                Err(CodeGenError::UndefinedMethod(method_name.to_string()))
+            );
+        }
+    }
 
-        // Use the specified type instead of inferring from value
-        let llvm_type = match var_type {
-            Some(Types::I32) => self.context.i32_type().into(),
-            Some(Types::I64) => self.context.i64_type().into(),
-            Some(Types::F32) => self.context.f32_type().into(),
-            Some(Types::F64) => self.context.f64_type().into(),
-            Some(Types::Bool) => self.context.bool_type().into(),
-            Some(Types::String) => self.context.ptr_type(AddressSpace::default()).into(),
-            None => self.context.i64_type().into(),
+    #[test]
+    fn link_modules_merges_definitions() {
+        let context = Context::create();
+        let compile = |name: &str, source: &str| {
+            let mut codegen = CodeGen::new(&context, name);
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            codegen.compile_statements(&statements).unwrap();
+            codegen.module
         };
 
-        let alloca = self.builder.build_alloca(llvm_type, identifier).unwrap();
-
-        let result = self.builder.build_store(alloca, val);
+        let library = context.create_module("library");
+        let i64_type = context.i64_type();
+        let one = library.add_function("one", i64_type.fn_type(&[], false), None);
+        let builder = context.create_builder();
+        builder.position_at_end(context.append_basic_block(one, "entry"));
+        builder
+            .build_return(Some(&i64_type.const_int(1, false)))
+            .unwrap();
 
-        if result.is_err() {
-            return Err(CodeGenError::StoreError(identifier.to_string()));
-        }
+        let linked = CodeGen::link_modules(vec![
+            compile("app", r#"fn two() -> i64 { 2 } print("app");"#),
+            library,
+        ])
+        .unwrap();
 
-        self.variables
-            .insert(identifier.to_string(), (alloca, llvm_type));
+        assert!(linked.verify().is_ok());
+        assert!(linked.get_function("one").is_some());
+        assert!(linked.get_function("two").is_some());
 
-        Ok(val)
+        assert_eq!(
+            CodeGen::link_modules(vec![
+                compile("a", r#"print("a");"#),
+                compile("b", r#"print("b");"#),
+            ])
+            .unwrap_err(),
+            CodeGenError::DuplicateSymbol("main".into(), "a".into(), "b".into())
+        );
     }
-}
 
-// If-Else
-impl<'ctx> CodeGen<'ctx> {
-    fn compile_if_else(
-        &mut self,
-        condition: &Expr,
-        then_branch: &Expr,
-        else_branch: &Option<Box<Expr>>,
-    ) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
-        let function = self.function.ok_or(CodeGenError::NoFunction).unwrap();
+    #[test]
+    fn only_pub_functions_clash_when_linking() {
+        let context = Context::create();
+        let library = || {
+            let library = context.create_module("library");
+            let i64_type = context.i64_type();
+            let one = library.add_function("one", i64_type.fn_type(&[], false), None);
+            let builder = context.create_builder();
+            builder.position_at_end(context.append_basic_block(one, "entry"));
+            builder
+                .build_return(Some(&i64_type.const_int(1, false)))
+                .unwrap();
+            library
+        };
+        let compile = |source: &str| {
+            let mut codegen = CodeGen::new(&context, "app");
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            codegen.compile_statements(&statements).unwrap();
+            codegen.module
+        };
 
-        let condition_val = self.compile_expression(condition)?;
+        let linked =
+            CodeGen::link_modules(vec![library(), compile("fn one() -> i64 { 11 } one();")])
+                .unwrap();
+        assert!(linked.verify().is_ok());
 
-        let condition_bool = match condition_val {
-            BasicValueEnum::IntValue(int_val) => {
-                if int_val.get_type().get_bit_width() == 1 {
-                    int_val
-                } else {
-                    let zero = int_val.get_type().const_zero();
-                    self.builder
-                        .build_int_compare(IntPredicate::NE, int_val, zero, "tobool")
-                        .unwrap()
-                }
-            }
-            _ => {
-                return Err(CodeGenError::TypeMismatchCustom(
-                    "Condition must be an integer".to_string(),
-                ));
-            }
+        assert_eq!(
+            CodeGen::link_modules(vec![library(), compile("pub fn one() -> i64 { 11 }")])
+                .unwrap_err(),
+            CodeGenError::DuplicateSymbol("one".into(), "library".into(), "app".into())
+        );
+    }
+
+    #[test]
+    fn function_errors() {
+        let compile = |source: &str| {
+            let context = Context::create();
+            let mut codegen = CodeGen::new(&context, "test");
+            let mut parser = Parser::new(source.to_string()).unwrap();
+            let statements = parser.parse().unwrap();
+            codegen.compile_statements(&statements).map(|_| ())
         };
 
-        let then_bb = self.context.append_basic_block(function, "then");
-        let else_bb = self.context.append_basic_block(function, "else");
-        let merge_bb = self.context.append_basic_block(function, "ifcont");
+        assert_eq!(
+            compile("fn f(a: i32) {} f()"),
+            Err(CodeGenError::WrongArgumentCount("f".into(), 1, 0))
+        );
+        assert_eq!(
+            compile("fn f(a: i32) {} f(true)"),
+            Err(CodeGenError::ArgumentTypeMismatch(
+                "f".into(),
+                "a".into(),
+                "i32".into(),
+                "bool".into()
+            ))
+        );
+        assert_eq!(
+            compile("fn f() -> i64 { return 1.5; }"),
+            Err(CodeGenError::ReturnTypeMismatch(
+                "f".into(),
+                "i64".into(),
+                "f64".into()
+            ))
+        );
+        assert_eq!(
+            compile("{ let x = 1; fn f() -> i64 { x } }"),
+            Err(CodeGenError::UndefinedVariable("x".into()))
+        );
+        assert_eq!(
+            compile("fn puts() {}"),
+            Err(CodeGenError::FunctionRedefinition("puts".into()))
+        );
+        assert_eq!(
+            compile("g()"),
+            Err(CodeGenError::UndefinedFunction("g".into()))
+        );
+        assert_eq!(
+            compile("len(1.5)"),
+            Err(CodeGenError::InvalidOperation("len(1.5)".into()))
+        );
+    }
 
-        let built_cond_branch =
-            self.builder
-                .build_conditional_branch(condition_bool, then_bb, else_bb);
+    #[test]
+    fn test_print_string() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test_print");
 
-        if built_cond_branch.is_err() {
-            return Err(CodeGenError::TypeMismatchCustom(
-                "Condition must be an integer".to_string(),
-            ));
-        }
+        let mut parser = Parser::new("print(\"Hello, World!\")".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
 
-        self.builder.position_at_end(then_bb);
-        let then_val = self.compile_expression(then_branch)?;
-        let built_unconditional_branch = self.builder.build_unconditional_branch(merge_bb);
+        codegen.compile_statements(&statements).unwrap();
 
-        if built_unconditional_branch.is_err() {
-            return Err(CodeGenError::TypeMismatchCustom(
-                "Branch must be an integer".to_string(),
-            ));
+        let result = codegen.module.verify();
+
+        dbg!(&result);
+        if let Err(error) = result {
+            dbg!(error);
+            panic!("Module verification failed");
         }
 
-        let then_bb_end = self.builder.get_insert_block().unwrap();
+        let ir_string = codegen.get_ir_string();
+        assert!(ir_string.contains("@puts"));
+        assert!(ir_string.contains("call i32 @puts"));
+    }
 
-        self.builder.position_at_end(else_bb);
-        let else_val = if let Some(else_expr) = else_branch {
-            self.compile_expression(else_expr)?
-        } else {
-            self.context.i64_type().const_int(0, false).into()
-        };
+    #[test]
+    fn strings_carry_their_length() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
 
-        let built_unconditional_branch = self.builder.build_unconditional_branch(merge_bb);
+        let statements = Parser::new(
+            r#"fn greet(name: string) { print(name); } let s = "héllo"; greet(s);"#.to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
 
-        if built_unconditional_branch.is_err() {
-            return Err(CodeGenError::TypeMismatchCustom(
-                "Branch must be an integer".to_string(),
-            ));
-        }
+        codegen.compile_statements(&statements).unwrap();
 
-        let else_bb_end = self.builder.get_insert_block().unwrap();
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("define internal void @greet({ ptr, i64 } %name)"));
+        // The length counts bytes, not characters
+        assert!(ir.contains("store { ptr, i64 } { ptr @str, i64 6 }, ptr %s"));
+        assert!(ir.contains("extractvalue { ptr, i64 }"));
+    }
 
-        // merge block with phi node
-        self.builder.position_at_end(merge_bb);
+    #[test]
+    fn negative_division_truncates_unless_euclidean() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
 
-        // Only create phi if both branches have the same type
-        if then_val.get_type() == else_val.get_type() {
-            let phi = self
-                .builder
-                .build_phi(then_val.get_type(), "iftmp")
-                .unwrap();
-            phi.add_incoming(&[(&then_val, then_bb_end), (&else_val, else_bb_end)]);
-            Ok(phi.as_basic_value())
-        } else {
-            Ok(then_val)
+        let statements = Parser::new(
+            "fn modulo(a: i64, b: i64) -> i64 { rem_euclid(a, b) }
+            print(-7 / 2); print(-7 % 2); print(div_euclid(-7, 2)); print(rem_euclid(-7, 2));"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        // Constant operands are folded, the rest is selected at runtime
+        for printed in ["i64 -3)", "i64 -1)", "i64 -4)", "i64 1)"] {
+            assert!(ir.contains(printed), "{} not printed", printed);
         }
+        assert!(ir.contains("%rem = srem i64 %"));
+        assert!(ir.contains("%rem_euclid = select i1 %rem_negative"));
     }
-}
 
-// Block
-impl<'ctx> CodeGen<'ctx> {
-    fn compile_block(&mut self, statements: &[Expr]) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
-        let mut last_val = self.context.i64_type().const_int(0, false).into();
+    #[test]
+    fn float_comparisons_follow_ieee() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
 
-        for statement in statements {
-            last_val = self.compile_expression(statement)?;
-        }
+        let statements = Parser::new(
+            "fn differ(a: f64, b: f64) -> bool { a != b }
+            fn same(a: f64, b: f64) -> bool { a == b }
+            fn classify(x: f64, y: f32) -> bool { is_nan(x) || is_inf(x) || is_inf(y) }"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
 
-        Ok(last_val)
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        // `!=` is true for NaN operands, `==` is false
+        assert!(ir.contains("%fne = fcmp une double %a"));
+        assert!(ir.contains("%feq = fcmp oeq double %a"));
+        assert!(ir.contains("%is_nan = fcmp uno double %x, %x"));
+        assert!(ir.contains("%fabs = call double @llvm.fabs.f64(double %x)"));
+        assert!(ir.contains("fcmp oeq double %fabs, 0x7FF0000000000000"));
+        assert!(ir.contains("call float @llvm.fabs.f32(float %y)"));
     }
-}
 
-// Display
-impl<'ctx> CodeGen<'ctx> {
-    pub fn print_ir(&self) {
-        self.module.print_to_stderr();
+    #[test]
+    fn input_calls_a_helper_defined_once() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements =
+            Parser::new("let name = input(); print(name); print(len(input()));".to_string())
+                .unwrap()
+                .parse()
+                .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("define internal { ptr, i64 } @rune_input()"));
+        assert!(ir.contains("declare i32 @getchar()"));
+        assert!(ir.contains("call ptr @realloc(ptr %buffer"));
+        assert_eq!(ir.matches("call { ptr, i64 } @rune_input()").count(), 2);
     }
 
-    pub fn get_ir_string(&self) -> String {
-        self.module.print_to_string().to_string()
+    #[test]
+    fn input_buffers_are_freed_after_their_statement() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "fn line() -> string { input() } let kept = input(); \
+             for i in 0..3 { print(input()); if len(input()) == 0 { break; } _ = line(); }"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("declare void @free(ptr)"));
+        // `kept` and the buffer `line` returns outlive their statements,
+        // the buffer read by `len` is freed on both sides of the `break`
+        assert_eq!(ir.matches("call void @free").count(), 3);
     }
-}
 
-// Print
-impl<'ctx> CodeGen<'ctx> {
-    fn compile_print(&mut self, value: &Expr) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
-        let printed_val = self.compile_expression(value)?;
+    #[test]
+    fn len_reads_the_length_without_scanning() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
 
-        let puts_fn = self.puts_fn.ok_or(CodeGenError::InternalError(
-            "puts function not declared".to_string(),
-        ))?;
+        let statements = Parser::new(
+            r#"fn size(name: string) -> i64 { len(name) } let xs = [1, 2, 3]; print(len(xs) + size("hi"));"#
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
 
-        let printed_val_i8_ptr: BasicValueEnum<'ctx> = match printed_val {
-            BasicValueEnum::PointerValue(ptr_val) => ptr_val.into(),
-            BasicValueEnum::IntValue(_int_val) => {
-                // If it's an integer, we need to convert it to a string.
-                // This is a simplified approach, for a robust solution you'd
-                // likely need a runtime function to convert integers to strings.
-                // For now, let's assume we are printing string literals directly.
-                // If you want to print integers, you'd need `sprintf` or similar.
-                return Err(CodeGenError::TypeMismatchCustom(
-                    "Printing integers directly not supported yet. Only strings.".to_string(),
-                ));
-            }
-            _ => {
-                return Err(CodeGenError::TypeMismatchCustom(
-                    "Only strings can be printed directly for now.".to_string(),
-                ));
-            }
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("%len = extractvalue { ptr, i64 } %"));
+        // The array's length is a constant
+        assert!(ir.contains("add i64 3, %call"));
+    }
+
+    #[test]
+    fn chars_are_code_points() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "fn next(c: char) -> char { return c; } let c: char = '\\u{1F600}'; print(next(c));"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("define internal i32 @next(i32 %c)"));
+        assert!(ir.contains("store i32 128512, ptr %c"));
+        // Printed as UTF-8 text, not as a number
+        assert!(ir.contains("%char_buf = alloca [5 x i8]"));
+        assert!(!ir.contains("@fmt"));
+    }
+
+    #[test]
+    fn arrays_are_indexed_with_bounds_checks() {
+        let source = "let xs: [i32; 3] = [1, 2, 3]; let i = 2; print(xs[i]); print([4, 5][0]);";
+        let compile = |options: &CompileOptions| {
+            let context = Context::create();
+            let mut codegen = CodeGen::with_options(&context, "test", options);
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+
+            codegen.compile_statements(&statements).unwrap();
+            assert!(codegen.module.verify().is_ok());
+            codegen.get_ir_string()
         };
 
-        let call_result = self
-            .builder
-            .build_call(puts_fn, &[printed_val_i8_ptr.into()], "puts_call")
-            .unwrap();
+        let ir = compile(&CompileOptions::default());
+        // The literal's elements are narrowed to the declared `i32`
+        assert!(ir.contains("store [3 x i32] [i32 1, i32 2, i32 3], ptr %xs"));
+        assert!(ir.contains("getelementptr inbounds [3 x i32], ptr %xs, i64 0, i64 %"));
+        assert!(ir.contains("store [2 x i64] [i64 4, i64 5], ptr %temp"));
+        assert!(ir.contains("icmp ult i64 %"));
+        assert!(ir.contains("call void @exit(i32 101)"));
 
-        Ok(call_result.try_as_basic_value().left().unwrap())
+        let ir = compile(&CompileOptions {
+            unchecked_indexing: true,
+            ..CompileOptions::default()
+        });
+        assert!(ir.contains("getelementptr inbounds [3 x i32]"));
+        assert!(!ir.contains("@exit"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rune_parser::parser::Parser;
+    #[test]
+    fn matches_switch_on_variant_tags() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "fn code(c: Color) -> i64 { match c { Color::Red => 1, Color::Blue => return 2, _ => 3 } } \
+             enum Color { Red, Green, Blue } \
+             let n = match Color::Green { Color::Red => 1, Color::Green => 2, Color::Blue => 3 };"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("define internal i64 @code(i32 %c)"));
+        assert!(ir.contains("switch i32 %c, label %arm"));
+        assert!(ir.contains("i32 2, label %arm"));
+        assert!(ir.contains("phi i64 [ 1, %arm"));
+        // Without a `_` arm the default case is unreachable
+        assert!(ir.contains("switch i32 1, label %nomatch"));
+    }
 
     #[test]
-    fn test_simple_arithmetic() {
+    fn match_arms_of_different_types_are_rejected() {
         let context = Context::create();
         let mut codegen = CodeGen::new(&context, "test");
 
-        let mut parser = Parser::new("let x = 5 + 3".to_string()).unwrap();
-        let statements = parser.parse().unwrap();
+        let statements = Parser::new(
+            r#"enum Color { Red, Blue } let n = match Color::Red { Color::Red => 1, _ => "one" };"#
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            codegen.compile_statements(&statements),
+            Err(CodeGenError::TypeMismatch("i64".into(), "string".into()))
+        );
+    }
+
+    #[test]
+    fn constant_strings_are_joined_at_compile_time() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(r#"print("Hello, " + "world" + "!");"#.to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
 
         codegen.compile_statements(&statements).unwrap();
 
-        // Verify module is valid
-        assert_ne!(codegen.module.to_string(), "");
         assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("c\"Hello, world!\\00\""));
+        assert_eq!(ir.matches("private unnamed_addr constant").count(), 1);
     }
 
     #[test]
-    fn test_variables() {
+    fn increments_add_at_the_variable_width() {
         let context = Context::create();
         let mut codegen = CodeGen::new(&context, "test");
 
-        let mut parser = Parser::new("let x = 10; let y = x + 5".to_string()).unwrap();
-        let statements = parser.parse().unwrap();
+        let statements = Parser::new("let mut x: i32 = 0; x += 1; x = 2 + x; x -= 3;".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
 
         codegen.compile_statements(&statements).unwrap();
 
-        let result = codegen.module.verify();
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert_eq!(ir.matches("add i32 %x").count(), 3);
+        assert!(ir.contains(", -3"));
+        assert!(!ir.contains("intcast"));
+    }
 
-        if !result.is_ok() {
-            panic!("Module verification failed");
-        }
+    #[test]
+    fn top_level_lets_used_by_functions_are_globals() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new(
+            "let mut count = 0; let step = 2;
+            fn bump() { count += 1; }
+            bump(); count = count + step; print(count);"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        codegen.compile_statements(&statements).unwrap();
+
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("@count = internal global i64 0"));
+        assert!(ir.contains("store i64 0, ptr @count"));
+        assert!(ir.contains("%step = alloca i64"));
+        assert!(!ir.contains("%count = alloca"));
+
+        // Functions see the globals declared before them
+        let statements = Parser::new("fn f() -> i64 { late } let late = 1;".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            CodeGen::new(&context, "test")
+                .compile_statements(&statements)
+                .unwrap_err(),
+            CodeGenError::UndefinedVariable("late".into())
+        );
     }
 
     #[test]
-    fn test_if_else() {
+    fn constants_are_folded_into_globals() {
         let context = Context::create();
         let mut codegen = CodeGen::new(&context, "test");
 
-        let mut parser =
-            Parser::new("let x = 5; if x > 3 { let y = 10 } else { let y = 20 }".to_string())
-                .unwrap();
-        let statements = parser.parse().unwrap();
+        let statements = Parser::new(
+            "fn limit() -> i64 { MAX }
+            const MAX: i64 = 100;
+            const HALF: i32 = MAX as i32 / 2 + size_of(i32) as i32;
+            let x = HALF;"
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
 
         codegen.compile_statements(&statements).unwrap();
 
-        let result = codegen.module.verify();
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("@MAX = internal unnamed_addr constant i64 100"));
+        assert!(ir.contains("@HALF = internal unnamed_addr constant i32 54"));
+        assert!(ir.contains("ret i64 100"));
+        assert!(!ir.contains("sdiv"));
 
-        dbg!(&result);
-        if !result.is_ok() {
-            dbg!(result.unwrap_err());
-            panic!("Module verification failed");
+        for source in [
+            "let x = 1; const N: i64 = x;",
+            "const N: i64 = 1; N = 2;",
+            "const N: string = \"s\";",
+        ] {
+            let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+            assert!(
+                matches!(
+                    CodeGen::new(&context, "test").compile_statements(&statements),
+                    Err(CodeGenError::InvalidConst(..))
+                ),
+                "{}",
+                source
+            );
         }
     }
 
     #[test]
-    fn explicit_type_annotation() {
+    fn casts_convert_between_numeric_types() {
         let context = Context::create();
         let mut codegen = CodeGen::new(&context, "test");
 
-        let mut parser = Parser::new("let x: i64 = 5;".to_string()).unwrap();
-        let statements = parser.parse().unwrap();
+        let statements = Parser::new(
+            "fn f(x: i64, y: f64, c: char, b: bool) -> f32 {
+                let a = x as i32; let d = a as i64; let e = y as i32;
+                let g = c as i64 + b as i64 + d + e as i64; g as f64 as f32
+            }"
+            .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
 
         codegen.compile_statements(&statements).unwrap();
 
-        let result = codegen.module.verify();
-
-        dbg!(&result);
-        if !result.is_ok() {
-            dbg!(result.unwrap_err());
-            panic!("Module verification failed");
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        for instruction in [
+            "trunc i64",
+            "sext i32",
+            "call i32 @llvm.fptosi.sat.i32.f64(double %y)",
+            "zext i32",
+            "zext i1",
+            "sitofp i64",
+            "fptrunc double",
+        ] {
+            assert!(
+                ir.contains(instruction),
+                "missing `{}` in {}",
+                instruction,
+                ir
+            );
         }
+
+        let statements = Parser::new("let s = \"s\" as i64;".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            CodeGen::new(&context, "test").compile_statements(&statements),
+            Err(CodeGenError::InvalidCast(..))
+        ));
     }
 
     #[test]
-    fn test_print_string() {
+    fn strings_with_nul_are_rejected() {
+        let context = Context::create();
+        let mut codegen = CodeGen::new(&context, "test");
+
+        let statements = Parser::new("print(\"a\0b\");".to_string())
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            codegen.compile_statements(&statements),
+            Err(CodeGenError::StringError(
+                "NUL byte at offset 1 would end the string early".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn print_numbers_and_booleans() {
         let context = Context::create();
         let mut codegen = CodeGen::new(&context, "test_print");
 
-        let mut parser = Parser::new("print \"Hello, World!\"".to_string()).unwrap();
+        let mut parser = Parser::new(
+            "let x: i32 = 7; print(42); print(x); print(2.5); print(1 < 2); print(false);"
+                .to_string(),
+        )
+        .unwrap();
         let statements = parser.parse().unwrap();
 
         codegen.compile_statements(&statements).unwrap();
 
-        let result = codegen.module.verify();
+        assert!(codegen.module.verify().is_ok());
+        let ir = codegen.get_ir_string();
+        assert!(ir.contains("declare i32 @printf(ptr, ...)"));
+        assert!(ir.contains("c\"%lld\\0A\\00\""));
+        assert!(ir.contains("c\"%d\\0A\\00\""));
+        assert!(ir.contains("c\"%g\\0A\\00\""));
+        assert!(ir.contains("select i1"));
+        // Format strings are shared between calls
+        assert_eq!(ir.matches("c\"%s\\0A\\00\"").count(), 1);
+    }
 
-        dbg!(&result);
-        if !result.is_ok() {
-            dbg!(result.unwrap_err());
-            panic!("Module verification failed");
-        }
+    #[test]
+    fn floats_print_shortest_unless_given_a_precision() {
+        let compile = |options: &CompileOptions| {
+            let context = Context::create();
+            let mut codegen = CodeGen::with_options(&context, "test", options);
+            let statements = Parser::new("print(2.5); print(0.5f32);".to_string())
+                .unwrap()
+                .parse()
+                .unwrap();
 
-        let ir_string = codegen.get_ir_string();
-        assert!(ir_string.contains("@puts"));
-        assert!(ir_string.contains("call i32 @puts"));
+            codegen.compile_statements(&statements).unwrap();
+            assert!(codegen.module.verify().is_ok());
+            codegen.get_ir_string()
+        };
+
+        let ir = compile(&CompileOptions::default());
+        assert!(ir.contains("define internal i32 @rune_print_float(double"));
+        assert!(ir.contains("call double @strtod(ptr %buffer"));
+        assert_eq!(ir.matches("call i32 @rune_print_float(double").count(), 2);
+
+        let ir = compile(&CompileOptions {
+            float_precision: Some(3),
+            ..CompileOptions::default()
+        });
+        assert!(!ir.contains("@rune_print_float"));
+        assert!(ir.contains("c\"%.*f\\0A\\00\""));
+        assert!(ir.contains("(ptr @fmt, i32 3, double 2.500000e+00)"));
     }
 }
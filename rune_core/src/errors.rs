@@ -1,16 +1,60 @@
 use std::fmt::{self};
 
-#[derive(PartialEq)]
+use rune_parser::span::Span;
+
+#[derive(Clone, PartialEq)]
 pub enum CodeGenError {
     UndefinedVariable(String),
     TypeMismatch(String, String),
     TypeMismatchCustom(String),
+    LetTypeMismatch(String, String, String, String),
+    AssignTypeMismatch(String, String, String, String),
     InvalidOperation(String),
     NoFunction,
     StringError(String),
     OperatorNotSupported(String, String),
     InternalError(String),
     StoreError(String),
+    UndefinedFunction(String),
+    FunctionRedefinition(String),
+    WrongArgumentCount(String, usize, usize),
+    ArgumentTypeMismatch(String, String, String, String),
+    ReturnTypeMismatch(String, String, String),
+    UndefinedMethod(String),
+    DuplicateSymbol(String, String, String),
+    LinkError(String),
+    AssignToImmutable(String),
+    OutsideLoop(String),
+    InvalidCast(String, String, String),
+    InvalidConst(String, String),
+    InvalidHook(String, String),
+    LiteralOutOfRange(String, String),
+    /// Any of the errors above, at a 1-based line and column of the source.
+    Located {
+        line: usize,
+        column: usize,
+        error: Box<CodeGenError>,
+    },
+}
+
+impl CodeGenError {
+    /// Locates the error at the start of `span` in `source`.
+    pub fn at(self, source: &str, span: Span) -> Self {
+        let (line, column) = span.line_column(source);
+        CodeGenError::Located {
+            line,
+            column,
+            error: Box::new(self.without_location()),
+        }
+    }
+
+    /// The error itself, dropping its location if it has one.
+    pub fn without_location(self) -> Self {
+        match self {
+            CodeGenError::Located { error, .. } => *error,
+            error => error,
+        }
+    }
 }
 
 impl fmt::Display for CodeGenError {
@@ -34,6 +78,14 @@ pub fn get_print_error(error: &CodeGenError) -> String {
             expected, actual
         ),
         CodeGenError::TypeMismatchCustom(msg) => format!("(C002): Type mismatch: {}", msg),
+        CodeGenError::LetTypeMismatch(identifier, expected, actual, initializer) => format!(
+            "(C002): Type mismatch in declaration of `{}`, expected `{}` but initializer `{}` has type `{}`",
+            identifier, expected, initializer, actual
+        ),
+        CodeGenError::AssignTypeMismatch(identifier, expected, actual, value) => format!(
+            "(C002): Type mismatch in assignment to `{}`, expected `{}` but value `{}` has type `{}`",
+            identifier, expected, value, actual
+        ),
         CodeGenError::InvalidOperation(op) => format!("(C003): Invalid operation `{}`", op),
         CodeGenError::NoFunction => "(C004): No function found".into(),
         CodeGenError::StringError(msg) => format!("(C005): String error: {}", msg),
@@ -41,5 +93,173 @@ pub fn get_print_error(error: &CodeGenError) -> String {
             format!("(C006): Operator `{}` not supported for `{}`", op1, op2)
         }
         CodeGenError::StoreError(var) => format!("(C007): Store error for variable `{}`", var),
+        CodeGenError::UndefinedFunction(name) => format!("(C008): Undefined function `{}`", name),
+        CodeGenError::FunctionRedefinition(name) => {
+            format!("(C009): Function `{}` is already defined", name)
+        }
+        CodeGenError::WrongArgumentCount(callee, expected, found) => format!(
+            "(C010): `{}` takes {} argument(s) but {} were given",
+            callee, expected, found
+        ),
+        CodeGenError::ArgumentTypeMismatch(callee, parameter, expected, actual) => format!(
+            "(C002): Type mismatch in call to `{}`, parameter `{}` expects `{}` but got `{}`",
+            callee, parameter, expected, actual
+        ),
+        CodeGenError::ReturnTypeMismatch(function, expected, actual) => format!(
+            "(C002): Type mismatch in return from `{}`, expected `{}` but got `{}`",
+            function, expected, actual
+        ),
+        CodeGenError::UndefinedMethod(name) => format!(
+            "(C011): Undefined method `{}`, expected a function taking the receiver as its first parameter",
+            name
+        ),
+        CodeGenError::DuplicateSymbol(symbol, first, second) => format!(
+            "(C012): Symbol `{}` is defined in both `{}` and `{}`",
+            symbol, first, second
+        ),
+        CodeGenError::LinkError(msg) => format!("(C013): Failed to link modules: {}", msg),
+        CodeGenError::AssignToImmutable(v) => format!(
+            "(C014): Cannot assign twice to immutable variable `{}`, declare it with `let mut`",
+            v
+        ),
+        CodeGenError::OutsideLoop(keyword) => format!("(C015): `{}` outside of a loop", keyword),
+        CodeGenError::InvalidCast(value, from, to) => format!(
+            "(C016): Cannot cast `{}` of type `{}` to `{}`",
+            value, from, to
+        ),
+        CodeGenError::InvalidConst(name, reason) => {
+            format!("(C017): Invalid constant `{}`, {}", name, reason)
+        }
+        CodeGenError::InvalidHook(hook, name) => format!(
+            "(C018): `#[{}]` function `{}` must take no parameters and return nothing",
+            hook, name
+        ),
+        CodeGenError::LiteralOutOfRange(literal, literal_type) => {
+            format!(
+                "(C019): Literal `{}` does not fit in `{}`",
+                literal, literal_type
+            )
+        }
+        CodeGenError::Located {
+            line,
+            column,
+            error,
+        } => format!("{}:{}: {}", line, column, get_print_error(error)),
+    }
+}
+
+/// Errors from the type checker, see [`crate::checker`].
+#[derive(Clone, PartialEq)]
+pub enum TypeError {
+    ParseError(String),
+    UndefinedVariable(String),
+    TypeMismatch(String, String),
+    OperatorNotSupported(String, String),
+    InvalidOperation(String),
+    UndefinedFunction(String),
+    WrongArgumentCount(String, usize, usize),
+    InvalidString(String),
+    AssignToImmutable(String),
+    LetTypeMismatch(String, String, String, String),
+    AssignTypeMismatch(String, String, String, String),
+    ArgumentTypeMismatch(String, String, String, String),
+    ReturnTypeMismatch(String, String, String),
+    UndefinedMethod(String),
+    DuplicateDefinition(String, String),
+    UndefinedType(String),
+    UndefinedVariant(String, String),
+    NonExhaustiveMatch(String, Vec<String>),
+    UnreachableArm(String),
+    OutsideLoop(String),
+    InvalidCast(String, String, String),
+    InvalidConst(String, String),
+    InvalidHook(String, String),
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", get_print_type_error(self))
+    }
+}
+
+impl fmt::Debug for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", get_print_type_error(self))
+    }
+}
+
+pub fn get_print_type_error(error: &TypeError) -> String {
+    match error {
+        TypeError::ParseError(msg) => format!("(T000): Could not parse expression: {}", msg),
+        TypeError::UndefinedVariable(v) => format!("(T001): Undefined variable `{}`", v),
+        TypeError::TypeMismatch(expected, actual) => format!(
+            "(T002): Type mismatch, expected `{}` but got `{}`",
+            expected, actual
+        ),
+        TypeError::OperatorNotSupported(op, types) => {
+            format!("(T003): Operator `{}` not supported for `{}`", op, types)
+        }
+        TypeError::InvalidOperation(op) => format!("(T004): Invalid operation `{}`", op),
+        TypeError::UndefinedFunction(name) => format!("(T005): Undefined function `{}`", name),
+        TypeError::WrongArgumentCount(callee, expected, found) => format!(
+            "(T006): `{}` takes {} argument(s) but {} were given",
+            callee, expected, found
+        ),
+        TypeError::InvalidString(msg) => format!("(T007): Invalid string literal: {}", msg),
+        TypeError::AssignToImmutable(v) => format!(
+            "(T008): Cannot assign twice to immutable variable `{}`, declare it with `let mut`",
+            v
+        ),
+        TypeError::LetTypeMismatch(identifier, expected, actual, initializer) => format!(
+            "(T002): Type mismatch in declaration of `{}`, expected `{}` but initializer `{}` has type `{}`",
+            identifier, expected, initializer, actual
+        ),
+        TypeError::AssignTypeMismatch(identifier, expected, actual, value) => format!(
+            "(T002): Type mismatch in assignment to `{}`, expected `{}` but value `{}` has type `{}`",
+            identifier, expected, value, actual
+        ),
+        TypeError::ArgumentTypeMismatch(callee, parameter, expected, actual) => format!(
+            "(T002): Type mismatch in call to `{}`, parameter `{}` expects `{}` but got `{}`",
+            callee, parameter, expected, actual
+        ),
+        TypeError::ReturnTypeMismatch(function, expected, actual) => format!(
+            "(T002): Type mismatch in return from `{}`, expected `{}` but got `{}`",
+            function, expected, actual
+        ),
+        TypeError::UndefinedMethod(name) => format!(
+            "(T009): Undefined method `{}`, expected a function taking the receiver as its first parameter",
+            name
+        ),
+        TypeError::DuplicateDefinition(name, first) => {
+            format!(
+                "(T010): `{}` is defined more than once, first {}",
+                name, first
+            )
+        }
+        TypeError::UndefinedType(name) => format!("(T011): Undefined type `{}`", name),
+        TypeError::UndefinedVariant(enum_name, variant) => {
+            format!("(T012): Enum `{}` has no variant `{}`", enum_name, variant)
+        }
+        TypeError::NonExhaustiveMatch(scrutinee, missing) => format!(
+            "(T013): Match on `{}` does not cover `{}`, add an arm for each or a `_` arm",
+            scrutinee,
+            missing.join("`, `")
+        ),
+        TypeError::UnreachableArm(pattern) => format!(
+            "(T014): Match arm `{}` is unreachable, earlier arms already cover it",
+            pattern
+        ),
+        TypeError::OutsideLoop(keyword) => format!("(T015): `{}` outside of a loop", keyword),
+        TypeError::InvalidCast(value, from, to) => format!(
+            "(T016): Cannot cast `{}` of type `{}` to `{}`",
+            value, from, to
+        ),
+        TypeError::InvalidConst(name, reason) => {
+            format!("(T017): Invalid constant `{}`, {}", name, reason)
+        }
+        TypeError::InvalidHook(hook, name) => format!(
+            "(T018): `#[{}]` function `{}` must take no parameters and return nothing",
+            hook, name
+        ),
     }
 }
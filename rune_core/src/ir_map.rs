@@ -0,0 +1,137 @@
+use inkwell::module::Module;
+use inkwell::values::{AnyValue, AsValueRef, InstructionOpcode, InstructionValue};
+
+/// IR created by one top-level statement, see
+/// [`crate::codegen::CodeGen::compile_statements_mapped`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatementIr {
+    /// Names of the globals, blocks and values it created, e.g. `%x`, `@str`.
+    pub names: Vec<String>,
+    /// Textual IR of the globals, block labels and instructions it created.
+    pub lines: Vec<IrLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrLine {
+    pub text: String,
+    /// Short plain-language description of what the line does.
+    pub note: Option<String>,
+}
+
+/// Something in the module a statement can create. `key` identifies it
+/// across walks of the module, even when it has no name.
+pub(crate) struct IrEntity {
+    pub key: String,
+    pub name: Option<String>,
+    pub line: Option<IrLine>,
+}
+
+/// Every global, function, block and instruction in `module`, in order.
+pub(crate) fn module_entities(module: &Module) -> Vec<IrEntity> {
+    let mut entities = Vec::new();
+
+    for global in module.get_globals() {
+        let name = format!("@{}", global.get_name().to_string_lossy());
+        entities.push(IrEntity {
+            key: name.clone(),
+            name: Some(name),
+            line: Some(IrLine {
+                text: global.print_to_string().to_string(),
+                note: Some("constant data in the binary".to_string()),
+            }),
+        });
+    }
+
+    for function in module.get_functions() {
+        let function_name = function.get_name().to_string_lossy().into_owned();
+        entities.push(IrEntity {
+            key: format!("@{}", function_name),
+            name: Some(format!("@{}", function_name)),
+            line: None,
+        });
+
+        for block in function.get_basic_blocks() {
+            let block_name = block.get_name().to_string_lossy().into_owned();
+            entities.push(IrEntity {
+                key: format!("@{}:%{}", function_name, block_name),
+                name: Some(format!("%{}", block_name)),
+                line: Some(IrLine {
+                    text: format!("{}:", block_name),
+                    note: Some("start of a basic block".to_string()),
+                }),
+            });
+
+            let mut instruction = block.get_first_instruction();
+            while let Some(current) = instruction {
+                let name = current
+                    .get_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .filter(|name| !name.is_empty());
+
+                entities.push(IrEntity {
+                    key: format!("{:p}", current.as_value_ref()),
+                    name: name.as_ref().map(|name| format!("%{}", name)),
+                    line: Some(IrLine {
+                        text: current.print_to_string().to_string().trim().to_string(),
+                        note: explain(current, name.as_deref()),
+                    }),
+                });
+
+                instruction = current.get_next_instruction();
+            }
+        }
+    }
+
+    entities
+}
+
+fn explain(instruction: InstructionValue, name: Option<&str>) -> Option<String> {
+    let note = match instruction.get_opcode() {
+        InstructionOpcode::Alloca => match name {
+            Some(name) => format!("stack slot for `{}`", name),
+            None => "stack slot".to_string(),
+        },
+        InstructionOpcode::Store => "write a value to a stack slot".to_string(),
+        InstructionOpcode::Load => match name {
+            Some(name) => format!("read the current value of `{}`", name),
+            None => "read a value from memory".to_string(),
+        },
+        InstructionOpcode::Add
+        | InstructionOpcode::Sub
+        | InstructionOpcode::Mul
+        | InstructionOpcode::SDiv
+        | InstructionOpcode::UDiv
+        | InstructionOpcode::SRem
+        | InstructionOpcode::URem => "integer arithmetic".to_string(),
+        InstructionOpcode::FAdd
+        | InstructionOpcode::FSub
+        | InstructionOpcode::FMul
+        | InstructionOpcode::FDiv
+        | InstructionOpcode::FRem
+        | InstructionOpcode::FNeg => "floating point arithmetic".to_string(),
+        InstructionOpcode::ICmp | InstructionOpcode::FCmp => {
+            "comparison producing an `i1` (bool)".to_string()
+        }
+        InstructionOpcode::And | InstructionOpcode::Or | InstructionOpcode::Xor => {
+            "bitwise operation".to_string()
+        }
+        InstructionOpcode::SIToFP => "convert an integer to a float".to_string(),
+        InstructionOpcode::Trunc | InstructionOpcode::SExt | InstructionOpcode::ZExt => {
+            "change the width of an integer".to_string()
+        }
+        InstructionOpcode::FPTrunc | InstructionOpcode::FPExt => {
+            "change the precision of a float".to_string()
+        }
+        InstructionOpcode::IntToPtr => "reinterpret an integer as a pointer".to_string(),
+        InstructionOpcode::Br if instruction.get_num_operands() == 3 => {
+            "branch on a condition".to_string()
+        }
+        InstructionOpcode::Br => "jump to another block".to_string(),
+        InstructionOpcode::Phi => "phi merging if/else values".to_string(),
+        InstructionOpcode::Call => "function call".to_string(),
+        InstructionOpcode::Return => "return from the function".to_string(),
+        _ => return None,
+    };
+
+    Some(note)
+}
@@ -1,2 +1,13 @@
+pub mod checker;
 pub mod codegen;
 pub mod errors;
+pub mod ir_map;
+pub mod options;
+pub mod prelude;
+pub mod query;
+pub mod stats;
+pub mod warnings;
+
+pub use checker::{TypeContext, type_of};
+/// The parser this crate was built against, see [`prelude`].
+pub use rune_parser;
@@ -0,0 +1,49 @@
+use inkwell::targets::{TargetData, TargetMachine, TargetTriple};
+
+/// Settings applied to a module before anything is compiled into it.
+///
+/// Without a triple and data layout LLVM falls back to generic defaults,
+/// which disagree with the real target on type sizes and alignment.
+#[derive(Debug, Default, Clone)]
+pub struct CompileOptions {
+    pub triple: Option<String>,
+    pub data_layout: Option<String>,
+    /// Skips the runtime check that array indices are in bounds.
+    pub unchecked_indexing: bool,
+    /// Prints floats with this many decimals, like `%.*f`, instead of the
+    /// fewest digits that read back as the same value.
+    pub float_precision: Option<u32>,
+}
+
+impl CompileOptions {
+    /// Options matching the triple and data layout of `target_machine`.
+    pub fn for_target_machine(target_machine: &TargetMachine) -> Self {
+        Self {
+            triple: Some(
+                target_machine
+                    .get_triple()
+                    .as_str()
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            data_layout: Some(
+                target_machine
+                    .get_target_data()
+                    .get_data_layout()
+                    .as_str()
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            unchecked_indexing: false,
+            float_precision: None,
+        }
+    }
+
+    pub(crate) fn target_triple(&self) -> Option<TargetTriple> {
+        self.triple.as_deref().map(TargetTriple::create)
+    }
+
+    pub(crate) fn target_data(&self) -> Option<TargetData> {
+        self.data_layout.as_deref().map(TargetData::create)
+    }
+}
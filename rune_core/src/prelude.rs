@@ -0,0 +1,27 @@
+//! The types an embedder needs to parse, check and compile a program.
+//!
+//! The parser's types are re-exported from the `rune_parser` this crate was
+//! built against, so code that imports them from here can't end up handing
+//! [`CodeGen`] an AST from a different version of the parser.
+
+pub use rune_parser::{
+    errors::ParserError,
+    format::{FormatOptions, Indent, format_source},
+    intrinsics::{self, Intrinsic},
+    parser::{
+        Parser,
+        expr::{Expr, Hook, MatchArm, Parameter, Pattern},
+        node_id::NodeId,
+        nodes::Nodes,
+        ops::{BinaryOp, UnaryOp},
+        types::Types,
+    },
+    span::Span,
+};
+
+pub use crate::{
+    checker::{TypeContext, type_of},
+    codegen::CodeGen,
+    errors::{CodeGenError, TypeError},
+    warnings::CodeGenWarning,
+};
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use rune_parser::parser::expr::Expr;
+use rune_parser::parser::node_id::{FNV_OFFSET, NodeId, fnv1a};
+use rune_parser::parser::types::Types;
+
+use crate::TypeContext;
+use crate::errors::TypeError;
+
+/// Memoized type checking of top-level statements, keyed by [`NodeId`].
+///
+/// A function body only sees its parameters, the signatures of the other
+/// functions, the enums, the constants and the top-level `let`s, so its
+/// result is reused as long as the statement and all of those in the file
+/// are unchanged. Other
+/// statements depend on everything before them and are always checked
+/// again.
+#[derive(Default)]
+pub struct CheckQueries {
+    results: HashMap<(NodeId, u64), Result<Types, TypeError>>,
+    hits: usize,
+}
+
+impl CheckQueries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks `statements`, whose ids come from [`Parser::statement_ids`].
+    /// On failure, returns the index of the statement that failed.
+    ///
+    /// [`Parser::statement_ids`]: rune_parser::parser::Parser::statement_ids
+    pub fn check(&mut self, statements: &[Expr], ids: &[NodeId]) -> Result<(), (usize, TypeError)> {
+        let mut context = TypeContext::new();
+        context.declare_functions(statements);
+
+        let signatures = signatures_hash(statements);
+
+        for (index, (statement, id)) in statements.iter().zip(ids).enumerate() {
+            let result = if matches!(statement, Expr::FunctionDef { .. }) {
+                let key = (*id, signatures);
+                if let Some(result) = self.results.get(&key) {
+                    self.hits += 1;
+                    result.clone()
+                } else {
+                    let result = context.check(statement);
+                    self.results.insert(key, result.clone());
+                    result
+                }
+            } else {
+                context.check(statement)
+            };
+
+            result.map_err(|error| (index, error))?;
+        }
+
+        Ok(())
+    }
+
+    /// How many function bodies were answered from the cache so far.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+fn signatures_hash(statements: &[Expr]) -> u64 {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Expr::FunctionDef {
+                name,
+                parameters,
+                return_type,
+                ..
+            } => Some(format!("{}{:?}{:?}", name, parameters, return_type)),
+            // Function bodies may use any enum in the file
+            Expr::EnumDef { .. } => Some(statement.to_string()),
+            // and any constant, whose value doesn't change their types
+            Expr::ConstDef {
+                name, const_type, ..
+            } => Some(format!("const {}{}", name, const_type)),
+            // and the globals, typed by their initializers
+            Expr::LetDeclaration { .. } => Some(statement.to_string()),
+            _ => None,
+        })
+        .fold(FNV_OFFSET, |hash, signature| {
+            fnv1a(hash, signature.as_bytes())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_parser::parser::Parser;
+
+    fn check(queries: &mut CheckQueries, source: &str) -> Result<(), (usize, TypeError)> {
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+        queries.check(&statements, parser.statement_ids())
+    }
+
+    #[test]
+    fn function_bodies_are_reused() {
+        let mut queries = CheckQueries::new();
+        let source = "fn double(x: i64) -> i64 { x * 2 } fn one() -> i64 { 1 } double(one());";
+
+        assert!(check(&mut queries, source).is_ok());
+        assert_eq!(queries.hits(), 0);
+
+        assert!(check(&mut queries, source).is_ok());
+        assert_eq!(queries.hits(), 2);
+
+        // Editing a top-level statement keeps the function results
+        let edited = "fn double(x: i64) -> i64 { x * 2 } fn one() -> i64 { 1 } double(3);";
+        assert!(check(&mut queries, edited).is_ok());
+        assert_eq!(queries.hits(), 4);
+    }
+
+    #[test]
+    fn signature_changes_invalidate_results() {
+        let mut queries = CheckQueries::new();
+
+        assert!(
+            check(
+                &mut queries,
+                "fn one() -> i64 { 1 } fn two() -> i64 { one() + 1 }"
+            )
+            .is_ok()
+        );
+
+        let edited = "fn one() -> bool { true } fn two() -> i64 { one() + 1 }";
+        let (index, _) = check(&mut queries, edited).unwrap_err();
+        assert_eq!(index, 1);
+        assert_eq!(queries.hits(), 0);
+
+        // Function bodies also see the globals
+        assert!(check(&mut queries, "let limit = 1; fn f() -> i64 { limit }").is_ok());
+        let edited = "let limit = true; fn f() -> i64 { limit }";
+        let (index, _) = check(&mut queries, edited).unwrap_err();
+        assert_eq!(index, 1);
+        assert_eq!(queries.hits(), 0);
+    }
+}
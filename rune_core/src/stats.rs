@@ -0,0 +1,71 @@
+use inkwell::module::Module;
+use inkwell::targets::TargetData;
+use inkwell::values::{FunctionValue, InstructionOpcode};
+
+/// Size of the code generated for a single function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionStats {
+    pub name: String,
+    pub basic_blocks: usize,
+    pub instructions: usize,
+    /// Total size of the function's `alloca`s. Spills added by the backend
+    /// are not included, so this is a lower bound.
+    pub stack_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleStats {
+    pub functions: Vec<FunctionStats>,
+}
+
+impl ModuleStats {
+    /// Collects statistics for every function with a body in `module`.
+    pub fn collect(module: &Module, target_data: &TargetData) -> Self {
+        let functions = module
+            .get_functions()
+            .filter(|function| function.count_basic_blocks() > 0)
+            .map(|function| function_stats(function, target_data))
+            .collect();
+
+        Self { functions }
+    }
+
+    pub fn basic_blocks(&self) -> usize {
+        self.functions.iter().map(|f| f.basic_blocks).sum()
+    }
+
+    pub fn instructions(&self) -> usize {
+        self.functions.iter().map(|f| f.instructions).sum()
+    }
+
+    pub fn stack_bytes(&self) -> u64 {
+        self.functions.iter().map(|f| f.stack_bytes).sum()
+    }
+}
+
+fn function_stats(function: FunctionValue, target_data: &TargetData) -> FunctionStats {
+    let mut instructions = 0;
+    let mut stack_bytes = 0;
+
+    for block in function.get_basic_blocks() {
+        let mut instruction = block.get_first_instruction();
+        while let Some(current) = instruction {
+            instructions += 1;
+
+            if current.get_opcode() == InstructionOpcode::Alloca
+                && let Ok(allocated_type) = current.get_allocated_type()
+            {
+                stack_bytes += target_data.get_abi_size(&allocated_type);
+            }
+
+            instruction = current.get_next_instruction();
+        }
+    }
+
+    FunctionStats {
+        name: function.get_name().to_string_lossy().into_owned(),
+        basic_blocks: function.count_basic_blocks() as usize,
+        instructions,
+        stack_bytes,
+    }
+}
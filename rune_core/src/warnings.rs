@@ -0,0 +1,70 @@
+use std::fmt::{self};
+
+#[derive(PartialEq, Clone)]
+pub enum CodeGenWarning {
+    ShadowedVariable(String),
+    UnreachableCode(String),
+    LiteralOverflow(String, String, String),
+    UnusedValue(String),
+    /// An integer operand converted to a float to match the other operand.
+    ImplicitPromotion(String, String, String),
+}
+
+/// The names warnings are allowed or denied by, one per variant.
+pub const LINTS: [&str; 5] = [
+    "shadowed_variable",
+    "unreachable_code",
+    "literal_overflow",
+    "unused_value",
+    "implicit_promotion",
+];
+
+impl CodeGenWarning {
+    /// The lint this warning belongs to, see [`LINTS`].
+    pub fn lint(&self) -> &'static str {
+        match self {
+            CodeGenWarning::ShadowedVariable(_) => LINTS[0],
+            CodeGenWarning::UnreachableCode(_) => LINTS[1],
+            CodeGenWarning::LiteralOverflow(..) => LINTS[2],
+            CodeGenWarning::UnusedValue(_) => LINTS[3],
+            CodeGenWarning::ImplicitPromotion(..) => LINTS[4],
+        }
+    }
+}
+
+impl fmt::Display for CodeGenWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", get_print_warning(self))
+    }
+}
+
+impl fmt::Debug for CodeGenWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", get_print_warning(self))
+    }
+}
+
+pub fn get_print_warning(warning: &CodeGenWarning) -> String {
+    match warning {
+        CodeGenWarning::ShadowedVariable(v) => format!(
+            "(W000): Redeclaration of `{}` shadows a variable in the same scope",
+            v
+        ),
+        CodeGenWarning::UnreachableCode(statement) => format!(
+            "(W001): Unreachable statement `{}` after a diverging statement",
+            statement
+        ),
+        CodeGenWarning::LiteralOverflow(literal, literal_type, narrowed) => format!(
+            "(W002): Literal `{}` does not fit in `{}` and becomes `{}`",
+            literal, literal_type, narrowed
+        ),
+        CodeGenWarning::UnusedValue(statement) => format!(
+            "(W003): Value of `{}` is discarded, write `_ = {}` if that is intended",
+            statement, statement
+        ),
+        CodeGenWarning::ImplicitPromotion(operand, from, to) => format!(
+            "(W004): `{}` is implicitly converted from `{}` to `{}` and may lose precision, write `as {}` if that is intended",
+            operand, from, to, to
+        ),
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::{self};
+
+#[derive(PartialEq)]
+pub enum InterpError {
+    InternalError(String),
+    UndefinedVariable(String),
+    TypeMismatchCustom(String),
+    LetTypeMismatch(String, String, String, String),
+    AssignTypeMismatch(String, String, String, String),
+    InvalidOperation(String),
+    OperatorNotSupported(String, String),
+    DivisionByZero,
+    IOError(String),
+    UndefinedFunction(String),
+    FunctionRedefinition(String),
+    WrongArgumentCount(String, usize, usize),
+    ArgumentTypeMismatch(String, String, String, String),
+    ReturnTypeMismatch(String, String, String),
+    AssignToImmutable(String),
+    IndexOutOfBounds(i64, usize),
+    UndefinedVariant(String, String),
+    NoMatchingArm(String),
+    OutsideLoop(String),
+    InvalidCast(String, String, String),
+    InvalidConst(String, String),
+    InvalidHook(String, String),
+    LiteralOutOfRange(String, String),
+}
+
+impl fmt::Display for InterpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", get_print_error(self))
+    }
+}
+
+impl fmt::Debug for InterpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", get_print_error(self))
+    }
+}
+
+pub fn get_print_error(error: &InterpError) -> String {
+    match error {
+        InterpError::InternalError(msg) => format!("(I000): Internal error: {}", msg),
+        InterpError::UndefinedVariable(v) => format!("(I001): Undefined variable `{}`", v),
+        InterpError::TypeMismatchCustom(msg) => format!("(I002): Type mismatch: {}", msg),
+        InterpError::LetTypeMismatch(identifier, expected, actual, initializer) => format!(
+            "(I002): Type mismatch in declaration of `{}`, expected `{}` but initializer `{}` has type `{}`",
+            identifier, expected, initializer, actual
+        ),
+        InterpError::AssignTypeMismatch(identifier, expected, actual, value) => format!(
+            "(I002): Type mismatch in assignment to `{}`, expected `{}` but value `{}` has type `{}`",
+            identifier, expected, value, actual
+        ),
+        InterpError::InvalidOperation(op) => format!("(I003): Invalid operation `{}`", op),
+        InterpError::OperatorNotSupported(op1, op2) => {
+            format!("(I004): Operator `{}` not supported for `{}`", op1, op2)
+        }
+        InterpError::DivisionByZero => "(I005): Division by zero".into(),
+        InterpError::IOError(msg) => format!("(I006): IO error: {}", msg),
+        InterpError::UndefinedFunction(name) => format!("(I007): Undefined function `{}`", name),
+        InterpError::FunctionRedefinition(name) => {
+            format!("(I008): Function `{}` is already defined", name)
+        }
+        InterpError::WrongArgumentCount(callee, expected, found) => format!(
+            "(I009): `{}` takes {} argument(s) but {} were given",
+            callee, expected, found
+        ),
+        InterpError::ArgumentTypeMismatch(callee, parameter, expected, actual) => format!(
+            "(I002): Type mismatch in call to `{}`, parameter `{}` expects `{}` but got `{}`",
+            callee, parameter, expected, actual
+        ),
+        InterpError::ReturnTypeMismatch(function, expected, actual) => format!(
+            "(I002): Type mismatch in return from `{}`, expected `{}` but got `{}`",
+            function, expected, actual
+        ),
+        InterpError::AssignToImmutable(name) => format!(
+            "(I010): Cannot assign twice to immutable variable `{}`, declare it with `let mut`",
+            name
+        ),
+        InterpError::IndexOutOfBounds(index, length) => format!(
+            "(I011): Index {} out of bounds for array of length {}",
+            index, length
+        ),
+        InterpError::UndefinedVariant(enum_name, variant) => format!(
+            "(I012): `{}::{}` is not a variant of a defined enum",
+            enum_name, variant
+        ),
+        InterpError::NoMatchingArm(value) => {
+            format!("(I013): No match arm matches `{}`", value)
+        }
+        InterpError::OutsideLoop(keyword) => {
+            format!("(I014): `{}` outside of a loop", keyword)
+        }
+        InterpError::InvalidCast(value, from, to) => format!(
+            "(I015): Cannot cast `{}` of type `{}` to `{}`",
+            value, from, to
+        ),
+        InterpError::InvalidConst(name, reason) => {
+            format!("(I016): Invalid constant `{}`, {}", name, reason)
+        }
+        InterpError::InvalidHook(hook, name) => format!(
+            "(I017): `#[{}]` function `{}` must take no parameters and return nothing",
+            hook, name
+        ),
+        InterpError::LiteralOutOfRange(literal, literal_type) => {
+            format!(
+                "(I018): Literal `{}` does not fit in `{}`",
+                literal, literal_type
+            )
+        }
+    }
+}
@@ -0,0 +1,1756 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Stdout, Write};
+use std::rc::Rc;
+
+use rune_parser::intrinsics::{Intrinsic, ParamKind};
+use rune_parser::parser::expr::{Expr, Hook, MatchArm, Parameter, Pattern};
+use rune_parser::parser::nodes::Nodes;
+use rune_parser::parser::ops::{BinaryOp, UnaryOp};
+use rune_parser::parser::types::Types;
+use rune_parser::scope::SymbolTable;
+
+use crate::errors::InterpError;
+use crate::value::{Value, fixed_float};
+
+/// Tree-walking interpreter over the parsed AST.
+///
+/// Mirrors the semantics of the LLVM backend in `rune_core` so the two
+/// engines can be compared against each other.
+pub struct Interpreter<W: Write> {
+    /// Value, declared type and whether the binding is mutable.
+    variables: SymbolTable<(Value, Types, bool)>,
+    /// Top-level `let`s, which function bodies can use too.
+    globals: HashMap<String, (Value, Types, bool)>,
+    functions: HashMap<String, Rc<Function>>,
+    /// Variants of each enum, in declaration order.
+    enums: HashMap<String, Vec<String>>,
+    /// Top-level constants, visible everywhere unless shadowed.
+    constants: HashMap<String, Value>,
+    /// The functions currently being called, innermost last.
+    frames: Vec<Frame>,
+    output: W,
+    /// Where `input()` reads lines from, stdin unless set by
+    /// [`Interpreter::with_input`].
+    input: Box<dyn BufRead + Send>,
+    /// Decimals to print floats with, shortest round-trip if `None`. See
+    /// [`Interpreter::with_float_precision`].
+    float_precision: Option<usize>,
+    /// Set once a `return` has been evaluated, unwinding all blocks up to
+    /// the enclosing call or the program itself.
+    returned: Option<Value>,
+    /// Set by `break` or `continue`, unwinding all blocks up to the
+    /// innermost loop.
+    jump: Option<Jump>,
+    /// How many loops enclose the code being evaluated, within the current
+    /// function.
+    loops: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Jump {
+    Break,
+    Continue,
+}
+
+struct Function {
+    parameters: Vec<Parameter>,
+    return_type: Option<Types>,
+    body: Expr,
+}
+
+struct Frame {
+    function: String,
+    return_type: Option<Types>,
+}
+
+impl Interpreter<Stdout> {
+    pub fn new() -> Self {
+        Self::with_output(io::stdout())
+    }
+}
+
+impl Default for Interpreter<Stdout> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> Interpreter<W> {
+    pub fn with_output(output: W) -> Self {
+        Self {
+            variables: SymbolTable::new(),
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+            enums: HashMap::new(),
+            constants: HashMap::new(),
+            frames: Vec::new(),
+            output,
+            input: Box::new(BufReader::new(io::stdin())),
+            float_precision: None,
+            returned: None,
+            jump: None,
+            loops: 0,
+        }
+    }
+
+    /// Reads the lines returned by `input()` from `input` instead of stdin.
+    pub fn with_input(mut self, input: impl BufRead + Send + 'static) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+
+    /// Prints floats with a fixed number of decimals, like the compiled
+    /// program does with `CompileOptions::float_precision`.
+    pub fn with_float_precision(mut self, precision: usize) -> Self {
+        self.float_precision = Some(precision);
+        self
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// The value the program returned from `main`, like the exit code of the
+    /// compiled binary.
+    pub fn exit_code(&self) -> i32 {
+        match self.returned {
+            Some(Value::Integer(value)) => value as i32,
+            Some(Value::Boolean(value)) => value as i32,
+            _ => 0,
+        }
+    }
+}
+
+// Core
+impl<W: Write> Interpreter<W> {
+    pub fn run(&mut self, statements: &[Expr]) -> Result<(), InterpError> {
+        // Functions and enums may be used before their definition
+        let mut hoisted = HashSet::new();
+        for statement in statements {
+            match statement {
+                Expr::FunctionDef { name, .. } => {
+                    if !hoisted.insert(name) {
+                        return Err(InterpError::FunctionRedefinition(name.clone()));
+                    }
+                    self.eval(statement)?;
+                }
+                Expr::EnumDef { .. } => {
+                    self.eval(statement)?;
+                }
+                _ => {}
+            }
+        }
+        // Constants may use the enums and each other, in order
+        for statement in statements {
+            if let Expr::ConstDef { .. } = statement {
+                self.eval(statement)?;
+            }
+        }
+
+        let mut exit_hooks = Vec::new();
+        for statement in statements {
+            if let Expr::FunctionDef {
+                name,
+                parameters,
+                return_type,
+                hook: Some(hook),
+                ..
+            } = statement
+            {
+                if !parameters.is_empty() || return_type.is_some() {
+                    return Err(InterpError::InvalidHook(hook.to_string(), name.clone()));
+                }
+                match hook {
+                    Hook::OnStart => {
+                        self.eval_call(name, &[])?;
+                    }
+                    Hook::OnExit => exit_hooks.push(name),
+                }
+            }
+        }
+
+        for statement in statements {
+            if let Expr::ConstDef { .. } = statement {
+                continue;
+            }
+            self.eval(statement)?;
+
+            if self.returned.is_some() {
+                break;
+            }
+        }
+
+        // Like `atexit` handlers, in reverse order and after a `return`
+        let returned = self.returned.take();
+        for name in exit_hooks.into_iter().rev() {
+            self.eval_call(name, &[])?;
+        }
+        self.returned = returned;
+
+        self.output
+            .flush()
+            .map_err(|err| InterpError::IOError(err.to_string()))
+    }
+
+    pub fn eval(&mut self, expr: &Expr) -> Result<Value, InterpError> {
+        match expr {
+            Expr::Variable { name, .. } => self
+                .variable(name)
+                .map(|(value, _, _)| value.clone())
+                .or_else(|| self.constants.get(name).cloned())
+                .ok_or_else(|| InterpError::UndefinedVariable(name.clone())),
+            Expr::Literal(node, _) => self.eval_literal(node),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => self.eval_binary_op(left, operator, right),
+            Expr::Unary {
+                operator, operand, ..
+            } => self.eval_unary_op(operator, operand),
+            Expr::Cast { value, target, .. } => self.eval_cast(value, target),
+            Expr::Assignment {
+                identifier, value, ..
+            } => self.eval_assignment(identifier, value),
+            Expr::Discard(value, _) => self.eval(value).map(|_| Value::Unit),
+            Expr::LetDeclaration {
+                identifier,
+                mutable,
+                value,
+                var_type,
+                ..
+            } => self.eval_let_declaration(identifier, *mutable, value, var_type),
+            Expr::IfElse {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => self.eval_if_else(condition, then_branch, else_branch),
+            Expr::For {
+                variable,
+                start,
+                end,
+                body,
+                ..
+            } => self.eval_for(variable, start, end, body),
+            Expr::Block(statements, _) => self.eval_block(statements),
+            Expr::Statement(value, _) => self.eval(value).map(|_| Value::Unit),
+            Expr::Print(expr, _) => self.eval_print(expr),
+            Expr::MethodCall { method_name, .. } => Err(InterpError::InvalidOperation(format!(
+                "method call `{}`",
+                method_name
+            ))),
+            Expr::SizeOf(query_type, _) => Ok(Value::Integer(layout_of(query_type).0)),
+            Expr::AlignOf(query_type, _) => Ok(Value::Integer(layout_of(query_type).1)),
+            Expr::Return(value, _) => self.eval_return(value),
+            Expr::Break(_) => self.eval_jump(Jump::Break),
+            Expr::Continue(_) => self.eval_jump(Jump::Continue),
+            Expr::FunctionDef {
+                name,
+                parameters,
+                return_type,
+                body,
+                ..
+            } => {
+                let function = Function {
+                    parameters: parameters.clone(),
+                    return_type: return_type.clone(),
+                    body: body.as_ref().clone(),
+                };
+                self.functions.insert(name.clone(), Rc::new(function));
+                Ok(Value::Unit)
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => self.eval_call(callee, arguments),
+            Expr::ArrayLiteral(elements, _) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| self.eval(element))
+                    .collect::<Result<Vec<Value>, InterpError>>()?;
+                Ok(Value::Array(elements))
+            }
+            Expr::Index { target, index, .. } => self.eval_index(target, index),
+            Expr::EnumDef { name, variants, .. } => {
+                self.enums.insert(name.clone(), variants.clone());
+                Ok(Value::Unit)
+            }
+            Expr::EnumVariant {
+                enum_name, variant, ..
+            } => self.eval_variant(enum_name, variant),
+            Expr::Match {
+                scrutinee, arms, ..
+            } => self.eval_match(scrutinee, arms),
+            Expr::ConstDef {
+                name,
+                const_type,
+                value,
+                ..
+            } => self.eval_const_def(name, const_type, value),
+        }
+    }
+
+    /// Value, type and mutability of the variable `name`, where locals and
+    /// parameters shadow globals.
+    fn variable(&self, name: &str) -> Option<&(Value, Types, bool)> {
+        self.variables.get(name).or_else(|| self.globals.get(name))
+    }
+
+    fn variable_mut(&mut self, name: &str) -> Option<&mut (Value, Types, bool)> {
+        self.variables
+            .get_mut(name)
+            .or_else(|| self.globals.get_mut(name))
+    }
+
+    fn eval_literal(&self, node: &Nodes) -> Result<Value, InterpError> {
+        match node {
+            Nodes::Integer(value) => Ok(Value::Integer(*value)),
+            Nodes::Float(value) => Ok(Value::Float(*value)),
+            // The lexer already rejects suffixed literals out of range
+            Nodes::TypedInteger(value, _) => Ok(Value::Integer(*value)),
+            Nodes::TypedFloat(value, literal_type) => {
+                coerce_value(&Value::Float(*value), literal_type).ok_or_else(|| {
+                    InterpError::InternalError(format!("Invalid float suffix `{}`", literal_type))
+                })
+            }
+            Nodes::Boolean(value) => Ok(Value::Boolean(*value)),
+            Nodes::String(value) => Ok(Value::String(value.clone())),
+            Nodes::Char(value) => Ok(Value::Char(*value)),
+        }
+    }
+}
+
+// Operations
+impl<W: Write> Interpreter<W> {
+    fn eval_binary_op(
+        &mut self,
+        left: &Expr,
+        operator: &BinaryOp,
+        right: &Expr,
+    ) -> Result<Value, InterpError> {
+        if let BinaryOp::And | BinaryOp::Or = operator {
+            return self.eval_logical_op(left, operator, right);
+        }
+
+        let left_val = self.eval(left)?;
+        let right_val = self.eval(right)?;
+
+        match (left_val, right_val) {
+            (Value::Integer(l), Value::Integer(r)) => eval_int_binary_op(l, operator, r),
+            (Value::Float(l), Value::Float(r)) => eval_float_binary_op(l, operator, r),
+            (Value::Integer(l), Value::Float(r)) => eval_float_binary_op(l as f64, operator, r),
+            (Value::Float(l), Value::Integer(r)) => eval_float_binary_op(l, operator, r as f64),
+            (Value::Boolean(l), Value::Boolean(r)) => eval_bool_binary_op(l, operator, r),
+            (Value::String(l), Value::String(r)) if *operator == BinaryOp::Add => {
+                Ok(Value::String(l + &r))
+            }
+            (Value::Char(l), Value::Char(r)) => eval_char_binary_op(l, operator, r),
+            (l @ Value::Enum(..), r @ Value::Enum(..)) if *operator == BinaryOp::Equal => {
+                Ok(Value::Boolean(l == r))
+            }
+            (l @ Value::Enum(..), r @ Value::Enum(..)) if *operator == BinaryOp::NotEqual => {
+                Ok(Value::Boolean(l != r))
+            }
+            (l, r) => Err(InterpError::OperatorNotSupported(
+                format!("{:?}", operator),
+                format!("{} | {}", l.type_name(), r.type_name()),
+            )),
+        }
+    }
+
+    /// Evaluates `right` only if `left` doesn't decide the result, like the
+    /// branches the LLVM backend emits.
+    fn eval_logical_op(
+        &mut self,
+        left: &Expr,
+        operator: &BinaryOp,
+        right: &Expr,
+    ) -> Result<Value, InterpError> {
+        let mut condition = |expr: &Expr| match self.eval(expr)? {
+            Value::Boolean(value) => Ok(value),
+            value => Err(InterpError::OperatorNotSupported(
+                format!("{:?}", operator),
+                value.type_name().to_string(),
+            )),
+        };
+
+        let result = match operator {
+            BinaryOp::And => condition(left)? && condition(right)?,
+            _ => condition(left)? || condition(right)?,
+        };
+
+        Ok(Value::Boolean(result))
+    }
+
+    fn eval_index(&mut self, target: &Expr, index: &Expr) -> Result<Value, InterpError> {
+        let elements = match self.eval(target)? {
+            Value::Array(elements) => elements,
+            _ => {
+                return Err(InterpError::InvalidOperation(format!(
+                    "indexing into `{}`",
+                    target
+                )));
+            }
+        };
+        let index = match self.eval(index)? {
+            Value::Integer(index) => index,
+            _ => {
+                return Err(InterpError::TypeMismatchCustom(
+                    "Array index must be an integer".to_string(),
+                ));
+            }
+        };
+
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| elements.get(i).cloned())
+            .ok_or(InterpError::IndexOutOfBounds(index, elements.len()))
+    }
+
+    fn eval_unary_op(&mut self, operator: &UnaryOp, operand: &Expr) -> Result<Value, InterpError> {
+        let operand_val = self.eval(operand)?;
+
+        match (operator, operand_val) {
+            (UnaryOp::Minus, Value::Integer(value)) => Ok(Value::Integer(value.wrapping_neg())),
+            (UnaryOp::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+            (UnaryOp::Not, Value::Boolean(value)) => Ok(Value::Boolean(!value)),
+            (UnaryOp::BitNot, Value::Integer(value)) => Ok(Value::Integer(!value)),
+            (UnaryOp::Minus, _) => Err(InterpError::OperatorNotSupported(
+                "-".into(),
+                operand.to_string(),
+            )),
+            (UnaryOp::Not, _) => Err(InterpError::OperatorNotSupported(
+                "!".into(),
+                operand.to_string(),
+            )),
+            (UnaryOp::BitNot, _) => Err(InterpError::OperatorNotSupported(
+                "~".into(),
+                operand.to_string(),
+            )),
+        }
+    }
+
+    /// `value as target`: integers wrap to the target width and floats
+    /// saturate when converted to an integer, as in Rust.
+    fn eval_cast(&mut self, value: &Expr, target: &Types) -> Result<Value, InterpError> {
+        let evaluated = self.eval(value)?;
+
+        let cast = match (&evaluated, target) {
+            (Value::Float(float), Types::I32) => Some(Value::Integer(*float as i32 as i64)),
+            (Value::Float(float), Types::I64) => Some(Value::Integer(*float as i64)),
+            (Value::Integer(int), Types::F32) => Some(Value::Float(*int as f32 as f64)),
+            (Value::Integer(int), Types::F64) => Some(Value::Float(*int as f64)),
+            (Value::Boolean(boolean), Types::I32 | Types::I64) => {
+                Some(Value::Integer(*boolean as i64))
+            }
+            (Value::Char(code_point), Types::I32 | Types::I64) => {
+                Some(Value::Integer(*code_point as i64))
+            }
+            // The tag, i.e. the variant's position in the definition
+            (Value::Enum(enum_name, variant), Types::I32 | Types::I64) => self
+                .enums
+                .get(enum_name)
+                .and_then(|variants| variants.iter().position(|name| name == variant))
+                .map(|tag| Value::Integer(tag as i64)),
+            (_, Types::Array(..)) => None,
+            // Between integer or float widths, or to the same type
+            (evaluated, target) => coerce_value(evaluated, target),
+        };
+
+        cast.ok_or_else(|| {
+            InterpError::InvalidCast(
+                value.to_string(),
+                evaluated.type_name().to_string(),
+                target.to_string(),
+            )
+        })
+    }
+}
+
+fn eval_int_binary_op(left: i64, operator: &BinaryOp, right: i64) -> Result<Value, InterpError> {
+    let result = match operator {
+        BinaryOp::Add => Value::Integer(left.wrapping_add(right)),
+        BinaryOp::Subtract => Value::Integer(left.wrapping_sub(right)),
+        BinaryOp::Multiply => Value::Integer(left.wrapping_mul(right)),
+        BinaryOp::Divide => {
+            if right == 0 {
+                return Err(InterpError::DivisionByZero);
+            }
+            Value::Integer(left.wrapping_div(right))
+        }
+        BinaryOp::Modulo => {
+            if right == 0 {
+                return Err(InterpError::DivisionByZero);
+            }
+            Value::Integer(left.wrapping_rem(right))
+        }
+        BinaryOp::Equal => Value::Boolean(left == right),
+        BinaryOp::NotEqual => Value::Boolean(left != right),
+        BinaryOp::Greater => Value::Boolean(left > right),
+        BinaryOp::Less => Value::Boolean(left < right),
+        BinaryOp::GreaterEqual => Value::Boolean(left >= right),
+        BinaryOp::LessEqual => Value::Boolean(left <= right),
+        BinaryOp::And | BinaryOp::Or => {
+            return Err(InterpError::InvalidOperation(
+                "Logical operations not supported on integers".to_string(),
+            ));
+        }
+    };
+    Ok(result)
+}
+
+/// [`ParamKind::accepts`] for values, which only know their kind and not
+/// their width.
+fn accepts(parameter: &ParamKind, value: &Value, first: &Value) -> bool {
+    match parameter {
+        ParamKind::Int => matches!(value, Value::Integer(_)),
+        ParamKind::Float => matches!(value, Value::Float(_)),
+        ParamKind::Sized => matches!(value, Value::String(_) | Value::Array(_)),
+        ParamKind::SameAsFirst => value.type_name() == first.type_name(),
+    }
+}
+
+fn eval_float_binary_op(left: f64, operator: &BinaryOp, right: f64) -> Result<Value, InterpError> {
+    let result = match operator {
+        BinaryOp::Add => Value::Float(left + right),
+        BinaryOp::Subtract => Value::Float(left - right),
+        BinaryOp::Multiply => Value::Float(left * right),
+        BinaryOp::Divide => Value::Float(left / right),
+        BinaryOp::Modulo => Value::Float(left % right),
+        BinaryOp::Equal => Value::Boolean(left == right),
+        BinaryOp::NotEqual => Value::Boolean(left != right),
+        BinaryOp::Greater => Value::Boolean(left > right),
+        BinaryOp::Less => Value::Boolean(left < right),
+        BinaryOp::GreaterEqual => Value::Boolean(left >= right),
+        BinaryOp::LessEqual => Value::Boolean(left <= right),
+        BinaryOp::And | BinaryOp::Or => {
+            return Err(InterpError::InvalidOperation(
+                "Logical operations not supported on floats".to_string(),
+            ));
+        }
+    };
+    Ok(result)
+}
+
+/// Characters only compare, by code point.
+fn eval_char_binary_op(left: char, operator: &BinaryOp, right: char) -> Result<Value, InterpError> {
+    match operator {
+        BinaryOp::Equal => Ok(Value::Boolean(left == right)),
+        BinaryOp::NotEqual => Ok(Value::Boolean(left != right)),
+        BinaryOp::Greater => Ok(Value::Boolean(left > right)),
+        BinaryOp::Less => Ok(Value::Boolean(left < right)),
+        BinaryOp::GreaterEqual => Ok(Value::Boolean(left >= right)),
+        BinaryOp::LessEqual => Ok(Value::Boolean(left <= right)),
+        _ => Err(InterpError::OperatorNotSupported(
+            format!("{:?}", operator),
+            "char | char".into(),
+        )),
+    }
+}
+
+fn eval_bool_binary_op(left: bool, operator: &BinaryOp, right: bool) -> Result<Value, InterpError> {
+    match operator {
+        BinaryOp::Equal => Ok(Value::Boolean(left == right)),
+        BinaryOp::NotEqual => Ok(Value::Boolean(left != right)),
+        BinaryOp::And => Ok(Value::Boolean(left && right)),
+        BinaryOp::Or => Ok(Value::Boolean(left || right)),
+        _ => Err(InterpError::OperatorNotSupported(
+            format!("{:?}", operator),
+            "bool | bool".into(),
+        )),
+    }
+}
+
+// Assignments
+impl<W: Write> Interpreter<W> {
+    fn eval_assignment(&mut self, identifier: &str, value: &Expr) -> Result<Value, InterpError> {
+        match self.variable(identifier) {
+            None if self.constants.contains_key(identifier) => {
+                return Err(InterpError::InvalidConst(
+                    identifier.to_string(),
+                    "cannot be assigned to".into(),
+                ));
+            }
+            None => return Err(InterpError::UndefinedVariable(identifier.to_string())),
+            Some((_, _, false)) => {
+                return Err(InterpError::AssignToImmutable(identifier.to_string()));
+            }
+            Some(_) => {}
+        }
+
+        let val = self.eval(value)?;
+
+        let Some((slot, var_type, _)) = self.variable_mut(identifier) else {
+            return Err(InterpError::UndefinedVariable(identifier.to_string()));
+        };
+
+        check_literal_width(value, var_type)?;
+        let val = coerce_value(&val, var_type).ok_or_else(|| {
+            InterpError::AssignTypeMismatch(
+                identifier.to_string(),
+                var_type.to_string(),
+                val.type_name().to_string(),
+                value.to_string(),
+            )
+        })?;
+
+        *slot = val;
+        Ok(Value::Unit)
+    }
+
+    fn eval_let_declaration(
+        &mut self,
+        identifier: &str,
+        mutable: bool,
+        value: &Expr,
+        var_type: &Option<Types>,
+    ) -> Result<Value, InterpError> {
+        let val = self.eval(value)?;
+
+        // Use the specified type, or infer it from the value. Values don't
+        // record their width, so a suffixed literal provides it
+        let var_type = match (var_type, value) {
+            (Some(var_type), _) => var_type.clone(),
+            (
+                None,
+                Expr::Literal(
+                    Nodes::TypedInteger(_, literal_type) | Nodes::TypedFloat(_, literal_type),
+                    _,
+                ),
+            ) => literal_type.clone(),
+            (None, _) => val.default_type(),
+        };
+
+        check_literal_width(value, &var_type)?;
+        let val = coerce_value(&val, &var_type).ok_or_else(|| {
+            InterpError::LetTypeMismatch(
+                identifier.to_string(),
+                var_type.to_string(),
+                val.type_name().to_string(),
+                value.to_string(),
+            )
+        })?;
+
+        // Outside any function or block, the binding is a global
+        if self.frames.is_empty() && self.variables.depth() == 1 {
+            self.globals
+                .insert(identifier.to_string(), (val, var_type, mutable));
+        } else {
+            self.variables.declare(identifier, (val, var_type, mutable));
+        }
+
+        Ok(Value::Unit)
+    }
+
+    fn eval_const_def(
+        &mut self,
+        name: &str,
+        const_type: &Types,
+        value: &Expr,
+    ) -> Result<Value, InterpError> {
+        if self.constants.contains_key(name) {
+            return Err(InterpError::InvalidConst(
+                name.to_string(),
+                "is defined more than once".into(),
+            ));
+        }
+        let is_const =
+            |name: &str| self.variable(name).is_none() && self.constants.contains_key(name);
+        if !value.is_constant(&is_const) {
+            return Err(InterpError::InvalidConst(
+                name.to_string(),
+                format!("initializer `{}` is not a constant expression", value),
+            ));
+        }
+
+        let val = self.eval(value)?;
+        let val = coerce_value(&val, const_type).ok_or_else(|| {
+            InterpError::InvalidConst(
+                name.to_string(),
+                format!(
+                    "expected `{}` but initializer `{}` has type `{}`",
+                    const_type,
+                    value,
+                    val.type_name()
+                ),
+            )
+        })?;
+
+        self.constants.insert(name.to_string(), val.clone());
+        Ok(val)
+    }
+}
+
+/// Size and ABI alignment in bytes, matching the x86-64 data layout the LLVM
+/// backend compiles for.
+fn layout_of(query_type: &Types) -> (i64, i64) {
+    match query_type {
+        Types::Bool => (1, 1),
+        // Enums are an `i32` tag, as in the LLVM backend
+        Types::I32 | Types::F32 | Types::Char | Types::Enum(_) => (4, 4),
+        Types::I64 | Types::F64 => (8, 8),
+        // A pointer and a length, as in the LLVM backend
+        Types::String => (16, 8),
+        Types::Array(element, length) => {
+            let (size, align) = layout_of(element);
+            (size * *length as i64, align)
+        }
+        // An empty struct in the LLVM backend
+        Types::Unit => (0, 1),
+    }
+}
+
+/// Applies the same implicit conversions as the LLVM backend: integers and
+/// floats are narrowed to the declared width.
+fn coerce_value(value: &Value, var_type: &Types) -> Option<Value> {
+    match (value, var_type) {
+        (Value::Integer(value), Types::I64) => Some(Value::Integer(*value)),
+        (Value::Integer(value), Types::I32) => Some(Value::Integer(*value as i32 as i64)),
+        (Value::Float(value), Types::F64) => Some(Value::Float(*value)),
+        (Value::Float(value), Types::F32) => Some(Value::Float(*value as f32 as f64)),
+        (Value::Boolean(value), Types::Bool) => Some(Value::Boolean(*value)),
+        (Value::String(value), Types::String) => Some(Value::String(value.clone())),
+        (Value::Char(value), Types::Char) => Some(Value::Char(*value)),
+        (Value::Unit, Types::Unit) => Some(Value::Unit),
+        (Value::Enum(enum_name, _), Types::Enum(name)) if enum_name == name => Some(value.clone()),
+        (Value::Array(elements), Types::Array(element, length)) if elements.len() == *length => {
+            elements
+                .iter()
+                .map(|value| coerce_value(value, element))
+                .collect::<Option<Vec<Value>>>()
+                .map(Value::Array)
+        }
+        _ => None,
+    }
+}
+
+/// Rejects an integer literal stored as a type too narrow for it, rather
+/// than keeping its low bits, as in the LLVM backend.
+fn check_literal_width(value: &Expr, var_type: &Types) -> Result<(), InterpError> {
+    match (value.integer_literal(), var_type) {
+        (Some(literal), Types::I32) if i32::try_from(literal).is_err() => Err(
+            InterpError::LiteralOutOfRange(value.to_string(), var_type.to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Whether a condition holds, any non-zero integer is true.
+fn truth_value(value: &Value) -> Option<bool> {
+    match value {
+        Value::Boolean(value) => Some(*value),
+        Value::Integer(value) => Some(*value != 0),
+        _ => None,
+    }
+}
+
+// If-Else
+impl<W: Write> Interpreter<W> {
+    fn eval_if_else(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Option<Box<Expr>>,
+    ) -> Result<Value, InterpError> {
+        let Some(condition_bool) = truth_value(&self.eval(condition)?) else {
+            return Err(InterpError::TypeMismatchCustom(
+                "Condition must be an integer".to_string(),
+            ));
+        };
+
+        match (condition_bool, else_branch) {
+            (true, Some(_)) => self.eval(then_branch),
+            // Without an `else`, the `if` is a statement
+            (true, None) => self.eval(then_branch).map(|_| Value::Unit),
+            (false, Some(else_expr)) => self.eval(else_expr),
+            (false, None) => Ok(Value::Unit),
+        }
+    }
+}
+
+// Enums
+impl<W: Write> Interpreter<W> {
+    fn eval_variant(&self, enum_name: &str, variant: &str) -> Result<Value, InterpError> {
+        match self.enums.get(enum_name) {
+            Some(variants) if variants.iter().any(|name| name == variant) => {
+                Ok(Value::Enum(enum_name.to_string(), variant.to_string()))
+            }
+            _ => Err(InterpError::UndefinedVariant(
+                enum_name.to_string(),
+                variant.to_string(),
+            )),
+        }
+    }
+
+    /// Evaluates the body of the first arm whose pattern matches.
+    fn eval_match(&mut self, scrutinee: &Expr, arms: &[MatchArm]) -> Result<Value, InterpError> {
+        let value = self.eval(scrutinee)?;
+
+        for arm in arms {
+            let matches = match &arm.pattern {
+                Pattern::Wildcard => true,
+                Pattern::Variant { enum_name, variant } => {
+                    self.eval_variant(enum_name, variant)? == value
+                }
+            };
+
+            if matches {
+                return self.eval(&arm.body);
+            }
+        }
+
+        Err(InterpError::NoMatchingArm(value.to_string()))
+    }
+}
+
+// For
+impl<W: Write> Interpreter<W> {
+    fn eval_for(
+        &mut self,
+        variable: &str,
+        start: &Expr,
+        end: &Expr,
+        body: &Expr,
+    ) -> Result<Value, InterpError> {
+        let (Value::Integer(start), Value::Integer(end)) = (self.eval(start)?, self.eval(end)?)
+        else {
+            return Err(InterpError::TypeMismatchCustom(
+                "Range bounds must be integers".to_string(),
+            ));
+        };
+
+        // The loop variable lives in its own scope around the body
+        self.variables.push_scope();
+        self.loops += 1;
+
+        let mut result = Ok(Value::Unit);
+        for index in start..end {
+            self.variables
+                .declare(variable, (Value::Integer(index), Types::I64, false));
+
+            result = self.eval(body).map(|_| Value::Unit);
+
+            if result.is_err() || self.returned.is_some() {
+                break;
+            }
+            if self.jump.take() == Some(Jump::Break) {
+                break;
+            }
+        }
+
+        self.loops -= 1;
+        self.variables.pop_scope();
+
+        result
+    }
+
+    fn eval_jump(&mut self, jump: Jump) -> Result<Value, InterpError> {
+        if self.loops == 0 {
+            let keyword = match jump {
+                Jump::Break => "break",
+                Jump::Continue => "continue",
+            };
+            return Err(InterpError::OutsideLoop(keyword.to_string()));
+        }
+
+        self.jump = Some(jump);
+        Ok(Value::Unit)
+    }
+}
+
+// Block
+impl<W: Write> Interpreter<W> {
+    fn eval_block(&mut self, statements: &[Expr]) -> Result<Value, InterpError> {
+        self.variables.push_scope();
+
+        let mut result = Ok(Value::Unit);
+        for statement in statements {
+            result = self.eval(statement);
+
+            if result.is_err() || self.returned.is_some() || self.jump.is_some() {
+                break;
+            }
+        }
+
+        self.variables.pop_scope();
+
+        result
+    }
+}
+
+// Return
+impl<W: Write> Interpreter<W> {
+    fn eval_return(&mut self, value: &Option<Box<Expr>>) -> Result<Value, InterpError> {
+        let val = match value {
+            Some(value) => self.eval(value)?,
+            None => Value::Integer(0),
+        };
+
+        let val = match self.frames.last() {
+            Some(frame) => match (&frame.return_type, value) {
+                (Some(return_type), Some(_)) => {
+                    coerce_value(&val, return_type).ok_or_else(|| {
+                        InterpError::ReturnTypeMismatch(
+                            frame.function.clone(),
+                            return_type.to_string(),
+                            val.type_name().to_string(),
+                        )
+                    })?
+                }
+                (None, None) => val,
+                (return_type, _) => {
+                    return Err(InterpError::ReturnTypeMismatch(
+                        frame.function.clone(),
+                        return_type
+                            .as_ref()
+                            .map_or("nothing".to_string(), |t| t.to_string()),
+                        value
+                            .as_ref()
+                            .map_or("nothing", |_| val.type_name())
+                            .to_string(),
+                    ));
+                }
+            },
+            // `main` returns an `i32`, wider integers are truncated
+            None => match val {
+                Value::Integer(_) | Value::Boolean(_) => val,
+                _ => {
+                    return Err(InterpError::TypeMismatchCustom(
+                        "Return must be an integer".to_string(),
+                    ));
+                }
+            },
+        };
+
+        self.returned = Some(val.clone());
+
+        Ok(val)
+    }
+}
+
+// Calls
+impl<W: Write> Interpreter<W> {
+    fn eval_call(&mut self, callee: &str, arguments: &[Expr]) -> Result<Value, InterpError> {
+        let Some(function) = self.functions.get(callee).cloned() else {
+            return match Intrinsic::resolve(callee) {
+                Some(intrinsic) => self.eval_intrinsic(intrinsic, arguments),
+                None => Err(InterpError::UndefinedFunction(callee.to_string())),
+            };
+        };
+
+        if arguments.len() != function.parameters.len() {
+            return Err(InterpError::WrongArgumentCount(
+                callee.to_string(),
+                function.parameters.len(),
+                arguments.len(),
+            ));
+        }
+
+        // Arguments are evaluated in the caller's scope, the body only sees
+        // its parameters
+        let mut scope = SymbolTable::new();
+        for (argument, parameter) in arguments.iter().zip(&function.parameters) {
+            let value = self.eval(argument)?;
+            let value = coerce_value(&value, &parameter.param_type).ok_or_else(|| {
+                InterpError::ArgumentTypeMismatch(
+                    callee.to_string(),
+                    parameter.name.clone(),
+                    parameter.param_type.to_string(),
+                    value.type_name().to_string(),
+                )
+            })?;
+            scope.declare(
+                &parameter.name,
+                (value, parameter.param_type.clone(), false),
+            );
+        }
+
+        let caller_variables = std::mem::replace(&mut self.variables, scope);
+        // Loops around the call can't be left from inside the function
+        let caller_loops = std::mem::take(&mut self.loops);
+        self.frames.push(Frame {
+            function: callee.to_string(),
+            return_type: function.return_type.clone(),
+        });
+
+        let result = self.eval(&function.body);
+
+        self.frames.pop();
+        self.loops = caller_loops;
+        self.variables = caller_variables;
+
+        let value = result?;
+
+        // An explicit `return` was already checked against the return type
+        if let Some(returned) = self.returned.take() {
+            return Ok(returned);
+        }
+
+        match &function.return_type {
+            Some(return_type) => coerce_value(&value, return_type).ok_or_else(|| {
+                InterpError::ReturnTypeMismatch(
+                    callee.to_string(),
+                    return_type.to_string(),
+                    value.type_name().to_string(),
+                )
+            }),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn eval_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        arguments: &[Expr],
+    ) -> Result<Value, InterpError> {
+        if arguments.len() != intrinsic.arity() {
+            return Err(InterpError::WrongArgumentCount(
+                intrinsic.name().to_string(),
+                intrinsic.arity(),
+                arguments.len(),
+            ));
+        }
+
+        let values = arguments
+            .iter()
+            .map(|argument| self.eval(argument))
+            .collect::<Result<Vec<Value>, InterpError>>()?;
+        let accepted = intrinsic
+            .entry()
+            .parameters
+            .iter()
+            .zip(&values)
+            .all(|(parameter, value)| accepts(parameter, value, &values[0]));
+        if !accepted {
+            return Err(InterpError::InvalidOperation(
+                intrinsic.call_text(arguments),
+            ));
+        }
+
+        match (intrinsic, values.as_slice()) {
+            // Strings count bytes, like the length compiled strings carry
+            (Intrinsic::Len, [Value::String(value)]) => Ok(Value::Integer(value.len() as i64)),
+            (Intrinsic::Len, [Value::Array(elements)]) => Ok(Value::Integer(elements.len() as i64)),
+            (Intrinsic::DivEuclid | Intrinsic::RemEuclid, [_, Value::Integer(0)]) => {
+                Err(InterpError::DivisionByZero)
+            }
+            (Intrinsic::DivEuclid, [Value::Integer(left), Value::Integer(right)]) => {
+                Ok(Value::Integer(left.wrapping_div_euclid(*right)))
+            }
+            (Intrinsic::RemEuclid, [Value::Integer(left), Value::Integer(right)]) => {
+                Ok(Value::Integer(left.wrapping_rem_euclid(*right)))
+            }
+            (Intrinsic::Input, []) => {
+                // Prompts printed without a newline must show before blocking
+                self.output
+                    .flush()
+                    .map_err(|err| InterpError::IOError(err.to_string()))?;
+
+                let mut line = String::new();
+                self.input
+                    .read_line(&mut line)
+                    .map_err(|err| InterpError::IOError(err.to_string()))?;
+                if line.ends_with('\n') {
+                    line.pop();
+                }
+
+                Ok(Value::String(line))
+            }
+            (Intrinsic::IsNan, [Value::Float(value)]) => Ok(Value::Boolean(value.is_nan())),
+            (Intrinsic::IsInf, [Value::Float(value)]) => Ok(Value::Boolean(value.is_infinite())),
+            _ => Err(InterpError::InternalError(format!(
+                "`{}` accepted arguments it has no lowering for",
+                intrinsic.name()
+            ))),
+        }
+    }
+}
+
+// Print
+impl<W: Write> Interpreter<W> {
+    fn eval_print(&mut self, expr: &Expr) -> Result<Value, InterpError> {
+        // Matches the `printf` formats of the compiled program
+        let value = match (self.eval(expr)?, self.float_precision) {
+            (Value::Unit, _) => {
+                return Err(InterpError::InvalidOperation(format!("print({})", expr)));
+            }
+            (Value::Float(value), Some(precision)) => fixed_float(value, precision),
+            (value, _) => value.to_string(),
+        };
+        writeln!(self.output, "{}", value).map_err(|err| InterpError::IOError(err.to_string()))?;
+
+        Ok(Value::Unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rune_parser::parser::Parser;
+
+    fn run_source(source: &str) -> Result<String, InterpError> {
+        let mut parser = Parser::new(source.to_string()).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements)?;
+
+        Ok(String::from_utf8(interpreter.into_output()).unwrap())
+    }
+
+    #[test]
+    fn test_simple_arithmetic() {
+        let mut parser = Parser::new("let x = 5 + 3 * 2; x".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        assert_eq!(interpreter.eval(&statements[0]).unwrap(), Value::Unit);
+
+        assert_eq!(
+            interpreter.eval(&statements[1]).unwrap(),
+            Value::Integer(11)
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut parser = Parser::new("let x = 10; let y = x + 5; y".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements[..2]).unwrap();
+
+        assert_eq!(
+            interpreter.eval(&statements[2]).unwrap(),
+            Value::Integer(15)
+        );
+    }
+
+    #[test]
+    fn test_mixed_int_float() {
+        let mut parser = Parser::new("2 * 1.5".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+
+        assert_eq!(interpreter.eval(&statements[0]).unwrap(), Value::Float(3.0));
+    }
+
+    #[test]
+    fn test_if_else() {
+        let output =
+            run_source("let x = 5; if x > 3 { print(\"big\") } else { print(\"small\") }").unwrap();
+
+        assert_eq!(output, "big\n");
+    }
+
+    #[test]
+    fn statements_evaluate_to_unit() {
+        let mut parser =
+            Parser::new("fn greet() {} if true { 1 } greet() {} print(1)".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        for statement in &statements {
+            assert_eq!(interpreter.eval(statement).unwrap(), Value::Unit);
+        }
+
+        assert_eq!(
+            run_source("fn greet() {} print(greet())"),
+            Err(InterpError::InvalidOperation("print(greet())".into()))
+        );
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        let output = run_source(
+            r#"
+            for i in 0..3 {
+                for j in 0..10 {
+                    if j == 2 { break; }
+                    if i == 1 { continue; }
+                    print(i * 10 + j);
+                }
+            }
+            "#,
+        );
+        assert_eq!(output.unwrap(), "0\n1\n20\n21\n");
+
+        assert_eq!(
+            run_source("fn f() { break; } for i in 0..2 { f(); }").unwrap_err(),
+            InterpError::OutsideLoop("break".into())
+        );
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        let output = run_source(
+            r#"
+            fn check() -> bool { print("checked"); true }
+            print(false && check());
+            print(2 > 1 || check());
+            print(1 != 0 && check());
+            "#,
+        );
+
+        assert_eq!(output.unwrap(), "false\ntrue\nchecked\ntrue\n");
+    }
+
+    #[test]
+    fn logical_operators_take_bools() {
+        let output = run_source("print(!true); print(~5); print(~0 == -1);");
+        assert_eq!(output.unwrap(), "false\n-6\ntrue\n");
+
+        assert_eq!(
+            run_source("print(!5);").unwrap_err(),
+            InterpError::OperatorNotSupported("!".into(), "5".into())
+        );
+        assert_eq!(
+            run_source("print(~true);").unwrap_err(),
+            InterpError::OperatorNotSupported("~".into(), "true".into())
+        );
+        assert_eq!(
+            run_source("print(1 && true);").unwrap_err(),
+            InterpError::OperatorNotSupported("And".into(), "i64".into())
+        );
+    }
+
+    #[test]
+    fn test_print_string() {
+        let output = run_source("print(\"Hello, World!\")").unwrap();
+
+        assert_eq!(output, "Hello, World!\n");
+    }
+
+    #[test]
+    fn test_print_numbers_and_booleans() {
+        let output =
+            run_source("let x: i32 = -7; print(x); print(40 + 2); print(2.5); print(x < 0);");
+
+        assert_eq!(output.unwrap(), "-7\n42\n2.5\ntrue\n");
+    }
+
+    #[test]
+    fn test_chars() {
+        let output =
+            run_source(r"let c: char = '\u{1F600}'; print(c); print('a' < 'b'); print('\n' == c);");
+
+        assert_eq!(output.unwrap(), "😀\ntrue\nfalse\n");
+    }
+
+    #[test]
+    fn test_string_concatenation() {
+        let output = run_source(r#"let name = "world"; print("Hello, " + name + "!");"#);
+
+        assert_eq!(output.unwrap(), "Hello, world!\n");
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let result = run_source("y = 10");
+
+        assert_eq!(
+            result.unwrap_err(),
+            InterpError::UndefinedVariable("y".into())
+        );
+    }
+
+    #[test]
+    fn test_let_type_mismatch() {
+        let result = run_source("let x: bool = 42");
+
+        assert_eq!(
+            result.unwrap_err(),
+            InterpError::LetTypeMismatch("x".into(), "bool".into(), "i64".into(), "42".into())
+        );
+    }
+
+    #[test]
+    fn test_let_integer_narrowing() {
+        let mut parser =
+            Parser::new("let x: i32 = -2147483648; let y: i32 = 4294967297i64; x + y".to_string())
+                .unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements[..2]).unwrap();
+
+        assert_eq!(
+            interpreter.eval(&statements[2]).unwrap(),
+            Value::Integer(-2147483647)
+        );
+        assert_eq!(
+            run_source("let x: i32 = 4294967297;").unwrap_err(),
+            InterpError::LiteralOutOfRange("4294967297".into(), "i32".into())
+        );
+        assert_eq!(
+            run_source("let mut x = 1i32; x = -2147483649;").unwrap_err(),
+            InterpError::LiteralOutOfRange("-2147483649".into(), "i32".into())
+        );
+    }
+
+    #[test]
+    fn test_literal_suffixes() {
+        let mut parser =
+            Parser::new("let mut x = 7i32; x = 4294967297i64; x + 0.1f32".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements[..2]).unwrap();
+
+        assert_eq!(
+            interpreter.eval(&statements[2]).unwrap(),
+            Value::Float(1.0 + 0.1f32 as f64)
+        );
+    }
+
+    #[test]
+    fn test_assignment_type_mismatch() {
+        let result = run_source("let mut x = 1; x = true;");
+
+        assert_eq!(
+            result.unwrap_err(),
+            InterpError::AssignTypeMismatch("x".into(), "i64".into(), "bool".into(), "true".into())
+        );
+    }
+
+    #[test]
+    fn test_assignment_is_unit() {
+        let output = run_source("let mut x = 1; let y = x = 5; print(x);");
+        assert_eq!(output.unwrap(), "5\n");
+
+        assert!(matches!(
+            run_source("let mut x = 1; let y: i64 = x = 5;").unwrap_err(),
+            InterpError::LetTypeMismatch(_, _, found, _) if found == "()"
+        ));
+    }
+
+    #[test]
+    fn test_block_ending_in_semicolon_is_unit() {
+        let output = run_source("let v: i64 = { 1 }; print(v);");
+        assert_eq!(output.unwrap(), "1\n");
+
+        assert!(matches!(
+            run_source("let v: i64 = { 1; };").unwrap_err(),
+            InterpError::LetTypeMismatch(_, _, found, _) if found == "()"
+        ));
+    }
+
+    #[test]
+    fn test_shadowing() {
+        let output = run_source(
+            r#"let x = 1; let x = "two"; print(x); { let mut x = 3; x = 4; } print(x);"#,
+        );
+
+        assert_eq!(output.unwrap(), "two\ntwo\n");
+    }
+
+    #[test]
+    fn test_immutable_bindings() {
+        for source in [
+            "let x = 1; x = 2;",
+            "fn f(x: i64) { x = 2; } f(1);",
+            "for i in 0..2 { i = 5; }",
+        ] {
+            assert!(
+                matches!(run_source(source), Err(InterpError::AssignToImmutable(_))),
+                "{}",
+                source
+            );
+        }
+
+        assert_eq!(
+            run_source("let mut x = 1; { x = x + 1; } if x == 2 { print(\"two\") }").unwrap(),
+            "two\n"
+        );
+    }
+
+    #[test]
+    fn test_block_scope() {
+        let result = run_source("{ let y = 1; } y = 2;");
+
+        assert_eq!(
+            result.unwrap_err(),
+            InterpError::UndefinedVariable("y".into())
+        );
+    }
+
+    #[test]
+    fn test_type_queries() {
+        let mut parser =
+            Parser::new("size_of(i32) + align_of(string) * size_of(bool)".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+
+        assert_eq!(
+            interpreter.eval(&statements[0]).unwrap(),
+            Value::Integer(12)
+        );
+    }
+
+    #[test]
+    fn test_casts() {
+        assert_eq!(
+            run_source(
+                r#"
+                enum Color { Red, Green }
+                let x: i64 = 4294967297;
+                print(x as i32);
+                print(7 as f64 / 2.0);
+                print(-2.75 as i64);
+                print(1e20 as i32);
+                print('a' as i64 + true as i64);
+                print(Color::Green as i32);
+            "#
+            )
+            .unwrap(),
+            "1\n3.5\n-2\n2147483647\n98\n1\n"
+        );
+        assert_eq!(
+            run_source("let s = \"1\"; s as i64").unwrap_err(),
+            InterpError::InvalidCast("s".into(), "string".into(), "i64".into())
+        );
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut parser = Parser::new(
+            r#"
+            #[on_exit] fn last() { print("last"); }
+            #[on_exit] fn flush() { print("flush"); }
+            print("main");
+            #[on_start] fn init() { print("init"); }
+            return 3;
+            "#
+            .to_string(),
+        )
+        .unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements).unwrap();
+
+        assert_eq!(interpreter.exit_code(), 3);
+        assert_eq!(
+            String::from_utf8(interpreter.into_output()).unwrap(),
+            "init\nmain\nflush\nlast\n"
+        );
+        assert_eq!(
+            run_source("#[on_start] fn f(x: i64) {}").unwrap_err(),
+            InterpError::InvalidHook("on_start".into(), "f".into())
+        );
+    }
+
+    #[test]
+    fn test_globals() {
+        assert_eq!(
+            run_source(
+                r#"
+                let mut count = 0;
+                fn bump(by: i64) { count += by; }
+                fn get(count: i64) -> i64 { return count; }
+                bump(2); bump(3);
+                print(count);
+                print(get(7));
+                { let count = 1; bump(count); }
+                print(count);
+            "#
+            )
+            .unwrap(),
+            "5\n7\n6\n"
+        );
+        assert_eq!(
+            run_source("let total = 1; fn f() { total = 2; } f();").unwrap_err(),
+            InterpError::AssignToImmutable("total".into())
+        );
+    }
+
+    #[test]
+    fn test_constants() {
+        assert_eq!(
+            run_source(
+                r#"
+                fn limit() -> i64 { return MAX * 2; }
+                print(limit());
+                const MAX: i64 = 100;
+                const SMALL: i32 = MAX as i32 / 3;
+                print(SMALL + 1);
+                { let MAX = 1; print(MAX); }
+            "#
+            )
+            .unwrap(),
+            "200\n34\n1\n"
+        );
+
+        for source in [
+            "let x = 1; const N: i64 = x + 1;",
+            "const N: i64 = 1; N = 2;",
+            "const N: i64 = 1; const N: i64 = 2;",
+            "const N: bool = 1;",
+        ] {
+            assert!(
+                matches!(run_source(source), Err(InterpError::InvalidConst(..))),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_early_return() {
+        let mut parser = Parser::new(
+            r#"let x = 3; if x > 2 { print("big"); return x; } print("small");"#.to_string(),
+        )
+        .unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements).unwrap();
+
+        assert_eq!(interpreter.exit_code(), 3);
+        assert_eq!(interpreter.output(), b"big\n");
+    }
+
+    #[test]
+    fn test_functions() {
+        let mut parser = Parser::new(
+            r#"
+            let mut total = 0;
+            for i in 0..5 { total = total + square(i); }
+            fn square(x: i64) -> i64 { x * x }
+            fn fact(n: i64) -> i64 { if n <= 1 { return 1; } n * fact(n - 1) }
+            fn greet() { print("hi"); return; print("unreachable"); }
+            greet();
+            if total == 30 { return fact(5); }
+            "#
+            .to_string(),
+        )
+        .unwrap();
+        let statements = parser.parse().unwrap();
+
+        let mut interpreter = Interpreter::with_output(Vec::new());
+        interpreter.run(&statements).unwrap();
+
+        assert_eq!(interpreter.exit_code(), 120);
+        assert_eq!(interpreter.output(), b"hi\n");
+    }
+
+    #[test]
+    fn test_function_errors() {
+        let run = |source: &str| {
+            let mut parser = Parser::new(source.to_string()).unwrap();
+            let statements = parser.parse().unwrap();
+            Interpreter::with_output(Vec::new()).run(&statements)
+        };
+
+        assert_eq!(
+            run("fn f(a: i32) {} f()"),
+            Err(InterpError::WrongArgumentCount("f".into(), 1, 0))
+        );
+        assert_eq!(
+            run("fn f(a: i32) {} f(true)"),
+            Err(InterpError::ArgumentTypeMismatch(
+                "f".into(),
+                "a".into(),
+                "i32".into(),
+                "bool".into()
+            ))
+        );
+        assert_eq!(
+            run("fn f() -> i64 { return \"s\"; } f()"),
+            Err(InterpError::ReturnTypeMismatch(
+                "f".into(),
+                "i64".into(),
+                "string".into()
+            ))
+        );
+        assert_eq!(
+            run("{ let x = 1; fn f() -> i64 { x } f() }"),
+            Err(InterpError::UndefinedVariable("x".into()))
+        );
+        assert_eq!(
+            run("fn f() {} fn f() {}"),
+            Err(InterpError::FunctionRedefinition("f".into()))
+        );
+        assert_eq!(run("g()"), Err(InterpError::UndefinedFunction("g".into())));
+    }
+
+    #[test]
+    fn test_for_loop() {
+        let output = run_source(
+            r#"let mut count = 0; for i in 1..4 { count = count + i; if count == 6 { print("six"); } }"#,
+        )
+        .unwrap();
+        assert_eq!(output, "six\n");
+
+        let mut parser = Parser::new("for i in 0..3 { } i".to_string()).unwrap();
+        let statements = parser.parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.run(&statements),
+            Err(InterpError::UndefinedVariable("i".into()))
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let result = run_source("let x = 10 / 0");
+
+        assert_eq!(result.unwrap_err(), InterpError::DivisionByZero);
+    }
+
+    #[test]
+    fn test_arrays() {
+        let output = run_source(
+            "let xs: [i32; 3] = [1, 2, 3 + 4]; let i = 2; print(xs[1] + xs[i]); print(xs); print(size_of([i32; 3]));",
+        );
+        assert_eq!(output.unwrap(), "9\n[1, 2, 7]\n12\n");
+
+        assert_eq!(
+            run_source("let xs = [1, 2]; print(xs[2]);").unwrap_err(),
+            InterpError::IndexOutOfBounds(2, 2)
+        );
+        assert_eq!(
+            run_source("let xs = [1, 2]; print(xs[-1]);").unwrap_err(),
+            InterpError::IndexOutOfBounds(-1, 2)
+        );
+        assert!(matches!(
+            run_source("let xs: [i64; 3] = [1, 2];").unwrap_err(),
+            InterpError::LetTypeMismatch(..)
+        ));
+    }
+
+    #[test]
+    fn test_enums_and_match() {
+        let output = run_source(
+            r#"
+            fn code(c: Color) -> i64 {
+                match c { Color::Red => 1, Color::Green => { 2 } _ => 3 }
+            }
+            enum Color { Red, Green, Blue }
+            let c: Color = Color::Green;
+            print(code(c) + code(Color::Blue));
+            print(c == Color::Green);
+            print(c);
+            print(size_of(Color));
+            "#,
+        );
+        assert_eq!(output.unwrap(), "5\ntrue\nColor::Green\n4\n");
+
+        assert_eq!(
+            run_source("enum Color { Red } let c = Color::Blue;").unwrap_err(),
+            InterpError::UndefinedVariant("Color".into(), "Blue".into())
+        );
+        assert_eq!(
+            run_source("enum Color { Red, Blue } match Color::Blue { Color::Red => 1 };")
+                .unwrap_err(),
+            InterpError::NoMatchingArm("Color::Blue".into())
+        );
+    }
+
+    #[test]
+    fn test_len_intrinsic() {
+        let output = run_source(
+            r#"
+            let xs = [1, 2, 3];
+            print(len(xs));
+            print(len("héllo"));
+            print(len(""));
+            "#,
+        );
+        assert_eq!(output.unwrap(), "3\n6\n0\n");
+
+        assert_eq!(
+            run_source("len(1.5);").unwrap_err(),
+            InterpError::InvalidOperation("len(1.5)".into())
+        );
+    }
+
+    #[test]
+    fn test_input_reads_lines() {
+        let statements = Parser::new(
+            r#"let name = input(); print("Hello, " + name); print(len(input())); print(len(input()));"#
+                .to_string(),
+        )
+        .unwrap()
+        .parse()
+        .unwrap();
+
+        let mut interpreter =
+            Interpreter::with_output(Vec::new()).with_input("Ada\nrune".as_bytes());
+        interpreter.run(&statements).unwrap();
+
+        let output = String::from_utf8(interpreter.into_output()).unwrap();
+        assert_eq!(output, "Hello, Ada\n4\n0\n");
+    }
+
+    #[test]
+    fn test_float_printing() {
+        let source = "print(0.1 + 0.2); print(100.0); print(1e20); print(0.00001); print(-2.5f32); print(0.0 / 0.0);";
+        assert_eq!(
+            run_source(source).unwrap(),
+            "0.30000000000000004\n100\n1e+20\n1e-05\n-2.5\nnan\n"
+        );
+
+        let statements = Parser::new(source.to_string()).unwrap().parse().unwrap();
+        let mut interpreter = Interpreter::with_output(Vec::new()).with_float_precision(2);
+        interpreter.run(&statements).unwrap();
+        let output = String::from_utf8(interpreter.into_output()).unwrap();
+        assert_eq!(
+            output,
+            "0.30\n100.00\n100000000000000000000.00\n0.00\n-2.50\nnan\n"
+        );
+    }
+
+    #[test]
+    fn test_division_of_negative_numbers() {
+        let output = run_source(
+            r#"
+            print(-7 / 2);
+            print(-7 % 2);
+            print(div_euclid(-7, 2));
+            print(rem_euclid(-7, 2));
+            print(div_euclid(7, -2));
+            print(rem_euclid(-7, -2));
+            "#,
+        );
+        assert_eq!(output.unwrap(), "-3\n-1\n-4\n1\n-3\n1\n");
+
+        assert_eq!(
+            run_source("rem_euclid(1, 0);").unwrap_err(),
+            InterpError::DivisionByZero
+        );
+        assert_eq!(
+            run_source("div_euclid(1.5, 2);").unwrap_err(),
+            InterpError::InvalidOperation("div_euclid(1.5, 2)".into())
+        );
+    }
+
+    #[test]
+    fn nan_compares_unequal_to_everything() {
+        let output = run_source(
+            r#"
+            let nan = 0.0 / 0.0;
+            let inf = -1.0 / 0.0;
+            print(nan == nan);
+            print(nan != nan);
+            print(nan < 1.0 || nan >= 1.0);
+            print(is_nan(nan));
+            print(is_inf(inf));
+            print(is_inf(nan));
+            "#,
+        );
+        assert_eq!(output.unwrap(), "false\ntrue\nfalse\ntrue\ntrue\nfalse\n");
+
+        assert_eq!(
+            run_source("is_nan(1);").unwrap_err(),
+            InterpError::InvalidOperation("is_nan(1)".into())
+        );
+    }
+}
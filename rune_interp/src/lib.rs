@@ -0,0 +1,3 @@
+pub mod errors;
+pub mod interpreter;
+pub mod value;
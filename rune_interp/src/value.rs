@@ -0,0 +1,132 @@
+use std::fmt;
+
+use rune_parser::parser::types::Types;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    Char(char),
+    Array(Vec<Value>),
+    /// An enum and one of its variants.
+    Enum(String, String),
+    /// `()`, the value of statements.
+    Unit,
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "i64",
+            Value::Float(_) => "f64",
+            Value::Boolean(_) => "bool",
+            Value::String(_) => "string",
+            Value::Char(_) => "char",
+            Value::Array(_) => "array",
+            Value::Enum(..) => "enum",
+            Value::Unit => "()",
+        }
+    }
+
+    /// The type a variable gets when declared without an annotation.
+    pub fn default_type(&self) -> Types {
+        match self {
+            Value::Integer(_) => Types::I64,
+            Value::Float(_) => Types::F64,
+            Value::Boolean(_) => Types::Bool,
+            Value::String(_) => Types::String,
+            Value::Char(_) => Types::Char,
+            Value::Array(elements) => Types::Array(
+                Box::new(elements.first().map_or(Types::I64, Value::default_type)),
+                elements.len(),
+            ),
+            Value::Enum(enum_name, _) => Types::Enum(enum_name.clone()),
+            Value::Unit => Types::Unit,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", shortest_float(*value)),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+            Value::Char(value) => write!(f, "{}", value),
+            Value::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Value::Enum(enum_name, variant) => write!(f, "{}::{}", enum_name, variant),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
+/// Formats `value` with the fewest significant digits that parse back to
+/// the same `f64`, as compiled programs print floats: `0.1` rather than
+/// `0.100000`. An exponent is only used below `1e-4` and from `1e17` on,
+/// written like `printf("%e")` writes it.
+pub fn shortest_float(value: f64) -> String {
+    if !value.is_finite() {
+        return non_finite_float(value);
+    }
+
+    for digits in 1..=17 {
+        // `{:e}` rounds correctly, so its digits are the ones `%g` prints
+        let scientific = format!("{:.*e}", digits - 1, value);
+        if scientific.parse::<f64>() != Ok(value) && digits < 17 {
+            continue;
+        }
+
+        let (mantissa, exponent) = scientific.split_once('e').unwrap();
+        let exponent: i32 = exponent.parse().unwrap();
+        if !(-4..17).contains(&exponent) {
+            return format!(
+                "{}e{}{:02}",
+                trim_fraction(mantissa),
+                if exponent < 0 { '-' } else { '+' },
+                exponent.abs()
+            );
+        }
+
+        let decimals = (digits as i32 - 1 - exponent).max(0) as usize;
+        return trim_fraction(&format!("{:.*}", decimals, value)).to_string();
+    }
+
+    unreachable!("17 significant digits always round-trip")
+}
+
+/// Formats `value` like `printf("%.*f")` with `precision` decimals.
+pub fn fixed_float(value: f64, precision: usize) -> String {
+    if !value.is_finite() {
+        return non_finite_float(value);
+    }
+    format!("{:.*}", precision, value)
+}
+
+/// `nan` and `inf` are spelled like C spells them.
+fn non_finite_float(value: f64) -> String {
+    if value.is_nan() {
+        "nan".to_string()
+    } else if value < 0.0 {
+        "-inf".to_string()
+    } else {
+        "inf".to_string()
+    }
+}
+
+/// Drops the trailing zeros of a fraction, and the point if nothing is left.
+fn trim_fraction(number: &str) -> &str {
+    if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::{self};
 
-#[derive(PartialEq)]
+use crate::parser::types::Types;
+use crate::span::Span;
+
+#[derive(Clone, PartialEq)]
 pub enum ParserError {
     UnexpectedCharacter(char),
     UnexpectedToken(String),
@@ -9,6 +12,39 @@ pub enum ParserError {
     ExpectedAfter(String, String),
     ExpectedAfterCustom(String, String, String),
     InvalidAssignment(String),
+    IntegerLiteralTooLarge(String, Types),
+    ExpectedExpression(String),
+    WrongArgumentCount(String, usize, usize),
+    UnknownAttribute(String),
+    /// A definition that may only appear at the top level, like `const`.
+    NotAtTopLevel(String),
+    InvalidEscape(String),
+    /// Any of the errors above, at a 1-based line and column of the source.
+    Located {
+        line: usize,
+        column: usize,
+        error: Box<ParserError>,
+    },
+}
+
+impl ParserError {
+    /// Locates the error at the start of `span` in `source`.
+    pub fn at(self, source: &str, span: Span) -> Self {
+        let (line, column) = span.line_column(source);
+        ParserError::Located {
+            line,
+            column,
+            error: Box::new(self.without_location()),
+        }
+    }
+
+    /// The error itself, dropping its location if it has one.
+    pub fn without_location(self) -> Self {
+        match self {
+            ParserError::Located { error, .. } => *error,
+            error => error,
+        }
+    }
 }
 
 impl fmt::Display for ParserError {
@@ -41,5 +77,36 @@ pub fn get_print_error(error: &ParserError) -> String {
         ParserError::InvalidAssignment(message) => {
             format!("(P006): Invalid assignment {}", message)
         }
+        ParserError::IntegerLiteralTooLarge(literal, literal_type) => {
+            let (min, max) = match literal_type {
+                Types::I32 => (i32::MIN as i64, i32::MAX as i64),
+                _ => (i64::MIN, i64::MAX),
+            };
+            format!(
+                "(P007): Integer literal `{}` is out of range for `{}` ({}..={})",
+                literal, literal_type, min, max
+            )
+        }
+        ParserError::ExpectedExpression(found) => {
+            format!("(P008): Expected expression, found `{}`", found)
+        }
+        ParserError::WrongArgumentCount(callee, expected, found) => format!(
+            "(P009): `{}` takes {} argument(s) but {} were given",
+            callee, expected, found
+        ),
+        ParserError::UnknownAttribute(name) => {
+            format!("(P010): Unknown attribute `{}`", name)
+        }
+        ParserError::NotAtTopLevel(keyword) => {
+            format!("(P011): `{}` is only allowed at the top level", keyword)
+        }
+        ParserError::InvalidEscape(literal) => {
+            format!("(P012): Unknown escape sequence in {}", literal)
+        }
+        ParserError::Located {
+            line,
+            column,
+            error,
+        } => format!("{}:{}: {}", line, column, get_print_error(error)),
     }
 }
@@ -0,0 +1,480 @@
+use crate::errors::ParserError;
+use crate::lexer::{LexedToken, Trivia, lex_lossless};
+use crate::parser::tokens::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs,
+}
+
+/// Style settings for [`format_source`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Lines longer than this are wrapped before a binary operator or after
+    /// a `,` where possible.
+    pub max_width: usize,
+    pub indent: Indent,
+    /// Whether the last statement of the file ends with a `;`. Those ending
+    /// a block are kept as written, since they make its value `()`.
+    pub trailing_semicolons: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            max_width: 100,
+            indent: Indent::Spaces(4),
+            trailing_semicolons: true,
+        }
+    }
+}
+
+/// Formats `source`, keeping comments and single blank lines between
+/// statements. Only whitespace and optional `;` are changed, so the result
+/// parses to the same program.
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String, ParserError> {
+    let lexed = lex_lossless(source)?;
+
+    let mut printer = Printer::new(options);
+    for index in 0..lexed.tokens.len() {
+        printer.token(&lexed.tokens, index);
+    }
+    printer.finish(&lexed.trailing_trivia);
+
+    Ok(printer.out)
+}
+
+struct Printer<'a> {
+    options: &'a FormatOptions,
+    out: String,
+    line: String,
+    depth: usize,
+    prev: Option<Token>,
+    prev_unary: bool,
+    pending_break: bool,
+    pending_blank: bool,
+    skip_next: bool,
+    /// Open `[` brackets, inside which `;` separates an array type's length.
+    brackets: usize,
+    parens: usize,
+    /// Open `{` braces. Those of an `enum` or `match` hold a `,`-separated
+    /// list, one item per line, and store the paren and bracket depth at
+    /// which their commas end an item.
+    braces: Vec<Option<usize>>,
+    /// Whether the next `{` opens a list, i.e. follows `enum` or `match`.
+    list_pending: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(options: &'a FormatOptions) -> Self {
+        Self {
+            options,
+            out: String::new(),
+            line: String::new(),
+            depth: 0,
+            prev: None,
+            prev_unary: false,
+            pending_break: false,
+            pending_blank: false,
+            skip_next: false,
+            brackets: 0,
+            parens: 0,
+            braces: Vec::new(),
+            list_pending: false,
+        }
+    }
+
+    fn token(&mut self, tokens: &[LexedToken], index: usize) {
+        if std::mem::take(&mut self.skip_next) {
+            return;
+        }
+
+        let lexed = &tokens[index];
+        let next = tokens.get(index + 1).map(|next| &next.token);
+
+        if lexed.token == Token::RightBrace
+            && let Some(Some(_)) = self.braces.last()
+        {
+            self.trailing_comma();
+        }
+
+        self.trivia(&lexed.leading_trivia);
+
+        match &lexed.token {
+            Token::KeywordEnum | Token::KeywordMatch => {
+                self.list_pending = true;
+                self.push(&lexed.text, &lexed.token);
+            }
+            Token::LeftBrace if next == Some(&Token::RightBrace) => {
+                self.list_pending = false;
+                self.skip_next = true;
+                self.push("{}", &Token::RightBrace);
+                self.after_block(tokens.get(index + 2).map(|after| &after.token));
+            }
+            Token::LeftBrace => {
+                let list = std::mem::take(&mut self.list_pending);
+                self.braces
+                    .push(list.then_some(self.parens + self.brackets));
+                self.push("{", &lexed.token);
+                self.depth += 1;
+                self.break_line();
+            }
+            Token::RightBrace => {
+                self.braces.pop();
+                self.depth = self.depth.saturating_sub(1);
+                self.pending_blank = false;
+                self.end_line();
+                self.push("}", &lexed.token);
+                self.after_block(next);
+            }
+            Token::LeftBracket => {
+                self.brackets += 1;
+                self.push("[", &lexed.token);
+            }
+            Token::RightBracket => {
+                self.brackets = self.brackets.saturating_sub(1);
+                self.push("]", &lexed.token);
+            }
+            Token::LeftParen => {
+                self.parens += 1;
+                self.push("(", &lexed.token);
+            }
+            Token::RightParen => {
+                self.parens = self.parens.saturating_sub(1);
+                self.push(")", &lexed.token);
+            }
+            Token::Comma if self.braces.last() == Some(&Some(self.parens + self.brackets)) => {
+                self.push(",", &lexed.token);
+                self.break_line();
+            }
+            Token::Semicolon if self.brackets > 0 => self.push(";", &lexed.token),
+            Token::Semicolon => {
+                if self.options.trailing_semicolons || next.is_some() {
+                    self.push(";", &lexed.token);
+                }
+                self.break_line();
+            }
+            token => self.push(&lexed.text, token),
+        }
+    }
+
+    fn finish(&mut self, trailing_trivia: &[Trivia]) {
+        self.trailing_semicolon();
+        self.trivia(trailing_trivia);
+        self.pending_blank = false;
+        self.end_line();
+    }
+
+    /// Places comments and keeps one blank line where the source had any.
+    fn trivia(&mut self, trivia: &[Trivia]) {
+        let mut newlines = 0;
+
+        for piece in trivia {
+            match piece {
+                Trivia::Whitespace(_) => {
+                    newlines += piece.newlines();
+                    if newlines >= 2
+                        && !self.out.is_empty()
+                        && (self.pending_break || self.line.is_empty())
+                        && self.prev != Some(Token::LeftBrace)
+                    {
+                        self.pending_blank = true;
+                    }
+                }
+                Trivia::LineComment(comment) => {
+                    if newlines == 0 && !self.line.is_empty() {
+                        // A comment trailing code stays on its line
+                        self.line.push(' ');
+                        self.line.push_str(comment);
+                    } else {
+                        self.end_line();
+                        self.indent(self.depth);
+                        self.line.push_str(comment);
+                    }
+                    self.pending_break = true;
+                    newlines = 0;
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, text: &str, token: &Token) {
+        if self.pending_break {
+            self.end_line();
+        }
+
+        let unary = is_unary(self.prev.as_ref(), token);
+        let space = !self.line.is_empty() && self.needs_space(token);
+
+        if self.line.is_empty() {
+            self.indent(self.depth);
+        } else if self.line.chars().count() + space as usize + text.chars().count()
+            > self.options.max_width
+            && (self.prev == Some(Token::Comma) || (is_binary(token) && !unary))
+        {
+            self.end_line();
+            self.indent(self.depth + 1);
+        } else if space {
+            self.line.push(' ');
+        }
+
+        self.line.push_str(text);
+        self.prev = Some(token.clone());
+        self.prev_unary = unary;
+    }
+
+    fn needs_space(&self, token: &Token) -> bool {
+        if self.prev_unary
+            || matches!(
+                token,
+                Token::RightParen
+                    | Token::RightBracket
+                    | Token::Comma
+                    | Token::Semicolon
+                    | Token::Colon
+                    | Token::ColonColon
+                    | Token::DotDot
+            )
+        {
+            return false;
+        }
+
+        match (&self.prev, token) {
+            (
+                Some(
+                    Token::LeftParen
+                    | Token::LeftBracket
+                    | Token::DotDot
+                    | Token::Hash
+                    | Token::ColonColon,
+                ),
+                _,
+            ) => false,
+            // Calls and type queries: `print(x)`, `size_of(i32)`
+            (Some(Token::Identifier(_) | Token::KeywordPrint), Token::LeftParen) => false,
+            // Indexing: `xs[i]`, `f()[0]`
+            (
+                Some(Token::Identifier(_) | Token::RightParen | Token::RightBracket),
+                Token::LeftBracket,
+            ) => false,
+            _ => true,
+        }
+    }
+
+    /// Adds the `;` a block or file's last statement is missing.
+    fn trailing_semicolon(&mut self) {
+        if self.options.trailing_semicolons
+            && self.prev.is_some()
+            && !matches!(
+                self.prev,
+                Some(Token::Semicolon | Token::LeftBrace | Token::RightBrace)
+            )
+        {
+            self.line.push(';');
+            self.prev = Some(Token::Semicolon);
+        }
+    }
+
+    /// Ends the last item of an `enum` or `match` with a `,`, unless it is
+    /// a block.
+    fn trailing_comma(&mut self) {
+        if !matches!(
+            self.prev,
+            Some(Token::Comma | Token::LeftBrace | Token::RightBrace)
+        ) {
+            self.line.push(',');
+            self.prev = Some(Token::Comma);
+        }
+    }
+
+    /// Keeps `} else`, `};` and `})` together, otherwise ends the line.
+    fn after_block(&mut self, next: Option<&Token>) {
+        if !matches!(
+            next,
+            Some(Token::KeywordElse | Token::Semicolon | Token::RightParen | Token::Comma)
+        ) {
+            self.break_line();
+        }
+    }
+
+    fn break_line(&mut self) {
+        self.pending_break = true;
+    }
+
+    fn end_line(&mut self) {
+        if !self.line.is_empty() {
+            self.out.push_str(self.line.trim_end());
+            self.out.push('\n');
+            self.line.clear();
+
+            if std::mem::take(&mut self.pending_blank) {
+                self.out.push('\n');
+            }
+        }
+        self.pending_break = false;
+    }
+
+    fn indent(&mut self, depth: usize) {
+        let unit = match self.options.indent {
+            Indent::Spaces(width) => " ".repeat(width),
+            Indent::Tabs => "\t".to_string(),
+        };
+        self.line.push_str(&unit.repeat(depth));
+    }
+}
+
+fn is_binary(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Plus
+            | Token::Minus
+            | Token::Star
+            | Token::Slash
+            | Token::Percent
+            | Token::Ampersand
+            | Token::EqualsEquals
+            | Token::NotEquals
+            | Token::GreaterThan
+            | Token::LessThan
+            | Token::GreaterThanEquals
+            | Token::LessThanEquals
+            | Token::And
+            | Token::Or
+    )
+}
+
+/// Whether `token` is a prefix operator, i.e. it does not follow an operand.
+fn is_unary(prev: Option<&Token>, token: &Token) -> bool {
+    let prefix = matches!(
+        token,
+        Token::Minus | Token::Bang | Token::Tilde | Token::Ampersand | Token::Star
+    );
+    let after_operand = matches!(
+        prev,
+        Some(
+            Token::Identifier(_)
+                | Token::Integer(_)
+                | Token::Float(_)
+                | Token::TypedInteger(_)
+                | Token::TypedFloat(_)
+                | Token::String(_)
+                | Token::Boolean(_)
+                | Token::Char(_)
+                | Token::RightParen
+                | Token::RightBracket
+                | Token::RightBrace
+        )
+    );
+
+    prefix && !after_operand
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::parser::expr::Expr;
+
+    fn format(source: &str) -> String {
+        format_source(source, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn normalizes_spacing_and_indentation() {
+        let source = "let x:i32=-1+2*3;if x>=2{print( \"big\" )}else{ }\nfor i in 0 .. x {x=x-i}\n# [ unroll( 2 ) ]for i in 0..x {}";
+
+        assert_eq!(
+            format(source),
+            "let x: i32 = -1 + 2 * 3;\n\
+             if x >= 2 {\n    print(\"big\")\n} else {}\n\
+             for i in 0..x {\n    x = x - i\n}\n\
+             #[unroll(2)] for i in 0..x {}\n"
+        );
+    }
+
+    #[test]
+    fn compound_assignments() {
+        assert_eq!(format("x+=1;y -=-2;z*=x"), "x += 1;\ny -= -2;\nz *= x;\n");
+    }
+
+    #[test]
+    fn arrays() {
+        let source = "let xs:[i64;3]=[ 1,-2,3 ];print(xs [1]-xs[ 0 ])";
+
+        assert_eq!(
+            format(source),
+            "let xs: [i64; 3] = [1, -2, 3];\nprint(xs[1] - xs[0]);\n"
+        );
+    }
+
+    #[test]
+    fn enums_and_matches() {
+        let source = "enum Color{Red,Green , Blue}\nlet n=match c{Color :: Red=>f(1,2),Color::Green=>{1}_=>3};";
+
+        assert_eq!(
+            format(source),
+            "enum Color {\n    Red,\n    Green,\n    Blue,\n}\n\
+             let n = match c {\n    Color::Red => f(1, 2),\n    Color::Green => {\n        1\n    }\n    _ => 3,\n};\n"
+        );
+        assert_eq!(format(&format(source)), format(source));
+    }
+
+    #[test]
+    fn hook_attributes() {
+        assert_eq!(
+            format("#[ on_exit ]fn done(){}"),
+            "#[on_exit] fn done() {}\n"
+        );
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(format("const MAX:i64=10*2;"), "const MAX: i64 = 10 * 2;\n");
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_lines() {
+        let source = "// header\nlet a = 1; // one\n\n\n\n{\n\n  // inside\n  a\n\n}\n";
+
+        assert_eq!(
+            format(source),
+            "// header\nlet a = 1; // one\n\n{\n    // inside\n    a\n}\n"
+        );
+    }
+
+    #[test]
+    fn applies_options() {
+        let options = FormatOptions {
+            max_width: 20,
+            indent: Indent::Tabs,
+            trailing_semicolons: false,
+        };
+        let source = "{ let total = first + second + third; total; } total;";
+
+        // Only the file's last `;` is optional, a block's makes it `()`
+        assert_eq!(
+            format_source(source, &options).unwrap(),
+            "{\n\tlet total = first + second\n\t\t+ third;\n\ttotal;\n}\ntotal\n"
+        );
+    }
+
+    #[test]
+    fn formatting_is_stable_and_preserves_the_program() {
+        let source = "let s = \"a\" \"b\";if !true {return 1} // done\nprint(s,)";
+        let formatted = format(source);
+
+        assert_eq!(format(&formatted), formatted);
+
+        let printed = |source: String| {
+            Parser::new(source)
+                .unwrap()
+                .parse()
+                .unwrap()
+                .iter()
+                .map(Expr::to_string)
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(printed(formatted), printed(source.to_string()));
+    }
+}
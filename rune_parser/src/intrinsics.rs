@@ -0,0 +1,211 @@
+use std::fmt;
+
+use crate::parser::types::Types;
+
+/// Functions every backend provides without a definition, called with the
+/// usual call syntax.
+///
+/// A call resolves to an intrinsic only when no function of that name is
+/// defined, so existing programs that define their own `len` keep calling it.
+///
+/// Each intrinsic is described once, by its entry in [`TABLE`].
+/// The checker types calls from the entry alone, and every backend checks
+/// arguments against it before lowering them, so adding an intrinsic is a
+/// table entry plus one lowering per backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    Len,
+    DivEuclid,
+    RemEuclid,
+    Input,
+    IsNan,
+    IsInf,
+}
+
+/// What an intrinsic accepts for one of its arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    /// An integer of any width, but not a `bool`.
+    Int,
+    /// An `f32` or an `f64`.
+    Float,
+    /// A string or an array, which have a length.
+    Sized,
+    /// A value of the same type as the first argument.
+    SameAsFirst,
+}
+
+/// The type of an intrinsic call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Returns {
+    Type(Types),
+    /// The type of the first argument.
+    First,
+}
+
+/// The entry of one intrinsic in [`TABLE`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub intrinsic: Intrinsic,
+    pub name: &'static str,
+    pub parameters: &'static [ParamKind],
+    pub returns: Returns,
+    /// One line on what the intrinsic does.
+    pub doc: &'static str,
+}
+
+/// Every intrinsic, in the order they are listed to users.
+pub static TABLE: [Entry; 6] = [
+    Entry {
+        intrinsic: Intrinsic::Len,
+        name: "len",
+        parameters: &[ParamKind::Sized],
+        returns: Returns::Type(Types::I64),
+        doc: "the number of bytes in a string or elements in an array",
+    },
+    Entry {
+        intrinsic: Intrinsic::DivEuclid,
+        name: "div_euclid",
+        parameters: &[ParamKind::Int, ParamKind::SameAsFirst],
+        returns: Returns::First,
+        doc: "integer division rounding so that the remainder is never negative, \
+              unlike `/` which rounds towards zero",
+    },
+    Entry {
+        intrinsic: Intrinsic::RemEuclid,
+        name: "rem_euclid",
+        parameters: &[ParamKind::Int, ParamKind::SameAsFirst],
+        returns: Returns::First,
+        doc: "the remainder of `div_euclid`, in `0..|b|` even when `a` is negative, \
+              unlike `%`",
+    },
+    Entry {
+        intrinsic: Intrinsic::Input,
+        name: "input",
+        parameters: &[],
+        returns: Returns::Type(Types::String),
+        doc: "the next line of stdin without its newline, or an empty string at \
+              the end of input",
+    },
+    Entry {
+        intrinsic: Intrinsic::IsNan,
+        name: "is_nan",
+        parameters: &[ParamKind::Float],
+        returns: Returns::Type(Types::Bool),
+        doc: "whether a float is NaN, the one value for which `x != x`",
+    },
+    Entry {
+        intrinsic: Intrinsic::IsInf,
+        name: "is_inf",
+        parameters: &[ParamKind::Float],
+        returns: Returns::Type(Types::Bool),
+        doc: "whether a float is positive or negative infinity",
+    },
+];
+
+impl Intrinsic {
+    /// The intrinsic called `name`, if any.
+    pub fn resolve(name: &str) -> Option<Self> {
+        TABLE
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.intrinsic)
+    }
+
+    pub fn entry(&self) -> &'static Entry {
+        TABLE
+            .iter()
+            .find(|entry| entry.intrinsic == *self)
+            .expect("every intrinsic has an entry")
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.entry().name
+    }
+
+    /// How many arguments the intrinsic takes.
+    pub fn arity(&self) -> usize {
+        self.entry().parameters.len()
+    }
+
+    /// The call as written, for errors about its arguments.
+    pub fn call_text(&self, arguments: &[impl fmt::Display]) -> String {
+        let arguments = arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect::<Vec<String>>();
+        format!("{}({})", self.name(), arguments.join(", "))
+    }
+}
+
+impl ParamKind {
+    /// Whether an argument of type `argument` is accepted, given the type of
+    /// the first argument.
+    pub fn accepts(&self, argument: &Types, first: &Types) -> bool {
+        match self {
+            ParamKind::Int => matches!(argument, Types::I32 | Types::I64),
+            ParamKind::Float => matches!(argument, Types::F32 | Types::F64),
+            ParamKind::Sized => matches!(argument, Types::String | Types::Array(..)),
+            ParamKind::SameAsFirst => argument == first,
+        }
+    }
+}
+
+impl fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamKind::Int => write!(f, "int"),
+            ParamKind::Float => write!(f, "float"),
+            ParamKind::Sized => write!(f, "string | array"),
+            ParamKind::SameAsFirst => write!(f, "same"),
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    /// The signature, like `len(string | array) -> i64`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|parameter| parameter.to_string())
+            .collect::<Vec<String>>();
+        write!(f, "{}({}) -> ", self.name, parameters.join(", "))?;
+        match &self.returns {
+            Returns::Type(return_type) => write!(f, "{}", return_type),
+            Returns::First => write!(f, "same"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intrinsics_resolve_by_name() {
+        assert_eq!(Intrinsic::resolve("len"), Some(Intrinsic::Len));
+        assert_eq!(Intrinsic::resolve("rem_euclid"), Some(Intrinsic::RemEuclid));
+        assert_eq!(Intrinsic::resolve("length"), None);
+        for entry in &TABLE {
+            assert_eq!(Intrinsic::resolve(entry.name), Some(entry.intrinsic));
+            assert_eq!(entry.intrinsic.entry(), entry);
+        }
+    }
+
+    #[test]
+    fn signatures() {
+        assert_eq!(
+            Intrinsic::Len.entry().to_string(),
+            "len(string | array) -> i64"
+        );
+        assert_eq!(
+            Intrinsic::DivEuclid.entry().to_string(),
+            "div_euclid(int, same) -> same"
+        );
+        assert_eq!(Intrinsic::Input.entry().to_string(), "input() -> string");
+        assert!(ParamKind::Int.accepts(&Types::I32, &Types::I32));
+        assert!(!ParamKind::Int.accepts(&Types::Bool, &Types::Bool));
+        assert!(!ParamKind::SameAsFirst.accepts(&Types::I64, &Types::I32));
+    }
+}
@@ -0,0 +1,237 @@
+use logos::Logos;
+
+use crate::errors::ParserError;
+use crate::parser::tokens::{Token, split_suffix};
+use crate::parser::types::Types;
+use crate::span::Span;
+
+/// Whitespace or a comment, which the parser never sees.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trivia {
+    Whitespace(String),
+    LineComment(String),
+}
+
+impl Trivia {
+    pub fn text(&self) -> &str {
+        match self {
+            Trivia::Whitespace(text) | Trivia::LineComment(text) => text,
+        }
+    }
+
+    /// Number of line breaks, so blank lines can be told apart from a
+    /// plain newline.
+    pub fn newlines(&self) -> usize {
+        self.text().matches('\n').count()
+    }
+}
+
+/// A token together with its source text and the trivia preceding it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexedToken {
+    pub token: Token,
+    pub span: Span,
+    pub text: String,
+    pub leading_trivia: Vec<Trivia>,
+}
+
+/// Output of [`lex_lossless`]. Concatenating every token's leading trivia
+/// and text, followed by `trailing_trivia`, reproduces the source exactly.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LosslessTokens {
+    pub tokens: Vec<LexedToken>,
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+impl LosslessTokens {
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        for token in &self.tokens {
+            token
+                .leading_trivia
+                .iter()
+                .for_each(|trivia| source.push_str(trivia.text()));
+            source.push_str(&token.text);
+        }
+        self.trailing_trivia
+            .iter()
+            .for_each(|trivia| source.push_str(trivia.text()));
+        source
+    }
+}
+
+/// Lexes `source` into tokens and their spans, dropping trivia. Errors are
+/// located at the offending token.
+pub fn lex(source: &str) -> Result<(Vec<Token>, Vec<Span>), ParserError> {
+    let mut lexer = Token::lexer(source);
+    let mut tokens = Vec::new();
+    let mut spans = Vec::new();
+
+    while let Some(token) = lexer.next() {
+        let span = Span::from(lexer.span());
+        spans.push(span);
+        match token {
+            Ok(t) => tokens.push(t),
+            Err(_) => {
+                let slice = lexer.slice();
+                let (digits, literal_type) = split_suffix(slice).unwrap_or((slice, Types::I64));
+                if digits.starts_with(|c: char| c.is_ascii_digit())
+                    && digits.chars().all(|c| c.is_ascii_digit() || c == '_')
+                {
+                    // Only an out of range integer fails the token callback
+                    return Err(ParserError::IntegerLiteralTooLarge(
+                        slice.to_string(),
+                        literal_type,
+                    )
+                    .at(source, span));
+                }
+
+                if let Ok(num) = slice.parse::<i64>() {
+                    tokens.push(Token::Integer(num));
+                } else if let Ok(num) = slice.parse::<f64>() {
+                    tokens.push(Token::Float(num));
+                } else if slice.len() >= 2 && slice.starts_with('"') && slice.ends_with('"') {
+                    // Only an unknown escape fails the string callback
+                    return Err(ParserError::InvalidEscape(slice.to_string()).at(source, span));
+                } else if slice == "true" || slice == "false" {
+                    tokens.push(Token::Boolean(slice == "true"));
+                } else if slice.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    tokens.push(Token::Identifier(slice.into()));
+                } else {
+                    return Err(
+                        ParserError::UnexpectedCharacter(slice.chars().next().unwrap())
+                            .at(source, span),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok((tokens, spans))
+}
+
+/// Lexes `source` keeping whitespace and comments, for tools such as the
+/// formatter that must reproduce them.
+pub fn lex_lossless(source: &str) -> Result<LosslessTokens, ParserError> {
+    let (tokens, spans) = lex(source)?;
+
+    let mut lexed = LosslessTokens::default();
+    let mut end = 0;
+
+    for (token, span) in tokens.into_iter().zip(spans) {
+        // Everything the lexer skipped between two tokens is trivia
+        lexed.tokens.push(LexedToken {
+            token,
+            text: source[span.range()].to_string(),
+            leading_trivia: split_trivia(&source[end..span.start]),
+            span,
+        });
+        end = span.end;
+    }
+
+    lexed.trailing_trivia = split_trivia(&source[end..]);
+
+    Ok(lexed)
+}
+
+fn split_trivia(mut gap: &str) -> Vec<Trivia> {
+    let mut trivia = Vec::new();
+
+    while !gap.is_empty() {
+        let (piece, rest) = if gap.starts_with("//") {
+            let len = gap.find('\n').unwrap_or(gap.len());
+            let (comment, rest) = gap.split_at(len);
+            (Trivia::LineComment(comment.to_string()), rest)
+        } else {
+            let len = gap.find("//").unwrap_or(gap.len());
+            let (whitespace, rest) = gap.split_at(len);
+            (Trivia::Whitespace(whitespace.to_string()), rest)
+        };
+
+        trivia.push(piece);
+        gap = rest;
+    }
+
+    trivia
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_exponents() {
+        let (tokens, _) = lex("1e9 2.5e-3 1.0E+6 1_0e1_0 4e2f32 1e").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Float(1e9),
+                Token::Float(2.5e-3),
+                Token::Float(1.0e6),
+                Token::Float(10e10),
+                Token::TypedFloat((400.0, Types::F32)),
+                Token::Integer(1),
+                Token::Identifier("e".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn char_literals() {
+        let (tokens, _) = lex(r"'a' '\n' '\'' '\u{1F600}' 'é'").unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Char('a'),
+                Token::Char('\n'),
+                Token::Char('\''),
+                Token::Char('😀'),
+                Token::Char('é'),
+            ]
+        );
+        assert!(lex(r"'\u{D800}'").is_err());
+        assert!(lex("'ab'").is_err());
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let (tokens, _) = lex("let x = 1; // one\n// two\nx").unwrap();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[5], Token::Identifier("x".into()));
+    }
+
+    #[test]
+    fn trivia_is_attached_to_the_next_token() {
+        let lexed = lex_lossless("let x = 1; // one\n\n// two\nx // end").unwrap();
+        let x = &lexed.tokens[5];
+
+        assert_eq!(
+            x.leading_trivia,
+            vec![
+                Trivia::Whitespace(" ".into()),
+                Trivia::LineComment("// one".into()),
+                Trivia::Whitespace("\n\n".into()),
+                Trivia::LineComment("// two".into()),
+                Trivia::Whitespace("\n".into()),
+            ]
+        );
+        assert_eq!(x.leading_trivia[2].newlines(), 2);
+        assert_eq!(
+            lexed.trailing_trivia,
+            vec![
+                Trivia::Whitespace(" ".into()),
+                Trivia::LineComment("// end".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let source = "  // header\nlet s = \"a // b\";\n\nprint(s); // done\n";
+
+        assert_eq!(lex_lossless(source).unwrap().to_source(), source);
+    }
+}
@@ -1,2 +1,7 @@
 pub mod errors;
+pub mod format;
+pub mod intrinsics;
+pub mod lexer;
 pub mod parser;
+pub mod scope;
+pub mod span;
@@ -5,102 +5,523 @@ use crate::parser::{
     ops::{BinaryOp, UnaryOp},
     types::Types,
 };
+use crate::span::Span;
 
+/// An expression, along with the [`Span`] of source it was parsed from.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
-    Literal(Nodes),
+    Literal(Nodes, Span),
+    /// A reference to a variable or parameter by name.
+    Variable {
+        name: String,
+        span: Span,
+    },
     Binary {
         left: Box<Expr>,
         operator: BinaryOp,
         right: Box<Expr>,
+        span: Span,
     },
     Unary {
         operator: UnaryOp,
         operand: Box<Expr>,
+        span: Span,
+    },
+    /// `value as i32`, converting between numeric types.
+    Cast {
+        value: Box<Expr>,
+        target: Types,
+        span: Span,
     },
     Assignment {
         identifier: String,
         value: Box<Expr>,
+        span: Span,
     },
+    /// `_ = value`, evaluating `value` only for its side effects.
+    Discard(Box<Expr>, Span),
     LetDeclaration {
         identifier: String,
+        /// Whether the binding was declared with `let mut` and may be assigned to.
+        mutable: bool,
         var_type: Option<Types>,
         value: Box<Expr>,
+        span: Span,
     },
     IfElse {
         condition: Box<Expr>,
         then_branch: Box<Expr>,
         else_branch: Option<Box<Expr>>,
+        span: Span,
+    },
+    For {
+        variable: String,
+        start: Box<Expr>,
+        end: Box<Expr>,
+        body: Box<Expr>,
+        /// How many times to unroll the loop, from `#[unroll(n)]`.
+        unroll: Option<u32>,
+        span: Span,
     },
-    Block(Vec<Expr>),
-    Print(Box<Expr>),
+    Block(Vec<Expr>, Span),
+    /// `value;` in a block, evaluated for its effects only. Its value is `()`.
+    Statement(Box<Expr>, Span),
+    Print(Box<Expr>, Span),
     MethodCall {
         target: Box<Expr>,
         method_name: String,
         arguments: Vec<Expr>,
+        span: Span,
+    },
+    SizeOf(Types, Span),
+    AlignOf(Types, Span),
+    Return(Option<Box<Expr>>, Span),
+    /// Leaves the innermost loop.
+    Break(Span),
+    /// Skips to the next iteration of the innermost loop.
+    Continue(Span),
+    FunctionDef {
+        /// Declared with `pub`, i.e. visible to other modules.
+        public: bool,
+        name: String,
+        parameters: Vec<Parameter>,
+        return_type: Option<Types>,
+        body: Box<Expr>,
+        /// Set by `#[on_start]` or `#[on_exit]`.
+        hook: Option<Hook>,
+        span: Span,
+    },
+    Call {
+        callee: String,
+        arguments: Vec<Expr>,
+        span: Span,
+    },
+    /// `[a, b, c]`
+    ArrayLiteral(Vec<Expr>, Span),
+    /// `target[index]`
+    Index {
+        target: Box<Expr>,
+        index: Box<Expr>,
+        span: Span,
+    },
+    /// `enum Color { Red, Green, Blue }`
+    EnumDef {
+        name: String,
+        variants: Vec<String>,
+        span: Span,
+    },
+    /// `Color::Red`
+    EnumVariant {
+        enum_name: String,
+        variant: String,
+        span: Span,
+    },
+    /// `match value { Color::Red => 1, _ => 0 }`
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+    /// `const MAX: i64 = 100`, only allowed at the top level.
+    ConstDef {
+        name: String,
+        const_type: Types,
+        value: Box<Expr>,
+        span: Span,
     },
 }
 
+impl Expr {
+    /// Where in the source the expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal(_, span)
+            | Expr::Discard(_, span)
+            | Expr::Block(_, span)
+            | Expr::Statement(_, span)
+            | Expr::Print(_, span)
+            | Expr::SizeOf(_, span)
+            | Expr::AlignOf(_, span)
+            | Expr::Return(_, span)
+            | Expr::Break(span)
+            | Expr::Continue(span)
+            | Expr::ArrayLiteral(_, span)
+            | Expr::Variable { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Cast { span, .. }
+            | Expr::Assignment { span, .. }
+            | Expr::LetDeclaration { span, .. }
+            | Expr::IfElse { span, .. }
+            | Expr::For { span, .. }
+            | Expr::MethodCall { span, .. }
+            | Expr::FunctionDef { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::EnumDef { span, .. }
+            | Expr::EnumVariant { span, .. }
+            | Expr::Match { span, .. }
+            | Expr::ConstDef { span, .. } => *span,
+        }
+    }
+
+    /// The expressions directly nested in this one, in source order.
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Literal(..)
+            | Expr::Variable { .. }
+            | Expr::SizeOf(..)
+            | Expr::AlignOf(..)
+            | Expr::Return(None, _)
+            | Expr::Break(_)
+            | Expr::Continue(_)
+            | Expr::EnumDef { .. }
+            | Expr::EnumVariant { .. } => Vec::new(),
+            Expr::Binary { left, right, .. } => vec![left, right],
+            Expr::Unary { operand: value, .. }
+            | Expr::Cast { value, .. }
+            | Expr::Assignment { value, .. }
+            | Expr::Discard(value, _)
+            | Expr::Statement(value, _)
+            | Expr::LetDeclaration { value, .. }
+            | Expr::Print(value, _)
+            | Expr::Return(Some(value), _)
+            | Expr::FunctionDef { body: value, .. }
+            | Expr::ConstDef { value, .. } => vec![value],
+            Expr::IfElse {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut children = vec![condition.as_ref(), then_branch];
+                children.extend(else_branch.as_deref());
+                children
+            }
+            Expr::For {
+                start, end, body, ..
+            } => vec![start, end, body],
+            Expr::Block(exprs, _)
+            | Expr::ArrayLiteral(exprs, _)
+            | Expr::Call {
+                arguments: exprs, ..
+            } => exprs.iter().collect(),
+            Expr::MethodCall {
+                target, arguments, ..
+            } => std::iter::once(target.as_ref()).chain(arguments).collect(),
+            Expr::Index { target, index, .. } => vec![target, index],
+            Expr::Match {
+                scrutinee, arms, ..
+            } => std::iter::once(scrutinee.as_ref())
+                .chain(arms.iter().map(|arm| &arm.body))
+                .collect(),
+        }
+    }
+
+    /// Whether the expression can be evaluated at compile time: literals,
+    /// `size_of` and `align_of`, enum variants and other constants, combined
+    /// with operators and casts. `is_const` tells if a name refers to a
+    /// constant.
+    pub fn is_constant(&self, is_const: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Expr::Literal(..) | Expr::SizeOf(..) | Expr::AlignOf(..) | Expr::EnumVariant { .. } => {
+                true
+            }
+            Expr::Variable { name, .. } => is_const(name),
+            Expr::Unary { operand, .. } => operand.is_constant(is_const),
+            Expr::Cast { value, .. } => value.is_constant(is_const),
+            Expr::Binary { left, right, .. } => {
+                left.is_constant(is_const) && right.is_constant(is_const)
+            }
+            _ => false,
+        }
+    }
+
+    /// The value of an integer literal without a suffix, or of one negated
+    /// with `-`. These take the type they are stored as, so only they can
+    /// be out of range for it.
+    pub fn integer_literal(&self) -> Option<i64> {
+        match self {
+            Expr::Literal(Nodes::Integer(value), _) => Some(*value),
+            Expr::Unary {
+                operator: UnaryOp::Minus,
+                operand,
+                ..
+            } => match operand.as_ref() {
+                Expr::Literal(Nodes::Integer(value), _) => Some(value.wrapping_neg()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// When a function runs without being called, see [`Expr::FunctionDef`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hook {
+    /// `#[on_start]`: before the first top-level statement, in order of
+    /// definition
+    OnStart,
+    /// `#[on_exit]`: when the program exits, in reverse order of definition
+    OnExit,
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Hook::OnStart => write!(f, "on_start"),
+            Hook::OnExit => write!(f, "on_exit"),
+        }
+    }
+}
+
+/// A typed parameter of a function definition, `name: Type`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub param_type: Types,
+}
+
+/// One `pattern => body` arm of a `match`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Expr,
+}
+
+/// What a match arm compares the value against.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `Color::Red`
+    Variant { enum_name: String, variant: String },
+    /// `_`, matching anything
+    Wildcard,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Variant { enum_name, variant } => write!(f, "{}::{}", enum_name, variant),
+            Pattern::Wildcard => write!(f, "_"),
+        }
+    }
+}
+
+/// Renders the expression as Rune source that parses back to the same tree.
 impl fmt::Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expr::Literal(node) => write!(f, "{:?}", node),
+            Expr::Literal(node, _) => write!(f, "{}", node),
+            Expr::Variable { name, .. } => write!(f, "{}", name),
             Expr::Binary {
                 left,
                 operator,
                 right,
-            } => write!(f, "({} {:?} {})", left, operator, right),
-            Expr::Unary { operator, operand } => {
-                write!(f, "{:?}{}", operator, operand)
+                ..
+            } => write!(
+                f,
+                "{} {} {}",
+                Operand(left, operator.precedence()),
+                operator,
+                Operand(right, operator.precedence() + 1)
+            ),
+            Expr::Unary {
+                operator, operand, ..
+            } => {
+                write!(f, "{}{}", operator, Operand(operand, u8::MAX))
+            }
+            Expr::Cast { value, target, .. } => {
+                write!(f, "{} as {}", Operand(value, CAST_PRECEDENCE), target)
             }
-            Expr::Assignment { identifier, value } => {
+            Expr::Assignment {
+                identifier, value, ..
+            } => {
                 write!(f, "{} = {}", identifier, value)
             }
+            Expr::Discard(value, _) => write!(f, "_ = {}", value),
             Expr::LetDeclaration {
                 identifier,
+                mutable,
                 value,
                 var_type,
+                ..
             } => {
-                write!(f, "let {}: {:?} = {}", identifier, var_type, value)
+                write!(f, "let ")?;
+                if *mutable {
+                    write!(f, "mut ")?;
+                }
+                write!(f, "{}", identifier)?;
+                if let Some(var_type) = var_type {
+                    write!(f, ": {}", var_type)?;
+                }
+                write!(f, " = {}", value)
             }
             Expr::IfElse {
                 condition,
                 then_branch,
                 else_branch,
-            } => write!(
-                f,
-                "if {} {{ {} }} else {{ {} }}",
-                condition,
-                then_branch,
-                else_branch
-                    .as_ref()
-                    .map_or("".to_string(), |e| e.to_string())
-            ),
-            Expr::Block(exprs) => write!(
-                f,
-                "{{ {} }}",
-                exprs
-                    .iter()
-                    .map(|e| e.to_string())
-                    .collect::<Vec<String>>()
-                    .join("; ")
-            ),
-            Expr::Print(expr) => write!(f, "print {}", expr),
+                ..
+            } => {
+                write!(f, "if {} {}", condition, Braced(then_branch))?;
+                match else_branch {
+                    Some(else_branch) => write!(f, " else {}", Braced(else_branch)),
+                    None => Ok(()),
+                }
+            }
+            Expr::For {
+                variable,
+                start,
+                end,
+                body,
+                unroll,
+                ..
+            } => {
+                if let Some(count) = unroll {
+                    write!(f, "#[unroll({})] ", count)?;
+                }
+                write!(f, "for {} in {}..{} {}", variable, start, end, Braced(body))
+            }
+            Expr::Block(exprs, _) if exprs.is_empty() => write!(f, "{{}}"),
+            Expr::Block(exprs, _) => write!(f, "{{ {} }}", comma_separated(exprs, " ")),
+            Expr::Statement(value, _) => write!(f, "{};", value),
+            Expr::Print(expr, _) => write!(f, "print({})", expr),
             Expr::MethodCall {
                 target,
                 method_name,
                 arguments,
+                ..
             } => write!(
                 f,
                 "{}.{}({})",
-                target,
+                Operand(target, u8::MAX),
                 method_name,
-                arguments
+                comma_separated(arguments, ", ")
+            ),
+            Expr::SizeOf(query_type, _) => write!(f, "size_of({})", query_type),
+            Expr::AlignOf(query_type, _) => write!(f, "align_of({})", query_type),
+            Expr::Return(Some(value), _) => write!(f, "return {}", value),
+            Expr::Return(None, _) => write!(f, "return"),
+            Expr::Break(_) => write!(f, "break"),
+            Expr::Continue(_) => write!(f, "continue"),
+            Expr::FunctionDef {
+                public,
+                name,
+                parameters,
+                return_type,
+                body,
+                hook,
+                ..
+            } => {
+                if let Some(hook) = hook {
+                    write!(f, "#[{}] ", hook)?;
+                }
+                if *public {
+                    write!(f, "pub ")?;
+                }
+
+                let parameters = parameters
                     .iter()
-                    .map(|e| e.to_string())
+                    .map(|parameter| format!("{}: {}", parameter.name, parameter.param_type))
                     .collect::<Vec<String>>()
-                    .join(", ")
-            ),
+                    .join(", ");
+                match return_type {
+                    Some(return_type) => write!(
+                        f,
+                        "fn {}({}) -> {} {}",
+                        name,
+                        parameters,
+                        return_type,
+                        Braced(body)
+                    ),
+                    None => write!(f, "fn {}({}) {}", name, parameters, Braced(body)),
+                }
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                write!(f, "{}({})", callee, comma_separated(arguments, ", "))
+            }
+            Expr::ArrayLiteral(elements, _) => {
+                write!(f, "[{}]", comma_separated(elements, ", "))
+            }
+            Expr::Index { target, index, .. } => {
+                write!(f, "{}[{}]", Operand(target, u8::MAX), index)
+            }
+            Expr::EnumDef { name, variants, .. } if variants.is_empty() => {
+                write!(f, "enum {} {{}}", name)
+            }
+            Expr::EnumDef { name, variants, .. } => {
+                write!(f, "enum {} {{ {} }}", name, variants.join(", "))
+            }
+            Expr::EnumVariant {
+                enum_name, variant, ..
+            } => write!(f, "{}::{}", enum_name, variant),
+            Expr::Match {
+                scrutinee, arms, ..
+            } => {
+                let arms = arms
+                    .iter()
+                    .map(|arm| format!("{} => {}", arm.pattern, arm.body))
+                    .collect::<Vec<String>>();
+                write!(f, "match {} {{ {} }}", scrutinee, arms.join(", "))
+            }
+            Expr::ConstDef {
+                name,
+                const_type,
+                value,
+                ..
+            } => write!(f, "const {}: {} = {}", name, const_type, value),
+        }
+    }
+}
+
+fn comma_separated(exprs: &[Expr], separator: &str) -> String {
+    exprs
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// How tightly `as` binds: looser than unary operators and indexing, but
+/// tighter than any binary operator.
+const CAST_PRECEDENCE: u8 = u8::MAX - 1;
+
+/// An operand of an operator, parenthesized unless it binds at least as
+/// tightly as the given precedence.
+struct Operand<'a>(&'a Expr, u8);
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Expr::Binary { operator, .. } if operator.precedence() >= self.1 => {
+                write!(f, "{}", self.0)
+            }
+            Expr::Cast { .. } if CAST_PRECEDENCE >= self.1 => write!(f, "{}", self.0),
+            Expr::Unary { .. }
+            | Expr::Literal(..)
+            | Expr::Variable { .. }
+            | Expr::Call { .. }
+            | Expr::MethodCall { .. }
+            | Expr::ArrayLiteral(..)
+            | Expr::Index { .. }
+            | Expr::EnumVariant { .. }
+            | Expr::SizeOf(..)
+            | Expr::AlignOf(..) => write!(f, "{}", self.0),
+            expr => write!(f, "({})", expr),
+        }
+    }
+}
+
+/// A body that must be written as a block, `if` and `for` bodies hold their
+/// statements directly.
+struct Braced<'a>(&'a Expr);
+
+impl fmt::Display for Braced<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Expr::Block(..) => write!(f, "{}", self.0),
+            expr => write!(f, "{{ {} }}", expr),
         }
     }
 }
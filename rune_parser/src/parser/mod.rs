@@ -1,64 +1,83 @@
 pub mod expr;
+pub mod node_id;
 pub mod nodes;
 pub mod ops;
 pub mod tokens;
+pub mod trace;
 pub mod types;
 
 use crate::errors::ParserError;
-use crate::parser::expr::Expr;
+use crate::lexer::lex;
+use crate::parser::expr::{Expr, Hook, MatchArm, Parameter, Pattern};
+use crate::parser::node_id::{NodeId, assign_ids};
 use crate::parser::nodes::Nodes;
 use crate::parser::ops::{BinaryOp, UnaryOp};
 use crate::parser::tokens::Token;
+use crate::parser::trace::{ParseTrace, RuleGuard, SharedTrace};
 use crate::parser::types::Types;
-use logos::Logos;
+use crate::span::Span;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parser {
+    source: String,
     tokens: Vec<Token>,
+    spans: Vec<Span>,
+    statement_spans: Vec<Span>,
+    statement_ids: Vec<NodeId>,
     current: usize,
+    /// Set by [`Parser::enable_trace`]. Clones of the parser share it.
+    trace: Option<SharedTrace>,
 }
 
 impl Parser {
     pub fn new(input: String) -> Result<Self, ParserError> {
-        let mut lexer = Token::lexer(&input);
-        let mut tokens = Vec::new();
+        let (tokens, spans) = lex(&input)?;
+
+        Ok(Parser {
+            source: input,
+            tokens,
+            spans,
+            statement_spans: Vec::new(),
+            statement_ids: Vec::new(),
+            current: 0,
+            trace: None,
+        })
+    }
 
-        while let Some(token) = lexer.next() {
-            match token {
-                Ok(t) => tokens.push(t),
-                Err(_) => {
-                    let slice = lexer.slice();
-                    if let Ok(num) = slice.parse::<i64>() {
-                        tokens.push(Token::Integer(num));
-                    } else if let Ok(num) = slice.parse::<f64>() {
-                        tokens.push(Token::Float(num));
-                    } else if slice.starts_with('"') && slice.ends_with('"') {
-                        let string_content = slice[1..slice.len() - 1].into();
-                        tokens.push(Token::String(string_content));
-                    } else if slice == "true" || slice == "false" {
-                        tokens.push(Token::Boolean(slice == "true"));
-                    } else if slice.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                        tokens.push(Token::Identifier(slice.into()));
-                    } else {
-                        return Err(ParserError::UnexpectedCharacter(
-                            slice.chars().next().unwrap(),
-                        ));
-                    }
-                }
-            }
-        }
+    /// Records a [`ParseTrace`] of the following calls to [`Parser::parse`].
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(SharedTrace::default());
+    }
+
+    /// The trace recorded so far, if [`Parser::enable_trace`] was called.
+    pub fn trace(&self) -> Option<ParseTrace> {
+        self.trace.as_ref().map(|trace| trace.borrow().clone())
+    }
+
+    pub fn token_count(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Spans of each top-level statement returned by the last call to
+    /// [`Parser::parse`], excluding any trailing `;`.
+    pub fn statement_spans(&self) -> &[Span] {
+        &self.statement_spans
+    }
 
-        Ok(Parser { tokens, current: 0 })
+    /// Stable ids of the top-level statements returned by the last call to
+    /// [`Parser::parse`].
+    pub fn statement_ids(&self) -> &[NodeId] {
+        &self.statement_ids
     }
 }
 
 impl Parser {
     fn match_token(&mut self, expected: &Token) -> bool {
-        if let Some(token) = self.peek() {
-            if std::mem::discriminant(token) == std::mem::discriminant(expected) {
-                self.advance();
-                return true;
-            }
+        if let Some(token) = self.peek()
+            && std::mem::discriminant(token) == std::mem::discriminant(expected)
+        {
+            self.advance();
+            return true;
         }
         false
     }
@@ -69,11 +88,33 @@ impl Parser {
 
     fn advance(&mut self) -> Option<&Token> {
         if !self.is_at_end() {
+            if let Some(trace) = &self.trace {
+                let event = format!("consume {}", self.describe_current());
+                trace.borrow_mut().push(event);
+            }
             self.current += 1;
         }
         self.previous()
     }
 
+    /// Logs `rule` in the trace until the returned guard is dropped.
+    fn enter(&self, rule: &str) -> RuleGuard {
+        RuleGuard::enter(&self.trace, || {
+            format!("{} at {}", rule, self.describe_current())
+        })
+    }
+
+    /// The current token as written in the source, and where.
+    fn describe_current(&self) -> String {
+        match self.spans.get(self.current) {
+            Some(span) => {
+                let (line, column) = span.line_column(&self.source);
+                format!("`{}` {}:{}", &self.source[span.range()], line, column)
+            }
+            None => "end of input".to_string(),
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.tokens.len()
     }
@@ -85,63 +126,196 @@ impl Parser {
             None
         }
     }
+
+    /// Span of the current token, or an empty one at the end of the source.
+    fn current_span(&self) -> Span {
+        let end = Span::new(self.source.len(), self.source.len());
+        self.spans.get(self.current).copied().unwrap_or(end)
+    }
+
+    /// Span of the last token consumed.
+    fn previous_span(&self) -> Span {
+        self.spans[self.current - 1]
+    }
+
+    /// The span from `start` to the end of the last token consumed.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous_span())
+    }
 }
 
 impl Parser {
     pub fn parse(&mut self) -> Result<Vec<Expr>, ParserError> {
         let mut statements = Vec::new();
+        self.statement_spans.clear();
 
         loop {
             if self.is_at_end() {
                 break;
             }
-            statements.push(self.statement()?);
+
+            let start = self.spans[self.current];
+            let expr = match self.peek() {
+                Some(Token::KeywordConst) => self.const_definition(),
+                Some(Token::Hash) => self.attributed(true),
+                _ => self.expression(),
+            };
+            let expr = expr.map_err(|error| {
+                let error = self.locate(error);
+                if let Some(trace) = &self.trace {
+                    trace.borrow_mut().push(format!("error {}", error));
+                }
+                error
+            })?;
+            let end = self.previous_span();
+
+            // Consume `;`
+            self.match_token(&Token::Semicolon);
+
+            statements.push(expr);
+            self.statement_spans.push(start.to(end));
         }
 
+        self.statement_ids = assign_ids(&statements);
+
         Ok(statements)
     }
 
+    /// Locates `error` at the token the parser stopped at, or at the end
+    /// of the source if it ran out of tokens.
+    fn locate(&self, error: ParserError) -> ParserError {
+        error.at(&self.source, self.current_span())
+    }
+
+    /// A statement in a block. One ending in `;` is kept as an
+    /// [`Expr::Statement`], since its value is `()`.
     fn statement(&mut self) -> Result<Expr, ParserError> {
         let expr = self.expression()?;
 
-        // Consume `;`
-        self.match_token(&Token::Semicolon);
+        if self.match_token(&Token::Semicolon) {
+            let span = self.span_from(expr.span());
+            return Ok(Expr::Statement(Box::new(expr), span));
+        }
 
         Ok(expr)
     }
 
     fn expression(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("expression");
         if let Some(Token::KeywordIf) = self.peek() {
             return self.if_else();
         }
+        if let Some(Token::KeywordFn | Token::KeywordPub) = self.peek() {
+            return self.function_definition(self.current_span(), None);
+        }
+        if let Some(Token::KeywordFor) = self.peek() {
+            return self.for_loop(self.current_span(), None);
+        }
+        if let Some(Token::Hash) = self.peek() {
+            return self.attributed(false);
+        }
         if let Some(Token::KeywordPrint) = self.peek() {
             return self.print();
         }
+        if let Some(Token::KeywordReturn) = self.peek() {
+            return self.return_statement();
+        }
+        if self.match_token(&Token::KeywordBreak) {
+            return Ok(Expr::Break(self.previous_span()));
+        }
+        if self.match_token(&Token::KeywordContinue) {
+            return Ok(Expr::Continue(self.previous_span()));
+        }
+        if let Some(Token::KeywordEnum) = self.peek() {
+            return self.enum_definition();
+        }
+        // Top-level constants are parsed by `parse`
+        if let Some(Token::KeywordConst) = self.peek() {
+            return Err(ParserError::NotAtTopLevel("const".into()));
+        }
         self.assignment()
     }
 
+    fn return_statement(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("return_statement");
+        let start = self.current_span();
+        self.advance(); // consume `return`
+
+        let value = match self.peek() {
+            None | Some(Token::Semicolon) | Some(Token::RightBrace) => None,
+            Some(_) => Some(Box::new(self.or()?)),
+        };
+        Ok(Expr::Return(value, self.span_from(start)))
+    }
+
     fn primary(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("primary");
         if let Some(token) = self.peek().cloned() {
+            let span = self.current_span();
             match token {
                 Token::Integer(value) => {
                     self.advance();
-                    Ok(Expr::Literal(Nodes::Integer(value)))
+                    Ok(Expr::Literal(Nodes::Integer(value), span))
                 }
                 Token::Float(value) => {
                     self.advance();
-                    Ok(Expr::Literal(Nodes::Float(value)))
+                    Ok(Expr::Literal(Nodes::Float(value), span))
+                }
+                Token::TypedInteger((value, literal_type)) => {
+                    self.advance();
+                    Ok(Expr::Literal(
+                        Nodes::TypedInteger(value, literal_type),
+                        span,
+                    ))
+                }
+                Token::TypedFloat((value, literal_type)) => {
+                    self.advance();
+                    Ok(Expr::Literal(Nodes::TypedFloat(value, literal_type), span))
                 }
                 Token::String(value) => {
                     self.advance();
-                    Ok(Expr::Literal(Nodes::String(value)))
+
+                    // Adjacent literals are joined: `"foo" "bar"` is `"foobar"`
+                    let mut value = value;
+                    while let Some(Token::String(next)) = self.peek() {
+                        value.push_str(next);
+                        self.advance();
+                    }
+
+                    Ok(Expr::Literal(Nodes::String(value), self.span_from(span)))
                 }
                 Token::Boolean(value) => {
                     self.advance();
-                    Ok(Expr::Literal(Nodes::Boolean(value)))
+                    Ok(Expr::Literal(Nodes::Boolean(value), span))
+                }
+                Token::Char(value) => {
+                    self.advance();
+                    Ok(Expr::Literal(Nodes::Char(value), span))
                 }
                 Token::Identifier(name) => {
                     self.advance();
-                    Ok(Expr::Literal(Nodes::Identifier(name)))
+                    if matches!(name.as_str(), "size_of" | "align_of")
+                        && self.peek() == Some(&Token::LeftParen)
+                    {
+                        return self.type_query(&name);
+                    }
+                    if self.match_token(&Token::LeftParen) {
+                        let arguments = self.argument_list(&name)?;
+                        return Ok(Expr::Call {
+                            callee: name,
+                            arguments,
+                            span: self.span_from(span),
+                        });
+                    }
+                    if self.match_token(&Token::ColonColon) {
+                        let variant = self.variant_name(&name)?;
+                        return Ok(Expr::EnumVariant {
+                            enum_name: name,
+                            variant,
+                            span: self.span_from(span),
+                        });
+                    }
+                    Ok(Expr::Variable { name, span })
                 }
                 Token::LeftParen => {
                     self.advance(); // consume `(`
@@ -163,7 +337,28 @@ impl Parser {
                         return Err(ParserError::ExpectedAfter("}".into(), "block".into()));
                     }
 
-                    Ok(Expr::Block(statements))
+                    Ok(Expr::Block(statements, self.span_from(span)))
+                }
+                Token::KeywordMatch => self.match_expression(),
+                Token::KeywordIf => self.if_else(),
+                Token::LeftBracket => {
+                    self.advance(); // consume `[`
+                    let mut elements = Vec::new();
+
+                    while !self.match_token(&Token::RightBracket) {
+                        elements.push(self.expression()?);
+
+                        if !self.match_token(&Token::Comma)
+                            && self.peek() != Some(&Token::RightBracket)
+                        {
+                            return Err(ParserError::ExpectedAfter(
+                                "]".into(),
+                                "array elements".into(),
+                            ));
+                        }
+                    }
+
+                    Ok(Expr::ArrayLiteral(elements, self.span_from(span)))
                 }
 
                 _ => Err(ParserError::UnexpectedToken(format!("{:?}", token))),
@@ -176,14 +371,17 @@ impl Parser {
 
 impl Parser {
     fn term(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("term");
         let mut expr = self.factor()?;
 
         while let Some(op) = self.match_term_op() {
             let right = self.factor()?;
+            let span = expr.span().to(right.span());
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator: op,
                 right: Box::new(right),
+                span,
             };
         }
 
@@ -191,14 +389,36 @@ impl Parser {
     }
 
     fn factor(&mut self) -> Result<Expr, ParserError> {
-        let mut expr = self.unary()?;
+        let _rule = self.enter("factor");
+        let mut expr = self.cast()?;
 
         while let Some(op) = self.match_factor_op() {
-            let right = self.unary()?;
+            let right = self.cast()?;
+            let span = expr.span().to(right.span());
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator: op,
                 right: Box::new(right),
+                span,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// A unary expression followed by any number of `as Type` suffixes,
+    /// `-x as f64` being `(-x) as f64`.
+    fn cast(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("cast");
+        let mut expr = self.unary()?;
+
+        while self.match_token(&Token::KeywordAs) {
+            let target = self.parse_type()?;
+            let span = self.span_from(expr.span());
+            expr = Expr::Cast {
+                value: Box::new(expr),
+                target,
+                span,
             };
         }
 
@@ -206,28 +426,55 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("unary");
+        let start = self.current_span();
         if let Some(op) = self.match_unary_op() {
             let expr = self.unary()?;
             return Ok(Expr::Unary {
                 operator: op,
                 operand: Box::new(expr),
+                span: self.span_from(start),
             });
         }
 
-        self.primary()
+        self.index()
+    }
+
+    /// A primary followed by any number of `[index]` suffixes.
+    fn index(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("index");
+        let mut expr = self.primary()?;
+
+        while self.match_token(&Token::LeftBracket) {
+            let index = self.expression()?;
+            if !self.match_token(&Token::RightBracket) {
+                return Err(ParserError::ExpectedAfter("]".into(), "index".into()));
+            }
+            let span = self.span_from(expr.span());
+            expr = Expr::Index {
+                target: Box::new(expr),
+                index: Box::new(index),
+                span,
+            };
+        }
+
+        Ok(expr)
     }
 }
 
 impl Parser {
     fn or(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("or");
         let mut expr = self.and()?;
 
         while self.match_token(&Token::Or) {
             let right = self.and()?;
+            let span = expr.span().to(right.span());
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator: BinaryOp::Or,
                 right: Box::new(right),
+                span,
             };
         }
 
@@ -235,14 +482,17 @@ impl Parser {
     }
 
     fn and(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("and");
         let mut expr = self.equality()?;
 
         while self.match_token(&Token::And) {
             let right = self.equality()?;
+            let span = expr.span().to(right.span());
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator: BinaryOp::And,
                 right: Box::new(right),
+                span,
             };
         }
 
@@ -250,14 +500,17 @@ impl Parser {
     }
 
     fn equality(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("equality");
         let mut expr = self.comparison()?;
 
         while let Some(op) = self.match_equality_op() {
             let right = self.comparison()?;
+            let span = expr.span().to(right.span());
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator: op,
                 right: Box::new(right),
+                span,
             };
         }
 
@@ -265,14 +518,17 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("comparison");
         let mut expr = self.term()?;
 
         while let Some(op) = self.match_comparison_op() {
             let right = self.term()?;
+            let span = expr.span().to(right.span());
             expr = Expr::Binary {
                 left: Box::new(expr),
                 operator: op,
                 right: Box::new(right),
+                span,
             };
         }
 
@@ -327,11 +583,29 @@ impl Parser {
         }
     }
 
+    fn match_compound_assignment_op(&mut self) -> Option<BinaryOp> {
+        if self.match_token(&Token::PlusEquals) {
+            Some(BinaryOp::Add)
+        } else if self.match_token(&Token::MinusEquals) {
+            Some(BinaryOp::Subtract)
+        } else if self.match_token(&Token::StarEquals) {
+            Some(BinaryOp::Multiply)
+        } else if self.match_token(&Token::SlashEquals) {
+            Some(BinaryOp::Divide)
+        } else if self.match_token(&Token::PercentEquals) {
+            Some(BinaryOp::Modulo)
+        } else {
+            None
+        }
+    }
+
     fn match_unary_op(&mut self) -> Option<UnaryOp> {
         if self.match_token(&Token::Minus) {
             Some(UnaryOp::Minus)
         } else if self.match_token(&Token::Bang) {
             Some(UnaryOp::Not)
+        } else if self.match_token(&Token::Tilde) {
+            Some(UnaryOp::BitNot)
         } else {
             None
         }
@@ -339,7 +613,28 @@ impl Parser {
 }
 
 impl Parser {
+    /// `size_of(Type)` or `align_of(Type)`, with the name already consumed.
+    fn type_query(&mut self, name: &str) -> Result<Expr, ParserError> {
+        let _rule = self.enter("type_query");
+        let start = self.previous_span();
+        self.advance(); // consume `(`
+
+        let query_type = self.parse_type()?;
+
+        if !self.match_token(&Token::RightParen) {
+            return Err(ParserError::ExpectedAfter(")".into(), name.into()));
+        }
+
+        let span = self.span_from(start);
+        if name == "size_of" {
+            Ok(Expr::SizeOf(query_type, span))
+        } else {
+            Ok(Expr::AlignOf(query_type, span))
+        }
+    }
+
     fn parse_type(&mut self) -> Result<Types, ParserError> {
+        let _rule = self.enter("parse_type");
         if let Some(token) = self.peek().cloned() {
             match token {
                 Token::Identifier(type_name) => {
@@ -351,10 +646,8 @@ impl Parser {
                         "f32" => Ok(Types::F32),
                         "f64" => Ok(Types::F64),
                         "String" => Ok(Types::String),
-                        _ => Err(ParserError::UnexpectedToken(format!(
-                            "unknown type: {}",
-                            type_name
-                        ))),
+                        // Whether the enum exists is up to the checker
+                        _ => Ok(Types::Enum(type_name)),
                     }
                 }
                 Token::TypeI32 => {
@@ -381,6 +674,40 @@ impl Parser {
                     self.advance();
                     Ok(Types::String)
                 }
+                Token::TypeChar => {
+                    self.advance();
+                    Ok(Types::Char)
+                }
+                Token::LeftBracket => {
+                    self.advance(); // consume `[`
+                    let element = self.parse_type()?;
+
+                    if !self.match_token(&Token::Semicolon) {
+                        return Err(ParserError::ExpectedAfter(
+                            ";".into(),
+                            "array element type".into(),
+                        ));
+                    }
+
+                    let length = match self.advance().cloned() {
+                        Some(Token::Integer(length)) if length >= 0 => length as usize,
+                        _ => {
+                            return Err(ParserError::ExpectedAfter(
+                                "array length".into(),
+                                ";".into(),
+                            ));
+                        }
+                    };
+
+                    if !self.match_token(&Token::RightBracket) {
+                        return Err(ParserError::ExpectedAfter(
+                            "]".into(),
+                            "array length".into(),
+                        ));
+                    }
+
+                    Ok(Types::Array(Box::new(element), length))
+                }
                 _ => {
                     dbg!(self.peek().cloned());
                     Err(ParserError::ExpectedToken("type".into()))
@@ -393,8 +720,12 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("assignment");
         // Check for `let`
+        let start = self.current_span();
         if self.match_token(&Token::KeywordLet) {
+            let mutable = self.match_token(&Token::KeywordMut);
+
             if let Some(Token::Identifier(name)) = self.peek().cloned() {
                 self.advance(); // consume identifier
 
@@ -416,8 +747,10 @@ impl Parser {
                 let value = self.assignment()?;
                 return Ok(Expr::LetDeclaration {
                     identifier: name,
+                    mutable,
                     var_type,
                     value: Box::new(value),
+                    span: self.span_from(start),
                 });
             } else {
                 return Err(ParserError::ExpectedAfter(
@@ -430,11 +763,16 @@ impl Parser {
         let expr = self.or()?;
 
         if self.match_token(&Token::Equals) {
-            if let Expr::Literal(Nodes::Identifier(name)) = expr {
+            if let Expr::Variable { name, .. } = expr {
                 let value = self.assignment()?;
+                let span = self.span_from(start);
+                if name == "_" {
+                    return Ok(Expr::Discard(Box::new(value), span));
+                }
                 return Ok(Expr::Assignment {
                     identifier: name,
                     value: Box::new(value),
+                    span,
                 });
             }
             return Err(ParserError::InvalidAssignment(
@@ -442,12 +780,38 @@ impl Parser {
             ));
         }
 
+        // `x += v` is sugar for `x = x + v`, left for codegen to spot
+        if let Some(operator) = self.match_compound_assignment_op() {
+            if let Expr::Variable { name, .. } = &expr
+                && name != "_"
+            {
+                let value = self.assignment()?;
+                let span = self.span_from(start);
+                return Ok(Expr::Assignment {
+                    identifier: name.clone(),
+                    value: Box::new(Expr::Binary {
+                        left: Box::new(expr),
+                        operator,
+                        right: Box::new(value),
+                        span,
+                    }),
+                    span,
+                });
+            }
+            return Err(ParserError::InvalidAssignment(format!(
+                "`{}=`, target must be an identifier",
+                operator
+            )));
+        }
+
         Ok(expr)
     }
 }
 
 impl Parser {
     fn if_else(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("if_else");
+        let start = self.current_span();
         if !self.match_token(&Token::KeywordIf) {
             return Err(ParserError::ExpectedToken("if".into()));
         }
@@ -455,6 +819,7 @@ impl Parser {
         let condition_expr = self.expression()?;
         let condition = Box::new(condition_expr);
 
+        let then_start = self.current_span();
         if !self.match_token(&Token::LeftBrace) {
             return Err(ParserError::ExpectedAfter(
                 "{".into(),
@@ -471,9 +836,10 @@ impl Parser {
             return Err(ParserError::ExpectedAfter("}".into(), "if-block".into()));
         }
 
-        let then_branch = Expr::Block(then_statements);
+        let then_branch = Expr::Block(then_statements, self.span_from(then_start));
 
         let else_branch = if self.match_token(&Token::KeywordElse) {
+            let else_start = self.current_span();
             if !self.match_token(&Token::LeftBrace) {
                 return Err(ParserError::ExpectedAfter("{".into(), "else".into()));
             }
@@ -487,7 +853,10 @@ impl Parser {
                 return Err(ParserError::ExpectedAfter("}".into(), "else-block".into()));
             }
 
-            Some(Box::new(Expr::Block(else_statements)))
+            Some(Box::new(Expr::Block(
+                else_statements,
+                self.span_from(else_start),
+            )))
         } else {
             None
         };
@@ -496,139 +865,1397 @@ impl Parser {
             condition,
             then_branch: Box::new(then_branch),
             else_branch,
+            span: self.span_from(start),
         })
     }
 }
 
 impl Parser {
-    fn print(&mut self) -> Result<Expr, ParserError> {
-        if self.match_token(&Token::KeywordPrint) {
-            if let Some(Token::LeftParen) = self.peek().cloned() {
-                self.advance(); // consume `(`
+    /// A function definition starting at `start`, which is before any
+    /// attribute setting its `hook`.
+    fn function_definition(
+        &mut self,
+        start: Span,
+        hook: Option<Hook>,
+    ) -> Result<Expr, ParserError> {
+        let _rule = self.enter("function_definition");
+        let public = self.match_token(&Token::KeywordPub);
+
+        if !self.match_token(&Token::KeywordFn) {
+            return Err(match public {
+                true => ParserError::ExpectedAfter("fn".into(), "pub".into()),
+                false => ParserError::ExpectedToken("fn".into()),
+            });
+        }
+
+        let name = match self.advance().cloned() {
+            Some(Token::Identifier(name)) => name,
+            _ => {
+                return Err(ParserError::ExpectedAfter(
+                    "function name".into(),
+                    "fn".into(),
+                ));
+            }
+        };
 
-                let expr = self.or()?;
+        if !self.match_token(&Token::LeftParen) {
+            return Err(ParserError::ExpectedAfter("(".into(), name));
+        }
 
-                if let Some(Token::RightParen) = self.peek().cloned() {
-                    self.advance(); // consume `)`
-                } else {
-                    return Err(ParserError::ExpectedAfterCustom(
-                        ")".into(),
-                        "print".into(),
-                        "expression".into(),
-                    ));
-                }
+        let parameters = self.parameter_list(&name)?;
 
-                Ok(Expr::Print(Box::new(expr)))
-            } else {
-                Err(ParserError::ExpectedAfter("(".into(), "print".into()))
-            }
+        let return_type = if self.match_token(&Token::Arrow) {
+            Some(self.parse_type()?)
         } else {
-            Err(ParserError::ExpectedAfter(
-                "print".into(),
-                "statement".into(),
-            ))
+            None
+        };
+
+        let body_start = self.current_span();
+        if !self.match_token(&Token::LeftBrace) {
+            return Err(ParserError::ExpectedAfter(
+                "{".into(),
+                "function signature".into(),
+            ));
+        }
+
+        let mut body_statements = Vec::new();
+        while !self.match_token(&Token::RightBrace) && !self.is_at_end() {
+            body_statements.push(self.statement()?);
+        }
+
+        if self.previous() != Some(&Token::RightBrace) {
+            return Err(ParserError::ExpectedAfter(
+                "}".into(),
+                "function body".into(),
+            ));
         }
+
+        Ok(Expr::FunctionDef {
+            public,
+            name,
+            parameters,
+            return_type,
+            body: Box::new(Expr::Block(body_statements, self.span_from(body_start))),
+            hook,
+            span: self.span_from(start),
+        })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Parses `name: Type` pairs up to and including the closing `)`, the
+    /// opening `(` has already been consumed.
+    fn parameter_list(&mut self, function: &str) -> Result<Vec<Parameter>, ParserError> {
+        let _rule = self.enter("parameter_list");
+        let mut parameters = Vec::new();
+
+        while !self.match_token(&Token::RightParen) {
+            let Some(Token::Identifier(name)) = self.advance().cloned() else {
+                return Err(ParserError::ExpectedAfterCustom(
+                    ")".into(),
+                    function.into(),
+                    "parameters".into(),
+                ));
+            };
 
-    #[test]
-    fn test_let_declaration() {
-        let mut parser = Parser::new(String::from("let x = 10")).expect("Expected Parser");
-        let statements = parser.parse().expect("Expected statements");
-        assert_eq!(statements.len(), 1);
-        assert_eq!(
-            statements[0],
-            Expr::LetDeclaration {
-                identifier: "x".into(),
-                var_type: None,
-                value: Box::new(Expr::Literal(Nodes::new_integer(10))),
+            if !self.match_token(&Token::Colon) {
+                return Err(ParserError::ExpectedAfter(":".into(), name));
             }
-        );
-    }
 
-    #[test]
-    fn test_let_declaration_with_type() {
-        let mut parser = Parser::new(String::from("let x: i32 = 10")).expect("Expected Parser");
-        let statements = parser.parse().expect("Expected statements");
-        assert_eq!(statements.len(), 1);
-        assert_eq!(
-            statements[0],
-            Expr::LetDeclaration {
-                identifier: "x".into(),
-                var_type: Some(Types::I32),
-                value: Box::new(Expr::Literal(Nodes::Integer(10))),
+            parameters.push(Parameter {
+                name,
+                param_type: self.parse_type()?,
+            });
+
+            if !self.match_token(&Token::Comma) && self.peek() != Some(&Token::RightParen) {
+                return Err(ParserError::ExpectedAfterCustom(
+                    ")".into(),
+                    function.into(),
+                    "parameters".into(),
+                ));
             }
-        );
+        }
+
+        Ok(parameters)
     }
+}
 
-    #[test]
-    fn test_assignment() {
-        let mut parser = Parser::new(String::from("x = 10")).expect("Expected Parser");
-        let statements = parser.parse().expect("Expected statements");
-        assert_eq!(statements.len(), 1);
-        assert_eq!(
-            statements[0],
-            Expr::Assignment {
-                identifier: "x".into(),
-                value: Box::new(Expr::Literal(Nodes::new_integer(10))),
+impl Parser {
+    fn enum_definition(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("enum_definition");
+        let start = self.current_span();
+        self.advance(); // consume `enum`
+
+        let name = match self.advance().cloned() {
+            Some(Token::Identifier(name)) => name,
+            _ => {
+                return Err(ParserError::ExpectedAfter(
+                    "enum name".into(),
+                    "enum".into(),
+                ));
             }
-        );
-    }
+        };
 
-    #[test]
-    fn test_multiple_statements_with_semicolons() {
-        let mut parser =
-            Parser::new(String::from("let x = 10; let y = 20; x + y")).expect("Expected Parser");
-        let statements = parser.parse().expect("Expected statements");
-        assert_eq!(statements.len(), 3);
+        if !self.match_token(&Token::LeftBrace) {
+            return Err(ParserError::ExpectedAfter("{".into(), name));
+        }
 
-        assert_eq!(
-            statements[0],
-            Expr::LetDeclaration {
-                identifier: "x".into(),
-                var_type: None,
-                value: Box::new(Expr::Literal(Nodes::Integer(10))),
+        let mut variants = Vec::new();
+        while !self.match_token(&Token::RightBrace) {
+            match self.advance().cloned() {
+                Some(Token::Identifier(variant)) => variants.push(variant),
+                _ => {
+                    return Err(ParserError::ExpectedAfter(
+                        "variant name".into(),
+                        format!("enum {}", name),
+                    ));
+                }
             }
-        );
 
-        assert_eq!(
-            statements[1],
-            Expr::LetDeclaration {
-                identifier: "y".into(),
-                var_type: None,
-                value: Box::new(Expr::Literal(Nodes::new_integer(20))),
+            if !self.match_token(&Token::Comma) && self.peek() != Some(&Token::RightBrace) {
+                return Err(ParserError::ExpectedAfter(
+                    ",".into(),
+                    "enum variant".into(),
+                ));
             }
-        );
+        }
 
-        assert_eq!(
-            statements[2],
-            Expr::Binary {
-                left: Box::new(Expr::Literal(Nodes::new_identifier("x".into()))),
-                operator: BinaryOp::Add,
-                right: Box::new(Expr::Literal(Nodes::new_identifier("y".into()))),
-            }
-        );
+        Ok(Expr::EnumDef {
+            name,
+            variants,
+            span: self.span_from(start),
+        })
     }
 
-    #[test]
-    fn test_block_with_braces() {
-        let mut parser =
-            Parser::new(String::from("{ let x = 10; x + 5 }")).expect("Expected Parser");
-        let statements = parser.parse().expect("Expected statements");
-        assert_eq!(statements.len(), 1);
-
-        if let Expr::Block(block_statements) = &statements[0] {
-            assert_eq!(block_statements.len(), 2);
-        } else {
-            panic!("Expected block expression");
-        }
-    }
+    fn const_definition(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("const_definition");
+        let start = self.current_span();
+        self.advance(); // consume `const`
 
-    #[test]
+        let name = match self.advance().cloned() {
+            Some(Token::Identifier(name)) => name,
+            _ => {
+                return Err(ParserError::ExpectedAfter(
+                    "constant name".into(),
+                    "const".into(),
+                ));
+            }
+        };
+
+        // Unlike `let`, the type is required
+        if !self.match_token(&Token::Colon) {
+            return Err(ParserError::ExpectedAfter(":".into(), name));
+        }
+        let const_type = self.parse_type()?;
+
+        if !self.match_token(&Token::Equals) {
+            return Err(ParserError::ExpectedAfter(
+                "=".into(),
+                const_type.to_string(),
+            ));
+        }
+
+        let value = self.or()?;
+        Ok(Expr::ConstDef {
+            name,
+            const_type,
+            value: Box::new(value),
+            span: self.span_from(start),
+        })
+    }
+
+    /// The `Variant` of `Enum::Variant`, with `Enum::` already consumed.
+    fn variant_name(&mut self, enum_name: &str) -> Result<String, ParserError> {
+        let _rule = self.enter("variant_name");
+        match self.advance().cloned() {
+            Some(Token::Identifier(variant)) => Ok(variant),
+            _ => Err(ParserError::ExpectedAfter(
+                "variant name".into(),
+                format!("{}::", enum_name),
+            )),
+        }
+    }
+
+    fn match_expression(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("match_expression");
+        let start = self.current_span();
+        self.advance(); // consume `match`
+
+        let scrutinee = self.or()?;
+
+        if !self.match_token(&Token::LeftBrace) {
+            return Err(ParserError::ExpectedAfter("{".into(), "match value".into()));
+        }
+
+        let mut arms = Vec::new();
+        while !self.match_token(&Token::RightBrace) {
+            if self.is_at_end() {
+                return Err(ParserError::ExpectedAfter("}".into(), "match arms".into()));
+            }
+
+            let pattern = self.pattern()?;
+
+            if !self.match_token(&Token::BigArrow) {
+                return Err(ParserError::ExpectedAfter("=>".into(), pattern.to_string()));
+            }
+
+            let body = self.expression()?;
+
+            // Like in Rust, a block needs no `,` before the next arm
+            if !self.match_token(&Token::Comma)
+                && !matches!(body, Expr::Block(..))
+                && self.peek() != Some(&Token::RightBrace)
+            {
+                return Err(ParserError::ExpectedAfter(",".into(), "match arm".into()));
+            }
+
+            arms.push(MatchArm { pattern, body });
+        }
+
+        Ok(Expr::Match {
+            scrutinee: Box::new(scrutinee),
+            arms,
+            span: self.span_from(start),
+        })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParserError> {
+        let _rule = self.enter("pattern");
+        match self.advance().cloned() {
+            Some(Token::Identifier(name)) if name == "_" => Ok(Pattern::Wildcard),
+            Some(Token::Identifier(enum_name)) => {
+                if !self.match_token(&Token::ColonColon) {
+                    return Err(ParserError::ExpectedAfter("::".into(), enum_name));
+                }
+                let variant = self.variant_name(&enum_name)?;
+                Ok(Pattern::Variant { enum_name, variant })
+            }
+            _ => Err(ParserError::ExpectedToken("pattern".into())),
+        }
+    }
+
+    /// `#[unroll(n)] for ...`, or `#[on_start] fn ...` and `#[on_exit] fn ...`
+    /// at the top level.
+    fn attributed(&mut self, top_level: bool) -> Result<Expr, ParserError> {
+        let _rule = self.enter("attributed");
+        let start = self.current_span();
+        self.advance(); // consume `#`
+
+        if !self.match_token(&Token::LeftBracket) {
+            return Err(ParserError::ExpectedAfter("[".into(), "#".into()));
+        }
+
+        let hook = match self.advance().cloned() {
+            Some(Token::Identifier(name)) if name == "unroll" => return self.unrolled_loop(start),
+            Some(Token::Identifier(name)) if name == "on_start" => Hook::OnStart,
+            Some(Token::Identifier(name)) if name == "on_exit" => Hook::OnExit,
+            Some(Token::Identifier(name)) => return Err(ParserError::UnknownAttribute(name)),
+            _ => {
+                return Err(ParserError::ExpectedAfter(
+                    "attribute name".into(),
+                    "#[".into(),
+                ));
+            }
+        };
+
+        let attribute = format!("#[{}]", hook);
+        if !self.match_token(&Token::RightBracket) {
+            return Err(ParserError::ExpectedAfter("]".into(), hook.to_string()));
+        }
+        if !top_level {
+            return Err(ParserError::NotAtTopLevel(attribute));
+        }
+        if !matches!(self.peek(), Some(Token::KeywordFn | Token::KeywordPub)) {
+            return Err(ParserError::ExpectedAfter("fn".into(), attribute));
+        }
+
+        self.function_definition(start, Some(hook))
+    }
+
+    /// The rest of `#[unroll(n)] for ...` starting at `start`, after `#[unroll`.
+    fn unrolled_loop(&mut self, start: Span) -> Result<Expr, ParserError> {
+        if !self.match_token(&Token::LeftParen) {
+            return Err(ParserError::ExpectedAfter("(".into(), "unroll".into()));
+        }
+
+        let count = match self.advance().cloned() {
+            Some(Token::Integer(count)) if count > 0 => u32::try_from(count)
+                .map_err(|_| ParserError::IntegerLiteralTooLarge(count.to_string(), Types::I32))?,
+            _ => {
+                return Err(ParserError::ExpectedAfter(
+                    "positive unroll count".into(),
+                    "unroll(".into(),
+                ));
+            }
+        };
+
+        if !self.match_token(&Token::RightParen) || !self.match_token(&Token::RightBracket) {
+            return Err(ParserError::ExpectedAfter(
+                ")]".into(),
+                "unroll count".into(),
+            ));
+        }
+
+        if self.peek() != Some(&Token::KeywordFor) {
+            return Err(ParserError::ExpectedAfter("for".into(), "#[unroll]".into()));
+        }
+
+        self.for_loop(start, Some(count))
+    }
+
+    /// A `for` loop starting at `loop_start`, which is before any
+    /// `#[unroll(n)]`.
+    fn for_loop(&mut self, loop_start: Span, unroll: Option<u32>) -> Result<Expr, ParserError> {
+        let _rule = self.enter("for_loop");
+        if !self.match_token(&Token::KeywordFor) {
+            return Err(ParserError::ExpectedToken("for".into()));
+        }
+
+        let variable = match self.advance().cloned() {
+            Some(Token::Identifier(name)) => name,
+            _ => {
+                return Err(ParserError::ExpectedAfter(
+                    "identifier".into(),
+                    "for".into(),
+                ));
+            }
+        };
+
+        if !self.match_token(&Token::KeywordIn) {
+            return Err(ParserError::ExpectedAfter(
+                "in".into(),
+                "loop variable".into(),
+            ));
+        }
+
+        let start = self.or()?;
+
+        if !self.match_token(&Token::DotDot) {
+            return Err(ParserError::ExpectedAfter(
+                "..".into(),
+                "range start".into(),
+            ));
+        }
+
+        let end = self.or()?;
+
+        let body_start = self.current_span();
+        if !self.match_token(&Token::LeftBrace) {
+            return Err(ParserError::ExpectedAfter("{".into(), "range".into()));
+        }
+
+        let mut body_statements = Vec::new();
+        while !self.match_token(&Token::RightBrace) && !self.is_at_end() {
+            body_statements.push(self.statement()?);
+        }
+
+        if self.previous() != Some(&Token::RightBrace) {
+            return Err(ParserError::ExpectedAfter("}".into(), "for-block".into()));
+        }
+
+        Ok(Expr::For {
+            variable,
+            start: Box::new(start),
+            end: Box::new(end),
+            body: Box::new(Expr::Block(body_statements, self.span_from(body_start))),
+            unroll,
+            span: self.span_from(loop_start),
+        })
+    }
+}
+
+impl Parser {
+    fn print(&mut self) -> Result<Expr, ParserError> {
+        let _rule = self.enter("print");
+        let start = self.current_span();
+        if self.match_token(&Token::KeywordPrint) {
+            if let Some(Token::LeftParen) = self.peek().cloned() {
+                self.advance(); // consume `(`
+
+                let mut arguments = self.argument_list("print")?;
+
+                if arguments.len() != 1 {
+                    return Err(ParserError::WrongArgumentCount(
+                        "print".into(),
+                        1,
+                        arguments.len(),
+                    ));
+                }
+
+                Ok(Expr::Print(
+                    Box::new(arguments.remove(0)),
+                    self.span_from(start),
+                ))
+            } else {
+                Err(ParserError::ExpectedAfter("(".into(), "print".into()))
+            }
+        } else {
+            Err(ParserError::ExpectedAfter(
+                "print".into(),
+                "statement".into(),
+            ))
+        }
+    }
+
+    /// Parses comma separated arguments up to and including the closing `)`.
+    /// The opening `(` must already be consumed. A trailing comma is allowed.
+    fn argument_list(&mut self, callee: &str) -> Result<Vec<Expr>, ParserError> {
+        let _rule = self.enter("argument_list");
+        let mut arguments = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some(Token::RightParen) => {
+                    self.advance();
+                    return Ok(arguments);
+                }
+                Some(Token::Comma) => {
+                    return Err(ParserError::ExpectedExpression(",".into()));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(ParserError::ExpectedAfterCustom(
+                        ")".into(),
+                        callee.into(),
+                        "arguments".into(),
+                    ));
+                }
+            }
+
+            arguments.push(self.or()?);
+
+            if !self.match_token(&Token::Comma) && self.peek() != Some(&Token::RightParen) {
+                return Err(ParserError::ExpectedAfterCustom(
+                    ")".into(),
+                    callee.into(),
+                    "arguments".into(),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variable(name: &str, start: usize) -> Expr {
+        Expr::Variable {
+            name: name.into(),
+            span: Span::new(start, start + name.len()),
+        }
+    }
+
+    #[test]
+    fn test_let_declaration() {
+        let mut parser = Parser::new(String::from("let x = 10")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(statements.len(), 1);
+        assert_eq!(
+            statements[0],
+            Expr::LetDeclaration {
+                identifier: "x".into(),
+                mutable: false,
+                var_type: None,
+                value: Box::new(Expr::Literal(Nodes::new_integer(10), Span::new(8, 10))),
+                span: Span::new(0, 10),
+            }
+        );
+    }
+
+    #[test]
+    fn test_let_declaration_with_type() {
+        let mut parser = Parser::new(String::from("let x: i32 = 10")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(statements.len(), 1);
+        assert_eq!(
+            statements[0],
+            Expr::LetDeclaration {
+                identifier: "x".into(),
+                mutable: false,
+                var_type: Some(Types::I32),
+                value: Box::new(Expr::Literal(Nodes::Integer(10), Span::new(13, 15))),
+                span: Span::new(0, 15),
+            }
+        );
+    }
+
+    #[test]
+    fn test_assignment() {
+        let mut parser = Parser::new(String::from("x = 10; _ = f()")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0],
+            Expr::Assignment {
+                identifier: "x".into(),
+                value: Box::new(Expr::Literal(Nodes::new_integer(10), Span::new(4, 6))),
+                span: Span::new(0, 6),
+            }
+        );
+        assert_eq!(
+            statements[1],
+            Expr::Discard(
+                Box::new(Expr::Call {
+                    callee: "f".into(),
+                    arguments: vec![],
+                    span: Span::new(12, 15),
+                }),
+                Span::new(8, 15)
+            )
+        );
+        assert_eq!(statements[1].to_string(), "_ = f()");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let mut parser = Parser::new(String::from("x += 1; x %= y * 2")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(statements[0].to_string(), "x = x + 1");
+        assert_eq!(statements[1].to_string(), "x = x % (y * 2)");
+
+        let mut parser = Parser::new(String::from("f() -= 1")).expect("Expected Parser");
+        let error = parser.parse().unwrap_err().to_string();
+        assert!(error.contains("(P006)"), "{}", error);
+    }
+
+    #[test]
+    fn test_multiple_statements_with_semicolons() {
+        let mut parser =
+            Parser::new(String::from("let x = 10; let y = 20; x + y")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(statements.len(), 3);
+
+        assert_eq!(
+            statements[0],
+            Expr::LetDeclaration {
+                identifier: "x".into(),
+                mutable: false,
+                var_type: None,
+                value: Box::new(Expr::Literal(Nodes::Integer(10), Span::new(8, 10))),
+                span: Span::new(0, 10),
+            }
+        );
+
+        assert_eq!(
+            statements[1],
+            Expr::LetDeclaration {
+                identifier: "y".into(),
+                mutable: false,
+                var_type: None,
+                value: Box::new(Expr::Literal(Nodes::new_integer(20), Span::new(20, 22))),
+                span: Span::new(12, 22),
+            }
+        );
+
+        assert_eq!(
+            statements[2],
+            Expr::Binary {
+                left: Box::new(variable("x", 24)),
+                operator: BinaryOp::Add,
+                right: Box::new(variable("y", 28)),
+                span: Span::new(24, 29),
+            }
+        );
+    }
+
+    #[test]
+    fn statement_spans() {
+        let source = "let x = 10;\nprint(\"hi\");  x + 1";
+        let mut parser = Parser::new(String::from(source)).expect("Expected Parser");
+        parser.parse().expect("Expected statements");
+
+        let spans: Vec<&str> = parser
+            .statement_spans()
+            .iter()
+            .map(|span| &source[span.range()])
+            .collect();
+
+        assert_eq!(spans, vec!["let x = 10", "print(\"hi\")", "x + 1"]);
+    }
+
+    #[test]
+    fn errors_are_located() {
+        let mut parser = Parser::new(String::from("let x = 1;\n  let = 2;")).unwrap();
+        assert_eq!(
+            parser.parse().unwrap_err().to_string(),
+            "2:7: (P005): Expected `identifier` after `let`"
+        );
+
+        let mut parser = Parser::new(String::from("print(1")).unwrap();
+        assert_eq!(
+            parser.parse().unwrap_err().to_string(),
+            "1:8: (P005): Expected `)` after `print` arguments"
+        );
+    }
+
+    #[test]
+    fn arrays_and_indexing() {
+        let mut parser = Parser::new(String::from("let xs: [i64; 3] = [1, 2, 3,]; xs[1]"))
+            .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements,
+            vec![
+                Expr::LetDeclaration {
+                    identifier: "xs".into(),
+                    mutable: false,
+                    var_type: Some(Types::Array(Box::new(Types::I64), 3)),
+                    value: Box::new(Expr::ArrayLiteral(
+                        vec![
+                            Expr::Literal(Nodes::Integer(1), Span::new(20, 21)),
+                            Expr::Literal(Nodes::Integer(2), Span::new(23, 24)),
+                            Expr::Literal(Nodes::Integer(3), Span::new(26, 27)),
+                        ],
+                        Span::new(19, 29)
+                    )),
+                    span: Span::new(0, 29),
+                },
+                Expr::Index {
+                    target: Box::new(variable("xs", 31)),
+                    index: Box::new(Expr::Literal(Nodes::Integer(1), Span::new(34, 35))),
+                    span: Span::new(31, 36),
+                },
+            ]
+        );
+
+        let mut parser = Parser::new(String::from("let xs: [i64 3] = [];")).unwrap();
+        assert_eq!(
+            parser.parse().unwrap_err().to_string(),
+            "1:14: (P005): Expected `;` after `array element type`"
+        );
+    }
+
+    #[test]
+    fn enums_and_match() {
+        let source = "enum Color { Red, Green, Blue, } let c: Color = Color::Red; \
+                      match c { Color::Red => { 1 } Color::Green => 2, _ => 3 }";
+        let mut parser = Parser::new(String::from(source)).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::EnumDef {
+                name: "Color".into(),
+                variants: vec!["Red".into(), "Green".into(), "Blue".into()],
+                span: Span::new(0, 32),
+            }
+        );
+        assert_eq!(
+            statements[1],
+            Expr::LetDeclaration {
+                identifier: "c".into(),
+                mutable: false,
+                var_type: Some(Types::Enum("Color".into())),
+                value: Box::new(Expr::EnumVariant {
+                    enum_name: "Color".into(),
+                    variant: "Red".into(),
+                    span: Span::new(48, 58),
+                }),
+                span: Span::new(33, 58),
+            }
+        );
+        let Expr::Match { arms, .. } = &statements[2] else {
+            panic!("Expected match expression");
+        };
+        assert_eq!(arms.len(), 3);
+        assert_eq!(arms[2].pattern, Pattern::Wildcard);
+        assert_eq!(
+            statements[2].to_string(),
+            "match c { Color::Red => { 1 }, Color::Green => 2, _ => 3 }"
+        );
+
+        let mut parser = Parser::new(String::from("match c { Color::Red => 1 Color::Blue => 2 }"))
+            .expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfter(",".into(), "match arm".into()))
+        );
+    }
+
+    #[test]
+    fn type_queries() {
+        let mut parser =
+            Parser::new(String::from("size_of(i32) + align_of(f64)")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::Binary {
+                left: Box::new(Expr::SizeOf(Types::I32, Span::new(0, 12))),
+                operator: BinaryOp::Add,
+                right: Box::new(Expr::AlignOf(Types::F64, Span::new(15, 28))),
+                span: Span::new(0, 28),
+            }
+        );
+
+        let mut parser = Parser::new(String::from("size_of(i32")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfter(")".into(), "size_of".into()))
+        );
+    }
+
+    #[test]
+    fn casts_bind_tighter_than_binary_operators() {
+        let mut parser = Parser::new(String::from("let y = -x as f64 * 2.0 + n as i32 as f32"))
+            .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        let Expr::LetDeclaration { value, .. } = &statements[0] else {
+            panic!("Expected a declaration, got {:?}", statements[0]);
+        };
+        let Expr::Binary { left, right, .. } = value.as_ref() else {
+            panic!("Expected a sum, got {:?}", value);
+        };
+        assert!(matches!(
+            left.as_ref(),
+            Expr::Binary { left, .. } if matches!(left.as_ref(), Expr::Cast { target: Types::F64, .. })
+        ));
+        assert!(
+            matches!(right.as_ref(), Expr::Cast { target: Types::F32, value, .. }
+            if matches!(value.as_ref(), Expr::Cast { target: Types::I32, .. }))
+        );
+        assert_eq!(
+            statements[0].to_string(),
+            "let y = -x as f64 * 2.0 + n as i32 as f32"
+        );
+
+        let cast = Expr::Unary {
+            operator: UnaryOp::Minus,
+            operand: Box::new(Expr::Cast {
+                value: Box::new(Expr::Literal(Nodes::Integer(1), Span::default())),
+                target: Types::I32,
+                span: Span::default(),
+            }),
+            span: Span::default(),
+        };
+        assert_eq!(cast.to_string(), "-(1 as i32)");
+
+        let mut parser = Parser::new(String::from("~x + 1")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert!(matches!(
+            &statements[0],
+            Expr::Binary { left, .. }
+                if matches!(left.as_ref(), Expr::Unary { operator: UnaryOp::BitNot, .. })
+        ));
+        assert_eq!(statements[0].to_string(), "~x + 1");
+    }
+
+    #[test]
+    fn hook_attributes() {
+        let mut parser = Parser::new(String::from(
+            "#[on_start] fn init() {}\n#[on_exit] pub fn done() {}",
+        ))
+        .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert!(matches!(
+            &statements[0],
+            Expr::FunctionDef { name, hook: Some(Hook::OnStart), .. } if name == "init"
+        ));
+        assert_eq!(statements[1].to_string(), "#[on_exit] pub fn done() {}");
+
+        for source in [
+            "fn f() { #[on_start] fn g() {} }",
+            "#[on_exit] let x = 1",
+            "#[on_start fn f() {}",
+        ] {
+            let mut parser = Parser::new(source.to_string()).expect("Expected Parser");
+            assert!(parser.parse().is_err(), "`{}` should not parse", source);
+        }
+    }
+
+    #[test]
+    fn children_in_source_order() {
+        let mut parser =
+            Parser::new(String::from("if a { f(b, c[d]) } else { e }")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        fn names(expr: &Expr, found: &mut Vec<String>) {
+            if let Expr::Variable { name, .. } = expr {
+                found.push(name.clone());
+            }
+            for child in expr.children() {
+                names(child, found);
+            }
+        }
+        let mut found = Vec::new();
+        names(&statements[0], &mut found);
+        assert_eq!(found, ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn expressions_span_their_source() {
+        let source = "#[on_start] fn init() { let x = -a[1] as f64; }\nprint(f(x) + 2)";
+        let mut parser = Parser::new(String::from(source)).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        fn spanned<'a>(expr: &Expr, source: &'a str, found: &mut Vec<&'a str>) {
+            found.push(&source[expr.span().range()]);
+            for child in expr.children() {
+                spanned(child, source, found);
+            }
+        }
+        let mut found = Vec::new();
+        for statement in &statements {
+            spanned(statement, source, &mut found);
+        }
+        assert_eq!(
+            found,
+            [
+                "#[on_start] fn init() { let x = -a[1] as f64; }",
+                "{ let x = -a[1] as f64; }",
+                "let x = -a[1] as f64;",
+                "let x = -a[1] as f64",
+                "-a[1] as f64",
+                "-a[1]",
+                "a[1]",
+                "a",
+                "1",
+                "print(f(x) + 2)",
+                "f(x) + 2",
+                "f(x)",
+                "x",
+                "2",
+            ]
+        );
+    }
+
+    #[test]
+    fn top_level_constants() {
+        let mut parser = Parser::new(String::from(
+            "const MAX: i64 = 100;\nconst HALF: i64 = MAX / 2 + size_of(i32) as i64;\nlet x = HALF",
+        ))
+        .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert!(matches!(
+            &statements[0],
+            Expr::ConstDef { name, const_type: Types::I64, value, .. }
+            if name == "MAX" && value.integer_literal() == Some(100)
+        ));
+        assert_eq!(
+            statements[1].to_string(),
+            "const HALF: i64 = MAX / 2 + size_of(i32) as i64"
+        );
+
+        let Expr::ConstDef { value, .. } = &statements[1] else {
+            panic!("Expected a constant, got {:?}", statements[1]);
+        };
+        assert!(value.is_constant(&|name| name == "MAX"));
+        assert!(!value.is_constant(&|_| false));
+
+        for source in ["fn f() { const N: i64 = 1 }", "const N = 1", "const N: i64"] {
+            let mut parser = Parser::new(source.to_string()).expect("Expected Parser");
+            assert!(parser.parse().is_err(), "`{}` should not parse", source);
+        }
+    }
+
+    #[test]
+    fn traces_rules_and_tokens() {
+        let mut parser = Parser::new(String::from("x = -1;\nprint(")).expect("Expected Parser");
+        parser.enable_trace();
+        assert!(parser.parse().is_err());
+
+        let trace = parser.trace().expect("Expected a trace").to_string();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines[0], "expression at `x` 1:1");
+        assert_eq!(lines[1], "  assignment at `x` 1:1");
+        assert_eq!(lines[13], "    consume `=` 1:3");
+        assert!(lines.contains(&"                      consume `-` 1:5"));
+        assert!(lines.contains(&"consume `;` 1:7"));
+        assert!(lines.contains(&"  print at `print` 2:1"));
+        assert!(lines.last().unwrap().starts_with("error 2:7: (P"));
+
+        let mut parser = Parser::new(String::from("x")).expect("Expected Parser");
+        parser.parse().expect("Expected statements");
+        assert_eq!(parser.trace(), None);
+    }
+
+    #[test]
+    fn adjacent_strings_are_concatenated() {
+        let mut parser = Parser::new(String::from("print(\"foo\" \"bar\"\n  \"baz\")"))
+            .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements,
+            vec![Expr::Print(
+                Box::new(Expr::Literal(
+                    Nodes::String("foobarbaz".into()),
+                    Span::new(6, 25)
+                )),
+                Span::new(0, 26)
+            )]
+        );
+    }
+
+    #[test]
+    fn string_escapes_and_raw_strings() {
+        let source = r#""a\\n\t\"\u{e9}\0"; r"C:\dir\n"; 'x'; '\"'"#;
+        let mut parser = Parser::new(String::from(source)).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements,
+            vec![
+                Expr::Literal(Nodes::String("a\\n\t\"\u{e9}\0".into()), Span::new(0, 18)),
+                Expr::Literal(Nodes::String("C:\\dir\\n".into()), Span::new(20, 31)),
+                Expr::Literal(Nodes::Char('x'), Span::new(33, 36)),
+                Expr::Literal(Nodes::Char('"'), Span::new(38, 42)),
+            ]
+        );
+        assert_eq!(statements[0].to_string(), r#""a\\n\t\"é\0""#);
+
+        let error = Parser::new(String::from("let s = \"a\\qb\";")).unwrap_err();
+        assert_eq!(
+            error.without_location(),
+            ParserError::InvalidEscape("\"a\\qb\"".into())
+        );
+    }
+
+    #[test]
+    fn multi_line_string() {
+        let mut parser =
+            Parser::new(String::from("\"line one\nline two\"")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements,
+            vec![Expr::Literal(
+                Nodes::String("line one\nline two".into()),
+                Span::new(0, 19)
+            )]
+        );
+    }
+
+    #[test]
+    fn numeric_separators() {
+        let mut parser = Parser::new(String::from("1_000_000 + 2_5.0_5")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::Binary {
+                left: Box::new(Expr::Literal(Nodes::Integer(1_000_000), Span::new(0, 9))),
+                operator: BinaryOp::Add,
+                right: Box::new(Expr::Literal(Nodes::Float(25.05), Span::new(12, 19))),
+                span: Span::new(0, 19),
+            }
+        );
+    }
+
+    #[test]
+    fn literal_suffixes() {
+        let mut parser = Parser::new(String::from("5i32 + 1_000i64; 2.5f32; 3f64")).unwrap();
+        let statements = parser.parse().unwrap();
+
+        assert_eq!(
+            statements,
+            vec![
+                Expr::Binary {
+                    left: Box::new(Expr::Literal(
+                        Nodes::TypedInteger(5, Types::I32),
+                        Span::new(0, 4)
+                    )),
+                    operator: BinaryOp::Add,
+                    right: Box::new(Expr::Literal(
+                        Nodes::TypedInteger(1000, Types::I64),
+                        Span::new(7, 15)
+                    )),
+                    span: Span::new(0, 15),
+                },
+                Expr::Literal(Nodes::TypedFloat(2.5, Types::F32), Span::new(17, 23)),
+                Expr::Literal(Nodes::TypedFloat(3.0, Types::F64), Span::new(25, 29)),
+            ]
+        );
+        assert_eq!(statements[2].to_string(), "3.0f64");
+
+        assert_eq!(
+            Parser::new(String::from("let x = 3_000_000_000i32;"))
+                .map_err(ParserError::without_location),
+            Err(ParserError::IntegerLiteralTooLarge(
+                "3_000_000_000i32".into(),
+                Types::I32
+            ))
+        );
+    }
+
+    #[test]
+    fn integer_literal_too_large() {
+        let result = Parser::new(String::from("let x = 99_999_999_999_999_999_999;"));
+
+        assert_eq!(
+            result,
+            Err(ParserError::Located {
+                line: 1,
+                column: 9,
+                error: Box::new(ParserError::IntegerLiteralTooLarge(
+                    "99_999_999_999_999_999_999".into(),
+                    Types::I64
+                )),
+            })
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "1:9: (P007): Integer literal `99_999_999_999_999_999_999` is out of range for `i64` (-9223372036854775808..=9223372036854775807)"
+        );
+    }
+
+    #[test]
+    fn print_arguments() {
+        let mut parser = Parser::new(String::from("print(\"a\",)")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse(),
+            Ok(vec![Expr::Print(
+                Box::new(Expr::Literal(Nodes::String("a".into()), Span::new(6, 9))),
+                Span::new(0, 11)
+            )])
+        );
+
+        let mut parser = Parser::new(String::from("print(,)")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedExpression(",".into()))
+        );
+
+        let mut parser = Parser::new(String::from("print(\"a\", \"b\")")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::WrongArgumentCount("print".into(), 1, 2))
+        );
+
+        let mut parser = Parser::new(String::from("print(\"a\" x)")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfterCustom(
+                ")".into(),
+                "print".into(),
+                "arguments".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn return_statements() {
+        let mut parser =
+            Parser::new(String::from("if x { return; } return x + 1")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::IfElse {
+                condition: Box::new(variable("x", 3)),
+                then_branch: Box::new(Expr::Block(
+                    vec![Expr::Statement(
+                        Box::new(Expr::Return(None, Span::new(7, 13))),
+                        Span::new(7, 14)
+                    )],
+                    Span::new(5, 16)
+                )),
+                else_branch: None,
+                span: Span::new(0, 16),
+            }
+        );
+        assert_eq!(
+            statements[1],
+            Expr::Return(
+                Some(Box::new(Expr::Binary {
+                    left: Box::new(variable("x", 24)),
+                    operator: BinaryOp::Add,
+                    right: Box::new(Expr::Literal(Nodes::Integer(1), Span::new(28, 29))),
+                    span: Span::new(24, 29),
+                })),
+                Span::new(17, 29)
+            )
+        );
+
+        let mut parser = Parser::new(String::from("for i in 0..3 { if i { continue; } break }"))
+            .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(
+            statements[0].to_string(),
+            "for i in 0..3 { if i { continue; } break }"
+        );
+    }
+
+    #[test]
+    fn function_definition_and_call() {
+        let mut parser = Parser::new(String::from(
+            "fn add(a: i32, b: i32,) -> i32 { return a + b; } pub fn log() {} add(1, 2)",
+        ))
+        .expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::FunctionDef {
+                public: false,
+                name: "add".into(),
+                parameters: vec![
+                    Parameter {
+                        name: "a".into(),
+                        param_type: Types::I32,
+                    },
+                    Parameter {
+                        name: "b".into(),
+                        param_type: Types::I32,
+                    },
+                ],
+                return_type: Some(Types::I32),
+                body: Box::new(Expr::Block(
+                    vec![Expr::Statement(
+                        Box::new(Expr::Return(
+                            Some(Box::new(Expr::Binary {
+                                left: Box::new(variable("a", 40)),
+                                operator: BinaryOp::Add,
+                                right: Box::new(variable("b", 44)),
+                                span: Span::new(40, 45),
+                            })),
+                            Span::new(33, 45)
+                        )),
+                        Span::new(33, 46)
+                    )],
+                    Span::new(31, 48)
+                )),
+                hook: None,
+                span: Span::new(0, 48),
+            }
+        );
+        assert_eq!(
+            statements[1],
+            Expr::FunctionDef {
+                public: true,
+                name: "log".into(),
+                parameters: Vec::new(),
+                return_type: None,
+                body: Box::new(Expr::Block(Vec::new(), Span::new(62, 64))),
+                hook: None,
+                span: Span::new(49, 64),
+            }
+        );
+        assert_eq!(
+            statements[2],
+            Expr::Call {
+                callee: "add".into(),
+                arguments: vec![
+                    Expr::Literal(Nodes::Integer(1), Span::new(69, 70)),
+                    Expr::Literal(Nodes::Integer(2), Span::new(72, 73)),
+                ],
+                span: Span::new(65, 74),
+            }
+        );
+
+        assert_eq!(statements[1].to_string(), "pub fn log() {}");
+
+        let mut parser = Parser::new(String::from("pub let x = 1;")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfter("fn".into(), "pub".into()))
+        );
+
+        let mut parser = Parser::new(String::from("fn f(a) {}")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfter(":".into(), "a".into()))
+        );
+    }
+
+    #[test]
+    fn nested_calls_and_trailing_commas() {
+        let mut parser =
+            Parser::new(String::from("foo(1, bar(2 + x,),)")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::Call {
+                callee: "foo".into(),
+                arguments: vec![
+                    Expr::Literal(Nodes::Integer(1), Span::new(4, 5)),
+                    Expr::Call {
+                        callee: "bar".into(),
+                        arguments: vec![Expr::Binary {
+                            left: Box::new(Expr::Literal(Nodes::Integer(2), Span::new(11, 12))),
+                            operator: BinaryOp::Add,
+                            right: Box::new(variable("x", 15)),
+                            span: Span::new(11, 16),
+                        }],
+                        span: Span::new(7, 18),
+                    },
+                ],
+                span: Span::new(0, 20),
+            }
+        );
+
+        let mut parser = Parser::new(String::from("foo(1,, 2)")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedExpression(",".into()))
+        );
+
+        let mut parser = Parser::new(String::from("foo(1 2)")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfterCustom(
+                ")".into(),
+                "foo".into(),
+                "arguments".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let source = r#"
+            let mut x: i32 = -(1 + 2) * 3 % 4;
+            let s = "a \"quoted\"\n\\ line";
+            let f = 2.0 / 0.5;
+            let tiny = 2.5e-7 * 1E300;
+            let c = '\u{1F600}' != '\u{7}';
+            let grid: [[i32; 2]; 2] = [[1, 2], [3, -4]];
+            x = grid[0][1] * -grid[x][0];
+            x = !true && (false || 1 != 2);
+            if x >= 1 { print(s); } else { {} }
+            if f < 1.0 { return; }
+            for i in 0..size_of(i64) { x = x + align_of(bool); }
+            #[unroll(2)] for i in 0..4 {}
+            fn add(a: i64, b: i64) -> i64 { return a + b; }
+            fn log() { print("log"); }
+            add(1, add(2, 3));
+        "#;
+        let statements = Parser::new(source.to_string())
+            .expect("Expected Parser")
+            .parse()
+            .expect("Expected statements");
+
+        let printed = statements
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<String>>()
+            .join(";\n");
+        let reparsed = Parser::new(printed.clone())
+            .expect("Expected Parser")
+            .parse()
+            .expect("Expected printed source to parse");
+
+        // Expressions carry their position, so compare the trees by their
+        // printed form
+        assert_eq!(
+            reparsed.iter().map(Expr::to_string).collect::<Vec<_>>(),
+            statements.iter().map(Expr::to_string).collect::<Vec<_>>(),
+            "{}",
+            printed
+        );
+        assert!(printed.starts_with("let mut x: i32 = -(1 + 2) * 3 % 4;\n"));
+        assert!(printed.contains("\nlet f = 2.0 / 0.5;\n"));
+    }
+
+    #[test]
+    fn statement_ids_are_stable() {
+        let ids = |source: &str| {
+            let mut parser = Parser::new(source.to_string()).expect("Expected Parser");
+            parser.parse().expect("Expected statements");
+            parser.statement_ids().to_vec()
+        };
+
+        let original = ids("let x = 1; print(s); let x = 1;");
+        let edited = ids("// note\nlet x  =  1;\n\nprint(t); let x = 1;");
+
+        assert_eq!(original.len(), 3);
+        assert_ne!(original[0], original[2]);
+        assert_eq!(original[0], edited[0]);
+        assert_ne!(original[1], edited[1]);
+        assert_eq!(original[2], edited[2]);
+    }
+
+    #[test]
+    fn for_range_loop() {
+        let mut parser =
+            Parser::new(String::from("for i in 0..n + 1 { print(s); }")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert_eq!(
+            statements[0],
+            Expr::For {
+                variable: "i".into(),
+                start: Box::new(Expr::Literal(Nodes::Integer(0), Span::new(9, 10))),
+                end: Box::new(Expr::Binary {
+                    left: Box::new(variable("n", 12)),
+                    operator: BinaryOp::Add,
+                    right: Box::new(Expr::Literal(Nodes::Integer(1), Span::new(16, 17))),
+                    span: Span::new(12, 17),
+                }),
+                body: Box::new(Expr::Block(
+                    vec![Expr::Statement(
+                        Box::new(Expr::Print(Box::new(variable("s", 26)), Span::new(20, 28))),
+                        Span::new(20, 29)
+                    )],
+                    Span::new(18, 31)
+                )),
+                unroll: None,
+                span: Span::new(0, 31),
+            }
+        );
+
+        let mut parser = Parser::new(String::from("for i 0..3 {}")).expect("Expected Parser");
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfter(
+                "in".into(),
+                "loop variable".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn unroll_attribute() {
+        let parse = |source: &str| {
+            Parser::new(source.to_string())
+                .expect("Expected Parser")
+                .parse()
+                .map_err(ParserError::without_location)
+        };
+
+        assert!(matches!(
+            parse("#[unroll(4)] for i in 0..8 {}").unwrap()[0],
+            Expr::For {
+                unroll: Some(4),
+                ..
+            }
+        ));
+        assert_eq!(
+            parse("#[inline] for i in 0..8 {}"),
+            Err(ParserError::UnknownAttribute("inline".into()))
+        );
+        assert_eq!(
+            parse("#[unroll(0)] for i in 0..8 {}"),
+            Err(ParserError::ExpectedAfter(
+                "positive unroll count".into(),
+                "unroll(".into()
+            ))
+        );
+        assert_eq!(
+            parse("#[unroll(2)] let x = 1"),
+            Err(ParserError::ExpectedAfter("for".into(), "#[unroll]".into()))
+        );
+    }
+
+    #[test]
+    fn test_block_with_braces() {
+        let mut parser =
+            Parser::new(String::from("{ let x = 10; x + 5 }")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+        assert_eq!(statements.len(), 1);
+
+        if let Expr::Block(block_statements, _) = &statements[0] {
+            assert_eq!(block_statements.len(), 2);
+        } else {
+            panic!("Expected block expression");
+        }
+    }
+
+    #[test]
     fn if_block() {
         let mut parser =
             Parser::new(String::from("if cond1 == cond2 {}")).expect("Expected Parser");
@@ -639,17 +2266,19 @@ mod tests {
             condition,
             then_branch,
             else_branch,
+            ..
         } = &statements[0]
         {
             assert_eq!(
                 **condition,
                 Expr::Binary {
-                    left: Box::new(Expr::Literal(Nodes::new_identifier("cond1".into()))),
+                    left: Box::new(variable("cond1", 3)),
                     operator: BinaryOp::Equal,
-                    right: Box::new(Expr::Literal(Nodes::new_identifier("cond2".into()))),
+                    right: Box::new(variable("cond2", 12)),
+                    span: Span::new(3, 17),
                 }
             );
-            if let Expr::Block(block_statements) = then_branch.as_ref() {
+            if let Expr::Block(block_statements, _) = then_branch.as_ref() {
                 assert_eq!(block_statements.len(), 0);
             } else {
                 panic!("Expected block expression");
@@ -671,24 +2300,26 @@ mod tests {
             condition,
             then_branch,
             else_branch,
+            ..
         } = &statements[0]
         {
             assert_eq!(
                 **condition,
                 Expr::Binary {
-                    left: Box::new(Expr::Literal(Nodes::new_identifier("cond1".into()))),
+                    left: Box::new(variable("cond1", 3)),
                     operator: BinaryOp::Equal,
-                    right: Box::new(Expr::Literal(Nodes::new_identifier("cond2".into()))),
+                    right: Box::new(variable("cond2", 12)),
+                    span: Span::new(3, 17),
                 }
             );
-            if let Expr::Block(block_statements) = then_branch.as_ref() {
+            if let Expr::Block(block_statements, _) = then_branch.as_ref() {
                 assert_eq!(block_statements.len(), 0);
             } else {
                 panic!("Expected block expression for then branch");
             }
             assert!(else_branch.is_some());
             if let Some(else_expr) = else_branch {
-                if let Expr::Block(block_statements) = else_expr.as_ref() {
+                if let Expr::Block(block_statements, _) = else_expr.as_ref() {
                     assert_eq!(block_statements.len(), 0);
                 } else {
                     panic!("Expected block expression for else branch");
@@ -710,23 +2341,25 @@ mod tests {
             condition,
             then_branch,
             else_branch,
+            ..
         } = &statements[0]
         {
             assert_eq!(
                 **condition,
                 Expr::Unary {
                     operator: UnaryOp::Not,
-                    operand: Box::new(Expr::Literal(Nodes::new_identifier("cond1".into()))),
+                    operand: Box::new(variable("cond1", 4)),
+                    span: Span::new(3, 9),
                 }
             );
-            if let Expr::Block(block_statements) = then_branch.as_ref() {
+            if let Expr::Block(block_statements, _) = then_branch.as_ref() {
                 assert_eq!(block_statements.len(), 0);
             } else {
                 panic!("Expected block expression for then branch");
             }
             assert!(else_branch.is_some());
             if let Some(else_expr) = else_branch {
-                if let Expr::Block(block_statements) = else_expr.as_ref() {
+                if let Expr::Block(block_statements, _) = else_expr.as_ref() {
                     assert_eq!(block_statements.len(), 0);
                 } else {
                     panic!("Expected block expression for else branch");
@@ -737,11 +2370,61 @@ mod tests {
         }
     }
 
+    #[test]
+    fn if_as_a_value() {
+        let mut parser =
+            Parser::new(String::from("let x = if c { 1 } else { 2 };")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        let Expr::LetDeclaration { value, .. } = &statements[0] else {
+            panic!("Expected let declaration");
+        };
+        assert!(matches!(value.as_ref(), Expr::IfElse { .. }));
+        assert_eq!(statements[0].to_string(), "let x = if c { 1 } else { 2 }");
+    }
+
+    #[test]
+    fn token_count() {
+        let parser = Parser::new(String::from("let x = 10;")).expect("Expected Parser");
+        assert_eq!(parser.token_count(), 5);
+    }
+
     #[test]
     fn invalid_char_should_panic() {
         let result = Parser::new(String::from("@"));
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), ParserError::UnexpectedCharacter('@'));
+        assert_eq!(
+            result.unwrap_err().without_location(),
+            ParserError::UnexpectedCharacter('@')
+        );
+    }
+
+    #[test]
+    fn mutable_let_declaration() {
+        let mut parser = Parser::new(String::from("let mut x = 1; x = 2; let mut = 3;"))
+            .expect("Expected Parser");
+
+        assert_eq!(
+            parser.parse().map_err(ParserError::without_location),
+            Err(ParserError::ExpectedAfter(
+                "identifier".into(),
+                "let".into()
+            ))
+        );
+
+        let mut parser =
+            Parser::new(String::from("let mut x: i64 = 1; let y = x;")).expect("Expected Parser");
+        let statements = parser.parse().expect("Expected statements");
+
+        assert!(matches!(
+            &statements[0],
+            Expr::LetDeclaration { identifier, mutable: true, var_type: Some(Types::I64), .. }
+                if identifier == "x"
+        ));
+        assert!(matches!(
+            &statements[1],
+            Expr::LetDeclaration { mutable: false, .. }
+        ));
     }
 
     #[test]
@@ -754,11 +2437,15 @@ mod tests {
             identifier,
             value,
             var_type,
+            ..
         } = &statements[0]
         {
             assert_eq!(identifier, "x");
             assert_eq!(var_type, &Some(Types::I32));
-            assert_eq!(value, &Box::new(Expr::Literal(Nodes::Integer(42))));
+            assert_eq!(
+                value,
+                &Box::new(Expr::Literal(Nodes::Integer(42), Span::new(13, 15)))
+            );
         } else {
             panic!("Expected let expression");
         }
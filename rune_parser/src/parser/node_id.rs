@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::expr::Expr;
+
+/// Identifies a top-level statement across edits to the rest of the file.
+///
+/// The id is a hash of the statement's source form (its `Display`, which
+/// leaves out spans), so it survives changes to whitespace, comments and the
+/// statements around it. Identical
+/// statements are told apart by how many came before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u64);
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across toolchains.
+pub fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+pub fn assign_ids(statements: &[Expr]) -> Vec<NodeId> {
+    let mut occurrences: HashMap<u64, u64> = HashMap::new();
+
+    statements
+        .iter()
+        .map(|statement| {
+            let hash = fnv1a(FNV_OFFSET, statement.to_string().as_bytes());
+
+            let occurrence = occurrences.entry(hash).or_default();
+            let id = fnv1a(hash, &occurrence.to_le_bytes());
+            *occurrence += 1;
+
+            NodeId(id)
+        })
+        .collect()
+}
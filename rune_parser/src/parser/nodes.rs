@@ -1,10 +1,19 @@
+use std::fmt;
+
+use crate::parser::types::Types;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Nodes {
     Integer(i64),
     Float(f64),
+    /// A literal with a type suffix, e.g. `5i32`
+    TypedInteger(i64, Types),
+    /// A literal with a type suffix, e.g. `2.5f32`
+    TypedFloat(f64, Types),
     String(String),
     Boolean(bool),
-    Identifier(String),
+    /// A Unicode scalar value, `'a'`
+    Char(char),
 }
 
 impl Nodes {
@@ -24,8 +33,44 @@ impl Nodes {
     pub fn new_boolean(value: bool) -> Self {
         Nodes::Boolean(value)
     }
-    #[inline]
-    pub fn new_identifier(value: String) -> Self {
-        Nodes::Identifier(value)
+}
+
+/// Renders the node as Rune source, e.g. strings are quoted and escaped.
+impl fmt::Display for Nodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Nodes::Integer(value) => write!(f, "{}", value),
+            // Debug keeps the `.0` that marks a whole number as a float
+            Nodes::Float(value) => write!(f, "{:?}", value),
+            Nodes::TypedInteger(value, literal_type) => write!(f, "{}{}", value, literal_type),
+            Nodes::TypedFloat(value, literal_type) => write!(f, "{:?}{}", value, literal_type),
+            Nodes::String(value) => {
+                write!(f, "\"")?;
+                for c in value.chars() {
+                    match c {
+                        '\n' => write!(f, "\\n")?,
+                        '\r' => write!(f, "\\r")?,
+                        '\t' => write!(f, "\\t")?,
+                        '\0' => write!(f, "\\0")?,
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32)?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Nodes::Boolean(value) => write!(f, "{}", value),
+            Nodes::Char(value) => match value {
+                '\n' => write!(f, "'\\n'"),
+                '\r' => write!(f, "'\\r'"),
+                '\t' => write!(f, "'\\t'"),
+                '\0' => write!(f, "'\\0'"),
+                '\'' => write!(f, "'\\''"),
+                '\\' => write!(f, "'\\\\'"),
+                c if c.is_control() => write!(f, "'\\u{{{:x}}}'", *c as u32),
+                c => write!(f, "'{}'", c),
+            },
+        }
     }
 }
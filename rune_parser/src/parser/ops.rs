@@ -1,10 +1,20 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum BinaryOp {
     Add,
     Subtract,
     Multiply,
+    /// Integer division rounds towards zero, so `-7 / 2` is `-3`. See the
+    /// `div_euclid` intrinsic for rounding towards negative infinity.
     Divide,
+    /// The remainder of [`BinaryOp::Divide`], taking the sign of the left
+    /// operand, so `-7 % 2` is `-1`. See the `rem_euclid` intrinsic for a
+    /// remainder that is never negative.
     Modulo,
+    /// Floats compare as IEEE 754 says: every comparison with a NaN is
+    /// false except `!=`, so `x != x` is true exactly when `x` is NaN and
+    /// `a != b` is always `!(a == b)`.
     Equal,
     NotEqual,
     Greater,
@@ -18,5 +28,53 @@ pub enum BinaryOp {
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOp {
     Minus,
+    /// Logical not, on `bool`s only.
     Not,
+    /// Bitwise not, `~`, on integers only.
+    BitNot,
+}
+
+impl BinaryOp {
+    /// Binding strength, higher binds tighter. All levels are left associative.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Equal | BinaryOp::NotEqual => 3,
+            BinaryOp::Greater | BinaryOp::Less | BinaryOp::GreaterEqual | BinaryOp::LessEqual => 4,
+            BinaryOp::Add | BinaryOp::Subtract => 5,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Modulo => 6,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Greater => ">",
+            BinaryOp::Less => "<",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Minus => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+            UnaryOp::BitNot => write!(f, "~"),
+        }
+    }
 }
@@ -1,7 +1,10 @@
-use logos::Logos;
+use logos::{Lexer, Logos};
+
+use crate::parser::types::Types;
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 #[logos(skip r"[ \t\n\f]+")]
+#[logos(skip r"//[^\n]*")]
 pub enum Token {
     // Arithmetic operators
     #[token("+")]
@@ -26,6 +29,17 @@ pub enum Token {
     EqualsEquals,
     #[token("!=")]
     NotEquals,
+    // Compound assignment, `x += 1` is `x = x + 1`
+    #[token("+=")]
+    PlusEquals,
+    #[token("-=")]
+    MinusEquals,
+    #[token("*=")]
+    StarEquals,
+    #[token("/=")]
+    SlashEquals,
+    #[token("%=")]
+    PercentEquals,
 
     // Comparison operators
     #[token(">")]
@@ -44,6 +58,8 @@ pub enum Token {
     Or,
     #[token("!")]
     Bang,
+    #[token("~")]
+    Tilde,
 
     // Delimiters
     #[token("(")]
@@ -58,25 +74,52 @@ pub enum Token {
     Semicolon,
     #[token(":")]
     Colon,
+    #[token("::")]
+    ColonColon,
+    #[token("..")]
+    DotDot,
+    #[token("[")]
+    LeftBracket,
+    #[token("]")]
+    RightBracket,
+    /// Starts an attribute, `#[unroll(4)]`
+    #[token("#")]
+    Hash,
 
-    #[regex(r"[0-9]+", |lex| lex.slice().parse::<i64>().ok())]
+    // `_` may be used as a digit separator, e.g. `1_000_000`
+    #[regex(r"[0-9][0-9_]*", |lex| lex.slice().replace('_', "").parse::<i64>().ok())]
     Integer(i64),
 
-    #[regex(r"[0-9]+\.[0-9]+", |lex| lex.slice().parse::<f64>().ok())]
+    // An exponent may stand in for the fraction, e.g. `1e9`, `2.5e-3`, `1.0E+6`
+    #[regex(r"[0-9][0-9_]*\.[0-9][0-9_]*([eE][+-]?[0-9][0-9_]*)?", |lex| lex.slice().replace('_', "").parse::<f64>().ok())]
+    #[regex(r"[0-9][0-9_]*[eE][+-]?[0-9][0-9_]*", |lex| lex.slice().replace('_', "").parse::<f64>().ok())]
     Float(f64),
 
-    #[regex(r#""([^"\\]|\\[nrt"\\])*""#, |lex| {
+    // A suffix gives the literal a type other than `i64` or `f64`, e.g. `5i32`
+    #[regex(r"[0-9][0-9_]*(i32|i64)", typed_integer)]
+    TypedInteger((i64, Types)),
+
+    #[regex(
+        r"[0-9][0-9_]*(\.[0-9][0-9_]*)?([eE][+-]?[0-9][0-9_]*)?(f32|f64)",
+        typed_float
+    )]
+    TypedFloat((f64, Types)),
+
+    /// `"a\tb"`, or the raw `r"a\tb"` whose backslashes are kept as written
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| {
         let slice = lex.slice();
-        // Remove quotes and handle escape sequences
-        let content = &slice[1..slice.len()-1];
-        Some(content.replace("\\n", "\n")
-                   .replace("\\r", "\r")
-                   .replace("\\t", "\t")
-                   .replace("\\\"", "\"")
-                   .replace("\\\\", "\\"))
+        unescape(&slice[1..slice.len() - 1])
+    })]
+    #[regex(r#"r"[^"]*""#, |lex| {
+        let slice = lex.slice();
+        Some(slice[2..slice.len() - 1].to_string())
     })]
     String(String),
 
+    /// `'a'`, `'\n'` or `'\u{1F600}'`
+    #[regex(r#"'([^'\\\n]|\\[nrt0'"\\]|\\u\{[0-9a-fA-F]{1,6}\})'"#, char_literal)]
+    Char(char),
+
     #[regex(r"true|false", |lex| match lex.slice() {
         "true" => Some(true),
         "false" => Some(false),
@@ -89,6 +132,8 @@ pub enum Token {
 
     #[token("let")]
     KeywordLet,
+    #[token("mut")]
+    KeywordMut,
     #[token("if")]
     KeywordIf,
     #[token("else")]
@@ -97,8 +142,28 @@ pub enum Token {
     KeywordWhile,
     #[token("for")]
     KeywordFor,
+    #[token("in")]
+    KeywordIn,
+    #[token("fn")]
+    KeywordFn,
+    #[token("pub")]
+    KeywordPub,
     #[token("print")]
     KeywordPrint,
+    #[token("return")]
+    KeywordReturn,
+    #[token("break")]
+    KeywordBreak,
+    #[token("continue")]
+    KeywordContinue,
+    #[token("enum")]
+    KeywordEnum,
+    #[token("match")]
+    KeywordMatch,
+    #[token("as")]
+    KeywordAs,
+    #[token("const")]
+    KeywordConst,
     #[token("->")]
     Arrow,
     #[token("=>")]
@@ -116,4 +181,83 @@ pub enum Token {
     TypeF64,
     #[token("string")]
     TypeString,
+    #[token("char")]
+    TypeChar,
+}
+
+/// Splits a literal such as `5i32` into its digits and the suffix type.
+pub fn split_suffix(literal: &str) -> Option<(&str, Types)> {
+    let (digits, suffix) = literal.split_at(literal.len().checked_sub(3)?);
+    let literal_type = match suffix {
+        "i32" => Types::I32,
+        "i64" => Types::I64,
+        "f32" => Types::F32,
+        "f64" => Types::F64,
+        _ => return None,
+    };
+    Some((digits, literal_type))
+}
+
+fn typed_integer(lex: &mut Lexer<Token>) -> Option<(i64, Types)> {
+    let (digits, literal_type) = split_suffix(lex.slice())?;
+    let value = digits.replace('_', "").parse::<i64>().ok()?;
+
+    // Out of range for the suffix fails like an out of range `i64`
+    if literal_type == Types::I32 && i32::try_from(value).is_err() {
+        return None;
+    }
+    Some((value, literal_type))
+}
+
+fn char_literal(lex: &mut Lexer<Token>) -> Option<char> {
+    let slice = lex.slice();
+    let content = unescape(&slice[1..slice.len() - 1])?;
+
+    let mut chars = content.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+/// Resolves the escape sequences in the contents of a string or char
+/// literal, which accept the same ones. `None` on an unknown escape.
+fn unescape(content: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(content.len());
+    let mut chars = content.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\'' => '\'',
+            '"' => '"',
+            '\\' => '\\',
+            'u' => {
+                let rest = chars.as_str().strip_prefix('{')?;
+                let (hex, rest) = rest.split_once('}')?;
+                if hex.is_empty() || hex.len() > 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return None;
+                }
+                chars = rest.chars();
+                // Surrogates and values past U+10FFFF are not characters
+                char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+            }
+            _ => return None,
+        };
+        unescaped.push(escaped);
+    }
+
+    Some(unescaped)
+}
+
+fn typed_float(lex: &mut Lexer<Token>) -> Option<(f64, Types)> {
+    let (digits, literal_type) = split_suffix(lex.slice())?;
+    let value = digits.replace('_', "").parse::<f64>().ok()?;
+    Some((value, literal_type))
 }
@@ -0,0 +1,57 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+/// A log of the parser's decisions, see [`crate::parser::Parser::enable_trace`].
+///
+/// Each grammar rule is logged as it is entered, with the token it is about
+/// to look at, followed by the tokens it consumes and the rules it calls,
+/// indented one level deeper. The parser picks between alternatives by
+/// peeking at that token and never rewinds, so the rule lines are also the
+/// points where a different token would have taken another path.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParseTrace {
+    lines: Vec<String>,
+    depth: usize,
+}
+
+impl ParseTrace {
+    pub(crate) fn push(&mut self, event: String) {
+        self.lines
+            .push(format!("{}{}", "  ".repeat(self.depth), event));
+    }
+}
+
+impl fmt::Display for ParseTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared with the [`RuleGuard`]s of the rules being parsed.
+pub(crate) type SharedTrace = Rc<RefCell<ParseTrace>>;
+
+/// Logs a rule on creation and ends it on drop, so every return path of the
+/// rule closes its level of the trace.
+pub(crate) struct RuleGuard(Option<SharedTrace>);
+
+impl RuleGuard {
+    pub(crate) fn enter(trace: &Option<SharedTrace>, event: impl FnOnce() -> String) -> Self {
+        if let Some(trace) = trace {
+            let mut trace = trace.borrow_mut();
+            trace.push(event());
+            trace.depth += 1;
+        }
+        RuleGuard(trace.clone())
+    }
+}
+
+impl Drop for RuleGuard {
+    fn drop(&mut self) {
+        if let Some(trace) = &self.0 {
+            let mut trace = trace.borrow_mut();
+            trace.depth = trace.depth.saturating_sub(1);
+        }
+    }
+}
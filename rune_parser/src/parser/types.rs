@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Types {
     I32,
@@ -6,4 +8,30 @@ pub enum Types {
     F32,
     F64,
     String,
+    /// A Unicode scalar value, stored as an `i32` code point.
+    Char,
+    /// A fixed-size array, `[T; n]`.
+    Array(Box<Types>, usize),
+    /// A user-defined `enum`, by name.
+    Enum(String),
+    /// `()`, the value of statements like `print` and of an `if` without
+    /// an `else`. It cannot be written in source.
+    Unit,
+}
+
+impl fmt::Display for Types {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Types::I32 => write!(f, "i32"),
+            Types::I64 => write!(f, "i64"),
+            Types::Bool => write!(f, "bool"),
+            Types::F32 => write!(f, "f32"),
+            Types::F64 => write!(f, "f64"),
+            Types::String => write!(f, "string"),
+            Types::Char => write!(f, "char"),
+            Types::Array(element, length) => write!(f, "[{}; {}]", element, length),
+            Types::Enum(name) => write!(f, "{}", name),
+            Types::Unit => write!(f, "()"),
+        }
+    }
 }
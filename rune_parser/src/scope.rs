@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+/// Lexically scoped symbol table shared by the backends.
+///
+/// Lookups walk from the innermost scope outwards. Declaring a name that
+/// already exists in the current scope shadows it, and the previous binding
+/// is handed back so the caller can decide whether to warn about it.
+#[derive(Debug, Clone)]
+pub struct SymbolTable<T> {
+    scopes: Vec<HashMap<String, T>>,
+}
+
+impl<T> SymbolTable<T> {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Pops the innermost scope. The global scope is never popped.
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Declares `name` in the current scope, returning the binding it shadows
+    /// if `name` was already declared in this same scope.
+    pub fn declare(&mut self, name: &str, value: T) -> Option<T> {
+        self.scopes
+            .last_mut()
+            .expect("symbol table always has a global scope")
+            .insert(name.to_string(), value)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+}
+
+impl<T> Default for SymbolTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redeclaration_returns_shadowed_binding() {
+        let mut table = SymbolTable::new();
+
+        assert_eq!(table.declare("x", 1), None);
+        assert_eq!(table.declare("x", 2), Some(1));
+        assert_eq!(table.get("x"), Some(&2));
+    }
+
+    #[test]
+    fn inner_scope_shadows_without_conflict() {
+        let mut table = SymbolTable::new();
+        table.declare("x", 1);
+
+        table.push_scope();
+        assert_eq!(table.declare("x", 2), None);
+        assert_eq!(table.get("x"), Some(&2));
+
+        *table.get_mut("x").unwrap() = 3;
+        table.pop_scope();
+
+        assert_eq!(table.get("x"), Some(&1));
+    }
+
+    #[test]
+    fn outer_bindings_are_visible_and_mutable() {
+        let mut table = SymbolTable::new();
+        table.declare("x", 1);
+
+        table.push_scope();
+        *table.get_mut("x").unwrap() = 5;
+        table.pop_scope();
+        table.pop_scope();
+
+        assert_eq!(table.depth(), 1);
+        assert_eq!(table.get("x"), Some(&5));
+    }
+}
@@ -0,0 +1,54 @@
+use std::ops::Range;
+
+/// A byte range in the source, as reported by the lexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// 1-based line and column of the start of the span in `source`. The
+    /// column counts characters, not bytes.
+    pub fn line_column(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        (line, column)
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span::new(range.start, range.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_column_counts_characters() {
+        let source = "let a = 1;\nlet é = 2;";
+
+        assert_eq!(Span::new(0, 3).line_column(source), (1, 1));
+        assert_eq!(Span::new(15, 17).line_column(source), (2, 5));
+        assert_eq!(Span::new(18, 19).line_column(source), (2, 7));
+        assert_eq!(Span::new(4, 5).to(Span::new(8, 9)), Span::new(4, 9));
+    }
+}